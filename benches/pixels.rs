@@ -0,0 +1,90 @@
+//! Benchmarks for the bulk pixel paths: fills, blends, channel adjustments and conversions.
+//!
+//! Run with `cargo bench --bench pixels`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use kodak::*;
+use std::hint::black_box;
+
+const SIDE: u32 = 1024;
+
+fn photo() -> Image {
+    Image::blank_with_colour(Dim::square(SIDE), Colour::rgb(90, 140, 200))
+}
+
+/// A sprite with an opaque body and a translucent border, like anti-aliased artwork.
+fn sprite() -> Image {
+    Image::blank_with_colour(Dim::square(512), Colour::rgba(255, 40, 40, 100)).fill_region(
+        Region {
+            l: Loc { x: 8, y: 8 },
+            d: Dim::square(496),
+        },
+        Colour::rgb(255, 40, 40),
+    )
+}
+
+fn fills(c: &mut Criterion) {
+    let region = Region {
+        l: Loc { x: 100, y: 100 },
+        d: Dim::square(800),
+    };
+    c.bench_function("fill_region", |b| {
+        b.iter_with_setup(photo, |img| {
+            black_box(img.fill_region(region, Colour::WHITE))
+        })
+    });
+}
+
+fn blends(c: &mut Criterion) {
+    let top = sprite();
+    c.bench_function("overlay", |b| {
+        b.iter_with_setup(photo, |img| {
+            black_box(img.overlay(top.clone(), Loc { x: 200, y: 200 }))
+        })
+    });
+    c.bench_function("premultiply", |b| {
+        b.iter_with_setup(sprite, |img| black_box(img.premultiply()))
+    });
+}
+
+fn adjustments(c: &mut Criterion) {
+    c.bench_function("adjust_channels", |b| {
+        b.iter_with_setup(photo, |img| black_box(img.adjust_channels(1.1, 1.0, 0.8)))
+    });
+    c.bench_function("multiply", |b| {
+        b.iter_with_setup(photo, |img| {
+            black_box(img.multiply(Colour::rgba(255, 200, 150, 255)))
+        })
+    });
+}
+
+fn conversions(c: &mut Criterion) {
+    let rgb = vec![128u8; SIDE as usize * SIDE as usize * 3];
+    c.bench_function("rgb_to_image", |b| {
+        b.iter(|| {
+            let view = ImageView::from_raw_parts(&rgb, Dim::square(SIDE), RawFormat::Rgb).unwrap();
+            black_box(view.to_image())
+        })
+    });
+
+    // Flattening colours one `Vec` at a time against the allocation-free array.
+    let colours = vec![Colour::rgb(90, 140, 200); SIDE as usize * SIDE as usize];
+    let mut group = c.benchmark_group("flatten");
+    group.bench_function("to_vec", |b| {
+        b.iter(|| black_box(colours.iter().flat_map(|c| c.to_vec()).collect::<Vec<u8>>()))
+    });
+    group.bench_function("to_array", |b| {
+        b.iter(|| {
+            black_box(
+                colours
+                    .iter()
+                    .flat_map(|c| c.to_array())
+                    .collect::<Vec<u8>>(),
+            )
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, fills, blends, adjustments, conversions);
+criterion_main!(benches);
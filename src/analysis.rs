@@ -0,0 +1,576 @@
+//! Image analysis: gradients, feature detection, skew estimation and registration.
+//!
+//! These functions work on the brightness of an image and return measurements rather than new
+//! images, which is why they live in their own module instead of on `Image`. The exceptions are
+//! `Image::deskew()`, `Image::height_to_normal()` and `Image::hillshade()`, which act on their
+//! measurements straight away.
+
+use crate::fft::fft_2d;
+use crate::{Colour, Dim, Image, Loc};
+
+/// The horizontal and vertical brightness gradients of an image, as computed by `sobel()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradients {
+    /// The dimensions of the image the gradients were computed for.
+    pub dimension: Dim,
+    /// The horizontal gradient of every pixel in row-major order; positive when brightness
+    /// increases to the right.
+    pub x: Vec<f32>,
+    /// The vertical gradient of every pixel in row-major order; positive when brightness
+    /// increases downwards.
+    pub y: Vec<f32>,
+}
+
+impl Gradients {
+    /// Returns the gradient magnitude of every pixel in row-major order.
+    pub fn magnitude(&self) -> Vec<f32> {
+        self.x
+            .iter()
+            .zip(&self.y)
+            .map(|(x, y)| (x * x + y * y).sqrt())
+            .collect()
+    }
+
+    /// Returns the gradient direction of every pixel in radians, measured clockwise from the
+    /// positive x axis (since y points down).
+    pub fn direction(&self) -> Vec<f32> {
+        self.x
+            .iter()
+            .zip(&self.y)
+            .map(|(x, y)| y.atan2(*x))
+            .collect()
+    }
+}
+
+/// Returns the brightness of every pixel from 0.0 to 1.0, using the BT.601 luma weights.
+pub(crate) fn brightness(img: &Image) -> Vec<f32> {
+    img.pixels
+        .iter()
+        .map(|c| (0.299 * c.r as f32 + 0.587 * c.g as f32 + 0.114 * c.b as f32) / 255.0)
+        .collect()
+}
+
+/// Computes brightness gradients with the 3 by 3 Sobel operator, repeating edge pixels beyond
+/// the border.
+///
+/// # Examples
+///
+/// ```
+/// # use kodak::*;
+/// // A vertical edge from black to white.
+/// let img = Image::blank(Dim::square(4))
+///     .fill_region(Region { l: Loc { x: 2, y: 0 }, d: Dim { w: 2, h: 4 } }, Colour::WHITE);
+/// let gradients = analysis::sobel(&img);
+/// assert!(gradients.x[1] > 0.0 && gradients.y[1] == 0.0);
+/// ```
+pub fn sobel(img: &Image) -> Gradients {
+    let (w, h) = (img.width as i64, img.height as i64);
+    let values = brightness(img);
+    let at = |x: i64, y: i64| values[(y.clamp(0, h - 1) * w + x.clamp(0, w - 1)) as usize];
+
+    let mut gx = Vec::with_capacity(values.len());
+    let mut gy = Vec::with_capacity(values.len());
+    for y in 0..h {
+        for x in 0..w {
+            gx.push(
+                (at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1))
+                    - (at(x - 1, y - 1) + 2.0 * at(x - 1, y) + at(x - 1, y + 1)),
+            );
+            gy.push(
+                (at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1))
+                    - (at(x - 1, y - 1) + 2.0 * at(x, y - 1) + at(x + 1, y - 1)),
+            );
+        }
+    }
+
+    Gradients {
+        dimension: img.get_dimensions(),
+        x: gx,
+        y: gy,
+    }
+}
+
+/// Finds corners with the Harris detector, strongest first.
+///
+/// The structure tensor of the Sobel gradients is summed over a 5 by 5 window around every
+/// pixel, and its Harris response `det - 0.04 trace²` is computed. A pixel is reported if its
+/// response is the largest in its 3 by 3 neighbourhood and at least `threshold` times the
+/// strongest response in the image, so `threshold` ranges from 0.0 (every local maximum) to 1.0
+/// (only the strongest corner); 0.01 to 0.1 are typical values.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use kodak::*;
+/// let img = Image::load_png("assets/checkerboard.png").unwrap();
+/// for corner in analysis::harris_corners(&img, 0.05) {
+///     println!("{} {}", corner.x, corner.y);
+/// }
+/// ```
+pub fn harris_corners(img: &Image, threshold: f32) -> Vec<Loc> {
+    const K: f32 = 0.04;
+    const RADIUS: i64 = 2;
+
+    let dim = img.get_dimensions();
+    let (w, h) = (dim.w as i64, dim.h as i64);
+    let gradients = sobel(img);
+    let products: Vec<[f32; 3]> = gradients
+        .x
+        .iter()
+        .zip(&gradients.y)
+        .map(|(x, y)| [x * x, x * y, y * y])
+        .collect();
+
+    let mut response = vec![0.0f32; products.len()];
+    for y in 0..h {
+        for x in 0..w {
+            let mut tensor = [0.0f32; 3];
+            for wy in (y - RADIUS).max(0)..=(y + RADIUS).min(h - 1) {
+                for wx in (x - RADIUS).max(0)..=(x + RADIUS).min(w - 1) {
+                    let p = products[(wy * w + wx) as usize];
+                    tensor = [tensor[0] + p[0], tensor[1] + p[1], tensor[2] + p[2]];
+                }
+            }
+            let det = tensor[0] * tensor[2] - tensor[1] * tensor[1];
+            let trace = tensor[0] + tensor[2];
+            response[(y * w + x) as usize] = det - K * trace * trace;
+        }
+    }
+
+    let strongest = response.iter().copied().fold(0.0f32, f32::max);
+    if strongest <= 0.0 {
+        return Vec::new();
+    }
+    let minimum = (threshold * strongest).max(f32::MIN_POSITIVE);
+
+    let mut corners: Vec<(Loc, f32)> = Vec::new();
+    for y in 0..h {
+        for x in 0..w {
+            let r = response[(y * w + x) as usize];
+            if r < minimum {
+                continue;
+            }
+            // Keep only local maxima; ties go to the first pixel in row-major order.
+            let is_maximum = (-1..=1i64).all(|dy| {
+                (-1..=1i64).all(|dx| {
+                    let (nx, ny) = (x + dx, y + dy);
+                    if (dx, dy) == (0, 0) || nx < 0 || ny < 0 || nx >= w || ny >= h {
+                        return true;
+                    }
+                    let other = response[(ny * w + nx) as usize];
+                    other < r || (other == r && (ny, nx) > (y, x))
+                })
+            });
+            if is_maximum {
+                corners.push((
+                    Loc {
+                        x: x as u32,
+                        y: y as u32,
+                    },
+                    r,
+                ));
+            }
+        }
+    }
+
+    corners.sort_by(|a, b| b.1.total_cmp(&a.1));
+    corners.into_iter().map(|(loc, _)| loc).collect()
+}
+
+/// The largest skew, in degrees either way, that `skew_angle()` looks for.
+const MAX_SKEW: f32 = 15.0;
+
+/// Returns which pixels count as ink: those darker than halfway between the darkest and the
+/// brightest pixel.
+fn ink(img: &Image) -> Vec<bool> {
+    let values = brightness(img);
+    let (min, max) = values
+        .iter()
+        .fold((1.0f32, 0.0f32), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+    let middle = (min + max) / 2.0;
+    values
+        .iter()
+        .map(|&v| max - min > 0.1 && v < middle)
+        .collect()
+}
+
+/// Estimates how far the lines of text in a scanned document are rotated, in degrees
+/// clockwise.
+///
+/// Dark pixels are projected onto lines at every candidate angle within 15 degrees either way;
+/// at the right angle the text lines fall into few, well-filled rows, which makes the profile
+/// as uneven as possible. The search runs in steps of half a degree, then refines to a tenth.
+/// Returns 0.0 for images without any contrast.
+pub fn skew_angle(img: &Image) -> f32 {
+    let dim = img.get_dimensions();
+    let points: Vec<(f32, f32)> = ink(img)
+        .into_iter()
+        .enumerate()
+        .filter(|(_, ink)| *ink)
+        .map(|(i, _)| {
+            let loc = Loc::from_index(i, dim);
+            (loc.x as f32, loc.y as f32)
+        })
+        .collect();
+    if points.is_empty() {
+        return 0.0;
+    }
+
+    // Pixels on a text line skewed clockwise by `degrees` all share the same projection.
+    let diagonal = (dim.w as f32).hypot(dim.h as f32);
+    let mut rows = vec![0u32; 2 * diagonal.ceil() as usize + 1];
+    let mut score = |degrees: f32| -> f64 {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        rows.iter_mut().for_each(|r| *r = 0);
+        for &(x, y) in &points {
+            rows[(y * cos - x * sin + diagonal).round() as usize] += 1;
+        }
+        rows.iter().map(|&r| (r as f64) * (r as f64)).sum()
+    };
+
+    let mut best = (0.0, score(0.0));
+    let mut search = |candidates: Vec<f32>| {
+        for degrees in candidates {
+            let s = score(degrees);
+            if s > best.1 {
+                best = (degrees, s);
+            }
+        }
+        best.0
+    };
+    let coarse = search((-30..=30).map(|i| i as f32 * MAX_SKEW / 30.0).collect());
+    search((-5..=5).map(|i| coarse + i as f32 * 0.1).collect())
+}
+
+/// Estimates how far `b` is moved relative to `a`, as `(dx, dy)` in pixels to the right and
+/// down, so that `b` looks like `a.shift(dx, dy, false)`. This registers photos of the same
+/// scene before they are stacked, averaged or stitched together.
+///
+/// The estimate uses phase correlation, which compares the images in the frequency domain and
+/// is not thrown by differences in brightness or contrast. Both images are padded to the next
+/// powers of two and tapered towards their borders, so shifts of up to about a quarter of
+/// their size are found reliably; the content they share decides the rest. An empty image
+/// gives `(0, 0)`.
+///
+/// # Examples
+///
+/// ```
+/// # use kodak::*;
+/// let img = Image::blank(Dim::square(32))
+///     .fill_region(Region { l: Loc { x: 8, y: 10 }, d: Dim { w: 6, h: 9 } }, Colour::WHITE);
+/// let moved = img.clone().shift(4, -3, false);
+/// assert_eq!(analysis::estimate_translation(&img, &moved), (4, -3));
+/// ```
+pub fn estimate_translation(a: &Image, b: &Image) -> (i32, i32) {
+    if a.pixels.is_empty() || b.pixels.is_empty() {
+        return (0, 0);
+    }
+    let w = a.width.max(b.width).next_power_of_two() as usize;
+    let h = a.height.max(b.height).next_power_of_two() as usize;
+
+    // The mean is removed so that the padding does not form an edge of its own, and a Hann
+    // window hides the borders of the images themselves.
+    let spectrum = |img: &Image| {
+        let values = brightness(img);
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        let hann = |i: u32, len: u32| {
+            0.5 - 0.5 * (2.0 * core::f32::consts::PI * (i as f32 + 0.5) / len as f32).cos()
+        };
+        let mut data = vec![(0.0, 0.0); w * h];
+        for (i, v) in values.iter().enumerate() {
+            let (x, y) = (i as u32 % img.width, i as u32 / img.width);
+            let window = hann(x, img.width) * hann(y, img.height);
+            data[y as usize * w + x as usize] = ((v - mean) * window, 0.0);
+        }
+        fft_2d(&mut data, w, h, false);
+        data
+    };
+
+    // The normalised cross-power spectrum transforms back into a peak at the shift.
+    let mut correlation: Vec<(f32, f32)> = spectrum(b)
+        .into_iter()
+        .zip(spectrum(a))
+        .map(|(p, q)| {
+            let product = (p.0 * q.0 + p.1 * q.1, p.1 * q.0 - p.0 * q.1);
+            let magnitude = product.0.hypot(product.1);
+            if magnitude > 1e-12 {
+                (product.0 / magnitude, product.1 / magnitude)
+            } else {
+                (0.0, 0.0)
+            }
+        })
+        .collect();
+    fft_2d(&mut correlation, w, h, true);
+
+    let peak = (0..correlation.len())
+        .max_by(|&i, &j| correlation[i].0.total_cmp(&correlation[j].0))
+        .unwrap_or(0);
+    let unwrap = |i: usize, len: usize| {
+        if i > len / 2 {
+            i as i32 - len as i32
+        } else {
+            i as i32
+        }
+    };
+    (unwrap(peak % w, w), unwrap(peak / w, h))
+}
+
+// The following impl block defines document cleanup functions for Images.
+impl Image {
+    /// Straightens a scanned or photographed document whose text is slightly rotated.
+    ///
+    /// The skew is estimated with `analysis::skew_angle()` and undone with `rotate()`. The
+    /// corners that rotate into view are filled with the average colour of the page, so they
+    /// blend in with the paper.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use kodak::*;
+    /// let scan = Image::load_png("assets/scan.png").unwrap();
+    /// scan.deskew().save_png("scan_straight.png").unwrap();
+    /// ```
+    pub fn deskew(&self) -> Image {
+        let angle = skew_angle(self);
+        if angle == 0.0 {
+            return self.clone();
+        }
+
+        let paper: Vec<_> = self
+            .pixels
+            .iter()
+            .zip(ink(self))
+            .filter(|(_, ink)| !ink)
+            .map(|(c, _)| c)
+            .collect();
+        let average = |channel: fn(&Colour) -> u8| {
+            (paper.iter().map(|c| channel(c) as u64).sum::<u64>() / paper.len().max(1) as u64) as u8
+        };
+        let background = Colour::rgba(
+            average(|c| c.r),
+            average(|c| c.g),
+            average(|c| c.b),
+            average(|c| c.a),
+        );
+        self.rotate(-angle, background)
+    }
+}
+
+/// Returns the unit surface normal of every pixel of a heightmap in row-major order, with x
+/// pointing right, y pointing up and z out of the image. `strength` is the height of white
+/// above black in pixels.
+fn surface_normals(heightmap: &Image, strength: f32) -> Vec<[f32; 3]> {
+    let gradients = sobel(heightmap);
+    gradients
+        .x
+        .iter()
+        .zip(&gradients.y)
+        .map(|(gx, gy)| {
+            // The Sobel operator weighs the differences over two pixels by four.
+            let (dx, dy) = (gx / 8.0 * strength, gy / 8.0 * strength);
+            // The image's y axis points down, the normal's up.
+            let (x, y, z) = (-dx, dy, 1.0);
+            let length = (x * x + y * y + z * z).sqrt();
+            [x / length, y / length, z / length]
+        })
+        .collect()
+}
+
+// The following impl block defines heightmap functions for Images.
+impl Image {
+    /// Turns a heightmap, in which brighter pixels are higher, into a tangent-space normal map,
+    /// as used for lighting in 3D rendering.
+    ///
+    /// The slopes are found with `analysis::sobel()`, and `strength` is the height of white
+    /// above black in pixels, so larger values give steeper bumps. The normals follow the
+    /// OpenGL convention, with green pointing up; invert the green channel for engines that
+    /// expect DirectX normal maps. The result is opaque.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// // A flat heightmap points straight out of the surface.
+    /// let flat = Image::blank_with_colour(Dim::square(8), Colour::rgb(90, 90, 90));
+    /// let normals = flat.height_to_normal(4.0);
+    /// assert_eq!(normals.get_pixel(Loc { x: 3, y: 3 }), Ok(Colour::rgb(128, 128, 255)));
+    /// ```
+    pub fn height_to_normal(&self, strength: f32) -> Image {
+        let encode = |v: f32| ((v * 0.5 + 0.5) * 255.0).round() as u8;
+        let pixels = surface_normals(self, strength)
+            .into_iter()
+            .map(|[x, y, z]| Colour::rgb(encode(x), encode(y), encode(z)))
+            .collect();
+        Image::from_pixels(self.get_dimensions(), pixels)
+    }
+
+    /// Renders a heightmap, in which brighter pixels are higher, as shaded relief: the terrain
+    /// is lit by a distant sun, so slopes facing it are bright and slopes facing away are dark.
+    ///
+    /// `azimuth` is the direction the light comes from in degrees clockwise from the top of the
+    /// image, and `altitude` its angle above the horizon in degrees; cartographers usually use
+    /// 315 and 45, a light from the top left. `exaggeration` is the height of white above black
+    /// in pixels, as with `height_to_normal()`. Flat ground gets the brightness
+    /// `sin(altitude)`, and the result is an opaque greyscale image, which can be multiplied
+    /// with a coloured elevation map.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use kodak::*;
+    /// let terrain = Image::load_png("assets/heightmap.png").unwrap();
+    /// terrain.hillshade(315.0, 45.0, 20.0).save_png("relief.png").unwrap();
+    /// ```
+    pub fn hillshade(&self, azimuth: f32, altitude: f32, exaggeration: f32) -> Image {
+        let (azimuth, altitude) = (azimuth.to_radians(), altitude.to_radians());
+        let light = [
+            altitude.cos() * azimuth.sin(),
+            altitude.cos() * azimuth.cos(),
+            altitude.sin(),
+        ];
+        let pixels = surface_normals(self, exaggeration)
+            .into_iter()
+            .map(|n| {
+                let lit = n[0] * light[0] + n[1] * light[1] + n[2] * light[2];
+                let v = (lit.max(0.0) * 255.0).round() as u8;
+                Colour::rgb(v, v, v)
+            })
+            .collect();
+        Image::from_pixels(self.get_dimensions(), pixels)
+    }
+}
+
+#[cfg(test)]
+mod analysis_tests {
+    use super::*;
+    use crate::Region;
+
+    #[test]
+    fn translations() {
+        // A few blobs of different brightness on a dark background.
+        let img = Image::blank(Dim { w: 48, h: 40 })
+            .fill_region(
+                Region {
+                    l: Loc { x: 10, y: 8 },
+                    d: Dim { w: 7, h: 5 },
+                },
+                Colour::WHITE,
+            )
+            .fill_region(
+                Region {
+                    l: Loc { x: 25, y: 20 },
+                    d: Dim { w: 4, h: 9 },
+                },
+                Colour::rgb(150, 150, 150),
+            );
+        for (dx, dy) in [(0, 0), (5, 2), (-6, 3), (2, -7)] {
+            let moved = img.clone().shift(dx, dy, false);
+            assert_eq!(estimate_translation(&img, &moved), (dx, dy));
+        }
+        // Brighter copies are still found, and images of different sizes can be compared.
+        let brighter = img
+            .clone()
+            .shift(3, 3, false)
+            .adjust_channels(1.5, 1.5, 1.5);
+        assert_eq!(estimate_translation(&img, &brighter), (3, 3));
+        let empty = Image::blank(Dim::square(0));
+        assert_eq!(estimate_translation(&empty, &img), (0, 0));
+    }
+
+    #[test]
+    fn corners_of_a_square() {
+        let img = Image::blank(Dim::square(20)).fill_region(
+            Region {
+                l: Loc { x: 5, y: 5 },
+                d: Dim::square(10),
+            },
+            Colour::WHITE,
+        );
+        let corners = harris_corners(&img, 0.1);
+
+        assert_eq!(corners.len(), 4);
+        for corner in corners {
+            let near = |v: u32, a: u32| v.abs_diff(a) <= 1;
+            assert!(near(corner.x, 5) || near(corner.x, 14));
+            assert!(near(corner.y, 5) || near(corner.y, 14));
+        }
+    }
+
+    #[test]
+    fn flat_image_has_no_corners() {
+        let img = Image::blank_with_colour(Dim::square(8), Colour::WHITE);
+        assert!(harris_corners(&img, 0.01).is_empty());
+        assert!(sobel(&img).magnitude().iter().all(|&m| m == 0.0));
+    }
+
+    #[test]
+    fn deskew_straightens_lines() {
+        // Lines of "text" on white paper, rotated by 4 degrees.
+        let mut page = Image::blank_with_colour(Dim { w: 120, h: 100 }, Colour::WHITE);
+        for line in 0..6 {
+            page = page.fill_region(
+                Region {
+                    l: Loc {
+                        x: 15,
+                        y: 15 + line * 12,
+                    },
+                    d: Dim { w: 90, h: 4 },
+                },
+                Colour::BLACK,
+            );
+        }
+        let skewed = page.rotate(4.0, Colour::WHITE);
+
+        assert!((skew_angle(&skewed) - 4.0).abs() <= 0.3);
+        assert!(skew_angle(&skewed.deskew()).abs() <= 0.3);
+        assert_eq!(skew_angle(&page), 0.0);
+    }
+
+    #[test]
+    fn normals_face_away_from_slopes() {
+        // A ramp that rises to the right, and turned clockwise, one that rises downwards.
+        let mut ramp = Image::blank(Dim::square(16));
+        for y in 0..16 {
+            for (x, c) in ramp.row_mut(y).iter_mut().enumerate() {
+                let v = (x * 16) as u8;
+                *c = Colour::rgb(v, v, v);
+            }
+        }
+        let centre = Loc { x: 8, y: 8 };
+        let gentle = ramp.height_to_normal(8.0).get_pixel(centre).unwrap();
+        assert!(gentle.r < 128 && gentle.g == 128 && gentle.b < 255);
+        let steep = ramp.height_to_normal(100.0).get_pixel(centre).unwrap();
+        assert!(steep.r < gentle.r && steep.b < gentle.b);
+
+        let turned = ramp
+            .rotate_90()
+            .height_to_normal(8.0)
+            .get_pixel(centre)
+            .unwrap();
+        assert!(turned.r == 128 && turned.g > 128);
+    }
+
+    #[test]
+    fn hillshade_lights_slopes_facing_the_sun() {
+        // A ridge along the middle column.
+        let mut ridge = Image::blank(Dim::square(16));
+        for y in 0..16 {
+            for (x, c) in ridge.row_mut(y).iter_mut().enumerate() {
+                let v = 255 - (x.abs_diff(8) * 30) as u8;
+                *c = Colour::rgb(v, v, v);
+            }
+        }
+        let shade = |img: &Image, x| img.get_pixel(Loc { x, y: 8 }).unwrap().r;
+
+        // Lit from the west, the west face is brighter than the east face.
+        let from_west = ridge.hillshade(270.0, 30.0, 10.0);
+        assert!(shade(&from_west, 4) > shade(&from_west, 12));
+        let from_east = ridge.hillshade(90.0, 30.0, 10.0);
+        assert!(shade(&from_east, 4) < shade(&from_east, 12));
+
+        let flat = Image::blank(Dim::square(4)).hillshade(315.0, 30.0, 10.0);
+        assert!(flat.pixels.iter().all(|&c| c == Colour::rgb(128, 128, 128)));
+        let overhead = Image::blank(Dim::square(4)).hillshade(0.0, 90.0, 1.0);
+        assert!(overhead.pixels.iter().all(|&c| c == Colour::WHITE));
+    }
+}
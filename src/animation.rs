@@ -0,0 +1,557 @@
+//! Sequences of images shown one after another, transitions between images, and animated PNG
+//! export.
+
+use crate::{Colour, Image};
+#[cfg(all(feature = "png", not(target_arch = "wasm32")))]
+use std::path::Path;
+use std::time::Duration;
+
+/// The highest frame rate `Animation::resample_fps()` accepts. Faster rates give delays under a
+/// millisecond, which no format or display can show.
+const MAX_FPS: f32 = 1000.0;
+
+/// The most frames `Animation::resample_fps()` produces, a little over four and a half hours at
+/// 60 frames per second.
+const MAX_RESAMPLED_FRAMES: f64 = (1 << 20) as f64;
+
+/// How the progress of a transition speeds up and slows down over time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Easing {
+    /// Constant speed from start to end.
+    #[default]
+    Linear,
+    /// Starts slowly and speeds up towards the end.
+    EaseIn,
+    /// Starts quickly and slows down towards the end.
+    EaseOut,
+    /// Starts and ends slowly, fastest in the middle.
+    EaseInOut,
+}
+
+impl Easing {
+    /// Maps linear progress `t` (0.0 to 1.0) to eased progress, using cubic curves.
+    ///
+    /// Every easing maps 0.0 to 0.0 and 1.0 to 1.0; values outside of that range are clamped.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (2.0 - 2.0 * t).powi(3) / 2.0
+                }
+            }
+        }
+    }
+}
+
+/// Fills in the printf-style number placeholder (`%d` or a zero-padded `%04d`) of a file name
+/// pattern. Returns `None` if the pattern has no placeholder.
+#[cfg(all(feature = "png", not(target_arch = "wasm32")))]
+fn format_frame_name(pattern: &str, index: usize) -> Option<String> {
+    let start = pattern.find('%')?;
+    let rest = &pattern[start + 1..];
+    let digits = rest.find('d')?;
+    let width: usize = match &rest[..digits] {
+        "" => 0,
+        spec if spec.starts_with('0') => spec.parse().ok()?,
+        _ => return None,
+    };
+    Some(format!(
+        "{}{:0width$}{}",
+        &pattern[..start],
+        index,
+        &rest[digits + 1..]
+    ))
+}
+
+/// A single image of an animation, together with how long it is shown.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    /// The image shown during this frame.
+    pub image: Image,
+    /// How long the image is shown before moving to the next frame.
+    pub delay: Duration,
+}
+
+/// A sequence of frames that all share the same dimensions.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use kodak::*;
+/// # use std::time::Duration;
+/// let red = Image::blank_with_colour(Dim::square(32), Colour::rgb(255, 0, 0));
+/// let blue = Image::blank_with_colour(Dim::square(32), Colour::rgb(0, 0, 255));
+/// Animation::new()
+///     .with_frame(red, Duration::from_millis(500))
+///     .with_frame(blue, Duration::from_millis(500))
+///     .save_apng("blink.png")
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Animation {
+    /// The frames, in the order they are shown.
+    pub frames: Vec<Frame>,
+    /// How many times the animation plays; 0 means it loops forever.
+    pub loop_count: u32,
+}
+
+impl Animation {
+    /// The delay given to generated frames, which corresponds to 30 frames per second.
+    pub const DEFAULT_DELAY: Duration = Duration::from_nanos(1_000_000_000 / 30);
+
+    /// Creates an empty animation that loops forever.
+    pub fn new() -> Self {
+        Animation::default()
+    }
+
+    /// Appends a frame that is shown for `delay`.
+    pub fn with_frame(mut self, image: Image, delay: Duration) -> Self {
+        self.push_frame(image, delay);
+        self
+    }
+
+    /// Appends a frame that is shown for `delay`, in place.
+    pub fn push_frame(&mut self, image: Image, delay: Duration) {
+        self.frames.push(Frame { image, delay });
+    }
+
+    /// Sets how many times the animation plays; 0 means it loops forever.
+    pub fn with_loop_count(self, loop_count: u32) -> Self {
+        Animation { loop_count, ..self }
+    }
+
+    /// Gives every frame the same delay.
+    pub fn with_frame_delay(mut self, delay: Duration) -> Self {
+        for frame in &mut self.frames {
+            frame.delay = delay;
+        }
+        self
+    }
+
+    /// Generates a crossfade from one image to another.
+    ///
+    /// The animation has `frames` frames, the first of which is `from` and the last of which is
+    /// `to`; the ones in between blend the two according to `easing`. Every frame is shown for
+    /// `Animation::DEFAULT_DELAY`, which can be changed with `with_frame_delay()`.
+    ///
+    /// This returns an `Err` if the images differ in size or if fewer than two frames are asked
+    /// for.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use kodak::*;
+    /// let day = Image::load_png("assets/day.png").unwrap();
+    /// let night = Image::load_png("assets/night.png").unwrap();
+    /// Animation::tween(&day, &night, 60, Easing::EaseInOut)
+    ///     .unwrap()
+    ///     .save_apng("sunset.png")
+    ///     .unwrap();
+    /// ```
+    pub fn tween(
+        from: &Image,
+        to: &Image,
+        frames: u32,
+        easing: Easing,
+    ) -> Result<Animation, &'static str> {
+        if from.get_dimensions() != to.get_dimensions() {
+            return Err("Both images of a tween must have the same size.");
+        }
+        if frames < 2 {
+            return Err("A tween needs at least two frames.");
+        }
+
+        let mut animation = Animation::new();
+        for i in 0..frames {
+            let t = easing.apply(i as f32 / (frames - 1) as f32);
+            let pixels: Vec<Colour> = from
+                .pixels
+                .iter()
+                .zip(&to.pixels)
+                .map(|(a, b)| a.lerp(*b, t))
+                .collect();
+            animation.push_frame(
+                Image {
+                    pixels: pixels.into(),
+                    ..from.clone()
+                },
+                Animation::DEFAULT_DELAY,
+            );
+        }
+        Ok(animation)
+    }
+
+    /// Retimes the animation to `target_fps` frames per second, showing every new frame for
+    /// the same time, by repeating or dropping frames.
+    ///
+    /// Every new frame shows the frame that was on screen when it starts, so the animation
+    /// keeps its duration, up to a frame. This suits screen captures and pixel art, where
+    /// mixing frames would smear; `resample_fps_crossfade()` gives smoother motion.
+    ///
+    /// This returns an `Err` if `target_fps` is not a positive number of at most 1000 frames per
+    /// second, or if the result would have more than 2^20 frames.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// # use std::time::Duration;
+    /// let animation = Animation::new()
+    ///     .with_frame(Image::blank(Dim::square(2)), Duration::from_millis(100))
+    ///     .with_frame(Image::blank(Dim::square(2)), Duration::from_millis(300));
+    /// let resampled = animation.resample_fps(20.0).unwrap();
+    /// assert_eq!(resampled.len(), 8);
+    /// assert_eq!(resampled.frames[0].delay, Duration::from_millis(50));
+    /// ```
+    pub fn resample_fps(&self, target_fps: f32) -> Result<Animation, &'static str> {
+        self.resample(target_fps, false)
+    }
+
+    /// Retimes the animation to `target_fps` frames per second like `resample_fps()`, but mixes
+    /// the two frames around the start of every new frame, in proportion to how close it is to
+    /// each of them. The last frame is not mixed with anything.
+    ///
+    /// This returns an `Err` in the same cases as `resample_fps()`, or if the frames differ in
+    /// size.
+    pub fn resample_fps_crossfade(&self, target_fps: f32) -> Result<Animation, &'static str> {
+        self.resample(target_fps, true)
+    }
+
+    /// Retimes the animation, crossfading between frames if `crossfade` is set.
+    fn resample(&self, target_fps: f32, crossfade: bool) -> Result<Animation, &'static str> {
+        if !(target_fps > 0.0 && target_fps <= MAX_FPS) {
+            return Err("The frame rate must be a positive number of at most 1000.");
+        }
+        if crossfade
+            && self
+                .frames
+                .windows(2)
+                .any(|w| w[0].image.get_dimensions() != w[1].image.get_dimensions())
+        {
+            return Err("All frames of an animation must have the same size.");
+        }
+        let mut resampled = Animation {
+            frames: Vec::new(),
+            loop_count: self.loop_count,
+        };
+        if self.frames.is_empty() {
+            return Ok(resampled);
+        }
+
+        // Times are kept in whole nanoseconds, so that frames that start together are not
+        // told apart by rounding.
+        let fps = target_fps as f64;
+        let count = (self.duration().as_secs_f64() * fps).round();
+        if count > MAX_RESAMPLED_FRAMES {
+            return Err("The resampled animation would have too many frames.");
+        }
+        let count = (count as usize).max(1);
+        let delay = Duration::from_nanos((1e9 / fps).round() as u64);
+        // The source frame on screen, and when it started.
+        let (mut current, mut start) = (0, Duration::ZERO);
+        for k in 0..count {
+            let t = Duration::from_nanos((k as f64 * 1e9 / fps).round() as u64);
+            while current + 1 < self.frames.len() && start + self.frames[current].delay <= t {
+                start += self.frames[current].delay;
+                current += 1;
+            }
+            let frame = &self.frames[current];
+            let image = match self.frames.get(current + 1) {
+                Some(next) if crossfade && !frame.delay.is_zero() => {
+                    let f = ((t - start).as_secs_f64() / frame.delay.as_secs_f64()) as f32;
+                    let pixels: Vec<Colour> = frame
+                        .image
+                        .pixels
+                        .iter()
+                        .zip(next.image.pixels.iter())
+                        .map(|(a, b)| a.lerp(*b, f))
+                        .collect();
+                    Image {
+                        pixels: pixels.into(),
+                        ..frame.image.clone()
+                    }
+                }
+                _ => frame.image.clone(),
+            };
+            resampled.push_frame(image, delay);
+        }
+        Ok(resampled)
+    }
+
+    /// Loads a sequence of PNG frames, such as the ones written by ffmpeg or `save_frames()`.
+    ///
+    /// `path` is either a directory, in which case every `.png` file in it is loaded, or a
+    /// pattern like `frames/shot_*.png` whose file name may contain the wildcards `*` and `?`.
+    /// Frames are ordered by file name, so numbers should be zero-padded; each is shown for
+    /// `Animation::DEFAULT_DELAY`.
+    ///
+    /// This returns an `Err` if the directory could not be read or a frame could not be decoded.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use kodak::*;
+    /// let animation = Animation::load_dir("render/frame_*.png").unwrap();
+    /// animation.save_apng("render.png").unwrap();
+    /// ```
+    #[cfg(all(feature = "png", not(target_arch = "wasm32")))]
+    pub fn load_dir(path: impl AsRef<Path>) -> Result<Animation, png::DecodingError> {
+        let files = crate::batch::expand_glob(path.as_ref(), |p| {
+            p.extension().is_some_and(|e| e == "png")
+        })?;
+
+        let mut animation = Animation::new();
+        for file in files {
+            animation.push_frame(Image::load_png(file)?, Animation::DEFAULT_DELAY);
+        }
+        Ok(animation)
+    }
+
+    /// Saves every frame as a separate PNG file in `dir`, which is created if needed.
+    ///
+    /// `pattern` names the files with a printf-style placeholder for the frame number, which
+    /// starts at 1 like ffmpeg's: `frame_%04d.png` gives `frame_0001.png`, `frame_0002.png` and
+    /// so on. Frame delays are not stored.
+    ///
+    /// This returns an `Err` if the pattern has no `%d` or `%0Nd` placeholder, or if a file
+    /// could not be written.
+    #[cfg(all(feature = "png", not(target_arch = "wasm32")))]
+    pub fn save_frames(
+        &self,
+        dir: impl AsRef<Path>,
+        pattern: &str,
+    ) -> Result<(), png::EncodingError> {
+        use std::io::{Error, ErrorKind};
+
+        let dir = dir.as_ref();
+        if format_frame_name(pattern, 0).is_none() {
+            return Err(png::EncodingError::IoError(Error::new(
+                ErrorKind::InvalidInput,
+                "The file name pattern needs a %d or %0Nd placeholder.",
+            )));
+        }
+        std::fs::create_dir_all(dir)?;
+
+        for (i, frame) in self.frames.iter().enumerate() {
+            let name = format_frame_name(pattern, i + 1).expect("The pattern was checked above.");
+            frame
+                .image
+                .write_png(dir.join(name), &frame.image.metadata)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the number of frames.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns whether the animation has no frames at all.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Returns the total time it takes to play the animation once, or `Duration::MAX` if that
+    /// is longer than a `Duration` can hold.
+    pub fn duration(&self) -> Duration {
+        self.frames
+            .iter()
+            .try_fold(Duration::ZERO, |total, f| total.checked_add(f.delay))
+            .unwrap_or(Duration::MAX)
+    }
+
+    /// Saves the animation as an animated PNG (APNG) file.
+    ///
+    /// Viewers that do not understand APNG show the first frame. Delays are stored in
+    /// milliseconds, up to 65.535 seconds per frame. The file is stored as RGB, or RGBA if any
+    /// frame has translucent pixels.
+    ///
+    /// This returns an `Err` if the animation has no frames, if the frames differ in size, or if
+    /// the file could not be written.
+    #[cfg(all(feature = "png", not(target_arch = "wasm32")))]
+    pub fn save_apng(&self, file_name: impl AsRef<Path>) -> Result<(), png::EncodingError> {
+        use std::fs::File;
+        use std::io::{BufWriter, Error, ErrorKind};
+
+        let invalid = |message: &str| {
+            png::EncodingError::IoError(Error::new(ErrorKind::InvalidInput, message))
+        };
+        let first = &self
+            .frames
+            .first()
+            .ok_or_else(|| invalid("An animation needs at least one frame."))?
+            .image;
+        let dimension = first.get_dimensions();
+        if self
+            .frames
+            .iter()
+            .any(|f| f.image.get_dimensions() != dimension)
+        {
+            return Err(invalid(
+                "All frames of an animation must have the same size.",
+            ));
+        }
+
+        let opaque = self
+            .frames
+            .iter()
+            .all(|f| f.image.pixels.iter().all(|c| c.is_opaque()));
+        let mut encoder = png::Encoder::new(
+            BufWriter::new(File::create(file_name)?),
+            dimension.w,
+            dimension.h,
+        );
+        encoder.set_color(if opaque {
+            png::ColorType::Rgb
+        } else {
+            png::ColorType::Rgba
+        });
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(self.frames.len() as u32, self.loop_count)?;
+
+        let mut writer = encoder.write_header()?;
+        for frame in &self.frames {
+            let millis = frame.delay.as_millis().min(u16::MAX as u128) as u16;
+            writer.set_frame_delay(millis, 1000)?;
+
+            let pixels = &frame.image.pixels;
+            let data = if opaque {
+                crate::simd::to_rgb_bytes(pixels)
+            } else {
+                crate::simd::to_rgba_bytes(pixels)
+            };
+            writer.write_image_data(&data)?;
+        }
+        writer.finish()
+    }
+}
+
+#[cfg(test)]
+mod animation_tests {
+    use super::*;
+    #[cfg(feature = "png")]
+    use crate::batch::matches_glob;
+    use crate::Dim;
+
+    #[test]
+    #[cfg(feature = "png")]
+    fn frame_names() {
+        assert!(matches_glob("frame_*.png", "frame_0001.png"));
+        assert!(matches_glob("f?a*e*.png", "frame_1.png"));
+        assert!(!matches_glob("frame_*.png", "frame_0001.png.bak"));
+        assert_eq!(format_frame_name("f_%04d.png", 7).unwrap(), "f_0007.png");
+        assert_eq!(format_frame_name("f_%d.png", 12).unwrap(), "f_12.png");
+        assert!(format_frame_name("frame.png", 1).is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "png")]
+    fn frame_directory_round_trip() {
+        let dir = std::env::temp_dir().join("kodak_frame_directory");
+        let _ = std::fs::remove_dir_all(&dir);
+        let animation = (0..12).fold(Animation::new(), |a, i| {
+            a.with_frame(
+                Image::blank_with_colour(Dim::square(2), Colour::rgb(i * 20, 0, 0)),
+                Animation::DEFAULT_DELAY,
+            )
+        });
+        animation.save_frames(&dir, "frame_%03d.png").unwrap();
+
+        let loaded = Animation::load_dir(dir.join("frame_*.png")).unwrap();
+        assert_eq!(loaded.len(), 12);
+        for (a, b) in loaded.frames.iter().zip(&animation.frames) {
+            assert_eq!(a.image.pixels, b.image.pixels);
+        }
+    }
+
+    #[test]
+    fn tween_ends_on_both_images() {
+        let from = Image::blank(Dim::square(2));
+        let to = Image::blank_with_colour(Dim::square(2), Colour::WHITE);
+        let animation = Animation::tween(&from, &to, 5, Easing::EaseInOut).unwrap();
+
+        assert_eq!(animation.len(), 5);
+        assert_eq!(animation.frames[0].image.pixels, from.pixels);
+        assert_eq!(
+            animation.frames[2].image.pixels[0],
+            Colour::rgb(128, 128, 128)
+        );
+        assert_eq!(animation.frames[4].image.pixels, to.pixels);
+        assert!(Animation::tween(&from, &to, 1, Easing::Linear).is_err());
+    }
+
+    #[test]
+    fn resampling_keeps_timing() {
+        let grey = |v| Image::blank_with_colour(Dim::square(1), Colour::rgb(v, v, v));
+        let animation = Animation::new()
+            .with_frame(grey(0), Duration::from_millis(100))
+            .with_frame(grey(100), Duration::from_millis(50))
+            .with_frame(grey(200), Duration::from_millis(100))
+            .with_loop_count(2);
+        let shades =
+            |a: &Animation| -> Vec<u8> { a.frames.iter().map(|f| f.image.pixels[0].r).collect() };
+
+        let doubled = animation.resample_fps(40.0).unwrap();
+        assert_eq!(shades(&doubled), [0, 0, 0, 0, 100, 100, 200, 200, 200, 200]);
+        assert_eq!(doubled.duration(), animation.duration());
+        assert_eq!(doubled.loop_count, 2);
+
+        let halved = animation.resample_fps(10.0).unwrap();
+        assert_eq!(shades(&halved), [0, 100, 200]);
+
+        let smooth = animation.resample_fps_crossfade(40.0).unwrap();
+        assert_eq!(
+            shades(&smooth),
+            [0, 25, 50, 75, 100, 150, 200, 200, 200, 200]
+        );
+
+        assert!(animation.resample_fps(0.0).is_err());
+        assert!(animation.resample_fps(f32::NAN).is_err());
+        assert!(animation.resample_fps(1001.0).is_err());
+        let endless = Animation::new()
+            .with_frame(Image::blank(Dim::square(2)), Duration::MAX)
+            .with_frame(Image::blank(Dim::square(2)), Duration::MAX);
+        assert_eq!(endless.duration(), Duration::MAX);
+        assert!(endless.resample_fps(1.0).is_err());
+        let mixed = animation
+            .clone()
+            .with_frame(Image::blank(Dim::square(2)), Duration::ZERO);
+        assert!(mixed.resample_fps(30.0).is_ok());
+        assert!(mixed.resample_fps_crossfade(30.0).is_err());
+        assert!(Animation::new().resample_fps(30.0).unwrap().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "png")]
+    fn apng_round_trip() {
+        let path = std::env::temp_dir().join("kodak_animation.png");
+        let colours = [Colour::WHITE, Colour::rgba(200, 0, 0, 100)];
+        let mut animation = Animation::new().with_loop_count(3);
+        for colour in colours {
+            animation.push_frame(
+                Image::blank_with_colour(Dim { w: 3, h: 2 }, colour),
+                Duration::from_millis(250),
+            );
+        }
+        animation.save_apng(&path).unwrap();
+
+        let decoder = png::Decoder::new(std::fs::File::open(&path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let control = *reader.info().animation_control().unwrap();
+        assert_eq!((control.num_frames, control.num_plays), (2, 3));
+
+        let mut buf = vec![0; reader.output_buffer_size()];
+        for colour in colours {
+            reader.next_frame(&mut buf).unwrap();
+            let fctl = reader.info().frame_control().unwrap();
+            assert_eq!((fctl.delay_num, fctl.delay_den), (250, 1000));
+            assert_eq!(buf[..4], colour.to_array()[..]);
+        }
+    }
+}
@@ -0,0 +1,272 @@
+//! Arrows, callouts and highlights for marking up screenshots.
+
+use crate::bitmap_font::GLYPH_SIZE;
+use crate::stroke::{Point, Shape};
+use crate::{Cap, Colour, Image, Join, Loc, Region, StrokeStyle};
+
+/// How arrows and callouts are drawn by `Image::draw_arrow()` and `Image::draw_callout()`.
+///
+/// # Examples
+///
+/// ```
+/// # use kodak::*;
+/// // A thin blue arrow with a small head.
+/// let style = AnnotationStyle {
+///     stroke: StrokeStyle::new(2.0),
+///     head_length: 8.0,
+///     ..AnnotationStyle::new(Colour::rgb(0, 80, 220))
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnotationStyle {
+    /// The colour of arrows and of the borders of callouts.
+    pub colour: Colour,
+    /// How the shafts of arrows and the borders of callouts are stroked.
+    pub stroke: StrokeStyle,
+    /// The length of arrowheads in pixels, from the tip to the back. They are as wide as they
+    /// are long.
+    pub head_length: f32,
+    /// The colour behind the text of callouts, or `None` to leave the image visible.
+    pub fill: Option<Colour>,
+    /// The colour of the text of callouts.
+    pub text_colour: Colour,
+    /// How much the built-in 8 by 8 pixel font is enlarged for the text of callouts.
+    pub text_scale: u32,
+}
+
+impl Default for AnnotationStyle {
+    fn default() -> Self {
+        AnnotationStyle::new(Colour::rgb(230, 30, 30))
+    }
+}
+
+impl AnnotationStyle {
+    /// Creates a style in the given colour, with 3 pixel wide round strokes, 15 pixel
+    /// arrowheads and black text at twice the size of the built-in font on white callouts.
+    pub fn new(colour: Colour) -> Self {
+        AnnotationStyle {
+            colour,
+            stroke: StrokeStyle::new(3.0)
+                .with_cap(Cap::Round)
+                .with_join(Join::Round),
+            head_length: 15.0,
+            fill: Some(Colour::WHITE),
+            text_colour: Colour::BLACK,
+            text_scale: 2,
+        }
+    }
+}
+
+/// Breaks text into lines of at most `width` characters, at spaces where possible.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+            let length = line.chars().count();
+            if length > 0 && length + 1 + word.len() > width {
+                lines.push(std::mem::take(&mut line));
+            } else if length > 0 {
+                line.push(' ');
+            }
+            // Words longer than a whole line are broken wherever they run out of room.
+            while word.len() > width {
+                lines.push(word.drain(..width).collect());
+            }
+            line.extend(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+// The following impl block defines annotation functions for Images.
+impl Image {
+    /// Draws an arrow from `from` to `to`, with its head at `to`.
+    ///
+    /// The shaft is stroked with `style.stroke` and ends inside the head, which is a filled
+    /// triangle of `style.head_length`. Arrows shorter than their head are drawn as a head
+    /// alone. Coordinates are in pixels, as for `stroke_line()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let img = Image::blank_with_colour(Dim { w: 80, h: 40 }, Colour::WHITE)
+    ///     .draw_arrow((10.0, 20.0), (70.0, 20.0), &AnnotationStyle::new(Colour::BLACK));
+    /// assert_eq!(img.get_pixel(Loc { x: 40, y: 20 }), Ok(Colour::BLACK));
+    /// ```
+    pub fn draw_arrow(self, from: Point, to: Point, style: &AnnotationStyle) -> Image {
+        let length = (to.0 - from.0).hypot(to.1 - from.1);
+        if length == 0.0 || !length.is_finite() {
+            return self;
+        }
+        let (dx, dy) = ((to.0 - from.0) / length, (to.1 - from.1) / length);
+        let head = style.head_length.clamp(0.0, length);
+
+        let mut img = self;
+        // The shaft stops halfway into the head, so that its end is hidden by the sides.
+        if length > head / 2.0 {
+            let end = (to.0 - dx * head / 2.0, to.1 - dy * head / 2.0);
+            img = img.stroke_line(from, end, style.colour, &style.stroke);
+        }
+        if head > 0.0 {
+            let back = (to.0 - dx * head, to.1 - dy * head);
+            let (nx, ny) = (-dy * head / 2.0, dx * head / 2.0);
+            let triangle = Shape::Polygon(vec![
+                to,
+                (back.0 + nx, back.1 + ny),
+                (back.0 - nx, back.1 - ny),
+            ]);
+            img = img.fill_shapes(&[triangle], style.colour);
+        }
+        img
+    }
+
+    /// Draws a box over `region` with `text` inside it, like a speech bubble for pointing
+    /// something out.
+    ///
+    /// The box is filled with `style.fill` and bordered by `style.stroke` in `style.colour`;
+    /// the border lies inside the region. The text uses the built-in bitmap font, as in
+    /// `draw_text_bitmap()`, and is wrapped at spaces to fit the box. Lines that do not fit are
+    /// left out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let region = Region { l: Loc { x: 10, y: 10 }, d: Dim { w: 120, h: 50 } };
+    /// let img = Image::blank_with_colour(Dim::square(160), Colour::rgb(40, 40, 40))
+    ///     .draw_callout(region, "Click here to save", &AnnotationStyle::default());
+    /// ```
+    pub fn draw_callout(self, region: Region, text: &str, style: &AnnotationStyle) -> Image {
+        let mut img = self;
+        if let Some(fill) = style.fill {
+            img = img.highlight_region(region, fill, 1.0);
+        }
+
+        let border = style.stroke.width.max(0.0);
+        if border > 0.0 {
+            let inset = border / 2.0;
+            let (left, top) = (region.l.x as f32 + inset, region.l.y as f32 + inset);
+            let right = (region.l.x as f32 + region.d.w as f32 - inset).max(left);
+            let bottom = (region.l.y as f32 + region.d.h as f32 - inset).max(top);
+            let corners = [(left, top), (right, top), (right, bottom), (left, bottom)];
+            img = img.stroke_polygon(&corners, style.colour, &style.stroke);
+        }
+
+        // The text keeps a glyph's width of space to the border.
+        let glyph = GLYPH_SIZE * style.text_scale.max(1);
+        let padding = border.ceil() as u32 + glyph / 2;
+        let columns = region.d.w.saturating_sub(2 * padding) / glyph;
+        let rows = region.d.h.saturating_sub(2 * padding) / glyph;
+        if columns == 0 || rows == 0 {
+            return img;
+        }
+        let lines = wrap(text, columns as usize);
+        let shown = lines.len().min(rows as usize);
+        let loc = Loc {
+            x: region.l.x.saturating_add(padding),
+            y: region.l.y.saturating_add(padding),
+        };
+        img.draw_text_bitmap(
+            &lines[..shown].join("\n"),
+            loc,
+            glyph / GLYPH_SIZE,
+            style.text_colour,
+        )
+    }
+
+    /// Tints `region` with `colour` at `opacity`, from 0.0 (invisible) to 1.0 (as opaque as
+    /// the colour), like going over it with a highlighter. Parts of the region that fall outside
+    /// of the image are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let region = Region { l: Loc { x: 0, y: 0 }, d: Dim { w: 4, h: 1 } };
+    /// let img = Image::blank_with_colour(Dim::square(4), Colour::WHITE)
+    ///     .highlight_region(region, Colour::rgb(255, 255, 0), 0.5);
+    /// assert_eq!(img.get_pixel(Loc { x: 0, y: 0 }), Ok(Colour::rgb(255, 255, 127)));
+    /// ```
+    pub fn highlight_region(mut self, region: Region, colour: Colour, opacity: f32) -> Image {
+        let alpha = (colour.a as f32 * opacity.clamp(0.0, 1.0)).round() as u8;
+        let colour = Colour { a: alpha, ..colour };
+        let x0 = region.l.x.min(self.width) as usize;
+        let x1 = region.l.x.saturating_add(region.d.w).min(self.width) as usize;
+        let y1 = region.l.y.saturating_add(region.d.h).min(self.height);
+        for y in region.l.y.min(self.height)..y1 {
+            for pixel in &mut self.row_mut(y)[x0..x1] {
+                *pixel = colour.over(*pixel);
+            }
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod annotate_tests {
+    use super::*;
+    use crate::Dim;
+
+    #[test]
+    fn wraps_text() {
+        assert_eq!(wrap("save the file now", 8), ["save the", "file now"]);
+        assert_eq!(wrap("unbelievable", 5), ["unbel", "ievab", "le"]);
+        assert_eq!(wrap("one\n\ntwo", 8), ["one", "", "two"]);
+    }
+
+    #[test]
+    fn arrows() {
+        let canvas = Image::blank_with_colour(Dim { w: 60, h: 30 }, Colour::WHITE);
+        let style = AnnotationStyle::new(Colour::BLACK);
+        let img = canvas.clone().draw_arrow((5.0, 15.0), (55.0, 15.0), &style);
+        let at = |x, y| img.get_pixel(Loc { x, y }).unwrap();
+        // The head is wider than the shaft, and comes to a point at the end.
+        assert_eq!(at(42, 10), Colour::BLACK);
+        assert_eq!(at(20, 12), Colour::WHITE);
+        assert_eq!(at(56, 15), Colour::WHITE);
+
+        assert_eq!(
+            canvas.clone().draw_arrow((5.0, 5.0), (5.0, 5.0), &style),
+            canvas
+        );
+        let stub = canvas
+            .clone()
+            .draw_arrow((50.0, 15.0), (55.0, 15.0), &style);
+        assert_ne!(stub, canvas);
+    }
+
+    #[test]
+    fn callouts_and_highlights() {
+        let canvas = Image::blank_with_colour(Dim::square(64), Colour::rgb(0, 0, 200));
+        let region = Region {
+            l: Loc { x: 8, y: 8 },
+            d: Dim { w: 56, h: 40 },
+        };
+        let style = AnnotationStyle::new(Colour::rgb(255, 0, 0));
+        let img = canvas.clone().draw_callout(region, "Hi", &style);
+        let at = |x, y| img.get_pixel(Loc { x, y }).unwrap();
+        assert_eq!(at(8, 20), Colour::rgb(255, 0, 0));
+        assert_eq!(at(7, 20), Colour::rgb(0, 0, 200));
+        assert_eq!(at(50, 30), Colour::WHITE);
+        // The H starts half a glyph inside the border, which is three pixels wide.
+        assert_eq!((at(19, 19), at(18, 19)), (Colour::BLACK, Colour::WHITE));
+
+        let tinted = canvas.clone().highlight_region(region, Colour::WHITE, 0.25);
+        assert_eq!(
+            tinted.get_pixel(Loc { x: 8, y: 8 }),
+            Ok(Colour::rgb(64, 64, 214))
+        );
+        let outside = Region {
+            l: Loc { x: 60, y: 60 },
+            d: Dim::square(10),
+        };
+        assert_eq!(
+            canvas.clone().highlight_region(outside, Colour::WHITE, 0.0),
+            canvas
+        );
+    }
+}
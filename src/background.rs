@@ -0,0 +1,120 @@
+//! Removing solid backgrounds, turning them into transparency.
+
+use crate::{Colour, Image};
+
+// The following impl block defines background removal functions for Images.
+impl Image {
+    /// Makes every pixel close to `background` transparent, as for a logo or sprite drawn on a
+    /// solid colour.
+    ///
+    /// The distance between two colours is their largest difference in red, green or blue.
+    /// Colours at most `tolerance` from the background become fully transparent, and colours
+    /// up to `feather` further away partly so, fading in until they are as opaque as they were.
+    /// This gives anti-aliased edges a soft alpha instead of a hard, jagged cut-out. Their
+    /// colours still carry some of the background, which `decontaminate_edges()` removes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let logo = Image::blank_with_colour(Dim { w: 3, h: 1 }, Colour::WHITE)
+    ///     .fill_region(Region::from_top_left(Dim::square(1)), Colour::rgb(0, 0, 255))
+    ///     .fill_region(Region { l: Loc { x: 1, y: 0 }, d: Dim::square(1) }, Colour::rgb(245, 245, 255));
+    /// let cut_out = logo.make_transparent(Colour::WHITE, 5, 10);
+    /// assert_eq!(cut_out.get_pixel(Loc { x: 0, y: 0 }).unwrap().a, 255);
+    /// assert_eq!(cut_out.get_pixel(Loc { x: 1, y: 0 }).unwrap().a, 128);
+    /// assert_eq!(cut_out.get_pixel(Loc { x: 2, y: 0 }).unwrap().a, 0);
+    /// ```
+    pub fn make_transparent(mut self, background: Colour, tolerance: u8, feather: u8) -> Image {
+        for c in self.pixels.iter_mut() {
+            let distance =
+                c.r.abs_diff(background.r)
+                    .max(c.g.abs_diff(background.g))
+                    .max(c.b.abs_diff(background.b));
+            let opacity = if distance <= tolerance {
+                0.0
+            } else if feather == 0 {
+                1.0
+            } else {
+                ((distance - tolerance) as f32 / feather as f32).min(1.0)
+            };
+            c.a = (c.a as f32 * opacity).round() as u8;
+        }
+        self
+    }
+
+    /// Takes the colour of `background` back out of partly transparent pixels, where it was
+    /// mixed into them in proportion to their transparency.
+    ///
+    /// After `make_transparent()`, the soft edges of a logo cut from white keep a light fringe
+    /// that shows on dark backdrops. This restores the colour the edge would have had on its
+    /// own, so it blends cleanly onto anything. Fully opaque and fully transparent pixels are
+    /// left alone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// // Pure blue, half mixed with white.
+    /// let edge = Image::blank_with_colour(Dim::square(1), Colour::rgba(127, 127, 255, 128));
+    /// let clean = edge.decontaminate_edges(Colour::WHITE);
+    /// assert_eq!(clean.get_pixel(Loc { x: 0, y: 0 }), Ok(Colour::rgba(0, 0, 255, 128)));
+    /// ```
+    pub fn decontaminate_edges(mut self, background: Colour) -> Image {
+        for c in self.pixels.iter_mut() {
+            if c.a == 0 || c.a == 255 {
+                continue;
+            }
+            let alpha = c.a as f32 / 255.0;
+            // The pixel is `alpha * foreground + (1 - alpha) * background`.
+            let unmix = |v: u8, bg: u8| {
+                (bg as f32 + (v as f32 - bg as f32) / alpha)
+                    .round()
+                    .clamp(0.0, 255.0) as u8
+            };
+            *c = Colour::rgba(
+                unmix(c.r, background.r),
+                unmix(c.g, background.g),
+                unmix(c.b, background.b),
+                c.a,
+            );
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod background_tests {
+    use super::*;
+    use crate::{Dim, Loc};
+
+    #[test]
+    fn removes_background_and_fringe() {
+        // A red stroke on grey, anti-aliased over a quarter, half and three quarters.
+        let grey = Colour::rgb(100, 100, 100);
+        let red = Colour::rgb(220, 40, 40);
+        let pixels = [0.0, 0.25, 0.5, 0.75, 1.0]
+            .iter()
+            .map(|&t| grey.lerp(red, t))
+            .collect();
+        let img = Image::from_pixels(Dim { w: 5, h: 1 }, pixels);
+
+        let hard = img.clone().make_transparent(grey, 10, 0);
+        let alphas: Vec<u8> = hard.pixels.iter().map(|c| c.a).collect();
+        assert_eq!(alphas, [0, 255, 255, 255, 255]);
+
+        let soft = img.make_transparent(grey, 0, 120);
+        let alphas: Vec<u8> = soft.pixels.iter().map(|c| c.a).collect();
+        assert_eq!(alphas, [0, 64, 128, 191, 255]);
+
+        let clean = soft.decontaminate_edges(grey);
+        for x in 1..5 {
+            let c = clean.get_pixel(Loc { x, y: 0 }).unwrap();
+            assert!(
+                c.r.abs_diff(red.r) <= 3 && c.g.abs_diff(red.g) <= 3,
+                "{x}: {c:?}"
+            );
+        }
+        assert_eq!(clean.pixels[0], Colour::rgba(100, 100, 100, 0));
+    }
+}
@@ -0,0 +1,494 @@
+//! Processing many image files at once, spread over several threads.
+//!
+//! The typical job is making thumbnails of a whole directory:
+//!
+//! ```no_run
+//! use kodak::batch::{self, BatchOptions};
+//! use kodak::*;
+//!
+//! let options = BatchOptions::new("thumbnails")
+//!     .with_extension("png")
+//!     .on_progress(|p| println!("{:3.0}% {}", p.fraction() * 100.0, p.path.display()));
+//! let report = batch::process("photos/*.jpg", &options, |img| {
+//!     img.resize_to_fit(Dim::square(256), ResizeFilter::Area)
+//! })
+//! .unwrap();
+//!
+//! for (path, error) in &report.failed {
+//!     eprintln!("{}: {error}", path.display());
+//! }
+//! ```
+
+use crate::formats::can_open;
+use crate::{CancelToken, Image};
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Matches a file name against a pattern in which `*` stands for any run of characters and `?`
+/// for any single character.
+pub(crate) fn matches_glob(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) =
+        (pattern.chars().collect(), name.chars().collect());
+    // Classic backtracking matcher: remember the last `*` and retry from there on a mismatch.
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Lists the files matching `path`, sorted by name.
+///
+/// `path` is either a directory, in which case the files in it are filtered by `keep`, or a
+/// pattern with `*` and `?` wildcards in its last component, such as `render/frame_*.png`.
+pub(crate) fn expand_glob<F: Fn(&Path) -> bool>(path: &Path, keep: F) -> io::Result<Vec<PathBuf>> {
+    let (dir, pattern) = if path.is_dir() {
+        (path, None)
+    } else {
+        (
+            path.parent()
+                .filter(|p| !p.as_os_str().is_empty())
+                .unwrap_or(Path::new(".")),
+            Some(
+                path.file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            ),
+        )
+    };
+
+    let mut files: Vec<_> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_file())
+        .filter(|entry| match &pattern {
+            Some(pattern) => matches_glob(pattern, &entry.file_name().to_string_lossy()),
+            None => keep(&entry.path()),
+        })
+        .map(|entry| entry.path())
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// The files a batch works on: either a list of paths, or a directory or wildcard pattern that
+/// is expanded when the batch starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inputs {
+    /// A directory, whose files in formats kodak can read are processed, or a pattern with `*`
+    /// and `?` wildcards in its file name, such as `photos/*.jpg`.
+    Glob(PathBuf),
+    /// An explicit list of files.
+    Paths(Vec<PathBuf>),
+}
+
+impl Inputs {
+    fn resolve(self) -> io::Result<Vec<PathBuf>> {
+        match self {
+            Inputs::Glob(path) => expand_glob(&path, |p| {
+                p.extension()
+                    .is_some_and(|e| can_open(&e.to_string_lossy()))
+            }),
+            Inputs::Paths(paths) => Ok(paths),
+        }
+    }
+}
+
+impl From<&str> for Inputs {
+    fn from(pattern: &str) -> Self {
+        Inputs::Glob(pattern.into())
+    }
+}
+
+impl From<&Path> for Inputs {
+    fn from(pattern: &Path) -> Self {
+        Inputs::Glob(pattern.into())
+    }
+}
+
+impl From<PathBuf> for Inputs {
+    fn from(pattern: PathBuf) -> Self {
+        Inputs::Glob(pattern)
+    }
+}
+
+impl From<Vec<PathBuf>> for Inputs {
+    fn from(paths: Vec<PathBuf>) -> Self {
+        Inputs::Paths(paths)
+    }
+}
+
+impl From<&[PathBuf]> for Inputs {
+    fn from(paths: &[PathBuf]) -> Self {
+        Inputs::Paths(paths.to_vec())
+    }
+}
+
+/// The state of a batch after a file has been finished, passed to the progress callback.
+#[derive(Debug, Clone, Copy)]
+pub struct Progress<'a> {
+    /// The number of files finished so far, this one included.
+    pub done: usize,
+    /// The total number of files in the batch.
+    pub total: usize,
+    /// The input file that was just finished.
+    pub path: &'a Path,
+    /// Why the file failed, or `None` if it was saved.
+    pub error: Option<&'a io::Error>,
+}
+
+impl Progress<'_> {
+    /// Returns the fraction of the batch that is finished, from 0.0 to 1.0, for progress bars.
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.done as f32 / self.total as f32
+        }
+    }
+}
+
+type ProgressCallback = Box<dyn Fn(Progress) + Send + Sync>;
+
+/// Where and how `process()` writes its results.
+pub struct BatchOptions {
+    output_dir: PathBuf,
+    extension: Option<String>,
+    threads: usize,
+    on_progress: Option<ProgressCallback>,
+    cancel: Option<CancelToken>,
+}
+
+impl fmt::Debug for BatchOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BatchOptions")
+            .field("output_dir", &self.output_dir)
+            .field("extension", &self.extension)
+            .field("threads", &self.threads)
+            .field("cancel", &self.cancel)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BatchOptions {
+    /// Writes every result into `output_dir`, which is created if needed, under the same file
+    /// name as its input. Uses one thread per CPU core.
+    pub fn new(output_dir: impl Into<PathBuf>) -> Self {
+        BatchOptions {
+            output_dir: output_dir.into(),
+            extension: None,
+            threads: 0,
+            on_progress: None,
+            cancel: None,
+        }
+    }
+
+    /// Replaces the extension of the output files, which converts them to that format.
+    pub fn with_extension(self, extension: &str) -> Self {
+        BatchOptions {
+            extension: Some(extension.trim_start_matches('.').to_string()),
+            ..self
+        }
+    }
+
+    /// Sets the number of worker threads. Zero, the default, uses one per CPU core.
+    pub fn with_threads(self, threads: usize) -> Self {
+        BatchOptions { threads, ..self }
+    }
+
+    /// Calls `callback` whenever a file has been finished, from whichever thread finished it.
+    pub fn on_progress<F: Fn(Progress) + Send + Sync + 'static>(self, callback: F) -> Self {
+        BatchOptions {
+            on_progress: Some(Box::new(callback)),
+            ..self
+        }
+    }
+
+    /// Stops the batch early once `cancel` has been cancelled. Files that are being processed
+    /// at that moment are finished, and the ones not yet started end up in
+    /// `BatchReport::cancelled`.
+    pub fn with_cancel(self, cancel: CancelToken) -> Self {
+        BatchOptions {
+            cancel: Some(cancel),
+            ..self
+        }
+    }
+
+    /// Returns the path a result for `input` is written to.
+    pub fn output_path(&self, input: &Path) -> PathBuf {
+        let mut path = self
+            .output_dir
+            .join(input.file_name().unwrap_or(input.as_os_str()));
+        if let Some(extension) = &self.extension {
+            path.set_extension(extension);
+        }
+        path
+    }
+
+    fn thread_count(&self, files: usize) -> usize {
+        let threads = match self.threads {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+        threads.clamp(1, files.max(1))
+    }
+}
+
+/// The outcome of `process()`, with the files in the order they were given or listed.
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    /// The input and output path of every file that was saved.
+    pub succeeded: Vec<(PathBuf, PathBuf)>,
+    /// The input path of every file that could not be loaded or saved, with the reason.
+    pub failed: Vec<(PathBuf, io::Error)>,
+    /// The input path of every file that was not started because the batch was cancelled.
+    pub cancelled: Vec<PathBuf>,
+}
+
+impl BatchReport {
+    /// Returns whether every file was processed successfully.
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty() && self.cancelled.is_empty()
+    }
+}
+
+/// Loads every input file, runs it through `transform` and saves the result as described by
+/// `options`, spreading the files over several threads.
+///
+/// Files are loaded with `Image::open()` and saved with `Image::save()`, so their formats follow
+/// from their extensions. A file that cannot be loaded or saved does not stop the others; it
+/// ends up in `BatchReport::failed`. So do files that would be saved to the same output path,
+/// such as `a.png` and `a.jpg` with `with_extension("png")`: none of them are processed.
+///
+/// This only returns an `Err` if the inputs could not be listed or the output directory could
+/// not be created.
+pub fn process<F>(
+    inputs: impl Into<Inputs>,
+    options: &BatchOptions,
+    transform: F,
+) -> io::Result<BatchReport>
+where
+    F: Fn(Image) -> Image + Sync,
+{
+    try_process(inputs, options, |img| Ok(transform(img)))
+}
+
+/// Like `process()`, but for transformations that can fail. A file whose transformation
+/// returns an `Err` is not saved and ends up in `BatchReport::failed`.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(output_dir = %options.output_dir.display())
+    )
+)]
+pub fn try_process<F>(
+    inputs: impl Into<Inputs>,
+    options: &BatchOptions,
+    transform: F,
+) -> io::Result<BatchReport>
+where
+    F: Fn(Image) -> io::Result<Image> + Sync,
+{
+    let files = inputs.into().resolve()?;
+    std::fs::create_dir_all(&options.output_dir)?;
+
+    // Files sharing an output path would overwrite each other from different threads.
+    let outputs: Vec<PathBuf> = files.iter().map(|f| options.output_path(f)).collect();
+    let mut uses: HashMap<&Path, usize> = HashMap::new();
+    for output in &outputs {
+        *uses.entry(output).or_default() += 1;
+    }
+    let clashes = |i: usize| uses[outputs[i].as_path()] > 1;
+
+    let next = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(files.len()));
+    let run_one = |i: usize| -> io::Result<PathBuf> {
+        let output = &outputs[i];
+        if clashes(i) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} is also the output of another file", output.display()),
+            ));
+        }
+        transform(Image::open(&files[i])?)?.save(output)?;
+        Ok(output.clone())
+    };
+
+    std::thread::scope(|scope| {
+        for _ in 0..options.thread_count(files.len()) {
+            scope.spawn(|| loop {
+                if options
+                    .cancel
+                    .as_ref()
+                    .is_some_and(CancelToken::is_cancelled)
+                {
+                    break;
+                }
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(input) = files.get(i) else {
+                    break;
+                };
+                let result = run_one(i);
+                #[cfg(feature = "tracing")]
+                if let Err(error) = &result {
+                    tracing::warn!(path = %input.display(), %error, "batch file failed");
+                }
+                let finished = done.fetch_add(1, Ordering::Relaxed) + 1;
+                if let Some(callback) = &options.on_progress {
+                    callback(Progress {
+                        done: finished,
+                        total: files.len(),
+                        path: input,
+                        error: result.as_ref().err(),
+                    });
+                }
+                results.lock().unwrap().push((i, result));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(i, _)| *i);
+    let mut report = BatchReport::default();
+    let mut started = vec![false; files.len()];
+    for (i, result) in results {
+        started[i] = true;
+        let input = files[i].clone();
+        match result {
+            Ok(output) => report.succeeded.push((input, output)),
+            Err(error) => report.failed.push((input, error)),
+        }
+    }
+    report.cancelled = files
+        .into_iter()
+        .zip(started)
+        .filter(|(_, started)| !started)
+        .map(|(input, _)| input)
+        .collect();
+    Ok(report)
+}
+
+#[cfg(all(test, feature = "png", feature = "tga"))]
+mod batch_tests {
+    use super::*;
+    use crate::{Colour, Dim, Loc};
+    use std::sync::Arc;
+
+    #[test]
+    fn thumbnails_a_directory() {
+        let dir = std::env::temp_dir().join("kodak_batch");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("in")).unwrap();
+        for i in 0..5 {
+            Image::blank_with_colour(Dim::square(8), Colour::rgb(i * 40, 0, 0))
+                .save(dir.join(format!("in/{i}.png")))
+                .unwrap();
+        }
+        std::fs::write(dir.join("in/broken.png"), b"not a png").unwrap();
+        std::fs::write(dir.join("in/notes.txt"), b"skipped").unwrap();
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let options = BatchOptions::new(dir.join("out"))
+            .with_extension("tga")
+            .with_threads(3)
+            .on_progress(move |p| {
+                assert_eq!(p.total, 6);
+                counter.fetch_add(1, Ordering::Relaxed);
+            });
+        let report = process(dir.join("in"), &options, |img| {
+            img.resize(Dim::square(2), crate::ResizeFilter::Nearest)
+        })
+        .unwrap();
+
+        assert_eq!(calls.load(Ordering::Relaxed), 6);
+        assert_eq!(report.failed.len(), 1);
+        assert!(report.failed[0].0.ends_with("broken.png"));
+        assert_eq!(report.succeeded.len(), 5);
+        let (input, output) = &report.succeeded[3];
+        assert!(input.ends_with("3.png") && output.ends_with("out/3.tga"));
+        let thumbnail = Image::open(output).unwrap();
+        assert_eq!(thumbnail.get_dimensions(), Dim::square(2));
+        assert_eq!(
+            thumbnail.get_pixel(Loc { x: 1, y: 1 }).unwrap(),
+            Colour::rgb(120, 0, 0)
+        );
+    }
+
+    #[test]
+    fn cancels_remaining_files() {
+        let dir = std::env::temp_dir().join("kodak_batch_cancel");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("in")).unwrap();
+        let inputs: Vec<PathBuf> = (0..4).map(|i| dir.join(format!("in/{i}.png"))).collect();
+        for input in &inputs {
+            Image::blank(Dim::square(2)).save(input).unwrap();
+        }
+
+        let cancel = CancelToken::new();
+        let handle = cancel.clone();
+        let fractions = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&fractions);
+        let options = BatchOptions::new(dir.join("out"))
+            .with_threads(1)
+            .with_cancel(cancel)
+            .on_progress(move |p| {
+                seen.lock().unwrap().push(p.fraction());
+                if p.done == 2 {
+                    handle.cancel();
+                }
+            });
+        let report = process(inputs.clone(), &options, |img| img).unwrap();
+
+        assert_eq!(*fractions.lock().unwrap(), [0.25, 0.5]);
+        assert_eq!(report.succeeded.len(), 2);
+        assert_eq!(report.cancelled, inputs[2..]);
+        assert!(!report.is_success());
+        assert!(!dir.join("out/2.png").exists());
+    }
+
+    #[test]
+    fn rejects_clashing_outputs() {
+        let dir = std::env::temp_dir().join("kodak_batch_clash");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("in")).unwrap();
+        let inputs: Vec<PathBuf> = ["a.png", "a.tga", "b.png"]
+            .iter()
+            .map(|name| dir.join("in").join(name))
+            .collect();
+        for input in &inputs {
+            Image::blank(Dim::square(2)).save(input).unwrap();
+        }
+
+        let options = BatchOptions::new(dir.join("out")).with_extension("png");
+        let report = process(inputs.clone(), &options, |img| img).unwrap();
+
+        let failed: Vec<&PathBuf> = report.failed.iter().map(|(input, _)| input).collect();
+        assert_eq!(failed, [&inputs[0], &inputs[1]]);
+        assert_eq!(report.failed[0].1.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(
+            report.succeeded,
+            [(inputs[2].clone(), dir.join("out/b.png"))]
+        );
+        assert!(!dir.join("out/a.png").exists());
+    }
+}
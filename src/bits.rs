@@ -0,0 +1,162 @@
+//! Looking at and rearranging the individual bits and channels of pixels, for forensics and
+//! steganalysis.
+
+use crate::{Colour, Image, Mask};
+
+/// One of the four channels of a colour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    /// The red channel.
+    Red,
+    /// The green channel.
+    Green,
+    /// The blue channel.
+    Blue,
+    /// The alpha channel.
+    Alpha,
+}
+
+impl Channel {
+    /// Returns the value of this channel of `c`.
+    fn get(self, c: &Colour) -> u8 {
+        match self {
+            Channel::Red => c.r,
+            Channel::Green => c.g,
+            Channel::Blue => c.b,
+            Channel::Alpha => c.a,
+        }
+    }
+
+    /// Returns this channel of `c` for changing.
+    fn get_mut(self, c: &mut Colour) -> &mut u8 {
+        match self {
+            Channel::Red => &mut c.r,
+            Channel::Green => &mut c.g,
+            Channel::Blue => &mut c.b,
+            Channel::Alpha => &mut c.a,
+        }
+    }
+}
+
+// The following impl block defines bit and channel manipulation functions for Images.
+impl Image {
+    /// Selects the pixels in which bit `bit` of `channel` is set, where bit 0 is the least
+    /// significant and bit 7 the most.
+    ///
+    /// The low bit planes of a photo look like noise; structure in them gives away edits and
+    /// data hidden with `embed_bytes()`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bit` is 8 or more, as does `set_bit_plane()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let img = Image::blank_with_colour(Dim::square(2), Colour::rgb(5, 0, 0));
+    /// assert!(img.bit_plane(Channel::Red, 2).contains(Loc { x: 0, y: 0 }));
+    /// assert!(!img.bit_plane(Channel::Red, 1).contains(Loc { x: 0, y: 0 }));
+    /// ```
+    pub fn bit_plane(&self, channel: Channel, bit: u8) -> Mask {
+        assert!(bit < 8, "A channel only has bits 0 to 7.");
+        Mask::from_fn(self.get_dimensions(), |loc| {
+            let value = channel.get(&self.pixels[loc.as_index(self.get_dimensions())]);
+            if value >> bit & 1 == 1 {
+                255
+            } else {
+                0
+            }
+        })
+    }
+
+    /// Sets bit `bit` of `channel` in the pixels that `mask` selects, as decided by
+    /// `Mask::contains()`, and clears it in all others.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mask` differs in size from the image.
+    pub fn set_bit_plane(mut self, channel: Channel, bit: u8, mask: &Mask) -> Image {
+        assert!(bit < 8, "A channel only has bits 0 to 7.");
+        assert_eq!(self.get_dimensions(), mask.get_dimensions());
+        for (c, &m) in self.pixels.iter_mut().zip(mask.values()) {
+            let value = channel.get_mut(c);
+            if m >= 128 {
+                *value |= 1 << bit;
+            } else {
+                *value &= !(1 << bit);
+            }
+        }
+        self
+    }
+
+    /// Exchanges two channels, which fixes images whose red and blue were mixed up, as happens
+    /// with BGR data.
+    pub fn swap_channels(mut self, a: Channel, b: Channel) -> Image {
+        for c in self.pixels.iter_mut() {
+            let (x, y) = (a.get(c), b.get(c));
+            *a.get_mut(c) = y;
+            *b.get_mut(c) = x;
+        }
+        self
+    }
+
+    /// Moves the red channel into green, green into blue and blue into red, which shifts every
+    /// hue by a third of the colour wheel. Alpha stays where it is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let img = Image::blank_with_colour(Dim::square(1), Colour::rgb(10, 20, 30));
+    /// let rotated = img.rotate_channels();
+    /// assert_eq!(rotated.get_pixel(Loc { x: 0, y: 0 }), Ok(Colour::rgb(30, 10, 20)));
+    /// ```
+    pub fn rotate_channels(mut self) -> Image {
+        for c in self.pixels.iter_mut() {
+            (c.r, c.g, c.b) = (c.b, c.r, c.g);
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod bits_tests {
+    use super::*;
+    use crate::{Dim, Loc};
+
+    #[test]
+    fn bit_planes_round_trip() {
+        let mut img = Image::blank(Dim { w: 16, h: 16 });
+        for (i, c) in img.pixels.iter_mut().enumerate() {
+            *c = Colour::rgba(i as u8, 255 - i as u8, 0, 200);
+        }
+        for bit in 0..8 {
+            let plane = img.bit_plane(Channel::Green, bit);
+            let cleared =
+                img.clone()
+                    .set_bit_plane(Channel::Green, bit, &Mask::new(Dim::square(16)));
+            assert_eq!(
+                cleared.clone().set_bit_plane(Channel::Green, bit, &plane),
+                img
+            );
+            assert!(cleared
+                .bit_plane(Channel::Green, bit)
+                .values()
+                .iter()
+                .all(|&v| v == 0));
+        }
+        let top = img.bit_plane(Channel::Red, 7);
+        assert!(top.contains(Loc { x: 0, y: 8 }) && !top.contains(Loc { x: 15, y: 7 }));
+
+        let swapped = img.clone().swap_channels(Channel::Red, Channel::Alpha);
+        assert_eq!(swapped.pixels[3], Colour::rgba(200, 252, 0, 3));
+        assert_eq!(swapped.swap_channels(Channel::Alpha, Channel::Red), img);
+        let rotated = img
+            .clone()
+            .rotate_channels()
+            .rotate_channels()
+            .rotate_channels();
+        assert_eq!(rotated, img);
+    }
+}
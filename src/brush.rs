@@ -0,0 +1,315 @@
+//! Brushes that paint by stamping their tip along a path, as in a paint program.
+
+use crate::{Colour, Dim, Image, Loc, ResizeFilter};
+
+/// The shape of a brush's tip.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BrushTip {
+    /// A circle.
+    Round,
+    /// An upright square.
+    Square,
+    /// The alpha channel of an image, stretched so that its longer side is the brush's size.
+    /// Opaque pixels paint fully and transparent ones not at all.
+    Image(Image),
+}
+
+/// A brush for `Image::paint_stroke()`.
+///
+/// # Examples
+///
+/// ```
+/// # use kodak::*;
+/// // An airbrush: large, soft and closely spaced.
+/// let airbrush = Brush::round(24.0).with_hardness(0.0).with_spacing(0.1);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Brush {
+    /// The shape of the tip.
+    pub tip: BrushTip,
+    /// The width of the tip in pixels.
+    pub size: f32,
+    /// How far from the centre round and square tips keep their full strength before fading
+    /// out towards their edge, from 0.0 (fading all the way from the centre) to 1.0 (a crisp,
+    /// anti-aliased edge). Image tips bring their own edges and ignore this.
+    pub hardness: f32,
+    /// The distance between stamps as a fraction of the size. Small values give smooth strokes;
+    /// values above 1.0 leave gaps between the stamps.
+    pub spacing: f32,
+}
+
+impl Brush {
+    /// Creates a hard round brush with stamps a quarter of its size apart.
+    pub fn round(size: f32) -> Self {
+        Brush {
+            tip: BrushTip::Round,
+            size,
+            hardness: 1.0,
+            spacing: 0.25,
+        }
+    }
+
+    /// Creates a hard square brush with stamps a quarter of its size apart.
+    pub fn square(size: f32) -> Self {
+        Brush {
+            tip: BrushTip::Square,
+            ..Brush::round(size)
+        }
+    }
+
+    /// Creates a brush whose tip is the alpha channel of `tip`, scaled to `size` pixels across.
+    pub fn from_image(tip: Image, size: f32) -> Self {
+        Brush {
+            tip: BrushTip::Image(tip),
+            ..Brush::round(size)
+        }
+    }
+
+    /// Sets the hardness, from 0.0 (soft) to 1.0 (hard).
+    pub fn with_hardness(self, hardness: f32) -> Self {
+        Brush { hardness, ..self }
+    }
+
+    /// Sets the distance between stamps, as a fraction of the size.
+    pub fn with_spacing(self, spacing: f32) -> Self {
+        Brush { spacing, ..self }
+    }
+}
+
+/// A brush tip ready to be stamped, with an image tip already scaled to size.
+enum Stamp {
+    Round { radius: f32, hardness: f32 },
+    Square { radius: f32, hardness: f32 },
+    Image(Image),
+}
+
+impl Stamp {
+    fn new(brush: &Brush) -> Stamp {
+        let radius = brush.size / 2.0;
+        let hardness = brush.hardness.clamp(0.0, 1.0);
+        match &brush.tip {
+            BrushTip::Round => Stamp::Round { radius, hardness },
+            BrushTip::Square => Stamp::Square { radius, hardness },
+            BrushTip::Image(tip) => {
+                let (w, h) = (tip.width.max(1) as f32, tip.height.max(1) as f32);
+                let scale = brush.size / w.max(h);
+                let dim = Dim {
+                    w: (w * scale).round().max(1.0) as u32,
+                    h: (h * scale).round().max(1.0) as u32,
+                };
+                Stamp::Image(tip.resize(dim, ResizeFilter::Bilinear))
+            }
+        }
+    }
+
+    /// Returns how far the stamp reaches from its centre, in pixels.
+    fn reach(&self) -> f32 {
+        match self {
+            Stamp::Round { radius, .. } => *radius,
+            Stamp::Square { radius, .. } => radius * core::f32::consts::SQRT_2,
+            Stamp::Image(tip) => tip.width.max(tip.height) as f32 / 2.0 * core::f32::consts::SQRT_2,
+        }
+    }
+
+    /// Returns the strength of the stamp, from 0.0 to 1.0, at `(dx, dy)` from its centre.
+    fn strength(&self, dx: f32, dy: f32) -> f32 {
+        // Fades from full strength at `hardness * radius` to nothing at `radius`, smoothly, and
+        // always anti-aliases the very edge.
+        let falloff = |distance: f32, radius: f32, hardness: f32| {
+            let edge = (radius + 0.5 - distance).clamp(0.0, 1.0);
+            let inner = radius * hardness;
+            if distance <= inner || hardness >= 1.0 {
+                return edge;
+            }
+            let t = ((distance - inner) / (radius - inner)).clamp(0.0, 1.0);
+            edge.min(1.0 - t * t * (3.0 - 2.0 * t))
+        };
+        match self {
+            &Stamp::Round { radius, hardness } => falloff(dx.hypot(dy), radius, hardness),
+            &Stamp::Square { radius, hardness } => {
+                falloff(dx.abs().max(dy.abs()), radius, hardness)
+            }
+            Stamp::Image(tip) => {
+                let x = dx + tip.width as f32 / 2.0;
+                let y = dy + tip.height as f32 / 2.0;
+                tip.sample_bilinear(x, y)
+                    .map_or(0.0, |c| c.a as f32 / 255.0)
+            }
+        }
+    }
+}
+
+/// Returns the centres of the stamps along a polyline through the centres of the pixels at
+/// `points`, `step` pixels apart, starting at the first point.
+fn stamp_centres(points: &[Loc], step: f32) -> Vec<(f32, f32)> {
+    let centre = |l: &Loc| (l.x as f32 + 0.5, l.y as f32 + 0.5);
+    let Some(first) = points.first() else {
+        return Vec::new();
+    };
+    let mut centres = vec![centre(first)];
+    // How far along the current segment the next stamp falls.
+    let mut next = step;
+    for segment in points.windows(2) {
+        let (a, b) = (centre(&segment[0]), centre(&segment[1]));
+        let length = (b.0 - a.0).hypot(b.1 - a.1);
+        while next <= length {
+            let t = next / length;
+            centres.push((a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t));
+            next += step;
+        }
+        next -= length;
+    }
+    centres
+}
+
+// The following impl block defines brush painting functions for Images.
+impl Image {
+    /// Paints `colour` with `brush` along the polyline through the pixels at `points`, by
+    /// stamping the brush's tip every `brush.spacing * brush.size` pixels.
+    ///
+    /// Overlapping stamps of one stroke do not build up: every pixel gets the strongest stamp
+    /// that touches it, so a translucent colour paints an even stroke. Paint a point with a
+    /// one-element slice.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let path = [Loc { x: 8, y: 8 }, Loc { x: 56, y: 8 }, Loc { x: 56, y: 56 }];
+    /// let img = Image::blank_with_colour(Dim::square(64), Colour::WHITE)
+    ///     .paint_stroke(&path, &Brush::round(6.0), Colour::rgb(200, 0, 0));
+    /// assert_eq!(img.get_pixel(Loc { x: 30, y: 8 }), Ok(Colour::rgb(200, 0, 0)));
+    /// assert_eq!(img.get_pixel(Loc { x: 30, y: 30 }), Ok(Colour::WHITE));
+    /// ```
+    pub fn paint_stroke(mut self, points: &[Loc], brush: &Brush, colour: Colour) -> Image {
+        if !brush.size.is_finite() || brush.size <= 0.0 || self.pixels.is_empty() {
+            return self;
+        }
+        let stamp = Stamp::new(brush);
+        let step = (brush.spacing * brush.size).max(0.5);
+        let centres = stamp_centres(points, step);
+        if centres.is_empty() {
+            return self;
+        }
+
+        let reach = stamp.reach() + 1.0;
+        let (w, h) = (self.width as i64, self.height as i64);
+        let clamp = |v: f32, max: i64| (v.floor() as i64).clamp(0, max);
+        let (lo, hi) = centres.iter().fold(
+            ((f32::MAX, f32::MAX), (f32::MIN, f32::MIN)),
+            |(lo, hi), &(x, y)| ((lo.0.min(x), lo.1.min(y)), (hi.0.max(x), hi.1.max(y))),
+        );
+        let (x0, x1) = (clamp(lo.0 - reach, w), clamp(hi.0 + reach + 1.0, w));
+        let (y0, y1) = (clamp(lo.1 - reach, h), clamp(hi.1 + reach + 1.0, h));
+        if x0 >= x1 || y0 >= y1 {
+            return self;
+        }
+
+        let span = (x1 - x0) as usize;
+        let mut strength = vec![0.0f32; span * (y1 - y0) as usize];
+        for &(cx, cy) in &centres {
+            let (sx0, sx1) = (
+                clamp(cx - reach, w).max(x0),
+                clamp(cx + reach + 1.0, w).min(x1),
+            );
+            let (sy0, sy1) = (
+                clamp(cy - reach, h).max(y0),
+                clamp(cy + reach + 1.0, h).min(y1),
+            );
+            for y in sy0..sy1 {
+                for x in sx0..sx1 {
+                    let s = stamp.strength(x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+                    let cell = &mut strength[(y - y0) as usize * span + (x - x0) as usize];
+                    *cell = cell.max(s);
+                }
+            }
+        }
+
+        for y in y0..y1 {
+            let row = &mut self.row_mut(y as u32)[x0 as usize..x1 as usize];
+            let strengths = &strength[(y - y0) as usize * span..][..span];
+            for (pixel, &s) in row.iter_mut().zip(strengths) {
+                if s > 0.0 {
+                    let alpha = (colour.a as f32 * s).round() as u8;
+                    *pixel = Colour { a: alpha, ..colour }.over(*pixel);
+                }
+            }
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod brush_tests {
+    use super::*;
+
+    #[test]
+    fn spaces_stamps() {
+        let path = [Loc { x: 0, y: 0 }, Loc { x: 10, y: 0 }, Loc { x: 10, y: 5 }];
+        let centres = stamp_centres(&path, 4.0);
+        assert_eq!(centres, [(0.5, 0.5), (4.5, 0.5), (8.5, 0.5), (10.5, 2.5)]);
+        assert_eq!(stamp_centres(&path[..1], 4.0), [(0.5, 0.5)]);
+        assert!(stamp_centres(&[], 4.0).is_empty());
+    }
+
+    #[test]
+    fn paints_with_tips() {
+        let canvas = Image::blank_with_colour(Dim::square(32), Colour::WHITE);
+        let path = [Loc { x: 4, y: 16 }, Loc { x: 28, y: 16 }];
+        let at = |img: &Image, x, y| img.get_pixel(Loc { x, y }).unwrap();
+
+        let hard = canvas
+            .clone()
+            .paint_stroke(&path, &Brush::round(8.0), Colour::BLACK);
+        assert_eq!(
+            (at(&hard, 16, 13), at(&hard, 16, 11)),
+            (Colour::BLACK, Colour::WHITE)
+        );
+        // A translucent colour does not build up where stamps overlap.
+        let half = Colour::rgba(0, 0, 0, 128);
+        let glaze = canvas.clone().paint_stroke(&path, &Brush::round(8.0), half);
+        assert_eq!(at(&glaze, 16, 16), at(&glaze, 17, 16));
+        assert_eq!(at(&glaze, 16, 16), half.over(Colour::WHITE));
+
+        let soft = Brush::round(8.0).with_hardness(0.0);
+        let soft = canvas.clone().paint_stroke(&path, &soft, Colour::BLACK);
+        let (centre, edge) = (at(&soft, 16, 16), at(&soft, 16, 13));
+        assert_eq!(centre, Colour::BLACK);
+        assert!(edge.r > 0 && edge.r < 255);
+
+        let square = canvas
+            .clone()
+            .paint_stroke(&path[..1], &Brush::square(8.0), Colour::BLACK);
+        assert_eq!(
+            (at(&square, 1, 13), at(&square, 7, 19)),
+            (Colour::BLACK, Colour::BLACK)
+        );
+        assert_eq!(at(&square, 9, 16), Colour::WHITE);
+
+        // A tip that is only opaque on its left half.
+        let mut tip = Image::blank_with_colour(Dim { w: 4, h: 4 }, Colour::TRANSPARENT);
+        for y in 0..4 {
+            tip.row_mut(y)[..2].fill(Colour::BLACK);
+        }
+        let stamped = canvas.clone().paint_stroke(
+            &[Loc { x: 16, y: 16 }],
+            &Brush::from_image(tip, 8.0),
+            Colour::rgb(0, 0, 255),
+        );
+        assert_eq!(at(&stamped, 13, 16), Colour::rgb(0, 0, 255));
+        assert_eq!(at(&stamped, 19, 16), Colour::WHITE);
+
+        let gaps = Brush::round(4.0).with_spacing(3.0);
+        let dotted = canvas.clone().paint_stroke(&path, &gaps, Colour::BLACK);
+        assert_eq!(
+            (at(&dotted, 4, 16), at(&dotted, 10, 16)),
+            (Colour::BLACK, Colour::WHITE)
+        );
+        assert_eq!(
+            canvas
+                .clone()
+                .paint_stroke(&path, &Brush::round(0.0), Colour::BLACK),
+            canvas
+        );
+    }
+}
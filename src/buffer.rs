@@ -0,0 +1,63 @@
+//! The shared, copy-on-write pixel storage behind `Image`.
+
+use crate::Colour;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::ops::{Deref, DerefMut};
+
+/// The pixels of an image in row-major order.
+///
+/// Cloning only bumps a reference count, so an image can be kept around while variants are
+/// derived from it. Mutable access goes through `DerefMut`, which copies the pixels first if
+/// they are shared with another image.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub(crate) struct Pixels(Arc<Vec<Colour>>);
+
+impl Pixels {
+    /// Returns whether both buffers are the same allocation.
+    #[cfg(test)]
+    pub(crate) fn ptr_eq(&self, other: &Pixels) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Deref for Pixels {
+    type Target = [Colour];
+
+    fn deref(&self) -> &[Colour] {
+        &self.0
+    }
+}
+
+impl DerefMut for Pixels {
+    fn deref_mut(&mut self) -> &mut [Colour] {
+        Arc::make_mut(&mut self.0).as_mut_slice()
+    }
+}
+
+impl From<Vec<Colour>> for Pixels {
+    fn from(pixels: Vec<Colour>) -> Self {
+        Pixels(Arc::new(pixels))
+    }
+}
+
+impl FromIterator<Colour> for Pixels {
+    fn from_iter<I: IntoIterator<Item = Colour>>(iter: I) -> Self {
+        Pixels::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl PartialEq<Vec<Colour>> for Pixels {
+    fn eq(&self, other: &Vec<Colour>) -> bool {
+        **self == other[..]
+    }
+}
+
+impl<'a> IntoIterator for &'a Pixels {
+    type Item = &'a Colour;
+    type IntoIter = core::slice::Iter<'a, Colour>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
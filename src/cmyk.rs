@@ -0,0 +1,116 @@
+//! Conversions to and from CMYK, the inks of four-colour printing.
+
+use crate::{Colour, Image};
+
+/// Divides by 255, rounding to nearest.
+fn div_255(value: u32) -> u8 {
+    ((value + 127) / 255) as u8
+}
+
+// The following impl block defines CMYK conversion functions for Colours.
+impl Colour {
+    /// Converts the colour to the cyan, magenta, yellow and black ink needed to print it, each
+    /// from 0 (no ink) to 255 (full coverage). Alpha is ignored.
+    ///
+    /// This is the naive conversion without a printer profile: black ink covers as much as
+    /// possible and the coloured inks make up the rest, so greys are printed with black alone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// assert_eq!(Colour::rgb(255, 0, 0).to_cmyk(), [0, 255, 255, 0]);
+    /// assert_eq!(Colour::rgb(128, 128, 128).to_cmyk(), [0, 0, 0, 127]);
+    /// assert_eq!(Colour::from_cmyk([0, 255, 255, 0]), Colour::rgb(255, 0, 0));
+    /// ```
+    pub fn to_cmyk(&self) -> [u8; 4] {
+        let max = self.r.max(self.g).max(self.b) as u32;
+        if max == 0 {
+            return [0, 0, 0, 255];
+        }
+        let ink = |channel: u8| (((max - channel as u32) * 255 + max / 2) / max) as u8;
+        [ink(self.r), ink(self.g), ink(self.b), 255 - max as u8]
+    }
+
+    /// Creates an opaque colour from cyan, magenta, yellow and black ink coverage, each from 0
+    /// to 255, undoing `to_cmyk()`.
+    pub fn from_cmyk(cmyk: [u8; 4]) -> Colour {
+        let [c, m, y, k] = cmyk.map(|ink| 255 - ink as u32);
+        Colour::rgb(div_255(c * k), div_255(m * k), div_255(y * k))
+    }
+}
+
+// The following impl block defines colour separation functions for Images.
+impl Image {
+    /// Splits the image into its cyan, magenta, yellow and black printing plates, in that
+    /// order, as converted by `Colour::to_cmyk()`.
+    ///
+    /// Each plate is a greyscale image of the same size in which white is no ink and black is
+    /// full coverage, the way separations are proofed. Translucent pixels are composited onto
+    /// white paper first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let img = Image::blank_with_colour(Dim::square(2), Colour::rgb(0, 255, 255));
+    /// let [cyan, magenta, yellow, black] = img.separations();
+    /// assert_eq!(cyan.get_pixel(Loc { x: 0, y: 0 }), Ok(Colour::BLACK));
+    /// assert_eq!(black.get_pixel(Loc { x: 0, y: 0 }), Ok(Colour::WHITE));
+    /// ```
+    pub fn separations(&self) -> [Image; 4] {
+        core::array::from_fn(|plate| {
+            let pixels = self
+                .pixels
+                .iter()
+                .map(|c| {
+                    let value = 255 - c.over(Colour::WHITE).to_cmyk()[plate];
+                    Colour::rgb(value, value, value)
+                })
+                .collect();
+            Image {
+                pixels,
+                ..self.clone()
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod cmyk_tests {
+    use super::*;
+    use crate::{Dim, Loc};
+
+    #[test]
+    fn round_trips() {
+        for r in (0..=255).step_by(5) {
+            for g in (0..=255).step_by(15) {
+                for b in (0..=255).step_by(17) {
+                    let colour = Colour::rgb(r, g, b);
+                    assert_eq!(Colour::from_cmyk(colour.to_cmyk()), colour);
+                }
+            }
+        }
+        assert_eq!(Colour::BLACK.to_cmyk(), [0, 0, 0, 255]);
+        assert_eq!(Colour::WHITE.to_cmyk(), [0, 0, 0, 0]);
+        assert_eq!(Colour::from_cmyk([255, 255, 255, 255]), Colour::BLACK);
+    }
+
+    #[test]
+    fn separates_plates() {
+        let img = Image::blank_with_colour(Dim { w: 2, h: 1 }, Colour::rgba(0, 0, 0, 0));
+        let img = img.fill_region(
+            crate::Region {
+                l: Loc { x: 1, y: 0 },
+                d: Dim::square(1),
+            },
+            Colour::rgb(255, 255, 0),
+        );
+        let plates = img.separations();
+        let at = |plate: usize, x| plates[plate].get_pixel(Loc { x, y: 0 }).unwrap();
+        // The transparent pixel is bare paper on every plate.
+        assert!((0..4).all(|plate| at(plate, 0) == Colour::WHITE));
+        assert_eq!([at(0, 1), at(1, 1), at(3, 1)], [Colour::WHITE; 3]);
+        assert_eq!(at(2, 1), Colour::BLACK);
+    }
+}
@@ -0,0 +1,412 @@
+//! Colour science utilities: luminance, contrast and conversions.
+
+#[cfg(not(any(feature = "std", test)))]
+use crate::math::Float;
+use crate::Colour;
+use core::ops::{Add, Mul, Sub};
+use core::str::FromStr;
+
+/// Converts an 8-bit sRGB channel value to linear light in `0.0..=1.0`.
+pub(crate) fn srgb_to_linear(channel: u8) -> f32 {
+    let c = channel as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts linear light in `0.0..=1.0` to an 8-bit sRGB channel value.
+pub(crate) fn linear_to_srgb(value: f32) -> u8 {
+    let v = value.clamp(0.0, 1.0);
+    let c = if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    };
+    (c * 255.0).round() as u8
+}
+
+/// Converts a colour to Oklab coordinates `[L, a, b]`.
+#[allow(clippy::excessive_precision)] // the published matrix coefficients
+pub(crate) fn to_oklab(colour: Colour) -> [f32; 3] {
+    let r = srgb_to_linear(colour.r);
+    let g = srgb_to_linear(colour.g);
+    let b = srgb_to_linear(colour.b);
+
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+
+    [
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    ]
+}
+
+/// Converts Oklab coordinates `[L, a, b]` back to a colour, clamping out-of-gamut values.
+#[allow(clippy::excessive_precision)] // the published matrix coefficients
+pub(crate) fn from_oklab(lab: [f32; 3]) -> Colour {
+    let l = (lab[0] + 0.3963377774 * lab[1] + 0.2158037573 * lab[2]).powi(3);
+    let m = (lab[0] - 0.1055613458 * lab[1] - 0.0638541728 * lab[2]).powi(3);
+    let s = (lab[0] - 0.0894841775 * lab[1] - 1.2914855480 * lab[2]).powi(3);
+
+    Colour::rgb(
+        linear_to_srgb(4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s),
+        linear_to_srgb(-1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s),
+        linear_to_srgb(-0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s),
+    )
+}
+
+/// Multiplies two 8-bit values as if they were fractions of 255, rounding to nearest.
+pub(crate) fn mul_u8(a: u8, b: u8) -> u8 {
+    ((a as u32 * b as u32 + 127) / 255) as u8
+}
+
+// The following impl block defines alpha compositing functions for Colours.
+impl Colour {
+    /// Converts a colour with straight alpha into premultiplied form, where the colour channels
+    /// have already been scaled by alpha.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let half_white = Colour::rgba(255, 255, 255, 128);
+    /// assert_eq!(half_white.premultiply(), Colour::rgba(128, 128, 128, 128));
+    /// ```
+    pub fn premultiply(self) -> Colour {
+        Colour {
+            r: mul_u8(self.r, self.a),
+            g: mul_u8(self.g, self.a),
+            b: mul_u8(self.b, self.a),
+            a: self.a,
+        }
+    }
+
+    /// Converts a premultiplied colour back into straight alpha.
+    ///
+    /// Fully transparent colours become `Colour::TRANSPARENT`, since their colour is unknown.
+    pub fn unpremultiply(self) -> Colour {
+        if self.a == 0 {
+            return Colour::TRANSPARENT;
+        }
+        let divide = |c: u8| ((c as u32 * 255 + self.a as u32 / 2) / self.a as u32).min(255) as u8;
+        Colour {
+            r: divide(self.r),
+            g: divide(self.g),
+            b: divide(self.b),
+            a: self.a,
+        }
+    }
+
+    /// Composites this colour on top of `background` using the Porter-Duff "over" operator.
+    ///
+    /// The maths is carried out on premultiplied values, so repeated blending does not build up
+    /// dark fringes around translucent edges.
+    pub fn over(self, background: Colour) -> Colour {
+        if self.a == 255 {
+            return self;
+        }
+        if self.a == 0 {
+            return background;
+        }
+
+        let src = self.premultiply();
+        let dst = background.premultiply();
+        let inverse = 255 - src.a;
+        Colour {
+            r: src.r + mul_u8(dst.r, inverse),
+            g: src.g + mul_u8(dst.g, inverse),
+            b: src.b + mul_u8(dst.b, inverse),
+            a: src.a + mul_u8(dst.a, inverse),
+        }
+        .unpremultiply()
+    }
+}
+
+// The following impl block defines accessibility-related functions for Colours.
+impl Colour {
+    /// Returns the relative luminance of the colour as defined by WCAG 2.x.
+    ///
+    /// The result lies between 0.0 (black) and 1.0 (white). The alpha channel is ignored.
+    pub fn relative_luminance(&self) -> f32 {
+        0.2126 * srgb_to_linear(self.r)
+            + 0.7152 * srgb_to_linear(self.g)
+            + 0.0722 * srgb_to_linear(self.b)
+    }
+
+    /// Returns the WCAG contrast ratio between two colours.
+    ///
+    /// The ratio ranges from 1.0 (no contrast) to 21.0 (black on white) and does not depend on
+    /// the order of the colours. WCAG AA asks for at least 4.5 for body text.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let ratio = Colour::BLACK.contrast_ratio(&Colour::WHITE);
+    /// assert!((ratio - 21.0).abs() < 1e-4);
+    /// ```
+    pub fn contrast_ratio(&self, other: &Colour) -> f32 {
+        let a = self.relative_luminance();
+        let b = other.relative_luminance();
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+
+    /// Picks black or white, whichever is more readable as a text colour on this background.
+    pub fn readable_text_colour(&self) -> Colour {
+        if self.contrast_ratio(&Colour::BLACK) >= self.contrast_ratio(&Colour::WHITE) {
+            Colour::BLACK
+        } else {
+            Colour::WHITE
+        }
+    }
+}
+
+// The following impl block defines interpolation functions for Colours.
+impl Colour {
+    /// Linearly interpolates between two colours (including alpha) in sRGB space.
+    ///
+    /// `t` is clamped to `0.0..=1.0`; 0.0 returns `self` and 1.0 returns `other`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let grey = Colour::BLACK.lerp(Colour::WHITE, 0.5);
+    /// assert_eq!(grey, Colour::rgb(128, 128, 128));
+    /// ```
+    pub fn lerp(self, other: Colour, t: f32) -> Colour {
+        let t = t.clamp(0.0, 1.0);
+        let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Colour {
+            r: channel(self.r, other.r),
+            g: channel(self.g, other.g),
+            b: channel(self.b, other.b),
+            a: channel(self.a, other.a),
+        }
+    }
+
+    /// Interpolates between two colours in the Oklab colour space.
+    ///
+    /// Oklab is perceptually uniform, so midpoints keep their brightness and saturation instead
+    /// of turning muddy as they can with `lerp()`. Alpha is interpolated linearly.
+    pub fn lerp_oklab(self, other: Colour, t: f32) -> Colour {
+        let t = t.clamp(0.0, 1.0);
+        let a = to_oklab(self);
+        let b = to_oklab(other);
+        let alpha = (self.a as f32 + (other.a as f32 - self.a as f32) * t).round() as u8;
+        Colour {
+            a: alpha,
+            ..from_oklab([
+                a[0] + (b[0] - a[0]) * t,
+                a[1] + (b[1] - a[1]) * t,
+                a[2] + (b[2] - a[2]) * t,
+            ])
+        }
+    }
+
+    /// Mixes two colours, where `weight` is the proportion of `self` in the result.
+    ///
+    /// This mirrors the `mix()` function found in CSS preprocessors: a weight of 0.75 gives a
+    /// colour that is three quarters `self` and one quarter `other`.
+    pub fn mix(self, other: Colour, weight: f32) -> Colour {
+        other.lerp(self, weight)
+    }
+}
+
+// The following impl block defines packed integer conversions for Colours.
+impl Colour {
+    /// Creates a Colour from a packed `0xRRGGBB` integer. The top eight bits are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let orange = Colour::from_rgb_u32(0xFF8000);
+    /// assert_eq!(orange, Colour::rgb(255, 128, 0));
+    /// assert_eq!(orange.to_u32(), 0xFF8000);
+    /// ```
+    pub fn from_rgb_u32(packed: u32) -> Self {
+        let [_, r, g, b] = packed.to_be_bytes();
+        Colour::rgb(r, g, b)
+    }
+
+    /// Creates a Colour from a packed `0xRRGGBBAA` integer.
+    pub fn from_rgba_u32(packed: u32) -> Self {
+        let [r, g, b, a] = packed.to_be_bytes();
+        Colour::rgba(r, g, b, a)
+    }
+
+    /// Packs the colour into a `0xRRGGBB` integer, discarding alpha.
+    pub fn to_u32(&self) -> u32 {
+        u32::from_be_bytes([0, self.r, self.g, self.b])
+    }
+
+    /// Packs the colour into a `0xRRGGBBAA` integer.
+    pub fn to_rgba_u32(&self) -> u32 {
+        u32::from_be_bytes([self.r, self.g, self.b, self.a])
+    }
+}
+
+/// Parses a colour written as `#RGB`, `#RRGGBB` or `#RRGGBBAA` (the `#` is optional), or as
+/// `black`, `white` or `transparent`.
+///
+/// # Examples
+///
+/// ```
+/// # use kodak::*;
+/// assert_eq!("#ff8000".parse(), Ok(Colour::rgb(255, 128, 0)));
+/// assert_eq!("#f80".parse(), Ok(Colour::rgb(255, 136, 0)));
+/// assert_eq!("White".parse(), Ok(Colour::WHITE));
+/// assert!("orange".parse::<Colour>().is_err());
+/// ```
+impl FromStr for Colour {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const INVALID: &str = "colours are written as #RGB, #RRGGBB or #RRGGBBAA";
+        if s.eq_ignore_ascii_case("black") {
+            return Ok(Colour::BLACK);
+        } else if s.eq_ignore_ascii_case("white") {
+            return Ok(Colour::WHITE);
+        } else if s.eq_ignore_ascii_case("transparent") {
+            return Ok(Colour::TRANSPARENT);
+        }
+
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        // from_str_radix accepts a leading sign, which is not valid here.
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(INVALID);
+        }
+        let value = u32::from_str_radix(hex, 16).map_err(|_| INVALID)?;
+        match hex.len() {
+            3 => {
+                let digit = |shift: u32| ((value >> shift) & 15) as u8 * 17;
+                Ok(Colour::rgb(digit(8), digit(4), digit(0)))
+            }
+            6 => Ok(Colour::from_rgb_u32(value)),
+            8 => Ok(Colour::from_rgba_u32(value)),
+            _ => Err(INVALID),
+        }
+    }
+}
+
+impl Add for Colour {
+    type Output = Self;
+    /// Adds the colour channels of two colours, saturating at 255. The alpha of `self` is kept.
+    fn add(self, rhs: Self) -> Self::Output {
+        Colour {
+            r: self.r.saturating_add(rhs.r),
+            g: self.g.saturating_add(rhs.g),
+            b: self.b.saturating_add(rhs.b),
+            a: self.a,
+        }
+    }
+}
+
+impl Sub for Colour {
+    type Output = Self;
+    /// Subtracts the colour channels of two colours, saturating at 0. The alpha of `self` is kept.
+    fn sub(self, rhs: Self) -> Self::Output {
+        Colour {
+            r: self.r.saturating_sub(rhs.r),
+            g: self.g.saturating_sub(rhs.g),
+            b: self.b.saturating_sub(rhs.b),
+            a: self.a,
+        }
+    }
+}
+
+impl Mul<f32> for Colour {
+    type Output = Self;
+    /// Scales the colour channels by a factor, rounding and clamping the result to `0..=255`.
+    /// Alpha is left untouched.
+    fn mul(self, rhs: f32) -> Self::Output {
+        let scale = |c: u8| (c as f32 * rhs).round().clamp(0.0, 255.0) as u8;
+        Colour {
+            r: scale(self.r),
+            g: scale(self.g),
+            b: scale(self.b),
+            a: self.a,
+        }
+    }
+}
+
+impl Mul for Colour {
+    type Output = Self;
+    /// Multiplies two colours channel by channel (alpha included), treating 255 as 1.0.
+    fn mul(self, rhs: Self) -> Self::Output {
+        Colour {
+            r: mul_u8(self.r, rhs.r),
+            g: mul_u8(self.g, rhs.g),
+            b: mul_u8(self.b, rhs.b),
+            a: mul_u8(self.a, rhs.a),
+        }
+    }
+}
+
+#[cfg(test)]
+mod colour_tests {
+    use super::*;
+
+    #[test]
+    fn contrast_is_symmetric() {
+        let grey = Colour::rgb(119, 119, 119);
+        let ratio = grey.contrast_ratio(&Colour::WHITE);
+        assert_eq!(ratio, Colour::WHITE.contrast_ratio(&grey));
+        assert!((ratio - 4.48).abs() < 0.01);
+    }
+
+    #[test]
+    fn oklab_round_trip() {
+        let colour = Colour::rgb(12, 200, 99);
+        assert_eq!(from_oklab(to_oklab(colour)), colour);
+        assert_eq!(Colour::WHITE.mix(Colour::BLACK, 1.0), Colour::WHITE);
+    }
+
+    #[test]
+    fn packed_round_trip() {
+        let colour = Colour::rgb(0x12, 0x34, 0x56);
+        assert_eq!(Colour::from_rgb_u32(colour.to_u32()), colour);
+        assert_eq!(Colour::from_rgba_u32(0x123456FF), colour);
+        assert_eq!("#123456".parse(), Ok(colour));
+        assert_eq!("123456ff".parse(), Ok(colour));
+        assert_eq!(
+            "#12345680".parse(),
+            Ok(Colour::rgba(0x12, 0x34, 0x56, 0x80))
+        );
+        assert!("#1234".parse::<Colour>().is_err());
+        assert!("+12345".parse::<Colour>().is_err());
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = Colour::rgb(200, 100, 0);
+        let b = Colour::rgb(100, 50, 10);
+        assert_eq!(a + b, Colour::rgb(255, 150, 10));
+        assert_eq!(b - a, Colour::rgb(0, 0, 10));
+        assert_eq!(a * 0.5, Colour::rgb(100, 50, 0));
+        assert_eq!(a * Colour::WHITE, a);
+        assert_eq!(a * Colour::BLACK, Colour::BLACK);
+    }
+
+    #[test]
+    fn alpha_compositing() {
+        let translucent = Colour::rgba(255, 0, 0, 128);
+        assert_eq!(translucent.over(Colour::BLACK), Colour::rgb(128, 0, 0));
+        assert_eq!(Colour::TRANSPARENT.over(Colour::WHITE), Colour::WHITE);
+        assert_eq!(translucent.premultiply().unpremultiply(), translucent);
+    }
+
+    #[test]
+    fn readable_text_colour() {
+        assert_eq!(
+            Colour::rgb(255, 255, 0).readable_text_colour(),
+            Colour::BLACK
+        );
+        assert_eq!(Colour::rgb(0, 0, 128).readable_text_colour(), Colour::WHITE);
+    }
+}
@@ -0,0 +1,306 @@
+//! Colour grading with affine colour matrices, the looks built on them, and anaglyphs.
+
+use crate::{Colour, Image};
+
+/// Sepia toning, as used by CSS.
+const SEPIA: [[f32; 4]; 3] = [
+    [0.393, 0.769, 0.189, 0.0],
+    [0.349, 0.686, 0.168, 0.0],
+    [0.272, 0.534, 0.131, 0.0],
+];
+
+/// A kind of colour vision deficiency, for `Image::simulate_colour_blindness()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColourBlindness {
+    /// No working long-wavelength (red) cones.
+    Protanopia,
+    /// No working medium-wavelength (green) cones, the most common kind.
+    Deuteranopia,
+    /// No working short-wavelength (blue) cones.
+    Tritanopia,
+}
+
+impl ColourBlindness {
+    /// Returns the simulation matrix by Machado, Oliveira and Fernandes (2009) at full severity.
+    fn matrix(self) -> [[f32; 4]; 3] {
+        match self {
+            ColourBlindness::Protanopia => [
+                [0.152286, 1.052583, -0.204868, 0.0],
+                [0.114503, 0.786281, 0.099216, 0.0],
+                [-0.003882, -0.048116, 1.051998, 0.0],
+            ],
+            ColourBlindness::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968, 0.0],
+                [0.280085, 0.672501, 0.047413, 0.0],
+                [-0.011820, 0.042940, 0.968881, 0.0],
+            ],
+            ColourBlindness::Tritanopia => [
+                [1.255528, -0.076749, -0.178779, 0.0],
+                [-0.078411, 0.930809, 0.147602, 0.0],
+                [0.004733, 0.691367, 0.303900, 0.0],
+            ],
+        }
+    }
+}
+
+/// How `Image::anaglyph()` mixes a stereo pair into one image for red/cyan glasses. The modes
+/// trade colour against ghosting and retinal rivalry, where one eye sees a bright colour that the
+/// other does not.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AnaglyphMode {
+    /// The brightness of the left image in red and of the right image in blue. Dark, but with
+    /// the least ghosting.
+    True,
+    /// The brightness of the left image in red and of the right image in green and blue.
+    Grey,
+    /// The red channel of the left image and the green and blue channels of the right image.
+    /// Keeps the most colour, but reds and cyans are only seen by one eye.
+    Colour,
+    /// The brightness of the left image in red and the green and blue channels of the right
+    /// image, a compromise between `Grey` and `Colour`.
+    HalfColour,
+    /// The least-squares projection by Eric Dubois, which keeps colours fairly well with little
+    /// ghosting.
+    #[default]
+    Dubois,
+}
+
+impl AnaglyphMode {
+    /// Returns the 3 by 3 matrices that the left and right images are multiplied by before they
+    /// are added together.
+    fn matrices(self) -> ([[f32; 3]; 3], [[f32; 3]; 3]) {
+        const LUMA: [f32; 3] = [0.299, 0.587, 0.114];
+        const NONE: [f32; 3] = [0.0; 3];
+        match self {
+            AnaglyphMode::True => ([LUMA, NONE, NONE], [NONE, NONE, LUMA]),
+            AnaglyphMode::Grey => ([LUMA, NONE, NONE], [NONE, LUMA, LUMA]),
+            AnaglyphMode::Colour => (
+                [[1.0, 0.0, 0.0], NONE, NONE],
+                [NONE, [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            ),
+            AnaglyphMode::HalfColour => {
+                ([LUMA, NONE, NONE], [NONE, [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]])
+            }
+            AnaglyphMode::Dubois => (
+                [
+                    [0.456, 0.500, 0.176],
+                    [-0.040, -0.038, -0.016],
+                    [-0.015, -0.021, -0.005],
+                ],
+                [
+                    [-0.043, -0.088, -0.002],
+                    [0.378, 0.734, -0.018],
+                    [-0.072, -0.113, 1.226],
+                ],
+            ),
+        }
+    }
+}
+
+/// Multiplies a colour by an affine colour matrix, leaving alpha alone.
+pub(crate) fn transform(colour: Colour, m: &[[f32; 4]; 3]) -> Colour {
+    let (r, g, b) = (colour.r as f32, colour.g as f32, colour.b as f32);
+    let channel = |row: &[f32; 4]| {
+        (row[0] * r + row[1] * g + row[2] * b + row[3] * 255.0)
+            .round()
+            .clamp(0.0, 255.0) as u8
+    };
+    Colour {
+        r: channel(&m[0]),
+        g: channel(&m[1]),
+        b: channel(&m[2]),
+        a: colour.a,
+    }
+}
+
+// The following impl block defines colour matrix functions for Images.
+impl Image {
+    /// Transforms the red, green and blue channels of every pixel with a 3 by 4 affine colour
+    /// matrix. Each row gives one output channel: the new red channel is
+    /// `m[0][0] * r + m[0][1] * g + m[0][2] * b + m[0][3]`, and so on, where the offset in the
+    /// last column is measured in full intensities, so 1.0 adds 255. The results are clamped to
+    /// the range of a channel, and alpha is left alone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// // Swap the red and blue channels, and lift the green channel by a tenth.
+    /// let swapped = Image::blank_with_colour(Dim::square(2), Colour::rgb(200, 100, 50))
+    ///     .apply_colour_matrix([
+    ///         [0.0, 0.0, 1.0, 0.0],
+    ///         [0.0, 1.0, 0.0, 0.1],
+    ///         [1.0, 0.0, 0.0, 0.0],
+    ///     ]);
+    /// assert_eq!(swapped.get_pixel(Loc { x: 0, y: 0 }), Ok(Colour::rgb(50, 126, 200)));
+    /// ```
+    pub fn apply_colour_matrix(mut self, m: [[f32; 4]; 3]) -> Image {
+        for c in self.pixels.iter_mut() {
+            *c = transform(*c, &m);
+        }
+        self
+    }
+
+    /// Gives the image the brown tint of an old photograph.
+    pub fn sepia(self) -> Image {
+        self.apply_colour_matrix(SEPIA)
+    }
+
+    /// Scales the saturation of the colours while keeping their brightness: 0.0 turns the image
+    /// grey, 1.0 leaves it unchanged and larger amounts make the colours more vivid.
+    pub fn saturate(self, amount: f32) -> Image {
+        let s = amount;
+        self.apply_colour_matrix([
+            [0.213 + 0.787 * s, 0.715 - 0.715 * s, 0.072 - 0.072 * s, 0.0],
+            [0.213 - 0.213 * s, 0.715 + 0.285 * s, 0.072 - 0.072 * s, 0.0],
+            [0.213 - 0.213 * s, 0.715 - 0.715 * s, 0.072 + 0.928 * s, 0.0],
+        ])
+    }
+
+    /// Shows the image roughly as it looks to someone with a colour vision deficiency, which
+    /// helps to check that charts and interfaces do not depend on telling those colours apart.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let red = Image::blank_with_colour(Dim::square(2), Colour::rgb(220, 40, 40));
+    /// let green = Image::blank_with_colour(Dim::square(2), Colour::rgb(40, 160, 40));
+    /// let (red, green) = (
+    ///     red.simulate_colour_blindness(ColourBlindness::Deuteranopia),
+    ///     green.simulate_colour_blindness(ColourBlindness::Deuteranopia),
+    /// );
+    /// // Both come out as a similar olive.
+    /// assert!(red.approx_eq(&green, 40));
+    /// ```
+    pub fn simulate_colour_blindness(self, kind: ColourBlindness) -> Image {
+        self.apply_colour_matrix(kind.matrix())
+    }
+
+    /// Combines the views of the left and right eye into an anaglyph, to be seen in 3D through
+    /// glasses with a red filter over the left eye and a cyan filter over the right. Each pixel
+    /// gets the more opaque alpha of the two views.
+    ///
+    /// This returns an `Err` if the views do not have the same dimensions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let left = Image::blank_with_colour(Dim::square(4), Colour::rgb(200, 100, 50));
+    /// let right = Image::blank_with_colour(Dim::square(4), Colour::rgb(10, 20, 30));
+    /// let stereo = Image::anaglyph(&left, &right, AnaglyphMode::Colour).unwrap();
+    /// assert_eq!(stereo.get_pixel(Loc { x: 0, y: 0 }), Ok(Colour::rgb(200, 20, 30)));
+    /// ```
+    pub fn anaglyph(
+        left: &Image,
+        right: &Image,
+        mode: AnaglyphMode,
+    ) -> Result<Image, &'static str> {
+        let dim = left.get_dimensions();
+        if right.get_dimensions() != dim {
+            return Err("The left and right images must have the same dimensions.");
+        }
+
+        let (ml, mr) = mode.matrices();
+        let pixels = left
+            .pixels
+            .iter()
+            .zip(right.pixels.iter())
+            .map(|(l, r)| {
+                let (l, r) = ([l.r, l.g, l.b, l.a], [r.r, r.g, r.b, r.a]);
+                let channel = |i: usize| {
+                    let v: f32 = (0..3)
+                        .map(|j| ml[i][j] * l[j] as f32 + mr[i][j] * r[j] as f32)
+                        .sum();
+                    v.round().clamp(0.0, 255.0) as u8
+                };
+                Colour::rgba(channel(0), channel(1), channel(2), l[3].max(r[3]))
+            })
+            .collect();
+        Ok(Image::from_pixels(dim, pixels))
+    }
+}
+
+#[cfg(test)]
+mod colour_matrix_tests {
+    use super::*;
+    use crate::{Dim, Loc};
+
+    #[test]
+    fn identity_and_offsets() {
+        let colours = [
+            Colour::rgba(0, 0, 0, 0),
+            Colour::rgba(12, 200, 255, 77),
+            Colour::WHITE,
+        ];
+        let identity = [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+        ];
+        for c in colours {
+            assert_eq!(transform(c, &identity), c);
+        }
+
+        let invert = [
+            [-1.0, 0.0, 0.0, 1.0],
+            [0.0, -1.0, 0.0, 1.0],
+            [0.0, 0.0, -1.0, 1.0],
+        ];
+        assert_eq!(transform(colours[1], &invert), Colour::rgba(243, 55, 0, 77));
+        assert_eq!(
+            transform(Colour::rgb(200, 200, 200), &[[2.0, 0.0, 0.0, 0.0]; 3]),
+            Colour::WHITE
+        );
+    }
+
+    #[test]
+    fn built_in_looks() {
+        let c = Colour::rgb(180, 90, 30);
+        let img = Image::blank_with_colour(Dim::square(1), c);
+        let at = |img: Image| img.get_pixel(Loc { x: 0, y: 0 }).unwrap();
+
+        assert_eq!(at(img.clone().saturate(1.0)), c);
+        let grey = at(img.clone().saturate(0.0));
+        assert!(grey.r == grey.g && grey.g == grey.b);
+        let sepia = at(img.sepia());
+        assert!(sepia.r > sepia.g && sepia.g > sepia.b);
+
+        // Every simulation keeps white white.
+        let white = Image::blank_with_colour(Dim::square(1), Colour::WHITE);
+        for kind in [
+            ColourBlindness::Protanopia,
+            ColourBlindness::Deuteranopia,
+            ColourBlindness::Tritanopia,
+        ] {
+            let seen = white.clone().simulate_colour_blindness(kind);
+            assert!(seen.approx_eq(&white, 1), "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn anaglyph_modes() {
+        let left = Image::blank_with_colour(Dim::square(2), Colour::rgba(255, 0, 0, 100));
+        let right = Image::blank_with_colour(Dim::square(2), Colour::rgba(0, 255, 255, 200));
+        let at = |mode| {
+            Image::anaglyph(&left, &right, mode)
+                .unwrap()
+                .get_pixel(Loc { x: 1, y: 1 })
+                .unwrap()
+        };
+
+        assert_eq!(at(AnaglyphMode::True), Colour::rgba(76, 0, 179, 200));
+        assert_eq!(at(AnaglyphMode::Grey), Colour::rgba(76, 179, 179, 200));
+        assert_eq!(at(AnaglyphMode::Colour), Colour::rgba(255, 255, 255, 200));
+        assert_eq!(
+            at(AnaglyphMode::HalfColour),
+            Colour::rgba(76, 255, 255, 200)
+        );
+        let dubois = at(AnaglyphMode::Dubois);
+        assert!(dubois.r > 90 && dubois.g > 150 && dubois.b > 200);
+
+        let small = Image::blank(Dim::square(1));
+        assert!(Image::anaglyph(&left, &small, AnaglyphMode::Grey).is_err());
+    }
+}
@@ -0,0 +1,207 @@
+//! Joining images side by side or one above the other, for strips and simple panoramas.
+
+use crate::{Colour, Dim, Image};
+
+/// Where images shorter than the strip they are part of are placed across it, as used by
+/// `ConcatOptions`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConcatAlign {
+    /// Images are aligned to the top of a horizontal strip, or the left of a vertical one.
+    #[default]
+    Start,
+    /// Images are centred across the strip.
+    Center,
+    /// Images are aligned to the bottom of a horizontal strip, or the right of a vertical one.
+    End,
+}
+
+/// Options for `Image::hconcat_with()` and `Image::vconcat_with()`.
+///
+/// # Examples
+///
+/// ```
+/// # use kodak::*;
+/// // Centred images on black, each fading into the next over 32 pixels.
+/// let options = ConcatOptions {
+///     align: ConcatAlign::Center,
+///     background: Colour::BLACK,
+///     overlap: 32,
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConcatOptions {
+    /// Where images that are smaller than the strip is wide are placed across it.
+    pub align: ConcatAlign,
+    /// The colour of the strip where no image covers it, and behind translucent images.
+    pub background: Colour,
+    /// How many pixels each image overlaps the one before it, cross-fading from one to the
+    /// other. Overlaps are limited to the length of the smaller of the two images.
+    pub overlap: u32,
+}
+
+impl Default for ConcatOptions {
+    fn default() -> Self {
+        ConcatOptions {
+            align: ConcatAlign::Start,
+            background: Colour::TRANSPARENT,
+            overlap: 0,
+        }
+    }
+}
+
+/// Joins `images` along the horizontal axis if `horizontal` is set, and along the vertical axis
+/// otherwise.
+fn concat(images: &[Image], options: ConcatOptions, horizontal: bool) -> Image {
+    // Lengths along the strip and across it.
+    let along = |img: &Image| if horizontal { img.width } else { img.height };
+    let across = |img: &Image| if horizontal { img.height } else { img.width };
+
+    let mut starts = Vec::with_capacity(images.len());
+    let mut overlaps = Vec::with_capacity(images.len());
+    let mut length = 0u32;
+    for (i, img) in images.iter().enumerate() {
+        let overlap = match i {
+            0 => 0,
+            _ => options.overlap.min(along(&images[i - 1])).min(along(img)),
+        };
+        let start = length - overlap;
+        starts.push(start);
+        overlaps.push(overlap);
+        length = start + along(img);
+    }
+    let thickness = images.iter().map(across).max().unwrap_or(0);
+    let dimension = if horizontal {
+        Dim {
+            w: length,
+            h: thickness,
+        }
+    } else {
+        Dim {
+            w: thickness,
+            h: length,
+        }
+    };
+
+    let mut strip = Image::blank_with_colour(dimension, options.background);
+    for ((img, &start), &overlap) in images.iter().zip(&starts).zip(&overlaps) {
+        let offset = match options.align {
+            ConcatAlign::Start => 0,
+            ConcatAlign::Center => (thickness - across(img)) / 2,
+            ConcatAlign::End => thickness - across(img),
+        };
+        let (x0, y0) = if horizontal {
+            (start, offset)
+        } else {
+            (offset, start)
+        };
+        for (y, source) in img.rows().enumerate() {
+            let target = &mut strip.row_mut(y0 + y as u32)[x0 as usize..x0 as usize + source.len()];
+            for (x, (t, &s)) in target.iter_mut().zip(source).enumerate() {
+                let position = if horizontal { x } else { y } as u32;
+                let weight = if position < overlap {
+                    (position as f32 + 0.5) / overlap as f32
+                } else {
+                    1.0
+                };
+                *t = t.lerp(s.over(*t), weight);
+            }
+        }
+    }
+    strip
+}
+
+// The following impl block defines concatenation functions for Images.
+impl Image {
+    /// Joins images side by side, from left to right, aligned to the top on a transparent
+    /// background. An empty slice gives an empty image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let strip = Image::hconcat(&[
+    ///     Image::blank_with_colour(Dim { w: 3, h: 2 }, Colour::WHITE),
+    ///     Image::blank_with_colour(Dim { w: 4, h: 5 }, Colour::BLACK),
+    /// ]);
+    /// assert_eq!(strip.get_dimensions(), Dim { w: 7, h: 5 });
+    /// assert_eq!(strip.get_pixel(Loc { x: 0, y: 4 }), Ok(Colour::TRANSPARENT));
+    /// ```
+    pub fn hconcat(images: &[Image]) -> Image {
+        Image::hconcat_with(images, ConcatOptions::default())
+    }
+
+    /// Joins images side by side like `hconcat()`, aligned, filled in and overlapping as given
+    /// by `options`.
+    pub fn hconcat_with(images: &[Image], options: ConcatOptions) -> Image {
+        concat(images, options, true)
+    }
+
+    /// Joins images one above the other, from top to bottom, aligned to the left on a
+    /// transparent background. An empty slice gives an empty image.
+    pub fn vconcat(images: &[Image]) -> Image {
+        Image::vconcat_with(images, ConcatOptions::default())
+    }
+
+    /// Joins images one above the other like `vconcat()`, aligned, filled in and overlapping as
+    /// given by `options`.
+    pub fn vconcat_with(images: &[Image], options: ConcatOptions) -> Image {
+        concat(images, options, false)
+    }
+}
+
+#[cfg(test)]
+mod concat_tests {
+    use super::*;
+    use crate::Loc;
+
+    #[test]
+    fn aligns_and_fills() {
+        let tall = Image::blank_with_colour(Dim { w: 2, h: 6 }, Colour::WHITE);
+        let short = Image::blank_with_colour(Dim { w: 4, h: 2 }, Colour::BLACK);
+        let grey = Colour::rgb(128, 128, 128);
+        let options = ConcatOptions {
+            align: ConcatAlign::Center,
+            background: grey,
+            overlap: 0,
+        };
+        let strip = Image::hconcat_with(&[tall.clone(), short.clone()], options);
+        assert_eq!(strip.get_dimensions(), Dim { w: 6, h: 6 });
+        let at = |img: &Image, x, y| img.get_pixel(Loc { x, y }).unwrap();
+        assert_eq!(at(&strip, 1, 0), Colour::WHITE);
+        assert_eq!((at(&strip, 3, 1), at(&strip, 3, 2)), (grey, Colour::BLACK));
+
+        let column = Image::vconcat_with(
+            &[tall, short],
+            ConcatOptions {
+                align: ConcatAlign::End,
+                ..options
+            },
+        );
+        assert_eq!(column.get_dimensions(), Dim { w: 4, h: 8 });
+        assert_eq!(
+            (at(&column, 1, 0), at(&column, 2, 0)),
+            (grey, Colour::WHITE)
+        );
+        assert_eq!(at(&column, 0, 7), Colour::BLACK);
+
+        assert_eq!(Image::vconcat(&[]).get_dimensions(), Dim { w: 0, h: 0 });
+    }
+
+    #[test]
+    fn overlaps_cross_fade() {
+        let white = Image::blank_with_colour(Dim { w: 6, h: 1 }, Colour::WHITE);
+        let black = Image::blank_with_colour(Dim { w: 6, h: 1 }, Colour::BLACK);
+        let options = ConcatOptions {
+            overlap: 4,
+            ..ConcatOptions::default()
+        };
+        let strip = Image::hconcat_with(&[white, black.clone()], options);
+        let reds: Vec<u8> = strip.row(0).iter().map(|c| c.r).collect();
+        assert_eq!(reds, [255, 255, 223, 159, 96, 32, 0, 0]);
+
+        // Overlaps are limited by the smaller image.
+        let dot = Image::blank_with_colour(Dim::square(1), Colour::WHITE);
+        let strip = Image::vconcat_with(&[black, dot], options);
+        assert_eq!(strip.get_dimensions(), Dim { w: 6, h: 1 });
+    }
+}
@@ -0,0 +1,219 @@
+//! Tracing the outlines of selections, to turn them into polygons.
+
+use crate::{Dim, Loc, Mask};
+
+/// The eight neighbours of a pixel, clockwise from the one to the left.
+const NEIGHBOURS: [(i64, i64); 8] = [
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+];
+
+/// Returns the distance from `p` to the line through `a` and `b`, or to `a` if they coincide.
+fn distance_to_line(p: Loc, a: Loc, b: Loc) -> f32 {
+    let (px, py) = (p.x as f32 - a.x as f32, p.y as f32 - a.y as f32);
+    let (dx, dy) = (b.x as f32 - a.x as f32, b.y as f32 - a.y as f32);
+    let length = dx.hypot(dy);
+    if length == 0.0 {
+        px.hypot(py)
+    } else {
+        (px * dy - py * dx).abs() / length
+    }
+}
+
+/// Simplifies an open polyline with the Douglas-Peucker algorithm, keeping its end points and
+/// no point closer than `tolerance` to the line between the points kept on either side of it.
+fn douglas_peucker(points: &[Loc], tolerance: f32) -> Vec<Loc> {
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let farthest = (1..points.len().saturating_sub(1))
+        .map(|i| (i, distance_to_line(points[i], first, last)))
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+    match farthest {
+        Some((i, distance)) if distance > tolerance => {
+            let mut kept = douglas_peucker(&points[..=i], tolerance);
+            kept.pop();
+            kept.extend(douglas_peucker(&points[i..], tolerance));
+            kept
+        }
+        _ if points.len() > 1 => vec![first, last],
+        _ => vec![first],
+    }
+}
+
+impl Mask {
+    /// Traces the outline of every connected shape in the selection, returning for each one the
+    /// pixels along its border in clockwise order, starting from its top left pixel.
+    ///
+    /// Pixels count as selected as decided by `contains()`, and touch when they are neighbours
+    /// horizontally, vertically or diagonally. Only outer borders are traced; holes in a shape
+    /// are not. Shapes are listed in the order that their top left pixels come in row by row.
+    /// Outlines of shapes only one pixel wide go along one side and back along the other.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let square = Mask::from_fn(Dim::square(4), |l| if l.x < 2 && l.y < 2 { 255 } else { 0 });
+    /// let outline = [Loc { x: 0, y: 0 }, Loc { x: 1, y: 0 }, Loc { x: 1, y: 1 }, Loc { x: 0, y: 1 }];
+    /// assert_eq!(square.contours(), [outline]);
+    /// ```
+    pub fn contours(&self) -> Vec<Vec<Loc>> {
+        let Dim { w, h } = self.get_dimensions();
+        let (w, h) = (w as i64, h as i64);
+        let on = |x: i64, y: i64| {
+            (0..w).contains(&x) && (0..h).contains(&y) && self.values()[(y * w + x) as usize] >= 128
+        };
+        let mut traced = vec![false; self.values().len()];
+        let mut contours = Vec::new();
+
+        for start in 0..w * h {
+            let (sx, sy) = (start % w, start / w);
+            if !on(sx, sy) || traced[start as usize] {
+                continue;
+            }
+
+            // Moore neighbour tracing: walk clockwise around each pixel, starting just after
+            // the background pixel that was left last. Nothing to the left of the first pixel is
+            // selected, since it comes first in its row.
+            let mut contour = vec![Loc {
+                x: sx as u32,
+                y: sy as u32,
+            }];
+            let (mut x, mut y, mut back) = (sx, sy, 0);
+            let mut second = None;
+            loop {
+                let found = (1..=8)
+                    .map(|i| (back + i) % 8)
+                    .find(|&d| on(x + NEIGHBOURS[d].0, y + NEIGHBOURS[d].1));
+                let Some(d) = found else { break };
+                let (nx, ny) = (x + NEIGHBOURS[d].0, y + NEIGHBOURS[d].1);
+                if (x, y) == (sx, sy) && second == Some((nx, ny)) {
+                    break;
+                }
+                second.get_or_insert((nx, ny));
+                // The last background pixel checked, as seen from the new pixel.
+                let (bx, by) = (
+                    x + NEIGHBOURS[(d + 7) % 8].0 - nx,
+                    y + NEIGHBOURS[(d + 7) % 8].1 - ny,
+                );
+                back = NEIGHBOURS
+                    .iter()
+                    .position(|&n| n == (bx, by))
+                    .expect("The pixel checked before is a neighbour of the next one.");
+                (x, y) = (nx, ny);
+                if (x, y) != (sx, sy) {
+                    contour.push(Loc {
+                        x: x as u32,
+                        y: y as u32,
+                    });
+                }
+            }
+            contours.push(contour);
+
+            // Marks the whole shape, so that it is not traced again from another of its pixels.
+            let mut stack = vec![(sx, sy)];
+            traced[start as usize] = true;
+            while let Some((x, y)) = stack.pop() {
+                for (dx, dy) in NEIGHBOURS {
+                    let (nx, ny) = (x + dx, y + dy);
+                    if on(nx, ny) && !traced[(ny * w + nx) as usize] {
+                        traced[(ny * w + nx) as usize] = true;
+                        stack.push((nx, ny));
+                    }
+                }
+            }
+        }
+        contours
+    }
+
+    /// Traces outlines like `contours()`, and simplifies them into polygons with the
+    /// Douglas-Peucker algorithm, leaving out every corner that lies within `tolerance` pixels
+    /// of the straight line past it. A tolerance of 1.0 or so keeps the shape while dropping
+    /// the steps of diagonal edges.
+    pub fn contours_simplified(&self, tolerance: f32) -> Vec<Vec<Loc>> {
+        self.contours()
+            .into_iter()
+            .map(|contour| {
+                // The outline is split at its start and at the point farthest from it, and the
+                // two halves simplified on their own.
+                let farthest = (0..contour.len())
+                    .max_by(|&i, &j| {
+                        let d = |k: usize| distance_to_line(contour[k], contour[0], contour[0]);
+                        d(i).total_cmp(&d(j))
+                    })
+                    .unwrap_or(0);
+                if farthest == 0 {
+                    return contour;
+                }
+                let mut closed = contour.clone();
+                closed.push(contour[0]);
+                let mut polygon = douglas_peucker(&closed[..=farthest], tolerance);
+                polygon.pop();
+                polygon.extend(douglas_peucker(&closed[farthest..], tolerance));
+                polygon.pop();
+                polygon
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod contour_tests {
+    use super::*;
+
+    fn locs(points: &[(u32, u32)]) -> Vec<Loc> {
+        points.iter().map(|&(x, y)| Loc { x, y }).collect()
+    }
+
+    #[test]
+    fn traces_outlines() {
+        // A 4 by 3 rectangle, a diagonal pair of pixels and a horizontal line.
+        let mask = Mask::from_fn(Dim { w: 10, h: 9 }, |l| {
+            let rectangle = (2..6).contains(&l.x) && (1..4).contains(&l.y);
+            let diagonal = (l.x, l.y) == (8, 1) || (l.x, l.y) == (9, 2);
+            let line = l.y == 7 && (1..4).contains(&l.x);
+            if rectangle || diagonal || line {
+                255
+            } else {
+                0
+            }
+        });
+        let contours = mask.contours();
+        assert_eq!(contours.len(), 3);
+        assert_eq!(
+            contours[0],
+            locs(&[
+                (2, 1),
+                (3, 1),
+                (4, 1),
+                (5, 1),
+                (5, 2),
+                (5, 3),
+                (4, 3),
+                (3, 3),
+                (2, 3),
+                (2, 2)
+            ])
+        );
+        assert_eq!(contours[1], locs(&[(8, 1), (9, 2)]));
+        assert_eq!(contours[2], locs(&[(1, 7), (2, 7), (3, 7), (2, 7)]));
+
+        let polygons = mask.contours_simplified(0.5);
+        assert_eq!(polygons[0], locs(&[(2, 1), (5, 1), (5, 3), (2, 3)]));
+        assert_eq!(polygons[1], contours[1]);
+        assert_eq!(polygons[2], locs(&[(1, 7), (3, 7)]));
+
+        let dot = Mask::from_fn(
+            Dim::square(3),
+            |l| if l.x == 2 && l.y == 0 { 255 } else { 0 },
+        );
+        assert_eq!(dot.contours(), [locs(&[(2, 0)])]);
+        assert_eq!(dot.contours_simplified(1.0), dot.contours());
+        assert!(Mask::new(Dim::square(3)).contours().is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+//! Tone curves, the finer-grained counterpart to scaling the channels.
+
+use crate::Image;
+
+/// A tone curve that maps every channel value to a new one, defined by control points that a
+/// smooth curve is drawn through.
+///
+/// A curve has a master curve, which applies to the red, green and blue channels alike, and
+/// optionally a curve for each of them. The channel curves are applied first and the master
+/// curve after them, as in most photo editors. Alpha is never changed.
+///
+/// Between the control points, the curve follows a monotone cubic spline: it passes through
+/// every point, and it only rises or falls where the points do, so it never overshoots the way
+/// an ordinary spline can. Before the first and after the last point the curve is flat. A curve
+/// without any points leaves its channel unchanged.
+///
+/// # Examples
+///
+/// ```
+/// # use kodak::*;
+/// // A gentle S-curve for more contrast, with the blue channel lifted a little.
+/// let curve = Curve::new(&[(0, 0), (64, 52), (192, 204), (255, 255)])
+///     .with_blue(&[(0, 12), (255, 255)]);
+/// let img = Image::blank_with_colour(Dim::square(4), Colour::rgb(64, 128, 192))
+///     .apply_curve(&curve);
+/// assert_eq!(img.get_pixel(Loc { x: 0, y: 0 }).unwrap().r, 52);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Curve {
+    master: Vec<(u8, u8)>,
+    red: Vec<(u8, u8)>,
+    green: Vec<(u8, u8)>,
+    blue: Vec<(u8, u8)>,
+}
+
+/// Sorts control points by their input value, keeping the last of any points with the same one.
+fn sorted(points: &[(u8, u8)]) -> Vec<(u8, u8)> {
+    let mut points = points.to_vec();
+    points.reverse();
+    points.sort_by_key(|&(x, _)| x);
+    points.dedup_by_key(|&mut (x, _)| x);
+    points
+}
+
+/// Computes the output of a curve for every input value.
+fn table(points: &[(u8, u8)]) -> [u8; 256] {
+    let mut table = [0; 256];
+    if points.is_empty() {
+        for (value, out) in table.iter_mut().enumerate() {
+            *out = value as u8;
+        }
+        return table;
+    }
+
+    let xs: Vec<f32> = points.iter().map(|&(x, _)| x as f32).collect();
+    let ys: Vec<f32> = points.iter().map(|&(_, y)| y as f32).collect();
+    let n = points.len();
+
+    // The secants between neighbouring points, and the tangents at the points, limited as
+    // described by Fritsch and Carlson to keep the curve monotone between them.
+    let secants: Vec<f32> = (0..n - 1)
+        .map(|k| (ys[k + 1] - ys[k]) / (xs[k + 1] - xs[k]))
+        .collect();
+    let mut tangents = vec![0.0; n];
+    if n > 1 {
+        tangents[0] = secants[0];
+        tangents[n - 1] = secants[n - 2];
+    }
+    for k in 1..n - 1 {
+        if secants[k - 1] * secants[k] > 0.0 {
+            tangents[k] = (secants[k - 1] + secants[k]) / 2.0;
+        }
+    }
+    for (k, &secant) in secants.iter().enumerate() {
+        if secant == 0.0 {
+            tangents[k] = 0.0;
+            tangents[k + 1] = 0.0;
+            continue;
+        }
+        let (a, b) = (tangents[k] / secant, tangents[k + 1] / secant);
+        let length = (a * a + b * b).sqrt();
+        if length > 3.0 {
+            tangents[k] = 3.0 / length * a * secant;
+            tangents[k + 1] = 3.0 / length * b * secant;
+        }
+    }
+
+    for (value, out) in table.iter_mut().enumerate() {
+        let x = value as f32;
+        let y = if x <= xs[0] {
+            ys[0]
+        } else if x >= xs[n - 1] {
+            ys[n - 1]
+        } else {
+            let k = xs.partition_point(|&p| p <= x) - 1;
+            let h = xs[k + 1] - xs[k];
+            let t = (x - xs[k]) / h;
+            let (t2, t3) = (t * t, t * t * t);
+            (2.0 * t3 - 3.0 * t2 + 1.0) * ys[k]
+                + (t3 - 2.0 * t2 + t) * h * tangents[k]
+                + (-2.0 * t3 + 3.0 * t2) * ys[k + 1]
+                + (t3 - t2) * h * tangents[k + 1]
+        };
+        *out = y.round().clamp(0.0, 255.0) as u8;
+    }
+    table
+}
+
+impl Curve {
+    /// Creates a curve with the given master control points, as `(input, output)` pairs. Keep
+    /// `(0, 0)` and `(255, 255)` among them to leave black and white where they are.
+    pub fn new(points: &[(u8, u8)]) -> Self {
+        Curve {
+            master: sorted(points),
+            ..Curve::default()
+        }
+    }
+
+    /// Sets the control points of the red channel's curve.
+    pub fn with_red(self, points: &[(u8, u8)]) -> Self {
+        Curve {
+            red: sorted(points),
+            ..self
+        }
+    }
+
+    /// Sets the control points of the green channel's curve.
+    pub fn with_green(self, points: &[(u8, u8)]) -> Self {
+        Curve {
+            green: sorted(points),
+            ..self
+        }
+    }
+
+    /// Sets the control points of the blue channel's curve.
+    pub fn with_blue(self, points: &[(u8, u8)]) -> Self {
+        Curve {
+            blue: sorted(points),
+            ..self
+        }
+    }
+
+    /// Returns lookup tables for the red, green and blue channels, with the master curve
+    /// applied after each channel's own curve.
+    pub(crate) fn tables(&self) -> [[u8; 256]; 3] {
+        let master = table(&self.master);
+        [&self.red, &self.green, &self.blue]
+            .map(|points| table(points).map(|channel| master[channel as usize]))
+    }
+}
+
+// The following impl block defines tone curve functions for Images.
+impl Image {
+    /// Maps the red, green and blue channels of every pixel through a tone curve.
+    pub fn apply_curve(mut self, curve: &Curve) -> Image {
+        let [r, g, b] = curve.tables();
+        for c in self.pixels.iter_mut() {
+            c.r = r[c.r as usize];
+            c.g = g[c.g as usize];
+            c.b = b[c.b as usize];
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod curve_tests {
+    use super::*;
+    use crate::{Colour, Dim};
+
+    #[test]
+    fn passes_through_its_points() {
+        let points = [(0, 10), (50, 40), (100, 200), (200, 220), (255, 230)];
+        let lookup = table(&sorted(&points));
+        for (x, y) in points {
+            assert_eq!(lookup[x as usize], y);
+        }
+        assert!(lookup.windows(2).all(|w| w[0] <= w[1]));
+
+        assert_eq!(table(&[]), core::array::from_fn(|v| v as u8));
+        assert_eq!(
+            table(&[(0, 0), (255, 255)]),
+            core::array::from_fn(|v| v as u8)
+        );
+    }
+
+    #[test]
+    fn flat_outside_and_at_extremes() {
+        let lookup = table(&sorted(&[(200, 180), (50, 20), (120, 240)]));
+        assert_eq!(lookup[0], 20);
+        assert_eq!(lookup[255], 180);
+        // The peak at 120 is not overshot.
+        assert!(lookup.iter().all(|&v| v <= 240));
+        assert_eq!(table(&[(30, 99)]), [99; 256]);
+        assert_eq!(sorted(&[(5, 1), (5, 2), (3, 0)]), [(3, 0), (5, 2)]);
+    }
+
+    #[test]
+    fn channel_curves_come_first() {
+        let curve = Curve::new(&[(0, 0), (100, 200), (255, 255)]).with_red(&[(0, 100), (255, 100)]);
+        let colour = Colour::rgba(7, 100, 255, 40);
+        let img = Image::blank_with_colour(Dim::square(2), colour).apply_curve(&curve);
+        let expected = Colour::rgba(200, 200, 255, 40);
+        assert!(img.pixels.iter().all(|&c| c == expected));
+        let identity = Image::blank_with_colour(Dim::square(2), colour);
+        assert_eq!(identity.clone().apply_curve(&Curve::default()), identity);
+    }
+}
@@ -0,0 +1,114 @@
+//! Removing isolated specks, such as hot pixels, dust and thin scratches.
+
+use crate::{Colour, Image};
+
+// The following impl block defines despeckling functions for Images.
+impl Image {
+    /// Replaces every pixel that stands out from its surroundings with the median of the three
+    /// by three pixels around it, leaving all others untouched.
+    ///
+    /// A pixel stands out if its red, green or blue differs by more than `threshold` from the
+    /// median of that channel in the square around it. Unlike a median filter over the whole
+    /// image, this keeps fine texture and sharp edges intact, and only removes hot pixels, dust
+    /// and scratches one pixel wide. Squares are cut off at the borders of the image. Alpha is
+    /// left alone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let scan = Image::blank_with_colour(Dim::square(3), Colour::rgb(90, 90, 90))
+    ///     .fill_region(Region { l: Loc { x: 1, y: 1 }, d: Dim::square(1) }, Colour::WHITE);
+    /// let clean = scan.despeckle(40);
+    /// assert_eq!(clean.get_pixel(Loc { x: 1, y: 1 }), Ok(Colour::rgb(90, 90, 90)));
+    /// ```
+    pub fn despeckle(&self, threshold: u8) -> Image {
+        let (w, h) = (self.width as i64, self.height as i64);
+        let mut pixels = self.pixels.clone();
+        let mut square: Vec<Colour> = Vec::with_capacity(9);
+        let mut channel = Vec::with_capacity(9);
+        for y in 0..h {
+            for x in 0..w {
+                square.clear();
+                for ny in (y - 1).max(0)..(y + 2).min(h) {
+                    for nx in (x - 1).max(0)..(x + 2).min(w) {
+                        square.push(self.pixels[(ny * w + nx) as usize]);
+                    }
+                }
+                let mut median = |get: fn(&Colour) -> u8| {
+                    channel.clear();
+                    channel.extend(square.iter().map(get));
+                    channel.sort_unstable();
+                    channel[channel.len() / 2]
+                };
+                let (r, g, b) = (median(|c| c.r), median(|c| c.g), median(|c| c.b));
+
+                let c = &mut pixels[(y * w + x) as usize];
+                if c.r.abs_diff(r) > threshold
+                    || c.g.abs_diff(g) > threshold
+                    || c.b.abs_diff(b) > threshold
+                {
+                    *c = Colour::rgba(r, g, b, c.a);
+                }
+            }
+        }
+        Image {
+            pixels,
+            ..self.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod despeckle_tests {
+    use super::*;
+    use crate::{Dim, Loc, Region};
+
+    #[test]
+    fn removes_specks_and_keeps_edges() {
+        // A dark half and a light half, with a hot pixel in the corner, a dark speck and a
+        // one pixel wide scratch across the light half.
+        let img = Image::blank_with_colour(Dim { w: 10, h: 8 }, Colour::rgb(200, 190, 180))
+            .fill_region(
+                Region::from_top_left(Dim { w: 4, h: 8 }),
+                Colour::rgb(30, 30, 30),
+            )
+            .fill_region(
+                Region::from_top_left(Dim::square(1)),
+                Colour::rgb(255, 0, 0),
+            )
+            .fill_region(
+                Region {
+                    l: Loc { x: 2, y: 5 },
+                    d: Dim::square(1),
+                },
+                Colour::rgb(60, 30, 30),
+            )
+            .fill_region(
+                Region {
+                    l: Loc { x: 5, y: 3 },
+                    d: Dim { w: 5, h: 1 },
+                },
+                Colour::rgba(20, 20, 20, 100),
+            );
+        let clean = img.despeckle(40);
+
+        let expected = Image::blank_with_colour(Dim { w: 10, h: 8 }, Colour::rgb(200, 190, 180))
+            .fill_region(
+                Region::from_top_left(Dim { w: 4, h: 8 }),
+                Colour::rgb(30, 30, 30),
+            )
+            .fill_region(
+                Region {
+                    l: Loc { x: 5, y: 3 },
+                    d: Dim { w: 5, h: 1 },
+                },
+                Colour::rgba(200, 190, 180, 100),
+            );
+        // The speck differs by 30, so it is only removed by a lower threshold.
+        let speck = Loc { x: 2, y: 5 };
+        assert_eq!(clean.get_pixel(speck), Ok(Colour::rgb(60, 30, 30)));
+        assert_eq!(clean.despeckle(20), expected);
+        assert_eq!(expected.despeckle(0), expected);
+    }
+}
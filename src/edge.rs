@@ -0,0 +1,107 @@
+//! What lies beyond the border of an image, for filters and transforms that look past it.
+
+use crate::{Colour, Image};
+
+/// How positions outside of an image are treated by filters, transforms and
+/// `Image::get_pixel_with_edge()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EdgeMode {
+    /// The edge pixels repeat forever, so borders neither darken nor pick up colours from
+    /// elsewhere.
+    #[default]
+    Clamp,
+    /// The image tiles: leaving one side enters the opposite one. This suits textures and other
+    /// images that are meant to repeat.
+    Wrap,
+    /// The image is reflected at its borders, edge pixels included, so that `-1` is `0` and
+    /// `-2` is `1`.
+    Mirror,
+    /// Everything outside of the image is this colour.
+    Constant(Colour),
+}
+
+impl EdgeMode {
+    /// Maps a coordinate on an axis `len` pixels long into the image, or returns `None` if it
+    /// falls outside in `Constant` mode.
+    pub(crate) fn resolve(self, i: i64, len: i64) -> Option<i64> {
+        match self {
+            EdgeMode::Clamp => Some(i.clamp(0, len - 1)),
+            EdgeMode::Wrap => Some(i.rem_euclid(len)),
+            EdgeMode::Mirror => {
+                let i = i.rem_euclid(2 * len);
+                Some(if i < len { i } else { 2 * len - 1 - i })
+            }
+            EdgeMode::Constant(_) => (0..len).contains(&i).then_some(i),
+        }
+    }
+
+    /// Returns the colour of the pixel at `(x, y)` in `img`, with `(x, y)` resolved by this mode.
+    /// Empty images are transparent everywhere, unless the mode gives a constant colour.
+    pub(crate) fn pixel(self, img: &Image, x: i64, y: i64) -> Colour {
+        let (w, h) = (img.width as i64, img.height as i64);
+        let fallback = match self {
+            EdgeMode::Constant(colour) => colour,
+            _ => Colour::TRANSPARENT,
+        };
+        if w == 0 || h == 0 {
+            return fallback;
+        }
+        match (self.resolve(x, w), self.resolve(y, h)) {
+            (Some(x), Some(y)) => img.pixels[(y * w + x) as usize],
+            _ => fallback,
+        }
+    }
+}
+
+// The following impl block defines edge handling functions for Images.
+impl Image {
+    /// Returns the colour of the pixel at `loc`, which may lie outside of the image, in which
+    /// case `mode` decides what is there.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let img = Image::blank_with_colour(Dim { w: 2, h: 1 }, Colour::WHITE)
+    ///     .fill_region(Region::from_top_left(Dim::square(1)), Colour::BLACK);
+    /// assert_eq!(img.get_pixel_with_edge((-1, 0), EdgeMode::Clamp), Colour::BLACK);
+    /// assert_eq!(img.get_pixel_with_edge((-1, 0), EdgeMode::Wrap), Colour::WHITE);
+    /// assert_eq!(img.get_pixel_with_edge((2, 0), EdgeMode::Mirror), Colour::WHITE);
+    /// let red = Colour::rgb(255, 0, 0);
+    /// assert_eq!(img.get_pixel_with_edge((0, 5), EdgeMode::Constant(red)), red);
+    /// ```
+    pub fn get_pixel_with_edge(&self, loc: (i64, i64), mode: EdgeMode) -> Colour {
+        mode.pixel(self, loc.0, loc.1)
+    }
+}
+
+#[cfg(test)]
+mod edge_tests {
+    use super::*;
+
+    #[test]
+    fn resolves_coordinates() {
+        let resolve = |mode: EdgeMode| (-4..8).map(|i| mode.resolve(i, 3)).collect::<Vec<_>>();
+        let some = |v: &[i64]| v.iter().map(|&i| Some(i)).collect::<Vec<_>>();
+        assert_eq!(
+            resolve(EdgeMode::Clamp),
+            some(&[0, 0, 0, 0, 0, 1, 2, 2, 2, 2, 2, 2])
+        );
+        assert_eq!(
+            resolve(EdgeMode::Wrap),
+            some(&[2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1])
+        );
+        assert_eq!(
+            resolve(EdgeMode::Mirror),
+            some(&[2, 2, 1, 0, 0, 1, 2, 2, 1, 0, 0, 1])
+        );
+        let constant = resolve(EdgeMode::Constant(Colour::BLACK));
+        assert_eq!(constant[3..8], [None, Some(0), Some(1), Some(2), None]);
+
+        let empty = Image::blank(crate::Dim { w: 0, h: 3 });
+        assert_eq!(
+            empty.get_pixel_with_edge((0, 0), EdgeMode::Wrap),
+            Colour::TRANSPARENT
+        );
+    }
+}
@@ -0,0 +1,237 @@
+//! Stylization effects such as chromatic aberration, glitching, vignettes and film grain.
+
+use crate::random::Rng;
+use crate::{Colour, Image, Loc};
+
+/// Options for `Image::glitch()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlitchOptions {
+    /// The seed for the random generator. The same seed always produces the same glitch.
+    pub seed: u64,
+    /// The number of horizontal bands that get displaced.
+    pub band_count: u32,
+    /// The maximum horizontal displacement of a band, in pixels.
+    pub shift: u32,
+}
+
+impl Default for GlitchOptions {
+    fn default() -> Self {
+        GlitchOptions {
+            seed: 0,
+            band_count: 8,
+            shift: 16,
+        }
+    }
+}
+
+// The following impl block defines stylization effects for Images.
+impl Image {
+    /// Looks up a pixel, clamping the coordinates to the edges of the image.
+    fn get_pixel_clamped(&self, x: i64, y: i64) -> Colour {
+        let loc = Loc {
+            x: x.clamp(0, self.width as i64 - 1) as u32,
+            y: y.clamp(0, self.height as i64 - 1) as u32,
+        };
+        self.pixels[loc.as_index(self.get_dimensions())]
+    }
+
+    /// Simulates chromatic aberration by displacing the red and blue channels.
+    ///
+    /// The green and alpha channels stay in place; the red channel is shifted by `offset_r` and the blue
+    /// channel by `offset_b`, both given as `(dx, dy)` in pixels. Opposite offsets for the two
+    /// channels give the classic fringed look.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let img = Image::blank_with_colour(Dim::square(16), Colour::WHITE)
+    ///     .chromatic_aberration((2, 0), (-2, 0));
+    /// ```
+    pub fn chromatic_aberration(&self, offset_r: (i32, i32), offset_b: (i32, i32)) -> Image {
+        if self.pixels.is_empty() {
+            return self.clone();
+        }
+
+        let dim = self.get_dimensions();
+        let pixels = (0..self.pixels.len())
+            .map(|i| {
+                let loc = Loc::from_index(i, dim);
+                let (x, y) = (loc.x as i64, loc.y as i64);
+                Colour {
+                    r: self
+                        .get_pixel_clamped(x - offset_r.0 as i64, y - offset_r.1 as i64)
+                        .r,
+                    b: self
+                        .get_pixel_clamped(x - offset_b.0 as i64, y - offset_b.1 as i64)
+                        .b,
+                    ..self.pixels[i]
+                }
+            })
+            .collect();
+
+        Image {
+            pixels,
+            ..self.clone()
+        }
+    }
+
+    /// Applies a deterministic "digital glitch" effect.
+    ///
+    /// A number of randomly chosen horizontal bands are shifted sideways (wrapping around), and
+    /// some of them get their red channel torn off from the rest. The result only depends on the
+    /// image and the options, so the same seed always yields the same output.
+    pub fn glitch(&self, options: GlitchOptions) -> Image {
+        let mut result = self.clone();
+        if self.pixels.is_empty() {
+            return result;
+        }
+
+        // Taking the pixels once copies them away from `self` a single time.
+        let pixels = &mut *result.pixels;
+        let mut rng = Rng::new(options.seed);
+        let width = self.width as i64;
+        let max_band_height = (self.height / 10).max(1);
+        let shift = options.shift.min(i32::MAX as u32) as i32;
+
+        for _ in 0..options.band_count {
+            let top = rng.below(self.height);
+            let band_height = 1 + rng.below(max_band_height);
+            let dx = rng.range_i32(-shift, shift) as i64;
+            let split_red = rng.next_f32() < 0.5;
+            let red_dx = dx + dx / 2;
+
+            for y in top..(top + band_height).min(self.height) {
+                let row_start = y as usize * self.width as usize;
+                let row = &self.pixels[row_start..row_start + self.width as usize];
+                for x in 0..width {
+                    let mut colour = row[(x - dx).rem_euclid(width) as usize];
+                    if split_red {
+                        colour.r = row[(x - red_dx).rem_euclid(width) as usize].r;
+                    }
+                    pixels[row_start + x as usize] = colour;
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Darkens the image towards its corners, like an old or cheap lens does.
+    ///
+    /// The darkening grows with the square of the distance from the centre, and reaches
+    /// `strength` at the corners: 0.0 changes nothing and 1.0 turns the corners black. Alpha is
+    /// left alone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let img = Image::blank_with_colour(Dim::square(9), Colour::WHITE).vignette(0.5);
+    /// assert_eq!(img.get_pixel(Loc { x: 4, y: 4 }), Ok(Colour::WHITE));
+    /// assert!(img.get_pixel(Loc { x: 0, y: 0 }).unwrap().r < 160);
+    /// ```
+    pub fn vignette(mut self, strength: f32) -> Image {
+        let dim = self.get_dimensions();
+        let (w, h) = (dim.w as f32, dim.h as f32);
+        for (i, c) in self.pixels.iter_mut().enumerate() {
+            let loc = Loc::from_index(i, dim);
+            // From the centre of the image, in halves of its width and height.
+            let dx = (loc.x as f32 + 0.5) / w * 2.0 - 1.0;
+            let dy = (loc.y as f32 + 0.5) / h * 2.0 - 1.0;
+            let factor = 1.0 - strength * (dx * dx + dy * dy) / 2.0;
+            let darken = |v: u8| (v as f32 * factor).round().clamp(0.0, 255.0) as u8;
+            (c.r, c.g, c.b) = (darken(c.r), darken(c.g), darken(c.b));
+        }
+        self
+    }
+
+    /// Adds film grain: random noise that brightens or darkens every pixel, by at most
+    /// `amount` of the full intensity, in all channels alike.
+    ///
+    /// Most of the noise is small, as it is on film, and an amount of around 0.1 is visible
+    /// without being distracting. The same seed always produces the same grain. Alpha is left
+    /// alone.
+    pub fn add_grain(mut self, amount: f32, seed: u64) -> Image {
+        let mut rng = Rng::new(seed);
+        for c in self.pixels.iter_mut() {
+            // The sum of two uniform numbers is more often close to the middle than far off.
+            let noise = (rng.next_f32() + rng.next_f32() - 1.0) * amount * 255.0;
+            let add = |v: u8| (v as f32 + noise).round().clamp(0.0, 255.0) as u8;
+            (c.r, c.g, c.b) = (add(c.r), add(c.g), add(c.b));
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod effects_tests {
+    use super::*;
+    use crate::{Dim, Region};
+
+    #[test]
+    fn chromatic_aberration_shifts_channels() {
+        let img = Image::blank(Dim { w: 10, h: 1 })
+            .fill_region(
+                Region {
+                    l: Loc { x: 5, y: 0 },
+                    d: Dim { w: 1, h: 1 },
+                },
+                Colour::WHITE,
+            )
+            .chromatic_aberration((1, 0), (-1, 0));
+
+        assert_eq!(
+            img.get_pixel(Loc { x: 6, y: 0 }).unwrap(),
+            Colour::rgb(255, 0, 0)
+        );
+        assert_eq!(
+            img.get_pixel(Loc { x: 5, y: 0 }).unwrap(),
+            Colour::rgb(0, 255, 0)
+        );
+        assert_eq!(
+            img.get_pixel(Loc { x: 4, y: 0 }).unwrap(),
+            Colour::rgb(0, 0, 255)
+        );
+    }
+
+    #[test]
+    fn vignette_and_grain() {
+        let grey = Image::blank_with_colour(Dim { w: 20, h: 10 }, Colour::rgba(200, 200, 200, 90));
+        let vignetted = grey.clone().vignette(1.0);
+        let at = |img: &Image, x, y| img.get_pixel(Loc { x, y }).unwrap();
+        assert!(at(&vignetted, 10, 5).r >= 198);
+        assert!(at(&vignetted, 0, 0).r < 40 && at(&vignetted, 0, 0).a == 90);
+        assert!(at(&vignetted, 0, 5).r > at(&vignetted, 0, 0).r);
+        assert_eq!(grey.clone().vignette(0.0), grey);
+
+        let grainy = grey.clone().add_grain(0.1, 7);
+        assert_eq!(grainy, grey.clone().add_grain(0.1, 7));
+        assert_ne!(grainy, grey);
+        assert!(grainy
+            .pixels
+            .iter()
+            .all(|c| c.r == c.g && c.g == c.b && c.r.abs_diff(200) <= 26 && c.a == 90));
+        let mean = grainy.pixels.iter().map(|c| c.r as f32).sum::<f32>() / 200.0;
+        assert!((mean - 200.0).abs() < 2.0);
+    }
+
+    #[test]
+    #[cfg(feature = "png")]
+    fn glitch_is_deterministic() {
+        let img = Image::load_png("tests/fixtures/test.png").unwrap();
+        let options = GlitchOptions {
+            seed: 42,
+            ..Default::default()
+        };
+        let glitched = img.glitch(options);
+        assert_eq!(glitched.pixels, img.glitch(options).pixels);
+        assert_ne!(glitched.pixels, img.pixels);
+
+        let reseeded = img.glitch(GlitchOptions {
+            seed: 43,
+            ..options
+        });
+        assert_ne!(glitched.pixels, reseeded.pixels);
+    }
+}
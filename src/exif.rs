@@ -0,0 +1,270 @@
+//! A small EXIF parser, covering the main IFD and the EXIF sub-IFD.
+
+use alloc::string::{String, ToString};
+#[cfg(all(feature = "jpeg", not(target_arch = "wasm32")))]
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A single value stored in an EXIF field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExifValue {
+    /// Unsigned bytes (type 1).
+    Byte(Vec<u8>),
+    /// A text string (type 2), without the trailing NUL.
+    Ascii(String),
+    /// Unsigned 16-bit integers (type 3).
+    Short(Vec<u16>),
+    /// Unsigned 32-bit integers (type 4).
+    Long(Vec<u32>),
+    /// Unsigned fractions as `(numerator, denominator)` (type 5).
+    Rational(Vec<(u32, u32)>),
+    /// Signed 32-bit integers (type 9).
+    SignedLong(Vec<i32>),
+    /// Signed fractions as `(numerator, denominator)` (type 10).
+    SignedRational(Vec<(i32, i32)>),
+    /// Raw bytes of a type that has no further interpretation (type 7 and unknown types).
+    Undefined(Vec<u8>),
+}
+
+impl ExifValue {
+    /// Returns the first value as an unsigned integer, if this is an integer field.
+    pub fn as_u32(&self) -> Option<u32> {
+        match self {
+            ExifValue::Byte(v) => v.first().map(|&x| x as u32),
+            ExifValue::Short(v) => v.first().map(|&x| x as u32),
+            ExifValue::Long(v) => v.first().copied(),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a string, if this is an ASCII field.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            ExifValue::Ascii(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+/// EXIF data as found in JPEG files (and in TIFF headers).
+///
+/// Only the fields of the main image (IFD0) and the EXIF sub-IFD are collected; thumbnails,
+/// GPS and maker notes are skipped. Fields are identified by their numeric tag; constants for
+/// the most common tags are provided.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exif {
+    /// The parsed fields as `(tag, value)` pairs.
+    fields: Vec<(u16, ExifValue)>,
+}
+
+impl Exif {
+    /// The tag of the camera manufacturer.
+    pub const MAKE: u16 = 0x010F;
+    /// The tag of the camera model.
+    pub const MODEL: u16 = 0x0110;
+    /// The tag of the orientation of the image.
+    pub const ORIENTATION: u16 = 0x0112;
+    /// The tag of the software used to create the image.
+    pub const SOFTWARE: u16 = 0x0131;
+    /// The tag of the date and time the file was changed.
+    pub const DATE_TIME: u16 = 0x0132;
+    /// The tag of the person who created the image.
+    pub const ARTIST: u16 = 0x013B;
+    /// The tag of the date and time the photo was taken.
+    pub const DATE_TIME_ORIGINAL: u16 = 0x9003;
+    /// The tag of the pointer to the EXIF sub-IFD.
+    const EXIF_IFD_POINTER: u16 = 0x8769;
+
+    /// Parses EXIF data from a TIFF-structured buffer.
+    ///
+    /// The buffer may optionally start with the `Exif\0\0` marker of a JPEG APP1 segment.
+    /// Returns `None` if the data is not valid EXIF.
+    pub fn parse(data: &[u8]) -> Option<Exif> {
+        let data = data.strip_prefix(b"Exif\0\0").unwrap_or(data);
+        let reader = TiffReader {
+            data,
+            big_endian: match data.get(0..2)? {
+                b"II" => false,
+                b"MM" => true,
+                _ => return None,
+            },
+        };
+        if reader.u16(2)? != 42 {
+            return None;
+        }
+
+        let mut fields = Vec::new();
+        reader.read_ifd(reader.u32(4)? as usize, &mut fields)?;
+
+        let sub_ifd = fields
+            .iter()
+            .find(|(tag, _)| *tag == Exif::EXIF_IFD_POINTER)
+            .and_then(|(_, value)| value.as_u32());
+        if let Some(offset) = sub_ifd {
+            // A broken sub-IFD should not invalidate the main fields.
+            let mut sub_fields = Vec::new();
+            if reader.read_ifd(offset as usize, &mut sub_fields).is_some() {
+                fields.extend(sub_fields);
+            }
+        }
+        fields.retain(|(tag, _)| *tag != Exif::EXIF_IFD_POINTER);
+
+        Some(Exif { fields })
+    }
+
+    /// Looks up the value of a field by tag.
+    pub fn get(&self, tag: u16) -> Option<&ExifValue> {
+        self.fields.iter().find(|(t, _)| *t == tag).map(|(_, v)| v)
+    }
+
+    /// Returns all parsed fields as `(tag, value)` pairs.
+    pub fn fields(&self) -> &[(u16, ExifValue)] {
+        &self.fields
+    }
+
+    /// Returns the orientation tag (1 to 8), if present and valid.
+    ///
+    /// 1 means the image is stored upright; the other values describe the rotation and mirroring
+    /// needed to display it correctly.
+    pub fn orientation(&self) -> Option<u16> {
+        self.get(Exif::ORIENTATION)
+            .and_then(ExifValue::as_u32)
+            .filter(|o| (1..=8).contains(o))
+            .map(|o| o as u16)
+    }
+
+    /// Sets the orientation tag, adding it if it was missing.
+    #[cfg(all(feature = "jpeg", not(target_arch = "wasm32")))]
+    pub(crate) fn set_orientation(&mut self, orientation: u16) {
+        self.fields.retain(|(t, _)| *t != Exif::ORIENTATION);
+        self.fields
+            .push((Exif::ORIENTATION, ExifValue::Short(vec![orientation])));
+    }
+
+    /// Returns the camera manufacturer.
+    pub fn make(&self) -> Option<&str> {
+        self.get(Exif::MAKE).and_then(ExifValue::as_str)
+    }
+
+    /// Returns the camera model.
+    pub fn model(&self) -> Option<&str> {
+        self.get(Exif::MODEL).and_then(ExifValue::as_str)
+    }
+
+    /// Returns the date and time the photo was taken, falling back to the modification date.
+    pub fn date_time(&self) -> Option<&str> {
+        self.get(Exif::DATE_TIME_ORIGINAL)
+            .or_else(|| self.get(Exif::DATE_TIME))
+            .and_then(ExifValue::as_str)
+    }
+}
+
+/// Reads values from a TIFF structure with the right byte order.
+struct TiffReader<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl TiffReader<'_> {
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self.data.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    /// Reads all entries of the IFD at `offset` into `fields`.
+    fn read_ifd(&self, offset: usize, fields: &mut Vec<(u16, ExifValue)>) -> Option<()> {
+        let count = self.u16(offset)? as usize;
+        for i in 0..count {
+            let entry = offset + 2 + i * 12;
+            let tag = self.u16(entry)?;
+            let kind = self.u16(entry + 2)?;
+            let n = self.u32(entry + 4)? as usize;
+
+            let size = match kind {
+                1 | 2 | 6 | 7 => 1,
+                3 | 8 => 2,
+                4 | 9 | 11 => 4,
+                5 | 10 | 12 => 8,
+                _ => continue,
+            };
+            let total = size * n;
+            let start = if total <= 4 {
+                entry + 8
+            } else {
+                self.u32(entry + 8)? as usize
+            };
+            let Some(raw) = self.data.get(start..start + total) else {
+                continue;
+            };
+
+            let value = match kind {
+                1 => ExifValue::Byte(raw.to_vec()),
+                2 => ExifValue::Ascii(
+                    String::from_utf8_lossy(raw)
+                        .trim_end_matches('\0')
+                        .to_string(),
+                ),
+                3 => ExifValue::Short((0..n).filter_map(|k| self.u16(start + k * 2)).collect()),
+                4 => ExifValue::Long((0..n).filter_map(|k| self.u32(start + k * 4)).collect()),
+                5 => ExifValue::Rational(
+                    (0..n)
+                        .filter_map(|k| {
+                            Some((self.u32(start + k * 8)?, self.u32(start + k * 8 + 4)?))
+                        })
+                        .collect(),
+                ),
+                9 => ExifValue::SignedLong(
+                    (0..n)
+                        .filter_map(|k| Some(self.u32(start + k * 4)? as i32))
+                        .collect(),
+                ),
+                10 => ExifValue::SignedRational(
+                    (0..n)
+                        .filter_map(|k| {
+                            Some((
+                                self.u32(start + k * 8)? as i32,
+                                self.u32(start + k * 8 + 4)? as i32,
+                            ))
+                        })
+                        .collect(),
+                ),
+                _ => ExifValue::Undefined(raw.to_vec()),
+            };
+            fields.push((tag, value));
+        }
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod exif_tests {
+    use super::*;
+
+    #[test]
+    fn parse_orientation_and_make() {
+        // Little-endian TIFF header with two IFD0 entries: Orientation = 6, Make = "Kodak".
+        let mut data = b"Exif\0\0II\x2a\x00\x08\x00\x00\x00".to_vec();
+        data.extend_from_slice(&[2, 0]);
+        data.extend_from_slice(&[0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0]);
+        data.extend_from_slice(&[0x0F, 0x01, 2, 0, 6, 0, 0, 0, 38, 0, 0, 0]);
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(b"Kodak\0");
+
+        let exif = Exif::parse(&data).unwrap();
+        assert_eq!(exif.orientation(), Some(6));
+        assert_eq!(exif.make(), Some("Kodak"));
+    }
+}
@@ -0,0 +1,300 @@
+//! Fourier transforms of images, for filtering in the frequency domain.
+
+use crate::analysis::brightness;
+use crate::{Colour, Dim, EdgeMode, Image};
+
+/// Transforms `data` in place with a radix-2 fast Fourier transform, or its inverse (without
+/// the division by the length). The length must be a power of two.
+pub(crate) fn fft(data: &mut [(f32, f32)], inverse: bool) {
+    let n = data.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut len = 2;
+    while len <= n {
+        let angle = sign * 2.0 * core::f32::consts::PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let (a, b) = (data[start + k], data[start + k + len / 2]);
+                let t = (b.0 * cos - b.1 * sin, b.0 * sin + b.1 * cos);
+                data[start + k] = (a.0 + t.0, a.1 + t.1);
+                data[start + k + len / 2] = (a.0 - t.0, a.1 - t.1);
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// Transforms a grid of `w` by `h` values, both powers of two, along its rows and then its
+/// columns.
+pub(crate) fn fft_2d(data: &mut [(f32, f32)], w: usize, h: usize, inverse: bool) {
+    for row in data.chunks_mut(w) {
+        fft(row, inverse);
+    }
+    let mut column = vec![(0.0, 0.0); h];
+    for x in 0..w {
+        for (y, c) in column.iter_mut().enumerate() {
+            *c = data[y * w + x];
+        }
+        fft(&mut column, inverse);
+        for (y, c) in column.iter().enumerate() {
+            data[y * w + x] = *c;
+        }
+    }
+}
+
+/// The two-dimensional Fourier transform of the brightness of an image, as returned by
+/// `Image::fft()`.
+///
+/// Frequencies can be filtered with `low_pass()`, `high_pass()` and `notch()`, the result turned
+/// back into a greyscale image with `ifft()`, and the spectrum itself inspected with
+/// `to_image()`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use kodak::*;
+/// // Remove the fine halftone pattern from a scan, then look at what is left.
+/// let scan = Image::load_png("assets/scan.png").unwrap();
+/// let spectrum = scan.fft().low_pass(0.3);
+/// spectrum.to_image().save_png("spectrum.png").unwrap();
+/// spectrum.ifft().save_png("scan_smooth.png").unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spectrum {
+    width: u32,
+    height: u32,
+    original: Dim,
+    values: Vec<(f32, f32)>,
+}
+
+impl Spectrum {
+    /// Returns the dimensions of the spectrum: those of the image, rounded up to powers of two.
+    pub fn get_dimensions(&self) -> Dim {
+        Dim {
+            w: self.width,
+            h: self.height,
+        }
+    }
+
+    /// Returns the complex value at `(u, v)`, as its real and imaginary parts. Negative
+    /// frequencies wrap around, so `(-1, 0)` is the same as `(width - 1, 0)`.
+    pub fn get(&self, u: i32, v: i32) -> (f32, f32) {
+        self.values[self.index(u, v)]
+    }
+
+    /// Returns the index of frequency `(u, v)`, wrapping negative frequencies around.
+    fn index(&self, u: i32, v: i32) -> usize {
+        let x = u.rem_euclid(self.width as i32) as usize;
+        let y = v.rem_euclid(self.height as i32) as usize;
+        y * self.width as usize + x
+    }
+
+    /// Multiplies every value by `gain`, which is given the distance of its frequency from zero,
+    /// where 1.0 is the highest frequency that the image can hold along either axis.
+    fn scale(mut self, gain: impl Fn(f32) -> f32) -> Spectrum {
+        let (w, h) = (self.width as i32, self.height as i32);
+        for (i, value) in self.values.iter_mut().enumerate() {
+            let (x, y) = (i as i32 % w, i as i32 / w);
+            let u = if x > w / 2 { x - w } else { x } as f32 / (w as f32 / 2.0).max(1.0);
+            let v = if y > h / 2 { y - h } else { y } as f32 / (h as f32 / 2.0).max(1.0);
+            let g = gain(u.hypot(v));
+            *value = (value.0 * g, value.1 * g);
+        }
+        self
+    }
+
+    /// Attenuates high frequencies with a Gaussian, which smooths the image without the ringing
+    /// of a hard cut-off. Frequencies at `cutoff` keep about 60% of their strength, where 1.0 is
+    /// the highest frequency along either axis.
+    pub fn low_pass(self, cutoff: f32) -> Spectrum {
+        let cutoff = cutoff.max(f32::EPSILON);
+        self.scale(|r| (-(r * r) / (2.0 * cutoff * cutoff)).exp())
+    }
+
+    /// Attenuates low frequencies, the opposite of `low_pass()`, which keeps edges and fine
+    /// detail. The average brightness is kept, so the result is not mostly black.
+    pub fn high_pass(self, cutoff: f32) -> Spectrum {
+        let cutoff = cutoff.max(f32::EPSILON);
+        self.scale(|r| match r {
+            0.0 => 1.0,
+            r => 1.0 - (-(r * r) / (2.0 * cutoff * cutoff)).exp(),
+        })
+    }
+
+    /// Removes the frequency `(u, v)` and everything within `radius` of it, along with its
+    /// mirror image `(-u, -v)`. This takes out periodic noise, such as scan lines or a moiré,
+    /// which shows up as bright dots away from the centre of `to_image()`.
+    pub fn notch(mut self, u: i32, v: i32, radius: f32) -> Spectrum {
+        let r = radius.max(0.0).floor() as i32;
+        for (cu, cv) in [(u, v), (-u, -v)] {
+            for dv in -r..=r {
+                for du in -r..=r {
+                    if ((du * du + dv * dv) as f32) <= radius * radius {
+                        let i = self.index(cu + du, cv + dv);
+                        self.values[i] = (0.0, 0.0);
+                    }
+                }
+            }
+        }
+        self
+    }
+
+    /// Transforms the spectrum back into an opaque greyscale image of the size of the original.
+    pub fn ifft(&self) -> Image {
+        let (w, h) = (self.width as usize, self.height as usize);
+        let mut values = self.values.clone();
+        fft_2d(&mut values, w, h, true);
+        let scale = 255.0 / (w * h) as f32;
+        let pixels = (0..self.original.h as usize)
+            .flat_map(|y| values[y * w..][..self.original.w as usize].iter())
+            .map(|v| {
+                let grey = (v.0 * scale).round().clamp(0.0, 255.0) as u8;
+                Colour::rgb(grey, grey, grey)
+            })
+            .collect();
+        Image {
+            pixels,
+            ..Image::blank(self.original)
+        }
+    }
+
+    /// Draws the magnitude of every frequency as an opaque greyscale image, on a logarithmic
+    /// scale from black to white, with the lowest frequencies in the centre as is customary.
+    pub fn to_image(&self) -> Image {
+        let (w, h) = (self.width as usize, self.height as usize);
+        let magnitudes: Vec<f32> = self.values.iter().map(|v| v.0.hypot(v.1).ln_1p()).collect();
+        let largest = magnitudes.iter().copied().fold(0.0, f32::max);
+        let pixels = (0..w * h)
+            .map(|i| {
+                let (x, y) = ((i % w + w / 2) % w, (i / w + h / 2) % h);
+                let grey = match largest {
+                    0.0 => 0,
+                    largest => (magnitudes[y * w + x] / largest * 255.0).round() as u8,
+                };
+                Colour::rgb(grey, grey, grey)
+            })
+            .collect();
+        Image {
+            pixels,
+            ..Image::blank(self.get_dimensions())
+        }
+    }
+}
+
+// The following impl block defines frequency domain functions for Images.
+impl Image {
+    /// Computes the Fourier transform of the image's brightness, which describes it as a sum of
+    /// waves of different frequencies.
+    ///
+    /// The image is extended to the next powers of two in both dimensions by mirroring it, so
+    /// that its borders do not show up as strong vertical and horizontal frequencies. Colour and
+    /// alpha are not kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let img = Image::blank_with_colour(Dim { w: 20, h: 10 }, Colour::rgb(90, 90, 90));
+    /// let spectrum = img.fft();
+    /// assert_eq!(spectrum.get_dimensions(), Dim { w: 32, h: 16 });
+    /// assert_eq!(spectrum.ifft(), img);
+    /// ```
+    pub fn fft(&self) -> Spectrum {
+        let (w, h) = (
+            self.width.next_power_of_two(),
+            self.height.next_power_of_two(),
+        );
+        let values = brightness(self);
+        let mut data = Vec::with_capacity(w as usize * h as usize);
+        if !values.is_empty() {
+            let (iw, ih) = (self.width as i64, self.height as i64);
+            for y in 0..h as i64 {
+                let y = EdgeMode::Mirror.resolve(y, ih).unwrap_or(0);
+                for x in 0..w as i64 {
+                    let x = EdgeMode::Mirror.resolve(x, iw).unwrap_or(0);
+                    data.push((values[(y * iw + x) as usize], 0.0));
+                }
+            }
+            fft_2d(&mut data, w as usize, h as usize, false);
+        } else {
+            data.resize(w as usize * h as usize, (0.0, 0.0));
+        }
+        Spectrum {
+            width: w,
+            height: h,
+            original: self.get_dimensions(),
+            values: data,
+        }
+    }
+}
+
+#[cfg(test)]
+mod fft_tests {
+    use super::*;
+    use crate::{Loc, Region};
+
+    #[test]
+    fn fft_round_trip() {
+        let original: Vec<(f32, f32)> = (0..16).map(|i| ((i * i % 7) as f32, 0.0)).collect();
+        let mut data = original.clone();
+        fft(&mut data, false);
+        assert!((data[0].0 - original.iter().map(|v| v.0).sum::<f32>()).abs() < 1e-3);
+        fft(&mut data, true);
+        for (a, b) in data.iter().zip(&original) {
+            assert!((a.0 / 16.0 - b.0).abs() < 1e-4 && (a.1 / 16.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn filters() {
+        // Grey with a bright stripe in every fourth column.
+        let mut striped = Image::blank_with_colour(Dim::square(32), Colour::rgb(100, 100, 100));
+        for row in striped.rows_mut() {
+            for pixel in row.iter_mut().step_by(4) {
+                *pixel = Colour::rgb(200, 200, 200);
+            }
+        }
+        let spectrum = striped.fft();
+        assert!(spectrum.get(8, 0).0.abs() > 10.0);
+        let view = spectrum.to_image();
+        assert_eq!(view.get_pixel(Loc { x: 16, y: 16 }), Ok(Colour::WHITE));
+
+        // The stripes are periodic noise at a quarter of the width, and their harmonics.
+        let flat = |img: &Image| {
+            let reds: Vec<u8> = img.pixels.iter().map(|c| c.r).collect();
+            reds.iter().max().unwrap() - reds.iter().min().unwrap() <= 2
+        };
+        let notched = spectrum.clone().notch(8, 0, 0.0).notch(16, 0, 0.0).ifft();
+        assert!(flat(&notched), "{:?}", notched.row(0));
+        assert_eq!(notched.get_pixel(Loc { x: 1, y: 0 }).unwrap().r, 125);
+        assert!(flat(&spectrum.clone().low_pass(0.1).ifft()));
+
+        // High-pass filtering keeps the average of a flat image and the edges of a square.
+        let grey = Image::blank_with_colour(Dim::square(8), Colour::rgb(60, 60, 60));
+        assert_eq!(grey.fft().high_pass(0.5).ifft(), grey);
+        let square = grey.clone().fill_region(
+            Region {
+                l: Loc { x: 2, y: 2 },
+                d: Dim::square(4),
+            },
+            Colour::WHITE,
+        );
+        let edges = square.fft().high_pass(0.5).ifft();
+        assert!(edges.get_pixel(Loc { x: 2, y: 2 }).unwrap().r > 128);
+
+        let empty = Image::blank(Dim { w: 0, h: 4 });
+        assert_eq!(empty.fft().ifft(), empty);
+    }
+}
@@ -0,0 +1,413 @@
+//! Convolution filters: arbitrary kernels, Gaussian blur and unsharp masking.
+
+use crate::{Colour, EdgeMode, Image};
+
+/// A square convolution kernel with an odd side length, as used by `Image::convolve()`.
+///
+/// Weights are stored row by row. They are not normalised, so a kernel whose weights do not sum
+/// to one brightens or darkens the image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Kernel {
+    size: u32,
+    weights: Vec<f32>,
+}
+
+impl Kernel {
+    /// Creates a kernel of `size` by `size` weights.
+    ///
+    /// This returns an `Err` if `size` is even or if there are not exactly `size * size`
+    /// weights.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// // Emboss.
+    /// let kernel = Kernel::new(3, vec![-2.0, -1.0, 0.0, -1.0, 1.0, 1.0, 0.0, 1.0, 2.0]).unwrap();
+    /// assert!(Kernel::new(2, vec![0.25; 4]).is_err());
+    /// ```
+    pub fn new(size: u32, weights: Vec<f32>) -> Result<Kernel, &'static str> {
+        if size.is_multiple_of(2) {
+            return Err("A kernel needs an odd side length.");
+        }
+        if weights.len() != size as usize * size as usize {
+            return Err("A kernel needs exactly size * size weights.");
+        }
+        Ok(Kernel { size, weights })
+    }
+
+    /// Creates a normalised Gaussian kernel. It extends three standard deviations from the
+    /// centre, so large values of `sigma` make for slow convolutions; `Image::blur()` is much
+    /// faster for those.
+    pub fn gaussian(sigma: f32) -> Kernel {
+        let line = gaussian_weights(sigma);
+        let weights = line
+            .iter()
+            .flat_map(|y| line.iter().map(move |x| x * y))
+            .collect();
+        Kernel {
+            size: line.len() as u32,
+            weights,
+        }
+    }
+
+    /// Creates a 3 by 3 kernel that sharpens edges.
+    pub fn sharpen() -> Kernel {
+        Kernel {
+            size: 3,
+            weights: vec![0.0, -1.0, 0.0, -1.0, 5.0, -1.0, 0.0, -1.0, 0.0],
+        }
+    }
+
+    /// Returns the side length of the kernel.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the weights of the kernel, row by row.
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+}
+
+/// Options for `Image::unsharp_mask()` and `Image::smart_resize_with()`.
+///
+/// The defaults are a light touch meant for freshly downscaled images: enough to bring back the
+/// crispness that averaging takes away, without visible halos.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnsharpMask {
+    /// The standard deviation of the blur that the image is compared against, in pixels. Larger
+    /// values sharpen coarser detail.
+    pub sigma: f32,
+    /// How much of the difference from the blurred image is added back: 0.0 leaves the image
+    /// alone and 1.0 doubles the contrast of edges.
+    pub amount: f32,
+    /// Differences from the blurred image no larger than this are left alone, so that noise and
+    /// smooth gradients are not sharpened along with edges.
+    pub threshold: u8,
+}
+
+impl Default for UnsharpMask {
+    fn default() -> Self {
+        UnsharpMask {
+            sigma: 0.5,
+            amount: 0.5,
+            threshold: 2,
+        }
+    }
+}
+
+/// Returns the normalised weights of a one-dimensional Gaussian that extends three standard
+/// deviations either way. A non-positive `sigma` gives the single weight 1.
+pub(crate) fn gaussian_weights(sigma: f32) -> Vec<f32> {
+    if sigma <= 0.0 {
+        return vec![1.0];
+    }
+    let radius = (3.0 * sigma).ceil() as i32;
+    let weights: Vec<f32> = (-radius..=radius)
+        .map(|i| (-(i * i) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total: f32 = weights.iter().sum();
+    weights.into_iter().map(|w| w / total).collect()
+}
+
+/// Returns the premultiplied channels of a colour, ready to be summed.
+pub(crate) fn premultiplied_channels(c: Colour) -> [f32; 4] {
+    let p = c.premultiply();
+    [p.r as f32, p.g as f32, p.b as f32, p.a as f32]
+}
+
+/// Turns a premultiplied sum back into a straight-alpha colour.
+pub(crate) fn to_colour(sum: [f32; 4]) -> Colour {
+    let channel = |v: f32| v.round().clamp(0.0, 255.0) as u8;
+    Colour::rgba(
+        channel(sum[0]),
+        channel(sum[1]),
+        channel(sum[2]),
+        channel(sum[3]),
+    )
+    .unpremultiply()
+}
+
+// The following impl block defines convolution functions for Images.
+impl Image {
+    /// Convolves the image with a kernel, repeating edge pixels beyond the border.
+    ///
+    /// The sums are computed on premultiplied colours, so transparent pixels do not bleed their
+    /// colour into their neighbours.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use kodak::*;
+    /// let img = Image::load_png("assets/olle_ma.png").unwrap();
+    /// img.convolve(&Kernel::sharpen()).save_png("olle_sharp.png").unwrap();
+    /// ```
+    pub fn convolve(&self, kernel: &Kernel) -> Image {
+        self.convolve_with_edge(kernel, EdgeMode::Clamp)
+    }
+
+    /// Convolves the image with a kernel like `convolve()`, with `edge` deciding what lies
+    /// beyond the border. `EdgeMode::Wrap` suits tiling textures, whose opposite sides meet.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(width = self.width, height = self.height, size = kernel.size)
+        )
+    )]
+    pub fn convolve_with_edge(&self, kernel: &Kernel, edge: EdgeMode) -> Image {
+        let (w, h) = (self.width as i64, self.height as i64);
+        let r = kernel.size as i64 / 2;
+        let premultiplied: Vec<[f32; 4]> = self
+            .pixels
+            .iter()
+            .map(|&c| premultiplied_channels(c))
+            .collect();
+        let constant = match edge {
+            EdgeMode::Constant(c) => premultiplied_channels(c),
+            _ => [0.0; 4],
+        };
+        let at = |x: i64, y: i64| match (edge.resolve(x, w), edge.resolve(y, h)) {
+            (Some(x), Some(y)) => premultiplied[(y * w + x) as usize],
+            _ => constant,
+        };
+
+        let mut pixels = Vec::with_capacity(self.pixels.len());
+        for y in 0..h {
+            for x in 0..w {
+                let mut sum = [0.0f32; 4];
+                for ky in -r..=r {
+                    for kx in -r..=r {
+                        let weight =
+                            kernel.weights[((ky + r) * kernel.size as i64 + kx + r) as usize];
+                        let p = at(x + kx, y + ky);
+                        for (s, v) in sum.iter_mut().zip(p) {
+                            *s += weight * v;
+                        }
+                    }
+                }
+                pixels.push(to_colour(sum));
+            }
+        }
+        Image {
+            pixels: pixels.into(),
+            ..self.clone()
+        }
+    }
+
+    /// Blurs the image with a Gaussian of standard deviation `sigma` pixels, repeating edge
+    /// pixels beyond the border.
+    ///
+    /// The blur is applied horizontally and then vertically, which gives the same result as
+    /// convolving with `Kernel::gaussian(sigma)` in a fraction of the time.
+    pub fn blur(&self, sigma: f32) -> Image {
+        self.blur_with_edge(sigma, EdgeMode::Clamp)
+    }
+
+    /// Blurs the image like `blur()`, with `edge` deciding what lies beyond the border.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(width = self.width, height = self.height, sigma = sigma)
+        )
+    )]
+    pub fn blur_with_edge(&self, sigma: f32, edge: EdgeMode) -> Image {
+        let weights = gaussian_weights(sigma);
+        let (w, h) = (self.width as i64, self.height as i64);
+        let r = weights.len() as i64 / 2;
+
+        let premultiplied: Vec<[f32; 4]> = self
+            .pixels
+            .iter()
+            .map(|&c| premultiplied_channels(c))
+            .collect();
+
+        let constant = match edge {
+            EdgeMode::Constant(c) => premultiplied_channels(c),
+            _ => [0.0; 4],
+        };
+
+        // Passes along rows, then along columns, keeping the intermediate sums unrounded.
+        let pass = |values: &[[f32; 4]], horizontal: bool| -> Vec<[f32; 4]> {
+            let mut out = Vec::with_capacity(values.len());
+            for y in 0..h {
+                for x in 0..w {
+                    let mut sum = [0.0f32; 4];
+                    for k in -r..=r {
+                        let source = if horizontal {
+                            edge.resolve(x + k, w).map(|sx| (sx, y))
+                        } else {
+                            edge.resolve(y + k, h).map(|sy| (x, sy))
+                        };
+                        let p = source.map_or(constant, |(sx, sy)| values[(sy * w + sx) as usize]);
+                        for (s, v) in sum.iter_mut().zip(p) {
+                            *s += weights[(k + r) as usize] * v;
+                        }
+                    }
+                    out.push(sum);
+                }
+            }
+            out
+        };
+        let rows = pass(&premultiplied, true);
+        let pixels = pass(&rows, false).into_iter().map(to_colour).collect();
+
+        Image {
+            pixels,
+            ..self.clone()
+        }
+    }
+
+    /// Sharpens the image by adding back the difference between it and a blurred copy of it.
+    ///
+    /// Each colour channel is pushed away from its blurred value by `options.amount` times the
+    /// difference, unless that difference is within `options.threshold`. Alpha is left as it is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let img = Image::blank_with_colour(Dim { w: 4, h: 1 }, Colour::rgb(100, 100, 100))
+    ///     .fill_region(Region::from_top_left(Dim { w: 2, h: 1 }), Colour::rgb(50, 50, 50));
+    /// let sharp = img.unsharp_mask(UnsharpMask { sigma: 1.0, amount: 1.0, threshold: 0 });
+    /// // Either side of the edge moves away from the other.
+    /// assert!(sharp.get_pixel(Loc { x: 1, y: 0 }).unwrap().r < 50);
+    /// assert!(sharp.get_pixel(Loc { x: 2, y: 0 }).unwrap().r > 100);
+    /// ```
+    pub fn unsharp_mask(&self, options: UnsharpMask) -> Image {
+        let blurred = self.blur(options.sigma);
+        let sharpen = |original: u8, blurred: u8| {
+            let difference = original as f32 - blurred as f32;
+            if difference.abs() <= options.threshold as f32 {
+                return original;
+            }
+            (original as f32 + options.amount * difference)
+                .round()
+                .clamp(0.0, 255.0) as u8
+        };
+        let pixels = self
+            .pixels
+            .iter()
+            .zip(blurred.pixels.iter())
+            .map(|(&c, &b)| Colour {
+                r: sharpen(c.r, b.r),
+                g: sharpen(c.g, b.g),
+                b: sharpen(c.b, b.b),
+                a: c.a,
+            })
+            .collect();
+        Image {
+            pixels,
+            ..self.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod filter_tests {
+    use super::*;
+    use crate::{Dim, Loc, Region};
+
+    #[test]
+    fn identity_kernel_keeps_pixels() {
+        let img = Image::blank(Dim::square(5)).fill_region(
+            Region {
+                l: Loc { x: 1, y: 2 },
+                d: Dim { w: 3, h: 1 },
+            },
+            Colour::rgb(200, 100, 50),
+        );
+        let mut weights = vec![0.0; 9];
+        weights[4] = 1.0;
+        let identity = Kernel::new(3, weights).unwrap();
+
+        assert_eq!(img.convolve(&identity).pixels, img.pixels);
+        assert_eq!(img.blur(0.0).pixels, img.pixels);
+    }
+
+    #[test]
+    fn blur_matches_gaussian_kernel() {
+        let img = Image::blank(Dim::square(9)).fill_region(
+            Region {
+                l: Loc { x: 4, y: 0 },
+                d: Dim { w: 5, h: 9 },
+            },
+            Colour::WHITE,
+        );
+        let blurred = img.blur(1.0);
+        let convolved = img.convolve(&Kernel::gaussian(1.0));
+
+        for (a, b) in blurred.pixels.iter().zip(&convolved.pixels) {
+            assert!(a.r.abs_diff(b.r) <= 1);
+        }
+        let edge = blurred.get_pixel(Loc { x: 4, y: 4 }).unwrap();
+        assert!(edge.r > 128 && edge.r < 255);
+    }
+
+    #[test]
+    fn edge_modes() {
+        // White on the right half, so only wrapping brings white in on the left.
+        let img = Image::blank(Dim::square(8)).fill_region(
+            Region {
+                l: Loc { x: 4, y: 0 },
+                d: Dim { w: 4, h: 8 },
+            },
+            Colour::WHITE,
+        );
+        let left = |img: &Image| img.get_pixel(Loc { x: 0, y: 4 }).unwrap();
+        let box_blur = Kernel::new(3, vec![1.0 / 9.0; 9]).unwrap();
+
+        assert_eq!(left(&img.convolve(&box_blur)), Colour::BLACK);
+        assert_eq!(
+            left(&img.convolve_with_edge(&box_blur, EdgeMode::Wrap)),
+            Colour::rgb(85, 85, 85)
+        );
+        let red = EdgeMode::Constant(Colour::rgb(255, 0, 0));
+        assert_eq!(
+            left(&img.convolve_with_edge(&box_blur, red)),
+            Colour::rgb(85, 0, 0)
+        );
+        assert!(left(&img.blur_with_edge(1.0, EdgeMode::Wrap)).r > 0);
+        assert_eq!(img.blur_with_edge(1.0, EdgeMode::Clamp), img.blur(1.0));
+        assert_eq!(
+            left(&img.blur_with_edge(1.0, EdgeMode::Mirror)),
+            Colour::BLACK
+        );
+    }
+
+    #[test]
+    fn unsharp_mask_respects_threshold() {
+        let grey = Colour::rgb(120, 120, 120);
+        let img = Image::blank_with_colour(Dim { w: 6, h: 1 }, grey).fill_region(
+            Region::from_top_left(Dim { w: 3, h: 1 }),
+            Colour::rgba(124, 124, 124, 200),
+        );
+        let options = UnsharpMask {
+            sigma: 1.0,
+            amount: 1.0,
+            threshold: 0,
+        };
+        let sharp = img.unsharp_mask(options);
+        let at = |img: &Image, x| img.get_pixel(Loc { x, y: 0 }).unwrap();
+        assert!(at(&sharp, 2).r > 124 && at(&sharp, 3).r < 120);
+        assert_eq!(at(&sharp, 2).a, 200);
+
+        // A step of four is too small to count as an edge with a threshold of four.
+        let subtle = img.unsharp_mask(UnsharpMask {
+            threshold: 4,
+            ..options
+        });
+        assert_eq!(subtle, img);
+        assert_eq!(
+            img.unsharp_mask(UnsharpMask {
+                amount: 0.0,
+                ..options
+            }),
+            img
+        );
+    }
+}
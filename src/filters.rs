@@ -0,0 +1,19 @@
+//! Ready-made filters as values, for applying the same look to many images or choosing one at
+//! run time.
+
+use crate::{Image, Preset};
+
+/// Returns a filter that gives an image the look of `preset`, as `Image::apply_preset()` does.
+///
+/// # Examples
+///
+/// ```
+/// # use kodak::*;
+/// let lomo = filters::preset(Preset::Lomo);
+/// let photos = vec![Image::blank(Dim::square(4)); 3];
+/// let graded: Vec<Image> = photos.into_iter().map(&lomo).collect();
+/// assert_eq!(graded[0], Image::blank(Dim::square(4)).apply_preset(Preset::Lomo));
+/// ```
+pub fn preset(preset: Preset) -> impl Fn(Image) -> Image {
+    move |img| img.apply_preset(preset)
+}
@@ -0,0 +1,213 @@
+//! AVIF decoding through a pure Rust AV1 decoder. Only available with the `avif` feature.
+
+use crate::{Colour, Dim, Image};
+use re_rav1d::dav1d::pixel::{MatrixCoefficients, YUVRange};
+use re_rav1d::dav1d::{Decoder, Error, Picture, PixelLayout, PlanarImageComponent, Settings};
+use std::io;
+use std::path::Path;
+
+fn invalid_data(message: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Decodes a single AV1 frame, as stored in an AVIF item.
+fn decode_frame(data: Vec<u8>) -> io::Result<Picture> {
+    // A single still frame gains nothing from frame threading, and without it the decoder
+    // hands out the picture as soon as the data has been sent.
+    let mut settings = Settings::new();
+    settings.set_n_threads(1);
+    settings.set_max_frame_delay(1);
+    let mut decoder = Decoder::with_settings(&settings).map_err(invalid_data)?;
+    let mut sent = decoder.send_data(data, None, None, None);
+    loop {
+        match decoder.get_picture() {
+            Ok(picture) => return Ok(picture),
+            Err(Error::Again) => match sent {
+                Err(Error::Again) => sent = decoder.send_pending_data(),
+                Err(e) => return Err(invalid_data(e)),
+                // All data has been sent, so drain the decoder.
+                Ok(()) => {
+                    return match decoder.get_picture() {
+                        Ok(picture) => Ok(picture),
+                        Err(_) => Err(invalid_data("The AV1 data contains no frame.")),
+                    }
+                }
+            },
+            Err(e) => return Err(invalid_data(e)),
+        }
+    }
+}
+
+/// Reads one sample of a plane, normalised so that the nominal range of the luma (and alpha)
+/// maps to 0.0..=1.0 and chroma is centred on 0.0.
+struct PlaneReader {
+    data: Vec<u8>,
+    stride: usize,
+    bits: usize,
+    full_range: bool,
+}
+
+impl PlaneReader {
+    fn new(picture: &Picture, component: PlanarImageComponent) -> Self {
+        PlaneReader {
+            data: picture.plane(component).to_vec(),
+            stride: picture.stride(component) as usize,
+            bits: picture.bit_depth(),
+            full_range: picture.color_range() == YUVRange::Full,
+        }
+    }
+
+    fn raw(&self, x: usize, y: usize) -> f32 {
+        if self.bits > 8 {
+            let i = y * self.stride + x * 2;
+            u16::from_ne_bytes([self.data[i], self.data[i + 1]]) as f32
+        } else {
+            self.data[y * self.stride + x] as f32
+        }
+    }
+
+    fn luma(&self, x: usize, y: usize) -> f32 {
+        let scale = (1 << (self.bits - 8)) as f32;
+        if self.full_range {
+            self.raw(x, y) / ((1 << self.bits) - 1) as f32
+        } else {
+            (self.raw(x, y) - 16.0 * scale) / (219.0 * scale)
+        }
+    }
+
+    fn chroma(&self, x: usize, y: usize) -> f32 {
+        let scale = (1 << (self.bits - 8)) as f32;
+        let centred = self.raw(x, y) - 128.0 * scale;
+        if self.full_range {
+            centred / ((1 << self.bits) - 1) as f32
+        } else {
+            centred / (224.0 * scale)
+        }
+    }
+}
+
+/// Converts normalised Y'CbCr to R'G'B' using the luma coefficients `kr` and `kb`.
+fn yuv_to_rgb(y: f32, cb: f32, cr: f32, (kr, kb): (f32, f32)) -> [f32; 3] {
+    let r = y + 2.0 * (1.0 - kr) * cr;
+    let b = y + 2.0 * (1.0 - kb) * cb;
+    let g = (y - kr * r - kb * b) / (1.0 - kr - kb);
+    [r, g, b]
+}
+
+fn to_u8(v: f32) -> u8 {
+    (v.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Converts a decoded frame to colours, ignoring alpha.
+fn picture_to_colours(picture: &Picture) -> Vec<Colour> {
+    let (width, height) = (picture.width() as usize, picture.height() as usize);
+    let layout = picture.pixel_layout();
+    let matrix = picture.matrix_coefficients();
+    let coefficients = match matrix {
+        MatrixCoefficients::BT709 => (0.2126, 0.0722),
+        MatrixCoefficients::BT2020NonConstantLuminance
+        | MatrixCoefficients::BT2020ConstantLuminance => (0.2627, 0.0593),
+        MatrixCoefficients::ST240M => (0.212, 0.087),
+        // BT.601 is what most encoders assume when nothing is specified.
+        _ => (0.299, 0.114),
+    };
+
+    let luma = PlaneReader::new(picture, PlanarImageComponent::Y);
+    let chroma = (layout != PixelLayout::I400).then(|| {
+        (
+            PlaneReader::new(picture, PlanarImageComponent::U),
+            PlaneReader::new(picture, PlanarImageComponent::V),
+        )
+    });
+    let (shift_x, shift_y) = match layout {
+        PixelLayout::I420 => (1, 1),
+        PixelLayout::I422 => (1, 0),
+        _ => (0, 0),
+    };
+
+    let mut colours = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let l = luma.luma(x, y);
+            let [r, g, b] = match &chroma {
+                None => [l; 3],
+                Some((u, v)) => {
+                    let (cx, cy) = (x >> shift_x, y >> shift_y);
+                    if matrix == MatrixCoefficients::Identity {
+                        // Planes hold G, B and R directly.
+                        [v.luma(cx, cy), l, u.luma(cx, cy)]
+                    } else {
+                        yuv_to_rgb(l, u.chroma(cx, cy), v.chroma(cx, cy), coefficients)
+                    }
+                }
+            };
+            colours.push(Colour::rgb(to_u8(r), to_u8(g), to_u8(b)));
+        }
+    }
+    colours
+}
+
+// The following impl block defines AVIF functions for Images.
+impl Image {
+    /// Loads a (still) AVIF image as an Image struct, including its alpha channel if it has one.
+    ///
+    /// This returns an `Err` if the file could not be read, or with `io::ErrorKind::InvalidData`
+    /// if it is not a valid AVIF file. Image sequences are not supported.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use kodak::*;
+    /// let img = Image::load_avif("assets/photo.avif").unwrap();
+    /// img.save_png("photo.png").unwrap();
+    /// ```
+    pub fn load_avif(file_name: impl AsRef<Path>) -> io::Result<Self> {
+        let data = std::fs::read(file_name)?;
+        let avif = avif_parse::read_avif(&mut data.as_slice())
+            .map_err(|e| invalid_data(format!("{:?}", e)))?;
+
+        let picture = decode_frame(avif.primary_item.to_vec())?;
+        let mut pixels = picture_to_colours(&picture);
+
+        if let Some(alpha_item) = &avif.alpha_item {
+            let alpha = decode_frame(alpha_item.to_vec())?;
+            if (alpha.width(), alpha.height()) != (picture.width(), picture.height()) {
+                return Err(invalid_data(
+                    "The alpha channel does not match the image size.",
+                ));
+            }
+            let plane = PlaneReader::new(&alpha, PlanarImageComponent::Y);
+            let width = picture.width() as usize;
+            for (i, pixel) in pixels.iter_mut().enumerate() {
+                pixel.a = to_u8(plane.luma(i % width, i / width));
+            }
+            if avif.premultiplied_alpha {
+                pixels = pixels.into_iter().map(Colour::unpremultiply).collect();
+            }
+        }
+
+        Ok(Image::from_pixels(
+            Dim {
+                w: picture.width(),
+                h: picture.height(),
+            },
+            pixels,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod avif_tests {
+    use super::*;
+
+    #[test]
+    fn yuv_conversion() {
+        let bt601 = (0.299, 0.114);
+        let grey = yuv_to_rgb(0.5, 0.0, 0.0, bt601);
+        assert!(grey.iter().all(|&c| (c - 0.5).abs() < 1e-6));
+
+        // Pure red in BT.601: Y' = 0.299, Cb = -0.1687, Cr = 0.5.
+        let red = yuv_to_rgb(0.299, -0.168_736, 0.5, bt601);
+        assert_eq!(red.map(to_u8), [255, 0, 0]);
+    }
+}
@@ -0,0 +1,77 @@
+//! farbfeld decoding and encoding. farbfeld is a trivial format from the suckless project:
+//! a magic string, the dimensions, and 16-bit big-endian RGBA samples.
+
+use crate::{Colour, Dim, Image};
+use std::io;
+use std::path::Path;
+
+const MAGIC: &[u8; 8] = b"farbfeld";
+
+// The following impl block defines farbfeld functions for Images.
+impl Image {
+    /// Loads a farbfeld image as an Image struct. The 16-bit samples are reduced to 8 bits.
+    ///
+    /// This returns an `Err` if the file could not be read, or with `io::ErrorKind::InvalidData`
+    /// if it is not a valid farbfeld file.
+    pub fn load_farbfeld(file_name: impl AsRef<Path>) -> io::Result<Self> {
+        let data = std::fs::read(file_name)?;
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Not a valid farbfeld file.");
+
+        if data.get(..8) != Some(MAGIC) || data.len() < 16 {
+            return Err(invalid());
+        }
+        let width = u32::from_be_bytes(data[8..12].try_into().unwrap());
+        let height = u32::from_be_bytes(data[12..16].try_into().unwrap());
+        let expected = (width as u64 * height as u64)
+            .checked_mul(8)
+            .filter(|&n| n == data.len() as u64 - 16)
+            .ok_or_else(invalid)?;
+
+        let pixels = data[16..16 + expected as usize]
+            .chunks_exact(8)
+            .map(|c| Colour::rgba(c[0], c[2], c[4], c[6]))
+            .collect();
+
+        Ok(Image::from_pixels(
+            Dim {
+                w: width,
+                h: height,
+            },
+            pixels,
+        ))
+    }
+
+    /// Saves an Image as a farbfeld file. Every 8-bit sample is widened to 16 bits exactly, so
+    /// loading the file again gives back the same pixels.
+    pub fn save_farbfeld(&self, file_name: impl AsRef<Path>) -> io::Result<()> {
+        let mut data = Vec::with_capacity(16 + self.pixels.len() * 8);
+        data.extend_from_slice(MAGIC);
+        data.extend_from_slice(&self.width.to_be_bytes());
+        data.extend_from_slice(&self.height.to_be_bytes());
+        for c in &self.pixels {
+            for v in [c.r, c.g, c.b, c.a] {
+                // Multiplying by 257 maps 0..=255 onto 0..=65535.
+                data.extend_from_slice(&(v as u16 * 257).to_be_bytes());
+            }
+        }
+
+        std::fs::write(file_name, data)
+    }
+}
+
+#[cfg(test)]
+mod farbfeld_tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let path = std::env::temp_dir().join("kodak_round_trip.ff");
+        let img = Image::blank_with_colour(Dim { w: 3, h: 2 }, Colour::rgba(1, 128, 255, 40));
+
+        img.save_farbfeld(&path).unwrap();
+        let loaded = Image::load_farbfeld(&path).unwrap();
+
+        assert_eq!(loaded.get_dimensions(), img.get_dimensions());
+        assert_eq!(loaded.pixels, img.pixels);
+    }
+}
@@ -0,0 +1,91 @@
+//! ICO encoding, for bundling several sizes of an icon (such as a favicon) into one file.
+
+use crate::{Colour, Dim, Image, Loc, Metadata, ResizeFilter};
+use std::io;
+use std::path::Path;
+
+// The following impl block defines ICO functions for Images.
+impl Image {
+    /// Saves the image as an .ico file containing one entry for each of the given sizes.
+    ///
+    /// Each entry is a square of `size` by `size` pixels. Images that are not square are scaled
+    /// to fit and centred on a transparent background. The entries are stored as PNG data, which
+    /// every browser and Windows Vista or later understands.
+    ///
+    /// Sizes must lie between 1 and 256; otherwise, or if `sizes` is empty, this returns an `Err`
+    /// with `io::ErrorKind::InvalidInput`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use kodak::*;
+    /// let logo = Image::load_png("assets/logo.png").unwrap();
+    /// logo.save_ico("favicon.ico", &[16, 32, 48]).unwrap();
+    /// ```
+    pub fn save_ico(&self, file_name: impl AsRef<Path>, sizes: &[u32]) -> io::Result<()> {
+        if sizes.is_empty() || sizes.iter().any(|s| !(1..=256).contains(s)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "ICO sizes must lie between 1 and 256 pixels.",
+            ));
+        }
+
+        let mut entries = Vec::with_capacity(sizes.len());
+        for &size in sizes {
+            let fitted = self.resize_to_fit(Dim::square(size), ResizeFilter::Bilinear);
+            let offset = Loc {
+                x: (size - fitted.width) / 2,
+                y: (size - fitted.height) / 2,
+            };
+            let icon = Image::blank_with_colour(Dim::square(size), Colour::TRANSPARENT)
+                .overlay(fitted, offset);
+
+            let mut png = Vec::new();
+            icon.encode_png(&mut png, &Metadata::default())
+                .map_err(io::Error::other)?;
+            entries.push((size, png));
+        }
+
+        // The header, then a 16-byte directory entry per image, then the image data.
+        let mut data = Vec::new();
+        data.extend_from_slice(&[0, 0, 1, 0]);
+        data.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        let mut offset = 6 + 16 * entries.len() as u32;
+        for (size, png) in &entries {
+            // A size of 256 is stored as 0.
+            let side = (*size % 256) as u8;
+            data.extend_from_slice(&[side, side, 0, 0]);
+            data.extend_from_slice(&1u16.to_le_bytes());
+            data.extend_from_slice(&32u16.to_le_bytes());
+            data.extend_from_slice(&(png.len() as u32).to_le_bytes());
+            data.extend_from_slice(&offset.to_le_bytes());
+            offset += png.len() as u32;
+        }
+        for (_, png) in &entries {
+            data.extend_from_slice(png);
+        }
+
+        std::fs::write(file_name, data)
+    }
+}
+
+#[cfg(test)]
+mod ico_tests {
+    use super::*;
+
+    #[test]
+    fn directory_lists_every_size() {
+        let path = std::env::temp_dir().join("kodak_favicon.ico");
+        let img = Image::load_png("tests/fixtures/test.png").unwrap();
+        img.save_ico(&path, &[16, 32, 256]).unwrap();
+
+        let data = std::fs::read(&path).unwrap();
+        assert_eq!(&data[..6], &[0, 0, 1, 0, 3, 0]);
+        let widths: Vec<u8> = (0..3).map(|i| data[6 + 16 * i]).collect();
+        assert_eq!(widths, vec![16, 32, 0]);
+
+        // The first entry's data must be a PNG file.
+        let offset = u32::from_le_bytes(data[18..22].try_into().unwrap()) as usize;
+        assert_eq!(&data[offset..offset + 4], b"\x89PNG");
+    }
+}
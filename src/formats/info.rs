@@ -0,0 +1,309 @@
+//! Reading the properties of an image file from its header, without decoding the pixels.
+
+#[cfg(not(target_arch = "wasm32"))]
+use super::{extension, unsupported};
+use crate::{ColourProfile, Dim, Image, Metadata};
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs::File;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::{self, BufReader};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+/// The channels an image file stores for each pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColourType {
+    /// A single grey channel.
+    Grey,
+    /// A grey channel and an alpha channel.
+    GreyAlpha,
+    /// Red, green and blue channels.
+    Rgb,
+    /// Red, green, blue and alpha channels.
+    Rgba,
+    /// An index into a palette of colours.
+    Indexed,
+    /// Cyan, magenta, yellow and black channels, as used for print.
+    Cmyk,
+}
+
+impl fmt::Display for ColourType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ColourType::Grey => "greyscale",
+            ColourType::GreyAlpha => "greyscale with alpha",
+            ColourType::Rgb => "RGB",
+            ColourType::Rgba => "RGBA",
+            ColourType::Indexed => "indexed",
+            ColourType::Cmyk => "CMYK",
+        })
+    }
+}
+
+/// The properties of an image, as returned by `ImageInfo::probe()` for files and
+/// `Image::info()` for images in memory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageInfo {
+    /// The name of the file format, such as `"PNG"`, or `None` for an image in memory.
+    pub format: Option<&'static str>,
+    /// The width and height in pixels.
+    pub dimensions: Dim,
+    /// The channels stored for each pixel.
+    pub colour_type: ColourType,
+    /// The number of bits per channel, or per palette index for indexed images.
+    pub bit_depth: u8,
+    /// The size of the file in bytes, or `None` for an image in memory.
+    pub file_size: Option<u64>,
+    /// The colour profile embedded in the file, if any.
+    pub colour_profile: Option<ColourProfile>,
+    /// The text, resolution and EXIF metadata found in the header.
+    pub metadata: Metadata,
+}
+
+impl ImageInfo {
+    /// Reads the properties of an image file, picking the format from the file extension like
+    /// `Image::open()`.
+    ///
+    /// Only the header is read for PNG, JPEG, TIFF, TGA and farbfeld files, so this is cheap even
+    /// for very large images. PNG text chunks that come after the pixel data are not seen. WebP
+    /// and AVIF files are decoded completely and reported as 8-bit RGBA.
+    ///
+    /// This returns an `Err` with `io::ErrorKind::Unsupported` if the extension is unknown or
+    /// its format is disabled, and an `Err` if the file could not be read or its header is
+    /// invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use kodak::*;
+    /// let info = ImageInfo::probe("holiday/beach.jpg").unwrap();
+    /// println!("{}x{} {}", info.dimensions.w, info.dimensions.h, info.colour_type);
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn probe(path: impl AsRef<Path>) -> io::Result<ImageInfo> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let file_size = file.metadata()?.len();
+        #[allow(unused_variables)] // without any codecs, nothing reads the file
+        let reader = BufReader::new(file);
+
+        let info = match extension(path).as_deref() {
+            #[cfg(feature = "png")]
+            Some("png") => probe_png(reader),
+            #[cfg(feature = "jpeg")]
+            Some("jpg" | "jpeg") => probe_jpeg(reader),
+            #[cfg(feature = "tiff")]
+            Some("tif" | "tiff") => probe_tiff(reader),
+            #[cfg(feature = "tga")]
+            Some("tga") => probe_tga(reader),
+            #[cfg(feature = "farbfeld")]
+            Some("ff" | "farbfeld") => probe_farbfeld(reader),
+            #[cfg(any(feature = "webp", feature = "avif"))]
+            Some(format @ ("webp" | "avif")) => Ok(ImageInfo {
+                format: Some(if format == "webp" { "WebP" } else { "AVIF" }),
+                ..Image::open(path)?.info()
+            }),
+            _ => Err(unsupported(path)),
+        }?;
+        Ok(ImageInfo {
+            file_size: Some(file_size),
+            ..info
+        })
+    }
+}
+
+/// Creates the info for a header that carries no metadata.
+#[allow(dead_code)] // only some of the codecs use it, and they can all be disabled
+fn plain(
+    format: &'static str,
+    dimensions: Dim,
+    colour_type: ColourType,
+    bit_depth: u8,
+) -> ImageInfo {
+    ImageInfo {
+        format: Some(format),
+        dimensions,
+        colour_type,
+        bit_depth,
+        file_size: None,
+        colour_profile: None,
+        metadata: Metadata::default(),
+    }
+}
+
+#[cfg(all(feature = "png", not(target_arch = "wasm32")))]
+fn probe_png(reader: impl io::Read) -> io::Result<ImageInfo> {
+    let reader = png::Decoder::new(reader).read_info()?;
+    let info = reader.info();
+    let colour_type = match info.color_type {
+        png::ColorType::Grayscale => ColourType::Grey,
+        png::ColorType::GrayscaleAlpha => ColourType::GreyAlpha,
+        png::ColorType::Rgb => ColourType::Rgb,
+        png::ColorType::Rgba => ColourType::Rgba,
+        png::ColorType::Indexed => ColourType::Indexed,
+    };
+    Ok(ImageInfo {
+        colour_profile: super::png::png_colour_space(info).0,
+        metadata: Metadata::from_png_info(info),
+        ..plain(
+            "PNG",
+            Dim {
+                w: info.width,
+                h: info.height,
+            },
+            colour_type,
+            info.bit_depth as u8,
+        )
+    })
+}
+
+#[cfg(all(feature = "jpeg", not(target_arch = "wasm32")))]
+fn probe_jpeg(reader: impl io::Read) -> io::Result<ImageInfo> {
+    let mut decoder = jpeg_decoder::Decoder::new(reader);
+    decoder.read_info().map_err(super::jpeg_error)?;
+    let info = decoder.info().expect("The JPEG header has just been read.");
+    let (colour_type, bit_depth) = match info.pixel_format {
+        jpeg_decoder::PixelFormat::L8 => (ColourType::Grey, 8),
+        jpeg_decoder::PixelFormat::L16 => (ColourType::Grey, 16),
+        jpeg_decoder::PixelFormat::RGB24 => (ColourType::Rgb, 8),
+        jpeg_decoder::PixelFormat::CMYK32 => (ColourType::Cmyk, 8),
+    };
+    Ok(ImageInfo {
+        colour_profile: decoder.icc_profile().map(ColourProfile::Icc),
+        metadata: Metadata {
+            exif: decoder.exif_data().and_then(crate::Exif::parse),
+            ..Metadata::default()
+        },
+        ..plain(
+            "JPEG",
+            Dim {
+                w: info.width as u32,
+                h: info.height as u32,
+            },
+            colour_type,
+            bit_depth,
+        )
+    })
+}
+
+#[cfg(all(feature = "tiff", not(target_arch = "wasm32")))]
+fn probe_tiff(reader: impl io::Read + io::Seek) -> io::Result<ImageInfo> {
+    use ::tiff::ColorType;
+
+    let read = || -> ::tiff::TiffResult<_> {
+        let mut decoder = ::tiff::decoder::Decoder::new(reader)?;
+        Ok((decoder.dimensions()?, decoder.colortype()?))
+    };
+    let ((w, h), colour_type) = read().map_err(super::tiff_error)?;
+    let (colour_type, bit_depth) = match colour_type {
+        ColorType::Gray(depth) => (ColourType::Grey, depth),
+        ColorType::GrayA(depth) => (ColourType::GreyAlpha, depth),
+        ColorType::RGB(depth) | ColorType::YCbCr(depth) => (ColourType::Rgb, depth),
+        ColorType::RGBA(depth) => (ColourType::Rgba, depth),
+        ColorType::Palette(depth) => (ColourType::Indexed, depth),
+        ColorType::CMYK(depth) => (ColourType::Cmyk, depth),
+    };
+    Ok(plain("TIFF", Dim { w, h }, colour_type, bit_depth))
+}
+
+#[cfg(all(feature = "tga", not(target_arch = "wasm32")))]
+fn probe_tga(mut reader: impl io::Read) -> io::Result<ImageInfo> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Not a valid TGA file.");
+    let mut header = [0; 18];
+    reader.read_exact(&mut header).map_err(|_| invalid())?;
+
+    let pixel_bits = header[16];
+    let (colour_type, bit_depth) = match (header[2], pixel_bits) {
+        (1 | 9, bits) => (ColourType::Indexed, bits),
+        (2 | 10, 15 | 16) => (ColourType::Rgb, 5),
+        (2 | 10, 24) => (ColourType::Rgb, 8),
+        (2 | 10, 32) => (ColourType::Rgba, 8),
+        (3 | 11, 8) => (ColourType::Grey, 8),
+        (3 | 11, 16) => (ColourType::GreyAlpha, 8),
+        _ => return Err(invalid()),
+    };
+    let dimensions = Dim {
+        w: u16::from_le_bytes([header[12], header[13]]) as u32,
+        h: u16::from_le_bytes([header[14], header[15]]) as u32,
+    };
+    Ok(plain("TGA", dimensions, colour_type, bit_depth))
+}
+
+#[cfg(all(feature = "farbfeld", not(target_arch = "wasm32")))]
+fn probe_farbfeld(mut reader: impl io::Read) -> io::Result<ImageInfo> {
+    let mut header = [0; 16];
+    reader.read_exact(&mut header)?;
+    if &header[..8] != b"farbfeld" {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Not a valid farbfeld file.",
+        ));
+    }
+    let dimensions = Dim {
+        w: u32::from_be_bytes(header[8..12].try_into().unwrap()),
+        h: u32::from_be_bytes(header[12..16].try_into().unwrap()),
+    };
+    Ok(plain("farbfeld", dimensions, ColourType::Rgba, 16))
+}
+
+// The following impl block defines inspection functions for Images.
+impl Image {
+    /// Returns the properties of the image in the same form as `ImageInfo::probe()`. Images in
+    /// memory always have 8-bit RGBA pixels.
+    pub fn info(&self) -> ImageInfo {
+        ImageInfo {
+            format: None,
+            dimensions: self.get_dimensions(),
+            colour_type: ColourType::Rgba,
+            bit_depth: 8,
+            file_size: None,
+            colour_profile: self.colour_profile.clone(),
+            metadata: self.metadata.clone(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "png", feature = "tga", feature = "farbfeld"))]
+mod info_tests {
+    use super::*;
+    use crate::Colour;
+
+    #[test]
+    fn probes_headers() {
+        let dir = std::env::temp_dir().join("kodak_info");
+        std::fs::create_dir_all(&dir).unwrap();
+        let img = Image::blank_with_colour(Dim { w: 7, h: 3 }, Colour::rgb(1, 2, 3))
+            .with_metadata(Metadata::default().with_text("Title", "Probe"))
+            .with_colour_profile(Some(ColourProfile::Srgb));
+
+        for name in ["opaque.png", "opaque.tga", "opaque.ff"] {
+            let path = dir.join(name);
+            img.save(&path).unwrap();
+            let info = ImageInfo::probe(&path).unwrap();
+            assert_eq!(info.dimensions, Dim { w: 7, h: 3 }, "{name}");
+            assert_eq!(
+                info.file_size,
+                Some(std::fs::metadata(&path).unwrap().len())
+            );
+        }
+
+        let png = ImageInfo::probe(dir.join("opaque.png")).unwrap();
+        assert_eq!(png.format, Some("PNG"));
+        assert_eq!((png.colour_type, png.bit_depth), (ColourType::Rgb, 8));
+        assert_eq!(png.colour_profile, Some(ColourProfile::Srgb));
+        assert_eq!(png.metadata.get("Title"), Some("Probe"));
+        let ff = ImageInfo::probe(dir.join("opaque.ff")).unwrap();
+        assert_eq!((ff.colour_type, ff.bit_depth), (ColourType::Rgba, 16));
+
+        assert_eq!(img.info().metadata, png.metadata);
+        assert_eq!(img.info().file_size, None);
+
+        std::fs::write(dir.join("broken.tga"), b"short").unwrap();
+        assert!(ImageInfo::probe(dir.join("broken.tga")).is_err());
+        assert_eq!(
+            ImageInfo::probe(dir.join("opaque.bmp")).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+}
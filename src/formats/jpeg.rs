@@ -0,0 +1,233 @@
+//! JPEG decoding, including EXIF metadata and orientation handling.
+
+use crate::Image;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{Colour, ColourProfile, Exif, Metadata};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+/// Reads the pixel density from the JFIF header that most JPEG files start with, in dots per
+/// inch. Returns `None` if there is no such header or it only gives an aspect ratio.
+#[cfg(not(target_arch = "wasm32"))]
+fn jfif_dpi(data: &[u8]) -> Option<(f32, f32)> {
+    let header = data.get(..18)?;
+    if header[..4] != [0xFF, 0xD8, 0xFF, 0xE0] || &header[6..11] != b"JFIF\0" {
+        return None;
+    }
+    let scale = match header[13] {
+        1 => 1.0,
+        // Dots per centimetre.
+        2 => 2.54,
+        _ => return None,
+    };
+    let density = |i: usize| u16::from_be_bytes([header[i], header[i + 1]]) as f32 * scale;
+    Some((density(14), density(16))).filter(|&(x, y)| x > 0.0 && y > 0.0)
+}
+
+/// Reads the sample precision in bits from the frame header, which `jpeg_decoder` does not
+/// report. Returns `None` if no frame header comes before the first scan.
+#[cfg(not(target_arch = "wasm32"))]
+fn sample_precision(data: &[u8]) -> Option<u8> {
+    let mut pos = 2;
+    loop {
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        let marker = *data.get(pos + 1)?;
+        match marker {
+            // Fill bytes before a marker.
+            0xFF => pos += 1,
+            // Markers without a segment.
+            0x01 | 0xD0..=0xD7 => pos += 2,
+            // Start of scan: the frame header should have come before it.
+            0xDA => return None,
+            0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+                return data.get(pos + 4).copied()
+            }
+            _ => {
+                let length = u16::from_be_bytes([*data.get(pos + 2)?, *data.get(pos + 3)?]);
+                pos += 2 + length as usize;
+            }
+        }
+    }
+}
+
+// The following impl block defines JPEG loading functions for Images.
+impl Image {
+    /// Loads a JPEG image as an Image struct.
+    ///
+    /// The pixels are returned as they are stored in the file, which for photos taken on phones
+    /// is often sideways; use `load_jpeg_oriented()` to apply the EXIF orientation. Any EXIF data
+    /// is available through `metadata().exif`, and an embedded ICC profile is kept as the image's
+    /// colour profile. The pixel density in the JFIF header, if any, becomes the image's DPI.
+    ///
+    /// This returns an `Err` if the file could not be opened or decoded.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_jpeg(file_name: impl AsRef<Path>) -> Result<Self, jpeg_decoder::Error> {
+        let data = std::fs::read(file_name).map_err(jpeg_decoder::Error::Io)?;
+        let mut decoder = jpeg_decoder::Decoder::new(data.as_slice());
+        let buf = decoder.decode()?;
+        let info = decoder
+            .info()
+            .expect("The JPEG header is read while decoding.");
+
+        let pixels: Vec<Colour> = match info.pixel_format {
+            jpeg_decoder::PixelFormat::L8 => buf.iter().map(|&v| Colour::rgb(v, v, v)).collect(),
+            jpeg_decoder::PixelFormat::L16 => {
+                // Samples range up to the precision of the file, which is often 12 bits.
+                let max = (1u32 << sample_precision(&data).unwrap_or(16).clamp(9, 16)) - 1;
+                buf.chunks_exact(2)
+                    .map(|c| {
+                        let v = (u16::from_ne_bytes([c[0], c[1]]) as u32).min(max);
+                        let v = ((v * 255 + max / 2) / max) as u8;
+                        Colour::rgb(v, v, v)
+                    })
+                    .collect()
+            }
+            jpeg_decoder::PixelFormat::RGB24 => buf
+                .chunks_exact(3)
+                .map(|c| Colour::rgb(c[0], c[1], c[2]))
+                .collect(),
+            jpeg_decoder::PixelFormat::CMYK32 => buf
+                .chunks_exact(4)
+                .map(|c| {
+                    let k = 255 - c[3] as u32;
+                    let channel = |v: u8| ((255 - v as u32) * k / 255) as u8;
+                    Colour::rgb(channel(c[0]), channel(c[1]), channel(c[2]))
+                })
+                .collect(),
+        };
+
+        Ok(Image {
+            width: info.width as u32,
+            height: info.height as u32,
+            pixels: pixels.into(),
+            colour_profile: decoder.icc_profile().map(ColourProfile::Icc),
+            metadata: Metadata {
+                exif: decoder.exif_data().and_then(Exif::parse),
+                dpi: jfif_dpi(&data),
+                ..Metadata::default()
+            },
+        })
+    }
+
+    /// Loads a JPEG image and rotates or mirrors it according to its EXIF orientation tag, so
+    /// that it comes out upright.
+    ///
+    /// The orientation in the returned image's EXIF data is reset to 1 (upright).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use kodak::*;
+    /// let photo = Image::load_jpeg_oriented("assets/phone_photo.jpg").unwrap();
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_jpeg_oriented(file_name: impl AsRef<Path>) -> Result<Self, jpeg_decoder::Error> {
+        let img = Image::load_jpeg(file_name)?;
+        let orientation = img
+            .metadata
+            .exif
+            .as_ref()
+            .and_then(Exif::orientation)
+            .unwrap_or(1);
+
+        let mut oriented = img.apply_orientation(orientation);
+        if let Some(exif) = oriented.metadata.exif.as_mut() {
+            exif.set_orientation(1);
+        }
+        Ok(oriented)
+    }
+
+    /// Rotates and mirrors the image according to an EXIF orientation value (1 to 8).
+    ///
+    /// Values outside of that range leave the image unchanged.
+    pub fn apply_orientation(&self, orientation: u16) -> Image {
+        match orientation {
+            2 => self.flip_horizontal(),
+            3 => self.rotate_180(),
+            4 => self.flip_vertical(),
+            5 => self.transpose(),
+            6 => self.rotate_90(),
+            7 => self.rotate_270().flip_horizontal(),
+            8 => self.rotate_270(),
+            _ => self.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod jpeg_tests {
+    use super::*;
+    use crate::{Dim, Loc};
+
+    #[test]
+    fn orientations_come_out_upright() {
+        // Give every pixel of an upright image its own colour, then store it the way a camera
+        // with each orientation would; applying the orientation must give the upright image.
+        let (w, h) = (3, 2);
+        let upright = Image::from_pixels(
+            Dim { w, h },
+            (0..w * h).map(|i| Colour::rgb(i as u8, 0, 0)).collect(),
+        );
+        // The pixel of the upright image found at (x, y) in the stored one.
+        let source = |orientation: u16, x: u32, y: u32| match orientation {
+            1 => (x, y),
+            2 => (w - 1 - x, y),
+            3 => (w - 1 - x, h - 1 - y),
+            4 => (x, h - 1 - y),
+            5 => (y, x),
+            6 => (w - 1 - y, x),
+            7 => (w - 1 - y, h - 1 - x),
+            _ => (y, h - 1 - x),
+        };
+
+        for orientation in 1..=8 {
+            let dim = if orientation < 5 {
+                Dim { w, h }
+            } else {
+                Dim { w: h, h: w }
+            };
+            let pixels = (0..dim.w * dim.h)
+                .map(|i| {
+                    let (x, y) = source(orientation, i % dim.w, i / dim.w);
+                    upright.get_pixel(Loc { x, y }).unwrap()
+                })
+                .collect();
+            let stored = Image::from_pixels(dim, pixels);
+            let restored = stored.apply_orientation(orientation);
+            assert_eq!(restored, upright, "orientation {orientation}");
+        }
+    }
+
+    #[test]
+    fn reads_jfif_density() {
+        let header = |units: u8, x: u16, y: u16| {
+            let mut data = vec![0xFF, 0xD8, 0xFF, 0xE0, 0, 16];
+            data.extend_from_slice(b"JFIF\0");
+            data.extend_from_slice(&[1, 2, units]);
+            data.extend_from_slice(&x.to_be_bytes());
+            data.extend_from_slice(&y.to_be_bytes());
+            data
+        };
+        assert_eq!(jfif_dpi(&header(1, 300, 150)), Some((300.0, 150.0)));
+        assert_eq!(jfif_dpi(&header(2, 100, 100)), Some((254.0, 254.0)));
+        assert_eq!(jfif_dpi(&header(0, 1, 1)), None);
+        assert_eq!(jfif_dpi(&header(1, 0, 72)), None);
+        assert_eq!(jfif_dpi(&header(1, 72, 72)[..12]), None);
+    }
+
+    #[test]
+    fn reads_sample_precision() {
+        // A JFIF segment, then the start of a lossless frame header with 12-bit samples.
+        let mut data = vec![0xFF, 0xD8, 0xFF, 0xE0, 0, 16];
+        data.extend_from_slice(b"JFIF\0");
+        data.extend_from_slice(&[1, 2, 0, 0, 1, 0, 1, 0, 0]);
+        data.extend_from_slice(&[0xFF, 0xC3, 0, 11, 12]);
+        assert_eq!(sample_precision(&data), Some(12));
+
+        data.truncate(data.len() - 5);
+        data.extend_from_slice(&[0xFF, 0xDA, 0, 8]);
+        assert_eq!(sample_precision(&data), None);
+    }
+}
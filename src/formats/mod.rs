@@ -0,0 +1,176 @@
+//! Image file formats. Every codec sits behind its own cargo feature, so programs that only
+//! manipulate images in memory do not have to compile decoders they never use.
+//!
+//! `png`, `jpeg`, `tiff`, `tga`, `farbfeld` and `ico` are enabled by default; `webp` and `avif`
+//! have to be asked for.
+
+#[cfg(all(feature = "avif", not(target_arch = "wasm32")))]
+mod avif;
+#[cfg(all(feature = "farbfeld", not(target_arch = "wasm32")))]
+mod farbfeld;
+#[cfg(all(feature = "ico", not(target_arch = "wasm32")))]
+mod ico;
+mod info;
+#[cfg(feature = "jpeg")]
+mod jpeg;
+#[cfg(feature = "png")]
+mod png;
+#[cfg(all(feature = "async", not(target_arch = "wasm32")))]
+mod png_async;
+#[cfg(all(feature = "png", not(target_arch = "wasm32")))]
+mod png_rows;
+#[cfg(all(feature = "tga", not(target_arch = "wasm32")))]
+mod tga;
+#[cfg(all(feature = "tiff", not(target_arch = "wasm32")))]
+mod tiff;
+#[cfg(all(feature = "webp", not(target_arch = "wasm32")))]
+mod webp;
+
+pub use info::{ColourType, ImageInfo};
+#[cfg(all(feature = "png", not(target_arch = "wasm32")))]
+pub use png_rows::PngRowReader;
+#[cfg(all(feature = "webp", not(target_arch = "wasm32")))]
+pub use webp::WebpQuality;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::Image;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+/// Wraps a decoder or encoder error as an `io::Error` of the given kind.
+#[cfg(all(any(feature = "jpeg", feature = "tiff"), not(target_arch = "wasm32")))]
+fn wrap(kind: io::ErrorKind, error: impl std::fmt::Display) -> io::Error {
+    io::Error::new(kind, error.to_string())
+}
+
+/// Converts a JPEG decoding error to an `io::Error`, keeping I/O errors as they are.
+#[cfg(all(feature = "jpeg", not(target_arch = "wasm32")))]
+fn jpeg_error(error: jpeg_decoder::Error) -> io::Error {
+    match error {
+        jpeg_decoder::Error::Io(e) => e,
+        e => wrap(io::ErrorKind::InvalidData, e),
+    }
+}
+
+/// Converts a TIFF error to an `io::Error`, keeping I/O errors as they are.
+#[cfg(all(feature = "tiff", not(target_arch = "wasm32")))]
+fn tiff_error(error: ::tiff::TiffError) -> io::Error {
+    match error {
+        ::tiff::TiffError::IoError(e) => e,
+        e => wrap(io::ErrorKind::InvalidData, e),
+    }
+}
+
+/// Returns the lowercase extension of a path, if it has one.
+#[cfg(not(target_arch = "wasm32"))]
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+}
+
+/// Returns whether `Image::open()` can read files with this extension, given the enabled
+/// features. The extension is compared case-insensitively and without the dot.
+#[allow(clippy::match_like_matches_macro)] // with every format enabled, all arms are `true`
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn can_open(extension: &str) -> bool {
+    match extension.to_ascii_lowercase().as_str() {
+        "png" => cfg!(feature = "png"),
+        "jpg" | "jpeg" => cfg!(feature = "jpeg"),
+        "tif" | "tiff" => cfg!(feature = "tiff"),
+        "tga" => cfg!(feature = "tga"),
+        "ff" | "farbfeld" => cfg!(feature = "farbfeld"),
+        "webp" => cfg!(feature = "webp"),
+        "avif" => cfg!(feature = "avif"),
+        _ => false,
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn unsupported(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "{} does not have the extension of a format kodak was built to handle.",
+            path.display()
+        ),
+    )
+}
+
+// The following impl block defines format-agnostic file functions for Images.
+#[cfg(not(target_arch = "wasm32"))]
+impl Image {
+    /// Loads an image, picking the format from the file extension.
+    ///
+    /// PNG, JPEG, TIFF, TGA and farbfeld files are recognised by default, and WebP and AVIF
+    /// files with their features. JPEGs are rotated according to their EXIF orientation and
+    /// only the first page of a TIFF is read.
+    ///
+    /// This returns an `Err` with `io::ErrorKind::Unsupported` if the extension is unknown or
+    /// its format is disabled, and an `Err` if the file could not be read or decoded.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use kodak::*;
+    /// let img = Image::open("holiday/beach.jpg").unwrap();
+    /// img.save("beach.png").unwrap();
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(path = %path.as_ref().display()))
+    )]
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Image> {
+        let path = path.as_ref();
+        match extension(path).as_deref() {
+            #[cfg(feature = "png")]
+            Some("png") => Ok(Image::from_png_bytes(&std::fs::read(path)?)?),
+            #[cfg(feature = "jpeg")]
+            Some("jpg" | "jpeg") => Image::load_jpeg_oriented(path).map_err(jpeg_error),
+            #[cfg(feature = "tiff")]
+            Some("tif" | "tiff") => Image::load_tiff(path).map_err(tiff_error),
+            #[cfg(feature = "tga")]
+            Some("tga") => Image::load_tga(path),
+            #[cfg(feature = "farbfeld")]
+            Some("ff" | "farbfeld") => Image::load_farbfeld(path),
+            #[cfg(feature = "webp")]
+            Some("webp") => Image::load_webp(path),
+            #[cfg(feature = "avif")]
+            Some("avif") => Image::load_avif(path),
+            _ => Err(unsupported(path)),
+        }
+    }
+
+    /// Saves the image, picking the format from the file extension.
+    ///
+    /// PNG, TIFF, TGA and farbfeld files can be written by default, and lossless WebP files
+    /// with the `webp` feature. JPEG and AVIF can only be read.
+    ///
+    /// This returns an `Err` with `io::ErrorKind::Unsupported` if the format cannot be written,
+    /// and an `Err` if the file could not be written.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(path = %path.as_ref().display(), width = self.width, height = self.height)
+        )
+    )]
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        match extension(path).as_deref() {
+            #[cfg(feature = "png")]
+            Some("png") => Ok(self.write_png(path, &self.metadata)?),
+            #[cfg(feature = "tiff")]
+            Some("tif" | "tiff") => self.save_tiff(path).map_err(tiff_error),
+            #[cfg(feature = "tga")]
+            Some("tga") => self.save_tga(path),
+            #[cfg(feature = "farbfeld")]
+            Some("ff" | "farbfeld") => self.save_farbfeld(path),
+            #[cfg(feature = "webp")]
+            Some("webp") => self.save_webp(path, WebpQuality::Lossless),
+            _ => Err(unsupported(path)),
+        }
+    }
+}
@@ -0,0 +1,603 @@
+//! PNG decoding and encoding, from files or from memory.
+
+use crate::{profile, Colour, ColourProfile, Image, Metadata};
+use core::ops::ControlFlow;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+// The following impl block defines PNG functions for Images.
+impl Image {
+    /// Loads a PNG image as an Image struct.
+    ///
+    /// This returns an `Err` if the PNG could not be decoded properly.
+    ///
+    /// Colour space chunks are honoured: an embedded ICC profile or sRGB chunk is kept as the
+    /// image's `ColourProfile`, and images that only carry a gAMA chunk are converted to sRGB.
+    ///
+    /// # Panics
+    ///
+    /// * if the specified file could not be opened.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use kodak::*;
+    /// let img = Image::load_png("assets/olle_ma.png").unwrap();
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_png(file_name: impl AsRef<Path>) -> Result<Self, png::DecodingError> {
+        use std::fs::File;
+        use std::io::BufReader;
+
+        Image::decode_png(BufReader::new(File::open(file_name).unwrap()), |_| {
+            ControlFlow::Continue(())
+        })
+    }
+
+    /// Like `load_png()`, but calls `on_progress` with the fraction of the image decoded so far,
+    /// from 0.0 to 1.0, after every row. Returning `ControlFlow::Break` from it stops decoding,
+    /// for instance once a `CancelToken` has been cancelled.
+    ///
+    /// Interlaced PNGs store their rows out of order, so for them progress is only reported
+    /// once the whole image has been decoded.
+    ///
+    /// This returns an `Err` if the file could not be opened or decoded, or an `IoError` of
+    /// kind `Interrupted` if decoding was cancelled.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use kodak::*;
+    /// # use std::ops::ControlFlow;
+    /// let img = Image::load_png_with_progress("assets/huge_scan.png", |done| {
+    ///     eprint!("\rDecoding: {:3.0}%", done * 100.0);
+    ///     ControlFlow::Continue(())
+    /// })
+    /// .unwrap();
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_png_with_progress<F>(
+        file_name: impl AsRef<Path>,
+        on_progress: F,
+    ) -> Result<Self, png::DecodingError>
+    where
+        F: FnMut(f32) -> ControlFlow<()>,
+    {
+        let file = std::fs::File::open(file_name)?;
+        Image::decode_png(std::io::BufReader::new(file), on_progress)
+    }
+
+    /// Decodes a PNG file that is already in memory, such as a `fetch()` response or an
+    /// uploaded file in the browser, where there is no file system to load from.
+    ///
+    /// This returns an `Err` if the PNG could not be decoded properly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let img = Image::blank_with_colour(Dim::square(4), Colour::WHITE);
+    /// let decoded = Image::from_png_bytes(&img.to_png_bytes()).unwrap();
+    /// assert_eq!(decoded.get_pixel(Loc { x: 3, y: 3 }), Ok(Colour::WHITE));
+    /// ```
+    pub fn from_png_bytes(data: &[u8]) -> Result<Self, png::DecodingError> {
+        Image::decode_png(data, |_| ControlFlow::Continue(()))
+    }
+
+    /// Encodes the image as a PNG file in memory, including its colour profile and metadata.
+    pub fn to_png_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        self.encode_png(&mut data, &self.metadata)
+            .expect("Encoding a PNG in memory should not fail.");
+        data
+    }
+
+    /// Decodes PNG data from any reader, reporting progress after every row.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    fn decode_png<R, F>(r: R, mut on_progress: F) -> Result<Self, png::DecodingError>
+    where
+        R: std::io::Read,
+        F: FnMut(f32) -> ControlFlow<()>,
+    {
+        let mut decoder = png::Decoder::new(r);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0u8; reader.output_buffer_size()];
+        let height = reader.info().height;
+        let cancelled = || {
+            png::DecodingError::IoError(std::io::Error::new(
+                std::io::ErrorKind::Interrupted,
+                "Decoding was cancelled.",
+            ))
+        };
+        if reader.info().interlaced || height == 0 {
+            reader.next_frame(&mut buf)?;
+            if on_progress(1.0).is_break() {
+                return Err(cancelled());
+            }
+        } else {
+            let row_len = buf.len() / height as usize;
+            for (y, target) in buf.chunks_exact_mut(row_len).enumerate() {
+                let row = reader.next_row()?.ok_or(png::DecodingError::IoError(
+                    std::io::ErrorKind::UnexpectedEof.into(),
+                ))?;
+                target.copy_from_slice(row.data());
+                if on_progress((y + 1) as f32 / height as f32).is_break() {
+                    return Err(cancelled());
+                }
+            }
+        }
+        // Text chunks may follow the image data, so read up to the end of the file.
+        reader.finish()?;
+        let info = reader.info();
+
+        let mut pixels = png_samples_to_colours(&buf, reader.output_color_type().0);
+        let (colour_profile, gamma) = png_colour_space(info);
+        if let Some(gamma) = gamma {
+            profile::gamma_to_srgb(&mut pixels, gamma);
+        }
+
+        let metadata = Metadata::from_png_info(info);
+
+        Ok(Image {
+            width: info.width,
+            height: info.height,
+            pixels: pixels.into(),
+            colour_profile,
+            metadata,
+        })
+    }
+
+    /// Saves an Image as a PNG file.
+    ///
+    /// If the image has a colour profile, it is embedded as an sRGB or iCCP chunk. The image's
+    /// metadata is written as well.
+    ///
+    /// This returns an `Err` if the file could not be written.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_png(&self, file_name: impl AsRef<Path>) -> Result<(), png::EncodingError> {
+        self.write_png(file_name, &self.metadata)
+    }
+
+    /// Encodes the image as a PNG file with the given metadata.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn write_png(
+        &self,
+        file_name: impl AsRef<Path>,
+        metadata: &Metadata,
+    ) -> Result<(), png::EncodingError> {
+        use std::fs::File;
+        use std::io::BufWriter;
+
+        self.encode_png(BufWriter::new(File::create(file_name)?), metadata)
+    }
+
+    /// Saves the image as a palette-based PNG of at most `max_colours` colours, clamped to
+    /// between 1 and 256, which is often a fraction of the size of a full-colour PNG for flat
+    /// graphics such as charts, icons and pixel art.
+    ///
+    /// Images with few enough colours are stored exactly; others are reduced as by
+    /// `quantize()`. Translucent colours are kept through a tRNS chunk, and palettes of up to
+    /// 2, 4 or 16 colours are packed into 1, 2 or 4 bits per pixel. The colour profile and
+    /// metadata are written as with `save_png()`.
+    ///
+    /// This returns an `Err` if the file could not be written.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use kodak::*;
+    /// let chart = Image::blank_with_colour(Dim { w: 640, h: 480 }, Colour::WHITE)
+    ///     .draw_text_bitmap("Sales", Loc { x: 8, y: 8 }, 2, Colour::BLACK);
+    /// chart.save_png_indexed("chart.png", 16).unwrap();
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_png_indexed(
+        &self,
+        file_name: impl AsRef<Path>,
+        max_colours: usize,
+    ) -> Result<(), png::EncodingError> {
+        use std::fs::File;
+        use std::io::BufWriter;
+
+        let (palette, indices) = self.palette(max_colours);
+        let samples = PngSamples::indexed(self.width, &palette, &indices);
+        let file = BufWriter::new(File::create(file_name)?);
+        self.write_png_samples(file, &self.metadata, &samples, None)
+    }
+
+    /// Saves the image as the smallest PNG kodak can produce without changing any pixel, a
+    /// lightweight take on tools like oxipng.
+    ///
+    /// Every layout that holds the image exactly is tried: a palette if it has at most 256
+    /// colours, greyscale at the lowest bit depth that fits if it is grey, and RGB or RGBA. Each
+    /// is compressed with the strongest setting and every row filter, and the smallest result
+    /// is written. This takes several times as long as `save_png()`. The colour profile and
+    /// metadata are written as with `save_png()`.
+    ///
+    /// This returns an `Err` if the file could not be written.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use kodak::*;
+    /// let img = Image::load_png("assets/olle_ma.png").unwrap();
+    /// img.save_png_optimized("olle_small.png").unwrap();
+    /// ```
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_png_optimized(
+        &self,
+        file_name: impl AsRef<Path>,
+    ) -> Result<(), png::EncodingError> {
+        let mut layouts = Vec::new();
+        let (palette, indices) = self.palette(256);
+        let exact = indices
+            .iter()
+            .zip(self.pixels.iter())
+            .all(|(&i, &c)| palette[i as usize] == c);
+        if exact {
+            layouts.push(PngSamples::indexed(self.width, &palette, &indices));
+        }
+        if self.pixels.iter().all(|c| c.r == c.g && c.g == c.b) {
+            layouts.push(PngSamples::grey(self));
+        }
+        layouts.push(PngSamples::truecolour(self));
+
+        // Every filter for all rows, and the encoder's per-row choice.
+        let filters = [
+            png::FilterType::NoFilter,
+            png::FilterType::Sub,
+            png::FilterType::Up,
+            png::FilterType::Avg,
+            png::FilterType::Paeth,
+        ]
+        .map(|f| (f, png::AdaptiveFilterType::NonAdaptive));
+        let adaptive = (png::FilterType::Sub, png::AdaptiveFilterType::Adaptive);
+
+        let mut smallest: Option<Vec<u8>> = None;
+        for samples in &layouts {
+            for filter in filters.into_iter().chain([adaptive]) {
+                let mut data = Vec::new();
+                self.write_png_samples(&mut data, &self.metadata, samples, Some(filter))?;
+                if smallest.as_ref().is_none_or(|s| data.len() < s.len()) {
+                    smallest = Some(data);
+                }
+            }
+        }
+        let smallest = smallest.expect("The image can always be stored as RGB or RGBA.");
+        Ok(std::fs::write(file_name, smallest)?)
+    }
+
+    /// Creates a PNG encoder with the image's dimensions, colour profile and the given metadata.
+    fn png_encoder<W: std::io::Write>(
+        &self,
+        w: W,
+        metadata: &Metadata,
+    ) -> Result<png::Encoder<'_, W>, png::EncodingError> {
+        let mut info = png::Info::with_size(self.width, self.height);
+        if let Some(ColourProfile::Icc(icc)) = &self.colour_profile {
+            info.icc_profile = Some(icc.into());
+        }
+
+        let mut encoder = png::Encoder::with_info(w, info)?;
+        if self.colour_profile == Some(ColourProfile::Srgb) {
+            encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+        }
+        metadata.apply_to_encoder(&mut encoder)?;
+        Ok(encoder)
+    }
+
+    /// Encodes samples laid out for PNG, with the image's dimensions and colour profile and the
+    /// given metadata, into any writer.
+    ///
+    /// With a `filter`, the strongest compression is used as well; without one, the encoder's
+    /// faster defaults are.
+    fn write_png_samples<W: std::io::Write>(
+        &self,
+        w: W,
+        metadata: &Metadata,
+        samples: &PngSamples,
+        filter: Option<(png::FilterType, png::AdaptiveFilterType)>,
+    ) -> Result<(), png::EncodingError> {
+        let mut encoder = self.png_encoder(w, metadata)?;
+        encoder.set_color(samples.colour_type);
+        encoder.set_depth(samples.depth);
+        if let Some((plte, trns)) = &samples.palette {
+            encoder.set_palette(plte.clone());
+            if !trns.is_empty() {
+                encoder.set_trns(trns.clone());
+            }
+        }
+        if let Some((filter, adaptive)) = filter {
+            encoder.set_compression(png::Compression::Best);
+            encoder.set_filter(filter);
+            encoder.set_adaptive_filter(adaptive);
+        }
+        encoder.write_header()?.write_image_data(&samples.data)
+    }
+
+    /// Encodes the image as PNG data with the given metadata into any writer.
+    pub(crate) fn encode_png<W: std::io::Write>(
+        &self,
+        w: W,
+        metadata: &Metadata,
+    ) -> Result<(), png::EncodingError> {
+        self.write_png_samples(w, metadata, &PngSamples::truecolour(self), None)
+    }
+}
+
+/// Pixels laid out in one of the formats a PNG can store them in.
+struct PngSamples {
+    colour_type: png::ColorType,
+    depth: png::BitDepth,
+    /// The PLTE and tRNS chunks, for indexed samples.
+    palette: Option<(Vec<u8>, Vec<u8>)>,
+    data: Vec<u8>,
+}
+
+impl PngSamples {
+    /// Lays out the image as 8-bit RGB, or RGBA if it has translucent pixels.
+    fn truecolour(img: &Image) -> Self {
+        // Only store an alpha channel if the image actually uses it.
+        let opaque = img.pixels.iter().all(|c| c.is_opaque());
+        let (colour_type, data) = if opaque {
+            (png::ColorType::Rgb, crate::simd::to_rgb_bytes(&img.pixels))
+        } else {
+            (
+                png::ColorType::Rgba,
+                crate::simd::to_rgba_bytes(&img.pixels),
+            )
+        };
+        PngSamples {
+            colour_type,
+            depth: png::BitDepth::Eight,
+            palette: None,
+            data,
+        }
+    }
+
+    /// Lays out a grey image as greyscale with alpha if it has translucent pixels, and
+    /// otherwise as greyscale at the lowest bit depth that holds every value exactly.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn grey(img: &Image) -> Self {
+        if !img.pixels.iter().all(|c| c.is_opaque()) {
+            return PngSamples {
+                colour_type: png::ColorType::GrayscaleAlpha,
+                depth: png::BitDepth::Eight,
+                palette: None,
+                data: img.pixels.iter().flat_map(|c| [c.r, c.a]).collect(),
+            };
+        }
+        // At a depth of n bits, the values that survive are multiples of 255 / (2^n - 1).
+        let step = |depth: png::BitDepth| (255 / ((1u32 << depth as u32) - 1)) as u8;
+        let depth = [
+            png::BitDepth::One,
+            png::BitDepth::Two,
+            png::BitDepth::Four,
+            png::BitDepth::Eight,
+        ]
+        .into_iter()
+        .find(|&depth| img.pixels.iter().all(|c| c.r % step(depth) == 0))
+        .expect("Every value fits in eight bits.");
+        let step = step(depth);
+        let values: Vec<u8> = img.pixels.iter().map(|c| c.r / step).collect();
+        PngSamples {
+            colour_type: png::ColorType::Grayscale,
+            depth,
+            palette: None,
+            data: pack_rows(&values, img.width, depth),
+        }
+    }
+
+    /// Lays out palette indices, at the lowest bit depth that holds them.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn indexed(width: u32, palette: &[Colour], indices: &[u8]) -> Self {
+        // Translucent entries go first, so that the tRNS chunk can stop after the last of them.
+        let mut order: Vec<usize> = (0..palette.len()).collect();
+        order.sort_by_key(|&i| palette[i].is_opaque());
+        let mut remap = vec![0u8; palette.len()];
+        for (new, &old) in order.iter().enumerate() {
+            remap[old] = new as u8;
+        }
+
+        let depth = match palette.len() {
+            0..=2 => png::BitDepth::One,
+            3..=4 => png::BitDepth::Two,
+            5..=16 => png::BitDepth::Four,
+            _ => png::BitDepth::Eight,
+        };
+        let plte = order
+            .iter()
+            .flat_map(|&i| [palette[i].r, palette[i].g, palette[i].b])
+            .collect();
+        let trns = order
+            .iter()
+            .map(|&i| palette[i].a)
+            .take_while(|&a| a < 255)
+            .collect();
+        let values: Vec<u8> = indices.iter().map(|&i| remap[i as usize]).collect();
+        PngSamples {
+            colour_type: png::ColorType::Indexed,
+            depth,
+            palette: Some((plte, trns)),
+            data: pack_rows(&values, width, depth),
+        }
+    }
+}
+
+/// Packs one value per pixel into rows of `depth` bits per value, from the most significant bit,
+/// with every row starting on a new byte.
+#[cfg(not(target_arch = "wasm32"))]
+fn pack_rows(values: &[u8], width: u32, depth: png::BitDepth) -> Vec<u8> {
+    let bits = depth as usize;
+    if bits == 8 {
+        return values.to_vec();
+    }
+    let per_byte = 8 / bits;
+    let mut data = Vec::with_capacity(values.len().div_ceil(per_byte));
+    for row in values.chunks(width.max(1) as usize) {
+        for group in row.chunks(per_byte) {
+            let byte = group
+                .iter()
+                .enumerate()
+                .fold(0u8, |byte, (k, &v)| byte | v << (8 - bits * (k + 1)));
+            data.push(byte);
+        }
+    }
+    data
+}
+
+/// Converts 8-bit samples, as produced by `png::Transformations::normalize_to_color8()`, to
+/// colours.
+pub(super) fn png_samples_to_colours(buf: &[u8], colour_type: png::ColorType) -> Vec<Colour> {
+    match colour_type {
+        png::ColorType::Rgb => crate::simd::from_rgb_bytes(buf),
+        png::ColorType::Rgba => crate::simd::from_rgba_bytes(buf),
+        png::ColorType::Grayscale => buf.iter().map(|&v| Colour::rgb(v, v, v)).collect(),
+        png::ColorType::GrayscaleAlpha => buf
+            .chunks_exact(2)
+            .map(|c| Colour::rgba(c[0], c[0], c[0], c[1]))
+            .collect(),
+        png::ColorType::Indexed => unreachable!("Indexed PNGs are expanded while decoding."),
+    }
+}
+
+/// Works out the colour profile of a PNG from its colour space chunks, along with the file gamma
+/// its pixels have to be converted from, if any.
+pub(super) fn png_colour_space(info: &png::Info) -> (Option<ColourProfile>, Option<f32>) {
+    if let Some(icc) = &info.icc_profile {
+        (Some(ColourProfile::Icc(icc.to_vec())), None)
+    } else if info.srgb.is_some() {
+        (Some(ColourProfile::Srgb), None)
+    } else if let Some(gamma) = info.source_gamma {
+        // A gamma of 1/2.2 is close enough to sRGB that converting would only add rounding.
+        let gamma = gamma.into_value();
+        (
+            Some(ColourProfile::Srgb),
+            Some(gamma).filter(|g| (g - 0.45455).abs() > 0.01),
+        )
+    } else {
+        (None, None)
+    }
+}
+
+#[cfg(test)]
+mod png_tests {
+    use super::*;
+
+    #[test]
+    fn reports_progress_and_cancels() {
+        let mut reported = Vec::new();
+        let img = Image::load_png_with_progress("tests/fixtures/test.png", |done| {
+            reported.push(done);
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        assert_eq!(img, Image::load_png("tests/fixtures/test.png").unwrap());
+        assert_eq!(reported.len(), img.height as usize);
+        assert!(reported.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(reported.last(), Some(&1.0));
+
+        let mut rows = 0;
+        let cancelled = Image::load_png_with_progress("tests/fixtures/test.png", |_| {
+            rows += 1;
+            if rows == 2 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+        assert!(matches!(
+            cancelled,
+            Err(png::DecodingError::IoError(e)) if e.kind() == std::io::ErrorKind::Interrupted
+        ));
+        assert_eq!(rows, 2);
+    }
+
+    #[test]
+    fn indexed_round_trip() {
+        let dir = std::env::temp_dir();
+        let path = dir.join("kodak_indexed.png");
+        let img = Image::blank_with_colour(crate::Dim { w: 5, h: 3 }, Colour::WHITE)
+            .fill_region(
+                crate::Region {
+                    l: crate::Loc { x: 1, y: 1 },
+                    d: crate::Dim { w: 3, h: 2 },
+                },
+                Colour::rgba(200, 0, 0, 100),
+            )
+            .fill_region(
+                crate::Region {
+                    l: crate::Loc { x: 4, y: 0 },
+                    d: crate::Dim::square(1),
+                },
+                Colour::TRANSPARENT,
+            );
+        img.save_png_indexed(&path, 256).unwrap();
+        assert_eq!(Image::load_png(&path).unwrap(), img);
+        let info = crate::ImageInfo::probe(&path).unwrap();
+        assert_eq!(
+            (info.colour_type, info.bit_depth),
+            (crate::ColourType::Indexed, 2)
+        );
+
+        let pixels = (0..=255).map(|v| Colour::rgb(v, v / 2, 255 - v)).collect();
+        let gradient = Image::from_pixels(crate::Dim { w: 64, h: 4 }, pixels);
+        gradient.save_png_indexed(&path, 16).unwrap();
+        assert_eq!(
+            Image::load_png(&path).unwrap(),
+            gradient.clone().quantize(16)
+        );
+        assert_eq!(crate::ImageInfo::probe(&path).unwrap().bit_depth, 4);
+    }
+
+    #[test]
+    fn optimized_is_lossless_and_small() {
+        let path = std::env::temp_dir().join("kodak_optimized.png");
+        let plain = std::env::temp_dir().join("kodak_unoptimized.png");
+        let probe = |img: &Image| {
+            img.save_png_optimized(&path).unwrap();
+            img.save_png(&plain).unwrap();
+            assert_eq!(&Image::load_png(&path).unwrap(), img);
+            let info = crate::ImageInfo::probe(&path).unwrap();
+            assert!(info.file_size <= crate::ImageInfo::probe(&plain).unwrap().file_size);
+            (info.colour_type, info.bit_depth)
+        };
+
+        let photo = Image::load_png("tests/fixtures/test.png").unwrap();
+        let (colour_type, _) = probe(&photo);
+        assert!(matches!(
+            colour_type,
+            crate::ColourType::Rgb | crate::ColourType::Rgba | crate::ColourType::Indexed
+        ));
+
+        // Noise in four colours compresses poorly, so the palette's two bits per pixel win.
+        let colours = [
+            Colour::rgb(200, 30, 30),
+            Colour::rgb(30, 200, 30),
+            Colour::rgb(30, 30, 200),
+            Colour::rgba(0, 0, 0, 0),
+        ];
+        let pixels = (0..1024u32)
+            .map(|i| colours[(i.wrapping_mul(2_654_435_761) >> 30) as usize])
+            .collect();
+        let noise = Image::from_pixels(crate::Dim { w: 32, h: 32 }, pixels);
+        assert_eq!(probe(&noise), (crate::ColourType::Indexed, 2));
+
+        let pixels = (0..400u32)
+            .map(|i| {
+                if i % 3 == 0 {
+                    Colour::WHITE
+                } else {
+                    Colour::BLACK
+                }
+            })
+            .collect();
+        let bilevel = Image::from_pixels(crate::Dim { w: 20, h: 20 }, pixels);
+        assert_eq!(probe(&bilevel).1, 1);
+
+        let pixels = (0..=255).map(|v| Colour::rgb(v, v, v)).collect();
+        let greys = Image::from_pixels(crate::Dim { w: 16, h: 16 }, pixels);
+        assert_eq!(probe(&greys), (crate::ColourType::Grey, 8));
+    }
+}
@@ -0,0 +1,97 @@
+//! PNG loading and saving for tokio runtimes. Only available with the `async` feature.
+//!
+//! Files are read and written with `tokio::fs`, and the decoding and encoding, which can take a
+//! while for large images, run on tokio's blocking thread pool. None of these functions block
+//! the thread that awaits them.
+
+use crate::Image;
+use std::path::Path;
+
+/// Runs `f` on the blocking thread pool. A panic in `f` is passed on to the caller.
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    match tokio::task::spawn_blocking(f).await {
+        Ok(value) => value,
+        Err(error) => std::panic::resume_unwind(error.into_panic()),
+    }
+}
+
+// The following impl block defines asynchronous PNG functions for Images.
+impl Image {
+    /// Loads a PNG image like `load_png()`, without blocking the async runtime.
+    ///
+    /// Unlike `load_png()`, this returns an `Err` rather than panicking if the file could not be
+    /// opened.
+    ///
+    /// # Panics
+    ///
+    /// * if it is not called from within a tokio runtime.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use kodak::*;
+    /// # async fn thumbnail() -> Result<(), Box<dyn std::error::Error>> {
+    /// let img = Image::load_png_async("uploads/photo.png").await?;
+    /// let small = img.resize_to_fit(Dim::square(128), ResizeFilter::Bilinear);
+    /// small.save_png_async("thumbnails/photo.png").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn load_png_async(file_name: impl AsRef<Path>) -> Result<Self, png::DecodingError> {
+        let data = tokio::fs::read(file_name).await?;
+        Image::from_png_bytes_async(data).await
+    }
+
+    /// Decodes a PNG file that is already in memory, such as a request body, like
+    /// `from_png_bytes()`, without blocking the async runtime.
+    pub async fn from_png_bytes_async(
+        data: impl AsRef<[u8]> + Send + 'static,
+    ) -> Result<Self, png::DecodingError> {
+        blocking(move || Image::from_png_bytes(data.as_ref())).await
+    }
+
+    /// Saves the image as a PNG file like `save_png()`, without blocking the async runtime.
+    ///
+    /// This returns an `Err` if the file could not be written.
+    pub async fn save_png_async(
+        &self,
+        file_name: impl AsRef<Path>,
+    ) -> Result<(), png::EncodingError> {
+        let data = self.to_png_bytes_async().await;
+        Ok(tokio::fs::write(file_name, data).await?)
+    }
+
+    /// Encodes the image as a PNG file in memory like `to_png_bytes()`, without blocking the
+    /// async runtime.
+    pub async fn to_png_bytes_async(&self) -> Vec<u8> {
+        // Clones share their pixels, so this does not copy the image.
+        let img = self.clone();
+        blocking(move || img.to_png_bytes()).await
+    }
+}
+
+#[cfg(test)]
+mod png_async_tests {
+    use crate::{Colour, Dim, Image, Loc};
+
+    #[tokio::test]
+    async fn round_trip() {
+        let path = std::env::temp_dir().join("kodak_async.png");
+        let img = Image::blank_with_colour(Dim { w: 5, h: 3 }, Colour::rgba(10, 200, 30, 128));
+        img.save_png_async(&path).await.unwrap();
+
+        let loaded = Image::load_png_async(&path).await.unwrap();
+        assert_eq!(loaded.get_dimensions(), Dim { w: 5, h: 3 });
+        assert_eq!(
+            loaded.get_pixel(Loc { x: 4, y: 2 }).unwrap(),
+            Colour::rgba(10, 200, 30, 128)
+        );
+
+        let bytes = img.to_png_bytes_async().await;
+        assert_eq!(bytes, img.to_png_bytes());
+        assert!(Image::from_png_bytes_async(b"not a png").await.is_err());
+        assert!(Image::load_png_async(path.with_extension("missing"))
+            .await
+            .is_err());
+    }
+}
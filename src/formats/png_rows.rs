@@ -0,0 +1,168 @@
+//! Row-by-row PNG decoding, for images too large to hold in memory at once.
+
+use super::png::{png_colour_space, png_samples_to_colours};
+use crate::{profile, Colour, Dim, Image};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Decodes a PNG file one row at a time, so that only a single row of pixels is held in memory.
+///
+/// Pixels come out exactly as `Image::load_png()` would produce them. Interlaced PNGs store
+/// their rows out of order and cannot be streamed; they are decoded in full when the reader is
+/// opened and then handed out row by row.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use kodak::*;
+/// // Average brightness of a gigapixel scan, without loading it.
+/// let mut reader = PngRowReader::open("assets/huge_scan.png").unwrap();
+/// let mut total = 0.0;
+/// while let Some(row) = reader.next_row().unwrap() {
+///     total += row.iter().map(|c| c.relative_luminance() as f64).sum::<f64>();
+/// }
+/// let dim = reader.dimensions();
+/// println!("{}", total / (dim.w as f64 * dim.h as f64));
+/// ```
+pub struct PngRowReader {
+    reader: png::Reader<BufReader<File>>,
+    colour_type: png::ColorType,
+    gamma: Option<f32>,
+    dimension: Dim,
+    next_row: u32,
+    row: Vec<Colour>,
+    /// The whole image, for interlaced files.
+    buffered: Option<Vec<Colour>>,
+}
+
+impl std::fmt::Debug for PngRowReader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The underlying decoder has no Debug implementation.
+        f.debug_struct("PngRowReader")
+            .field("dimension", &self.dimension)
+            .field("next_row", &self.next_row)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PngRowReader {
+    /// Opens a PNG file and reads its header.
+    ///
+    /// This returns an `Err` if the file could not be opened or is not a valid PNG file.
+    pub fn open(file_name: impl AsRef<Path>) -> Result<Self, png::DecodingError> {
+        let mut decoder = png::Decoder::new(BufReader::new(File::open(file_name)?));
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+
+        let info = reader.info();
+        let dimension = Dim {
+            w: info.width,
+            h: info.height,
+        };
+        let interlaced = info.interlaced;
+        let (_, gamma) = png_colour_space(info);
+        let colour_type = reader.output_color_type().0;
+
+        let buffered = if interlaced {
+            let mut buf = vec![0u8; reader.output_buffer_size()];
+            reader.next_frame(&mut buf)?;
+            let mut pixels = png_samples_to_colours(&buf, colour_type);
+            if let Some(gamma) = gamma {
+                profile::gamma_to_srgb(&mut pixels, gamma);
+            }
+            Some(pixels)
+        } else {
+            None
+        };
+
+        Ok(PngRowReader {
+            reader,
+            colour_type,
+            gamma,
+            dimension,
+            next_row: 0,
+            row: Vec::with_capacity(dimension.w as usize),
+            buffered,
+        })
+    }
+
+    /// Returns the dimensions of the image.
+    pub fn dimensions(&self) -> Dim {
+        self.dimension
+    }
+
+    /// Returns the index of the row that the next call to `next_row()` returns.
+    pub fn row_index(&self) -> u32 {
+        self.next_row
+    }
+
+    /// Decodes the next row, top to bottom. Returns `None` after the last row.
+    pub fn next_row(&mut self) -> Result<Option<&[Colour]>, png::DecodingError> {
+        if self.next_row >= self.dimension.h {
+            return Ok(None);
+        }
+        let index = self.next_row;
+        self.next_row += 1;
+
+        if let Some(pixels) = &self.buffered {
+            let w = self.dimension.w as usize;
+            let start = index as usize * w;
+            return Ok(Some(&pixels[start..start + w]));
+        }
+
+        let Some(row) = self.reader.next_row()? else {
+            self.next_row = self.dimension.h;
+            return Ok(None);
+        };
+        self.row = png_samples_to_colours(row.data(), self.colour_type);
+        if let Some(gamma) = self.gamma {
+            profile::gamma_to_srgb(&mut self.row, gamma);
+        }
+        Ok(Some(&self.row))
+    }
+}
+
+// The following impl block defines streaming PNG functions for Images.
+impl Image {
+    /// Decodes a PNG file row by row, calling `f` with the index and pixels of every row in
+    /// turn, and returns the dimensions of the image.
+    ///
+    /// This allows processing images far larger than memory; see `PngRowReader` for details.
+    /// It returns an `Err` if the file could not be opened or decoded.
+    pub fn load_png_rows<F>(
+        file_name: impl AsRef<Path>,
+        mut f: F,
+    ) -> Result<Dim, png::DecodingError>
+    where
+        F: FnMut(u32, &[Colour]),
+    {
+        let mut reader = PngRowReader::open(file_name)?;
+        loop {
+            let index = reader.row_index();
+            match reader.next_row()? {
+                Some(row) => f(index, row),
+                None => return Ok(reader.dimensions()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod png_rows_tests {
+    use super::*;
+
+    #[test]
+    fn rows_match_full_decode() {
+        let img = Image::load_png("tests/fixtures/test.png").unwrap();
+        let mut rows = Vec::new();
+        let dim = Image::load_png_rows("tests/fixtures/test.png", |i, row| {
+            assert_eq!(i as usize, rows.len() / img.width as usize);
+            rows.extend_from_slice(row);
+        })
+        .unwrap();
+
+        assert_eq!(dim, img.get_dimensions());
+        assert_eq!(img.pixels, rows);
+    }
+}
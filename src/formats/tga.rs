@@ -0,0 +1,263 @@
+//! TGA (Truevision TARGA) decoding and encoding, as used by a lot of game tooling.
+
+use crate::{Colour, Dim, Image};
+use std::io;
+use std::path::Path;
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Decodes one pixel from its 1 to 4 little-endian bytes.
+fn decode_pixel(bytes: &[u8], greyscale: bool) -> Colour {
+    match (bytes.len(), greyscale) {
+        (1, _) => Colour::rgb(bytes[0], bytes[0], bytes[0]),
+        (2, true) => Colour::rgba(bytes[0], bytes[0], bytes[0], bytes[1]),
+        (2, false) => {
+            // 5 bits per channel in the order ARRRRRGG GGGBBBBB.
+            let v = u16::from_le_bytes([bytes[0], bytes[1]]);
+            let expand = |c: u16| ((c & 0x1F) * 255 / 31) as u8;
+            Colour::rgb(expand(v >> 10), expand(v >> 5), expand(v))
+        }
+        (3, _) => Colour::rgb(bytes[2], bytes[1], bytes[0]),
+        _ => Colour::rgba(bytes[2], bytes[1], bytes[0], bytes[3]),
+    }
+}
+
+// The following impl block defines TGA functions for Images.
+impl Image {
+    /// Loads a TGA image as an Image struct.
+    ///
+    /// Uncompressed and run-length encoded files are supported, in true colour (16, 24 or 32
+    /// bits), greyscale (with or without alpha) and colour-mapped flavours. This returns an `Err`
+    /// if the file could not be read, or with `io::ErrorKind::InvalidData` if it is not a TGA
+    /// file this function understands.
+    pub fn load_tga(file_name: impl AsRef<Path>) -> io::Result<Self> {
+        let data = std::fs::read(file_name)?;
+        let header = data
+            .get(..18)
+            .ok_or_else(|| invalid_data("The file is too short to be a TGA image."))?;
+
+        let id_length = header[0] as usize;
+        let has_map = header[1] == 1;
+        let image_type = header[2];
+        let map_first = u16::from_le_bytes([header[3], header[4]]) as usize;
+        let map_length = u16::from_le_bytes([header[5], header[6]]) as usize;
+        let map_entry_bytes = (header[7] as usize).div_ceil(8);
+        let width = u16::from_le_bytes([header[12], header[13]]) as u32;
+        let height = u16::from_le_bytes([header[14], header[15]]) as u32;
+        let pixel_bytes = (header[16] as usize).div_ceil(8);
+        let descriptor = header[17];
+
+        let (colour_mapped, greyscale, rle) = match image_type {
+            1 => (true, false, false),
+            2 => (false, false, false),
+            3 => (false, true, false),
+            9 => (true, false, true),
+            10 => (false, false, true),
+            11 => (false, true, true),
+            _ => return Err(invalid_data("Unsupported TGA image type.")),
+        };
+        if !(1..=4).contains(&pixel_bytes) || (colour_mapped && !has_map) {
+            return Err(invalid_data("Unsupported TGA pixel format."));
+        }
+        if colour_mapped && !(1..=4).contains(&map_entry_bytes) {
+            return Err(invalid_data("Unsupported TGA colour map entry size."));
+        }
+
+        let map_start = 18 + id_length;
+        let map_size = if has_map {
+            map_length * map_entry_bytes
+        } else {
+            0
+        };
+        let pixel_start = map_start + map_size;
+        let palette: Vec<Colour> = if colour_mapped {
+            data.get(map_start..pixel_start)
+                .ok_or_else(|| invalid_data("The TGA colour map is truncated."))?
+                .chunks_exact(map_entry_bytes)
+                .map(|c| decode_pixel(c, false))
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        // Read the raw pixel values, undoing the run-length encoding if needed. A run-length
+        // packet holds at most 128 pixels, so the header cannot claim more pixels than the rest
+        // of the file could hold; checking that first keeps a tiny malformed file from making
+        // us allocate gigabytes.
+        let count = width as usize * height as usize;
+        let remaining = data.len().saturating_sub(pixel_start);
+        let max_count = if rle {
+            remaining / (1 + pixel_bytes) * 128
+        } else {
+            remaining / pixel_bytes
+        };
+        if count > max_count {
+            return Err(invalid_data("The TGA pixel data is truncated."));
+        }
+        let mut raw: Vec<&[u8]> = Vec::with_capacity(count);
+        let mut pos = pixel_start;
+        let take = |pos: &mut usize| -> io::Result<&[u8]> {
+            let bytes = data
+                .get(*pos..*pos + pixel_bytes)
+                .ok_or_else(|| invalid_data("The TGA pixel data is truncated."))?;
+            *pos += pixel_bytes;
+            Ok(bytes)
+        };
+        while raw.len() < count {
+            if rle {
+                let packet = *data
+                    .get(pos)
+                    .ok_or_else(|| invalid_data("The TGA pixel data is truncated."))?;
+                pos += 1;
+                let run = (packet & 0x7F) as usize + 1;
+                if packet & 0x80 != 0 {
+                    let bytes = take(&mut pos)?;
+                    raw.extend(std::iter::repeat_n(bytes, run));
+                } else {
+                    for _ in 0..run {
+                        raw.push(take(&mut pos)?);
+                    }
+                }
+            } else {
+                raw.push(take(&mut pos)?);
+            }
+        }
+        raw.truncate(count);
+
+        let colours: Vec<Colour> = raw
+            .into_iter()
+            .map(|bytes| {
+                if colour_mapped {
+                    let index = bytes
+                        .iter()
+                        .rev()
+                        .fold(0usize, |acc, &b| acc << 8 | b as usize);
+                    palette
+                        .get(index.wrapping_sub(map_first))
+                        .copied()
+                        .ok_or_else(|| invalid_data("A TGA colour index is out of range."))
+                } else {
+                    Ok(decode_pixel(bytes, greyscale))
+                }
+            })
+            .collect::<io::Result<_>>()?;
+
+        // Rows are stored bottom to top unless bit 5 of the descriptor is set, and pixels left
+        // to right unless bit 4 is set.
+        let bottom_up = descriptor & 0x20 == 0;
+        let right_to_left = descriptor & 0x10 != 0;
+        let (w, h) = (width as usize, height as usize);
+        let mut pixels = Vec::with_capacity(count);
+        for y in 0..h {
+            let row = if bottom_up { h - 1 - y } else { y };
+            for x in 0..w {
+                let column = if right_to_left { w - 1 - x } else { x };
+                pixels.push(colours[row * w + column]);
+            }
+        }
+
+        Ok(Image::from_pixels(
+            Dim {
+                w: width,
+                h: height,
+            },
+            pixels,
+        ))
+    }
+
+    /// Saves an Image as an uncompressed TGA file.
+    ///
+    /// The image is stored as 24-bit colour, or 32-bit if it has translucent pixels. TGA cannot
+    /// store images wider or taller than 65535 pixels; in that case this returns an `Err` with
+    /// `io::ErrorKind::InvalidInput`.
+    pub fn save_tga(&self, file_name: impl AsRef<Path>) -> io::Result<()> {
+        let (Ok(width), Ok(height)) = (u16::try_from(self.width), u16::try_from(self.height))
+        else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "TGA images can be at most 65535 pixels wide and tall.",
+            ));
+        };
+        let opaque = self.pixels.iter().all(|c| c.is_opaque());
+
+        let mut data = vec![0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        data.extend_from_slice(&width.to_le_bytes());
+        data.extend_from_slice(&height.to_le_bytes());
+        if opaque {
+            // 24 bits per pixel, stored top to bottom.
+            data.extend_from_slice(&[24, 0x20]);
+            data.extend(self.pixels.iter().flat_map(|c| [c.b, c.g, c.r]));
+        } else {
+            // 32 bits per pixel with 8 alpha bits, stored top to bottom.
+            data.extend_from_slice(&[32, 0x28]);
+            data.extend(self.pixels.iter().flat_map(|c| [c.b, c.g, c.r, c.a]));
+        }
+
+        std::fs::write(file_name, data)
+    }
+}
+
+#[cfg(test)]
+mod tga_tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "png")]
+    fn round_trip() {
+        let path = std::env::temp_dir().join("kodak_round_trip.tga");
+        let img = Image::load_png("tests/fixtures/test.png").unwrap();
+
+        img.save_tga(&path).unwrap();
+        let loaded = Image::load_tga(&path).unwrap();
+
+        assert_eq!(loaded.get_dimensions(), img.get_dimensions());
+        assert_eq!(loaded.pixels, img.pixels);
+    }
+
+    #[test]
+    fn bottom_up_rle() {
+        let path = std::env::temp_dir().join("kodak_bottom_up_rle.tga");
+        // A 2x2 greyscale image: a run of two black pixels (bottom row), then two raw pixels.
+        let mut data = vec![0, 0, 11, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 2, 0, 8, 0];
+        data.extend_from_slice(&[0x81, 0, 0x01, 100, 200]);
+        std::fs::write(&path, data).unwrap();
+
+        let loaded = Image::load_tga(&path).unwrap();
+        assert_eq!(
+            loaded.pixels,
+            vec![
+                Colour::rgb(100, 100, 100),
+                Colour::rgb(200, 200, 200),
+                Colour::BLACK,
+                Colour::BLACK,
+            ]
+        );
+    }
+
+    #[test]
+    fn huge_header_is_rejected() {
+        let path = std::env::temp_dir().join("kodak_huge_header.tga");
+        // A 65535x65535 true colour image with a single run-length packet of pixel data.
+        let mut data = vec![
+            0, 0, 10, 0, 0, 0, 0, 0, 0, 0, 0, 0, 255, 255, 255, 255, 24, 0,
+        ];
+        data.extend_from_slice(&[0xFF, 1, 2, 3]);
+        std::fs::write(&path, data).unwrap();
+
+        let error = Image::load_tga(&path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn empty_map_entries_are_rejected() {
+        let path = std::env::temp_dir().join("kodak_empty_map_entries.tga");
+        // A 1x1 colour-mapped image whose two map entries are zero bits wide.
+        let data = [0, 1, 1, 0, 0, 2, 0, 0, 0, 0, 0, 0, 1, 0, 1, 0, 8, 0, 0];
+        std::fs::write(&path, data).unwrap();
+
+        let error = Image::load_tga(&path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,219 @@
+//! TIFF decoding and encoding, including multi-page files.
+
+use crate::{Colour, Dim, Image};
+use ::tiff::decoder::{ifd::Value, Decoder, DecodingResult};
+use ::tiff::encoder::{colortype, Rational, TiffEncoder};
+use ::tiff::tags::{ResolutionUnit, Tag};
+use ::tiff::{ColorType, TiffError, TiffUnsupportedError};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek};
+use std::path::Path;
+
+/// The number of centimetres in an inch.
+const CM_PER_INCH: f32 = 2.54;
+
+/// Reads the resolution of the current page in dots per inch, if it has one in inches or
+/// centimetres.
+fn read_dpi<R: Read + Seek>(decoder: &mut Decoder<R>) -> Result<Option<(f32, f32)>, TiffError> {
+    let scale = match decoder.find_tag_unsigned::<u16>(Tag::ResolutionUnit)? {
+        // Inches are the default unit.
+        None | Some(2) => 1.0,
+        Some(3) => CM_PER_INCH,
+        Some(_) => return Ok(None),
+    };
+    let mut resolution = |tag| -> Result<Option<f32>, TiffError> {
+        Ok(match decoder.find_tag(tag)? {
+            Some(Value::Rational(n, d)) if d != 0 => Some(n as f32 / d as f32 * scale),
+            _ => None,
+        })
+    };
+    Ok(resolution(Tag::XResolution)?.zip(resolution(Tag::YResolution)?))
+}
+
+/// Decodes the page the decoder is currently positioned at.
+fn read_page<R: Read + Seek>(decoder: &mut Decoder<R>) -> Result<Image, TiffError> {
+    let (width, height) = decoder.dimensions()?;
+    let colour_type = decoder.colortype()?;
+    let dpi = read_dpi(decoder)?;
+
+    // Reduce every supported sample type to 8 bits.
+    let samples: Vec<u8> = match decoder.read_image()? {
+        DecodingResult::U8(v) => v,
+        DecodingResult::U16(v) => v.iter().map(|&s| (s >> 8) as u8).collect(),
+        DecodingResult::F32(v) => v
+            .iter()
+            .map(|&s| (s.clamp(0.0, 1.0) * 255.0).round() as u8)
+            .collect(),
+        _ => {
+            return Err(TiffError::UnsupportedError(
+                TiffUnsupportedError::UnsupportedDataType,
+            ))
+        }
+    };
+
+    let pixels: Vec<Colour> = match colour_type {
+        ColorType::Gray(_) => samples.iter().map(|&v| Colour::rgb(v, v, v)).collect(),
+        ColorType::GrayA(_) => samples
+            .chunks_exact(2)
+            .map(|c| Colour::rgba(c[0], c[0], c[0], c[1]))
+            .collect(),
+        ColorType::RGB(_) => samples
+            .chunks_exact(3)
+            .map(|c| Colour::rgb(c[0], c[1], c[2]))
+            .collect(),
+        ColorType::RGBA(_) => samples
+            .chunks_exact(4)
+            .map(|c| Colour::rgba(c[0], c[1], c[2], c[3]))
+            .collect(),
+        ColorType::CMYK(_) => samples
+            .chunks_exact(4)
+            .map(|c| {
+                let k = 255 - c[3] as u32;
+                let channel = |v: u8| ((255 - v as u32) * k / 255) as u8;
+                Colour::rgb(channel(c[0]), channel(c[1]), channel(c[2]))
+            })
+            .collect(),
+        other => {
+            return Err(TiffError::UnsupportedError(
+                TiffUnsupportedError::UnsupportedColorType(other),
+            ))
+        }
+    };
+
+    if pixels.len() != width as usize * height as usize {
+        return Err(TiffError::FormatError(
+            ::tiff::TiffFormatError::InconsistentSizesEncountered,
+        ));
+    }
+
+    let mut img = Image::from_pixels(
+        Dim {
+            w: width,
+            h: height,
+        },
+        pixels,
+    );
+    img.metadata.dpi = dpi;
+    Ok(img)
+}
+
+/// Writes 8-bit samples as a new page, with the image's resolution if it has one.
+fn write_samples<C, W>(
+    encoder: &mut TiffEncoder<W>,
+    img: &Image,
+    samples: &[u8],
+) -> Result<(), TiffError>
+where
+    C: colortype::ColorType<Inner = u8>,
+    W: std::io::Write + Seek,
+{
+    let mut page = encoder.new_image::<C>(img.width, img.height)?;
+    if let Some((x, y)) = img.metadata.dpi {
+        // Hundredths of a dot per inch are precise enough for any printer.
+        let rational = |dpi: f32| Rational {
+            n: (dpi * 100.0).round() as u32,
+            d: 100,
+        };
+        page.resolution_unit(ResolutionUnit::Inch);
+        page.x_resolution(rational(x));
+        page.y_resolution(rational(y));
+    }
+    page.write_data(samples)
+}
+
+/// Appends an image as a new page to a TIFF encoder.
+fn write_page<W: std::io::Write + Seek>(
+    encoder: &mut TiffEncoder<W>,
+    img: &Image,
+) -> Result<(), TiffError> {
+    if img.pixels.iter().all(|c| c.is_opaque()) {
+        let data = crate::simd::to_rgb_bytes(&img.pixels);
+        write_samples::<colortype::RGB8, _>(encoder, img, &data)
+    } else {
+        let data = crate::simd::to_rgba_bytes(&img.pixels);
+        write_samples::<colortype::RGBA8, _>(encoder, img, &data)
+    }
+}
+
+// The following impl block defines TIFF functions for Images.
+impl Image {
+    /// Loads the first page of a TIFF file as an Image struct.
+    ///
+    /// Greyscale, RGB and CMYK files with or without alpha are supported, at 8 or 16 bits per
+    /// sample or as 32-bit floats. This returns an `Err` if the file could not be opened or
+    /// decoded.
+    pub fn load_tiff(file_name: impl AsRef<Path>) -> Result<Self, TiffError> {
+        let mut decoder = Decoder::new(BufReader::new(File::open(file_name)?))?;
+        read_page(&mut decoder)
+    }
+
+    /// Loads every page (directory) of a TIFF file, in order.
+    ///
+    /// Multi-page TIFFs are common for scanned documents and microscopy stacks.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use kodak::*;
+    /// let pages = Image::load_tiff_pages("scan.tiff").unwrap();
+    /// println!("The scan has {} pages", pages.len());
+    /// ```
+    pub fn load_tiff_pages(file_name: impl AsRef<Path>) -> Result<Vec<Self>, TiffError> {
+        let mut decoder = Decoder::new(BufReader::new(File::open(file_name)?))?;
+        let mut pages = vec![read_page(&mut decoder)?];
+        while decoder.more_images() {
+            decoder.next_image()?;
+            pages.push(read_page(&mut decoder)?);
+        }
+        Ok(pages)
+    }
+
+    /// Saves an Image as a single-page TIFF file.
+    ///
+    /// The image is stored as 8-bit RGB, or RGBA if it has translucent pixels, along with its
+    /// resolution if it has one.
+    pub fn save_tiff(&self, file_name: impl AsRef<Path>) -> Result<(), TiffError> {
+        Image::save_tiff_pages(std::slice::from_ref(self), file_name)
+    }
+
+    /// Saves several images as the pages of one TIFF file. The pages may differ in size.
+    pub fn save_tiff_pages(pages: &[Image], file_name: impl AsRef<Path>) -> Result<(), TiffError> {
+        let mut encoder = TiffEncoder::new(BufWriter::new(File::create(file_name)?))?;
+        for page in pages {
+            write_page(&mut encoder, page)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tiff_tests {
+    use super::*;
+
+    #[test]
+    fn multi_page_round_trip() {
+        let path = std::env::temp_dir().join("kodak_multi_page.tiff");
+        let pages = vec![
+            Image::blank_with_colour(Dim::square(3), Colour::WHITE),
+            Image::blank_with_colour(Dim { w: 2, h: 5 }, Colour::rgba(10, 20, 30, 40)),
+        ];
+
+        Image::save_tiff_pages(&pages, &path).unwrap();
+        let loaded = Image::load_tiff_pages(&path).unwrap();
+
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].pixels, pages[0].pixels);
+        assert_eq!(loaded[1].get_dimensions(), Dim { w: 2, h: 5 });
+        assert_eq!(loaded[1].pixels, pages[1].pixels);
+        assert_eq!(loaded[0].dpi(), None);
+    }
+
+    #[test]
+    fn resolution_round_trip() {
+        let path = std::env::temp_dir().join("kodak_tiff_dpi.tiff");
+        let mut img = Image::blank(Dim::square(2));
+        img.set_dpi(300.0, 72.5);
+        img.save_tiff(&path).unwrap();
+        assert_eq!(Image::load_tiff(&path).unwrap().dpi(), Some((300.0, 72.5)));
+    }
+}
@@ -0,0 +1,93 @@
+//! WebP decoding and encoding through libwebp. Only available with the `webp` feature.
+
+use crate::{Dim, Image};
+use std::io;
+use std::path::Path;
+
+/// How `Image::save_webp()` should compress the image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WebpQuality {
+    /// Lossy compression with a quality between 0.0 (smallest) and 100.0 (best).
+    Lossy(f32),
+    /// Lossless compression, which keeps every pixel exactly.
+    Lossless,
+}
+
+// The following impl block defines WebP functions for Images.
+impl Image {
+    /// Loads a (still) WebP image as an Image struct.
+    ///
+    /// This returns an `Err` if the file could not be read, or with `io::ErrorKind::InvalidData`
+    /// if it is not a valid WebP file. Animated WebP files are not supported.
+    pub fn load_webp(file_name: impl AsRef<Path>) -> io::Result<Self> {
+        let data = std::fs::read(file_name)?;
+        let decoded = ::webp::Decoder::new(&data).decode().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The file is not a valid still WebP image.",
+            )
+        })?;
+
+        let pixels = if decoded.is_alpha() {
+            crate::simd::from_rgba_bytes(&decoded)
+        } else {
+            crate::simd::from_rgb_bytes(&decoded)
+        };
+
+        Ok(Image::from_pixels(
+            Dim {
+                w: decoded.width(),
+                h: decoded.height(),
+            },
+            pixels,
+        ))
+    }
+
+    /// Saves an Image as a WebP file, either lossy or lossless.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use kodak::*;
+    /// let img = Image::load_png("assets/olle_ma.png").unwrap();
+    /// img.save_webp("olle_ma.webp", WebpQuality::Lossy(80.0)).unwrap();
+    /// ```
+    pub fn save_webp(&self, file_name: impl AsRef<Path>, quality: WebpQuality) -> io::Result<()> {
+        let opaque = self.pixels.iter().all(|c| c.is_opaque());
+        let data: Vec<u8> = if opaque {
+            crate::simd::to_rgb_bytes(&self.pixels)
+        } else {
+            crate::simd::to_rgba_bytes(&self.pixels)
+        };
+
+        let encoder = if opaque {
+            ::webp::Encoder::from_rgb(&data, self.width, self.height)
+        } else {
+            ::webp::Encoder::from_rgba(&data, self.width, self.height)
+        };
+        let encoded = match quality {
+            WebpQuality::Lossy(q) => encoder.encode_simple(false, q.clamp(0.0, 100.0)),
+            WebpQuality::Lossless => encoder.encode_simple(true, 75.0),
+        }
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("{:?}", e)))?;
+
+        std::fs::write(file_name, &*encoded)
+    }
+}
+
+#[cfg(test)]
+mod webp_tests {
+    use super::*;
+
+    #[test]
+    fn lossless_round_trip() {
+        let path = std::env::temp_dir().join("kodak_lossless.webp");
+        let img = Image::load_png("tests/fixtures/test.png").unwrap();
+
+        img.save_webp(&path, WebpQuality::Lossless).unwrap();
+        let loaded = Image::load_webp(&path).unwrap();
+
+        assert_eq!(loaded.get_dimensions(), img.get_dimensions());
+        assert_eq!(loaded.pixels, img.pixels);
+    }
+}
@@ -0,0 +1,167 @@
+//! `Arbitrary` implementations for fuzzers (the `arbitrary` feature) and property tests (the
+//! `proptest` feature).
+//!
+//! Coordinates and sizes stay below `MAX_COORDINATE`, so that an arbitrary `Dim` can be turned
+//! into an image without running out of memory, and arbitrary images are at most
+//! `MAX_IMAGE_SIDE` pixels wide and high, so that every case runs quickly.
+
+use crate::{Colour, Dim, Image, Loc, Region};
+
+/// The largest coordinate, width or height of an arbitrary `Loc`, `Dim` or `Region`.
+const MAX_COORDINATE: u32 = 1024;
+/// The largest width and height of an arbitrary `Image`.
+const MAX_IMAGE_SIDE: u32 = 64;
+
+#[cfg(feature = "arbitrary")]
+mod with_arbitrary {
+    use super::*;
+    use arbitrary::{Arbitrary, Result, Unstructured};
+
+    impl<'a> Arbitrary<'a> for Colour {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            let [r, g, b, a] = <[u8; 4]>::arbitrary(u)?;
+            Ok(Colour::rgba(r, g, b, a))
+        }
+    }
+
+    impl<'a> Arbitrary<'a> for Loc {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            Ok(Loc {
+                x: u.int_in_range(0..=MAX_COORDINATE)?,
+                y: u.int_in_range(0..=MAX_COORDINATE)?,
+            })
+        }
+    }
+
+    impl<'a> Arbitrary<'a> for Dim {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            Ok(Dim {
+                w: u.int_in_range(0..=MAX_COORDINATE)?,
+                h: u.int_in_range(0..=MAX_COORDINATE)?,
+            })
+        }
+    }
+
+    impl<'a> Arbitrary<'a> for Region {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            Ok(Region {
+                l: Loc::arbitrary(u)?,
+                d: Dim::arbitrary(u)?,
+            })
+        }
+    }
+
+    impl<'a> Arbitrary<'a> for Image {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            let dim = Dim {
+                w: u.int_in_range(1..=MAX_IMAGE_SIDE)?,
+                h: u.int_in_range(1..=MAX_IMAGE_SIDE)?,
+            };
+            let pixels = (0..dim.w * dim.h)
+                .map(|_| Colour::arbitrary(u))
+                .collect::<Result<_>>()?;
+            Ok(Image::from_pixels(dim, pixels))
+        }
+    }
+}
+
+#[cfg(feature = "proptest")]
+mod with_proptest {
+    use super::*;
+    use proptest::prelude::*;
+
+    impl Arbitrary for Colour {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Colour>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            any::<[u8; 4]>()
+                .prop_map(|[r, g, b, a]| Colour::rgba(r, g, b, a))
+                .boxed()
+        }
+    }
+
+    impl Arbitrary for Loc {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Loc>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            (0..=MAX_COORDINATE, 0..=MAX_COORDINATE)
+                .prop_map(|(x, y)| Loc { x, y })
+                .boxed()
+        }
+    }
+
+    impl Arbitrary for Dim {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Dim>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            (0..=MAX_COORDINATE, 0..=MAX_COORDINATE)
+                .prop_map(|(w, h)| Dim { w, h })
+                .boxed()
+        }
+    }
+
+    impl Arbitrary for Region {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Region>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            (any::<Loc>(), any::<Dim>())
+                .prop_map(|(l, d)| Region { l, d })
+                .boxed()
+        }
+    }
+
+    impl Arbitrary for Image {
+        type Parameters = ();
+        type Strategy = BoxedStrategy<Image>;
+
+        fn arbitrary_with(_: ()) -> Self::Strategy {
+            (1..=MAX_IMAGE_SIDE, 1..=MAX_IMAGE_SIDE)
+                .prop_flat_map(|(w, h)| {
+                    let dim = Dim { w, h };
+                    proptest::collection::vec(any::<Colour>(), (w * h) as usize)
+                        .prop_map(move |pixels| Image::from_pixels(dim, pixels))
+                })
+                .boxed()
+        }
+    }
+}
+
+#[cfg(all(test, feature = "arbitrary"))]
+mod arbitrary_tests {
+    use super::*;
+    use arbitrary::{Arbitrary, Unstructured};
+
+    #[test]
+    fn stays_in_bounds() {
+        let data: Vec<u8> = (0..=255).cycle().take(4096).collect();
+        let mut u = Unstructured::new(&data);
+        let region = Region::arbitrary(&mut u).unwrap();
+        assert!(region.l.x <= MAX_COORDINATE && region.d.h <= MAX_COORDINATE);
+        let img = Image::arbitrary(&mut u).unwrap();
+        let dim = img.get_dimensions();
+        assert!((1..=MAX_IMAGE_SIDE).contains(&dim.w) && (1..=MAX_IMAGE_SIDE).contains(&dim.h));
+    }
+}
+
+#[cfg(all(test, feature = "proptest"))]
+mod proptest_tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn flipping_twice_is_the_identity(img in any::<Image>()) {
+            let flipped = img.flip_horizontal().flip_horizontal();
+            prop_assert_eq!(flipped, img);
+        }
+
+        #[test]
+        fn colours_survive_packing(colour in any::<Colour>()) {
+            prop_assert_eq!(Colour::from_rgba_u32(colour.to_rgba_u32()), colour);
+        }
+    }
+}
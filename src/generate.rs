@@ -0,0 +1,499 @@
+//! Generators: building new images out of other images, or from nothing but text.
+//!
+//! These functions take several images, or none at all, rather than transforming one, which is
+//! why they live in their own module instead of on `Image`.
+
+use crate::filter::premultiplied_channels;
+use crate::{
+    Alignment, Colour, Dim, EdgeMode, FontStack, Image, Loc, Region, ResizeFilter, Rng,
+    StrokeStyle, TextLayout, TextStyle,
+};
+
+/// Options for `captcha()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaptchaOptions {
+    /// The seed for the random generator. The same text and seed always give the same image.
+    pub seed: u64,
+    /// The size of the image.
+    pub dimension: Dim,
+    /// The colour of the text and of the lines drawn through it.
+    pub colour: Colour,
+    /// The colour that the blotchy background pattern is mixed into.
+    pub background: Colour,
+    /// The number of lines drawn through the text.
+    pub lines: u32,
+    /// How far the image is moved around by waves, in pixels.
+    pub distortion: f32,
+    /// The amount of grain added on top, as for `Image::add_grain()`.
+    pub noise: f32,
+}
+
+impl Default for CaptchaOptions {
+    fn default() -> Self {
+        CaptchaOptions {
+            seed: 0,
+            dimension: Dim { w: 160, h: 60 },
+            colour: Colour::rgb(40, 40, 100),
+            background: Colour::rgb(225, 225, 210),
+            lines: 5,
+            distortion: 3.0,
+            noise: 0.15,
+        }
+    }
+}
+
+/// Returns the average of the premultiplied channels of an image, so that transparent pixels
+/// count for nothing.
+fn average(img: &Image) -> [f32; 4] {
+    let mut sum = [0.0; 4];
+    for &c in img.pixels.iter() {
+        for (s, v) in sum.iter_mut().zip(premultiplied_channels(c)) {
+            *s += v;
+        }
+    }
+    sum.map(|s| s / img.pixels.len().max(1) as f32)
+}
+
+/// Rebuilds `target` out of the images in `tile_library`: the target is divided into cells of
+/// `tile_dim`, and every cell is replaced by the library image whose average colour is closest
+/// to that of the cell.
+///
+/// Library images are cropped around their centre to the proportions of `tile_dim` and then
+/// scaled down to it, so photos of any shape can be used. Library images may be used any number
+/// of times, and empty ones are ignored. The result has the dimensions of the target; cells at
+/// its right and bottom edges get the top left part of their tile. The larger and more varied
+/// the library, and the smaller the tiles, the more the result looks like the target.
+///
+/// # Panics
+///
+/// Panics if `tile_dim` is zero in either direction, or if the library has no images that are
+/// not empty.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use kodak::*;
+/// let target = Image::load_png("assets/olle_voader.png").unwrap();
+/// let library: Vec<Image> = ["assets/a.png", "assets/b.png", "assets/c.png"]
+///     .iter()
+///     .map(|path| Image::load_png(path).unwrap())
+///     .collect();
+/// generate::photomosaic(&target, &library, Dim::square(16)).save_png("mosaic.png").unwrap();
+/// ```
+pub fn photomosaic(target: &Image, tile_library: &[Image], tile_dim: Dim) -> Image {
+    assert!(
+        tile_dim.w > 0 && tile_dim.h > 0,
+        "Tiles must be at least one pixel in size."
+    );
+    let tiles: Vec<(Image, [f32; 4])> = tile_library
+        .iter()
+        .filter(|img| img.width > 0 && img.height > 0)
+        .map(|img| {
+            // The largest centred region with the proportions of a tile.
+            let (w, h) = (img.width as u64, img.height as u64);
+            let (tw, th) = (tile_dim.w as u64, tile_dim.h as u64);
+            let d = if w * th > h * tw {
+                Dim {
+                    w: (h * tw / th).max(1) as u32,
+                    h: img.height,
+                }
+            } else {
+                Dim {
+                    w: img.width,
+                    h: (w * th / tw).max(1) as u32,
+                }
+            };
+            let l = Loc {
+                x: (img.width - d.w) / 2,
+                y: (img.height - d.h) / 2,
+            };
+            let tile = img
+                .copy_region(Region { l, d })
+                .resize(tile_dim, ResizeFilter::Area);
+            let colour = average(&tile);
+            (tile, colour)
+        })
+        .collect();
+    assert!(!tiles.is_empty(), "The tile library has no images.");
+
+    let mut mosaic = target.clone();
+    for y in (0..target.height).step_by(tile_dim.h as usize) {
+        for x in (0..target.width).step_by(tile_dim.w as usize) {
+            let cell = target.copy_region(Region {
+                l: Loc { x, y },
+                d: tile_dim,
+            });
+            let colour = average(&cell);
+            let distance = |other: &[f32; 4]| -> f32 {
+                colour
+                    .iter()
+                    .zip(other)
+                    .map(|(a, b)| (a - b) * (a - b))
+                    .sum()
+            };
+            let (tile, _) = tiles
+                .iter()
+                .min_by(|a, b| distance(&a.1).total_cmp(&distance(&b.1)))
+                .expect("There is at least one tile.");
+            for row in 0..cell.height {
+                mosaic.row_mut(y + row)[x as usize..][..cell.width as usize]
+                    .copy_from_slice(&tile.row(row)[..cell.width as usize]);
+            }
+        }
+    }
+    mosaic
+}
+
+/// Renders `text` in `fg` onto a canvas of `bg` that fits it exactly, with `padding` pixels
+/// on every side, for titles, placeholders and social media cards.
+///
+/// The text is drawn at `size` pixels by `font`, which is either a single `Font` or a
+/// `FontStack` with fallbacks. Lines break at newlines only and are centred on each other.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use kodak::*;
+/// let font = Font::load("assets/DejaVuSans.ttf").unwrap();
+/// generate::banner("Coming soon", &font, 48.0, 24, Colour::WHITE, Colour::rgb(30, 30, 60))
+///     .save_png("coming_soon.png").unwrap();
+/// ```
+pub fn banner(
+    text: &str,
+    font: impl Into<FontStack>,
+    size: f32,
+    padding: u32,
+    fg: Colour,
+    bg: Colour,
+) -> Image {
+    let fonts = font.into();
+    let layout = TextLayout {
+        alignment: Alignment::Center,
+        ..TextLayout::default()
+    };
+    let dim = fonts
+        .measure_layout(text, size, &layout)
+        .expand(2 * padding);
+    Image::blank_with_colour(dim, bg).draw_text(
+        text,
+        fonts,
+        size,
+        Loc {
+            x: padding,
+            y: padding,
+        },
+        &TextStyle::fill(fg),
+        &layout,
+    )
+}
+
+/// Draws `text` as a captcha: hard for a program to read, and easy enough for a person.
+///
+/// The characters are drawn in the built-in bitmap font, each scaled to fit, turned by a random
+/// angle and moved up or down a little. They lie on a pattern of soft random blotches and are
+/// crossed by lines, and the whole image is warped by waves and covered in grain. Everything
+/// random comes from `options.seed`; pick a fresh seed for every captcha.
+///
+/// # Examples
+///
+/// ```
+/// # use kodak::*;
+/// let options = generate::CaptchaOptions {
+///     seed: 1234,
+///     ..Default::default()
+/// };
+/// let img = generate::captcha("K7W2QX", options);
+/// assert_eq!(img.get_dimensions(), options.dimension);
+/// ```
+pub fn captcha(text: &str, options: CaptchaOptions) -> Image {
+    let dim = options.dimension;
+    let mut rng = Rng::new(options.seed);
+    if dim.w == 0 || dim.h == 0 {
+        return Image::blank_with_colour(dim, options.background);
+    }
+    let (w, h) = (dim.w as f32, dim.h as f32);
+
+    // Scaling up a small random image gives large, soft blotches.
+    let blotches = Image::random(
+        Dim {
+            w: dim.w / 12 + 2,
+            h: dim.h / 12 + 2,
+        },
+        &mut rng,
+    )
+    .resize(dim, ResizeFilter::Bilinear);
+    let pixels = blotches
+        .pixels
+        .iter()
+        .map(|c| options.background.lerp(Colour::rgb(c.r, c.g, c.b), 0.3))
+        .collect();
+    let mut img = Image::from_pixels(dim, pixels);
+
+    let count = text.chars().count();
+    if count > 0 {
+        let cell = w * 0.9 / count as f32;
+        let scale = ((cell * 0.8).min(h * 0.6) / 8.0).floor().max(1.0) as u32;
+        let size = 8 * scale;
+        // Room around every character for it to turn without being cut off.
+        let margin = size / 3;
+        for (i, c) in text.chars().enumerate() {
+            let glyph =
+                Image::blank_with_colour(Dim::square(size + 2 * margin), Colour::TRANSPARENT)
+                    .draw_text_bitmap(
+                        c.encode_utf8(&mut [0; 4]),
+                        Loc {
+                            x: margin,
+                            y: margin,
+                        },
+                        scale,
+                        options.colour,
+                    )
+                    .rotate(rng.range_i32(-25, 25) as f32, Colour::TRANSPARENT);
+            let x = w * 0.05 + cell * i as f32 + (cell - size as f32) / 2.0;
+            let y = (h - size as f32) / 2.0 + (rng.next_f32() - 0.5) * size as f32 / 3.0;
+            let place =
+                |v: f32, len: u32| ((v - margin as f32).round().max(0.0) as u32).min(len - 1);
+            img = img.overlay(
+                glyph,
+                Loc {
+                    x: place(x, dim.w),
+                    y: place(y, dim.h),
+                },
+            );
+        }
+    }
+
+    for _ in 0..options.lines {
+        let from = (rng.next_f32() * w * 0.3, rng.next_f32() * h);
+        let to = (w - rng.next_f32() * w * 0.3, rng.next_f32() * h);
+        let style = StrokeStyle::new(1.0 + rng.next_f32());
+        img = img.stroke_line(from, to, options.colour, &style);
+    }
+
+    // Waves along both axes, with random lengths and phases.
+    let tau = std::f32::consts::TAU;
+    let (fx, fy) = (
+        tau / (w * (0.4 + 0.4 * rng.next_f32())),
+        tau / (h * (0.6 + 0.6 * rng.next_f32())),
+    );
+    let (px, py) = (rng.next_f32() * tau, rng.next_f32() * tau);
+    let amplitude = options.distortion;
+    img.remap_with_edge(dim, EdgeMode::Clamp, |x, y| {
+        Some((
+            x + amplitude * (y * fy + py).sin(),
+            y + amplitude * (x * fx + px).sin(),
+        ))
+    })
+    .add_grain(options.noise, options.seed)
+}
+
+/// Creates a blue noise threshold map, made with Ulichney's void-and-cluster method: an image
+/// of greys in which every level from black to white occurs about equally often, and the pixels
+/// at or below any level are spread as evenly as possible, without clumps or visible patterns.
+///
+/// Comparing the brightness of an image with a threshold map that is tiled across it dithers
+/// the image to black and white. Blue noise gives the fine, even grain of good halftoning,
+/// without the cross-hatching of a Bayer matrix. The map tiles seamlessly, so a small one, such
+/// as 64 by 64 pixels, is enough; it takes time proportional to the square of its area to make.
+/// The same seed always gives the same map.
+///
+/// # Examples
+///
+/// ```
+/// # use kodak::*;
+/// let map = generate::blue_noise(Dim::square(16), 7);
+/// let mut levels: Vec<u8> = map.row(0).iter().map(|c| c.r).collect();
+/// levels.sort();
+/// levels.dedup();
+/// assert_eq!(levels.len(), 16);
+/// ```
+pub fn blue_noise(dim: Dim, seed: u64) -> Image {
+    let (w, h) = (dim.w as i64, dim.h as i64);
+    let n = (w * h) as usize;
+    if n == 0 {
+        return Image::blank(dim);
+    }
+
+    // The energy of a pixel is the sum of a Gaussian around every set pixel, wrapping around the
+    // edges, so that the map tiles.
+    let sigma = 1.5_f32;
+    let r = (3.0 * sigma).ceil() as i64;
+    let kernel: Vec<(i64, i64, f32)> = (-r..=r)
+        .flat_map(|dy| (-r..=r).map(move |dx| (dx, dy)))
+        .map(|(dx, dy)| {
+            let weight = (-((dx * dx + dy * dy) as f32) / (2.0 * sigma * sigma)).exp();
+            (dx, dy, weight)
+        })
+        .collect();
+    let mut energy = vec![0.0_f32; n];
+    let spread = |energy: &mut [f32], i: usize, sign: f32| {
+        let (x, y) = (i as i64 % w, i as i64 / w);
+        for &(dx, dy, weight) in &kernel {
+            let j = (y + dy).rem_euclid(h) * w + (x + dx).rem_euclid(w);
+            energy[j as usize] += sign * weight;
+        }
+    };
+    // The set pixel in the tightest cluster, or the unset pixel in the largest void.
+    let extreme = |energy: &[f32], set: &[bool], want: bool| -> usize {
+        let candidates = (0..n).filter(|&i| set[i] == want);
+        let found = if want {
+            candidates.max_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+        } else {
+            candidates.min_by(|&a, &b| energy[a].total_cmp(&energy[b]))
+        };
+        found.expect("There are set and unset pixels.")
+    };
+
+    // A random tenth of the pixels, at least one, spread out evenly by moving the pixel from the
+    // tightest cluster to the largest void until that changes nothing.
+    let mut rng = Rng::new(seed);
+    let mut set = vec![false; n];
+    let initial = (n / 10).max(1);
+    let mut placed = 0;
+    while placed < initial {
+        let i = rng.below(n as u32) as usize;
+        if !set[i] {
+            set[i] = true;
+            spread(&mut energy, i, 1.0);
+            placed += 1;
+        }
+    }
+    if initial < n {
+        for _ in 0..n {
+            let cluster = extreme(&energy, &set, true);
+            set[cluster] = false;
+            spread(&mut energy, cluster, -1.0);
+            let void = extreme(&energy, &set, false);
+            set[void] = true;
+            spread(&mut energy, void, 1.0);
+            if void == cluster {
+                break;
+            }
+        }
+    }
+
+    // The initial pixels are ranked by taking them away from the tightest clusters first, and
+    // the rest by filling the largest voids first.
+    let mut rank = vec![0; n];
+    let (mut pattern, mut pattern_energy) = (set.clone(), energy.clone());
+    for r in (0..initial).rev() {
+        let cluster = extreme(&pattern_energy, &pattern, true);
+        pattern[cluster] = false;
+        spread(&mut pattern_energy, cluster, -1.0);
+        rank[cluster] = r;
+    }
+    for r in initial..n {
+        let void = extreme(&energy, &set, false);
+        set[void] = true;
+        spread(&mut energy, void, 1.0);
+        rank[void] = r;
+    }
+
+    let pixels = rank
+        .into_iter()
+        .map(|r| {
+            let v = (r * 256 / n) as u8;
+            Colour::rgb(v, v, v)
+        })
+        .collect();
+    Image::from_pixels(dim, pixels)
+}
+
+#[cfg(test)]
+mod generate_tests {
+    use super::*;
+
+    #[test]
+    fn photomosaic_picks_closest_tiles() {
+        // A target with a red left half and a blue right half, and a library of a red, a dark
+        // blue and a wide green image with a blue centre.
+        let target = Image::blank_with_colour(Dim { w: 10, h: 5 }, Colour::rgb(30, 30, 250))
+            .fill_region(
+                Region::from_top_left(Dim { w: 4, h: 5 }),
+                Colour::rgb(250, 20, 20),
+            );
+        let red = Image::blank_with_colour(Dim::square(8), Colour::rgb(200, 0, 0));
+        let dark_blue = Image::blank_with_colour(Dim::square(3), Colour::rgb(0, 0, 90));
+        let wide = Image::blank_with_colour(Dim { w: 12, h: 4 }, Colour::rgb(0, 200, 0))
+            .fill_region(
+                Region {
+                    l: Loc { x: 4, y: 0 },
+                    d: Dim { w: 4, h: 4 },
+                },
+                Colour::rgb(0, 0, 255),
+            );
+        let library = [red, Image::blank(Dim { w: 0, h: 4 }), dark_blue, wide];
+
+        let mosaic = photomosaic(&target, &library, Dim::square(4));
+        assert_eq!(mosaic.get_dimensions(), target.get_dimensions());
+        let at = |x, y| mosaic.get_pixel(Loc { x, y }).unwrap();
+        assert_eq!(at(0, 0), Colour::rgb(200, 0, 0));
+        assert_eq!(at(3, 4), Colour::rgb(200, 0, 0));
+        // The wide image is cropped to its blue centre, which beats the dark blue one.
+        assert_eq!(at(4, 0), Colour::rgb(0, 0, 255));
+        assert_eq!(at(9, 4), Colour::rgb(0, 0, 255));
+    }
+
+    #[test]
+    fn captchas_are_reproducible() {
+        let options = CaptchaOptions {
+            seed: 99,
+            noise: 0.0,
+            lines: 0,
+            ..Default::default()
+        };
+        let img = captcha("AB12", options);
+        assert_eq!(img.get_dimensions(), Dim { w: 160, h: 60 });
+        assert_eq!(img, captcha("AB12", options));
+        assert_ne!(
+            img,
+            captcha(
+                "AB12",
+                CaptchaOptions {
+                    seed: 100,
+                    ..options
+                }
+            )
+        );
+        assert_ne!(img, captcha("AB13", options));
+
+        // The text shows, and the background stays light around it.
+        let close = |c: &Colour| c.r.abs_diff(40) < 30 && c.b.abs_diff(100) < 30;
+        let text = img.pixels.iter().filter(|c| close(c)).count();
+        assert!(text > 200, "{text}");
+        assert!(img.row(0).iter().all(|c| c.r > 120));
+
+        let empty = CaptchaOptions {
+            dimension: Dim { w: 0, h: 10 },
+            ..options
+        };
+        assert_eq!(captcha("", options).row(30).len(), 160);
+        assert_eq!(captcha("AB", empty).get_dimensions(), empty.dimension);
+    }
+
+    #[test]
+    fn blue_noise_is_even() {
+        let map = blue_noise(Dim::square(32), 3);
+        assert_eq!(map, blue_noise(Dim::square(32), 3));
+        // Every level occurs four times.
+        let mut counts = [0; 256];
+        for c in map.pixels.iter() {
+            counts[c.r as usize] += 1;
+        }
+        assert!(counts.iter().all(|&n| n == 4));
+
+        // The darkest eighth is spread out: no two of its pixels touch, even across the edges.
+        for (i, c) in map.pixels.iter().enumerate() {
+            if c.r >= 32 {
+                continue;
+            }
+            let (x, y) = (i as i64 % 32, i as i64 / 32);
+            for (dx, dy) in [(1, 0), (0, 1), (1, 1), (1, -1)] {
+                let j = (y + dy).rem_euclid(32) * 32 + (x + dx).rem_euclid(32);
+                assert!(map.pixels[j as usize].r >= 32, "{x} {y}");
+            }
+        }
+        assert_eq!(blue_noise(Dim::square(1), 0).pixels[0], Colour::BLACK);
+        assert_eq!(blue_noise(Dim { w: 0, h: 5 }, 0).get_dimensions().h, 5);
+    }
+}
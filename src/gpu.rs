@@ -0,0 +1,561 @@
+//! Compute shader versions of resizing, blurring, convolving and compositing. Only available
+//! with the `gpu` feature.
+//!
+//! Uploading an image and reading it back has a fixed cost, so small images are processed on the
+//! CPU instead. The CPU is also used when there is no suitable graphics adapter or the image is
+//! too large for the adapter's limits, so every function always returns a result. Both paths give
+//! the same pixels, give or take one step per channel.
+
+use crate::filter::gaussian_weights;
+use crate::simd::to_rgba_bytes;
+use crate::{Colour, Dim, Image, Kernel, Loc, ResizeFilter};
+use std::fmt;
+use wgpu::util::DeviceExt;
+
+/// Images with fewer pixels than this are processed on the CPU.
+const MIN_PIXELS: usize = 512 * 512;
+
+/// The side length of a workgroup in the shaders.
+const WORKGROUP: u32 = 8;
+
+/// The compute pipelines, one per shader entry point.
+struct Pipelines {
+    resize_nearest: wgpu::ComputePipeline,
+    resize_bilinear: wgpu::ComputePipeline,
+    convolve: wgpu::ComputePipeline,
+    blur_horizontal: wgpu::ComputePipeline,
+    blur_vertical: wgpu::ComputePipeline,
+    overlay: wgpu::ComputePipeline,
+}
+
+/// Everything needed to run the shaders on one adapter.
+struct Context {
+    adapter_name: String,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    layout: wgpu::BindGroupLayout,
+    pipelines: Pipelines,
+}
+
+/// The values of the `Params` uniform in the shaders.
+#[derive(Default)]
+struct Params {
+    src: Dim,
+    dst: Dim,
+    scale: (f32, f32),
+    radius: u32,
+    offset: Loc,
+}
+
+impl Params {
+    fn to_bytes(&self) -> Vec<u8> {
+        [
+            self.src.w,
+            self.src.h,
+            self.dst.w,
+            self.dst.h,
+            self.scale.0.to_bits(),
+            self.scale.1.to_bits(),
+            self.radius,
+            self.offset.x,
+            self.offset.y,
+            0,
+            0,
+            0,
+        ]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect()
+    }
+}
+
+/// A single shader invocation over a grid of pixels.
+struct Pass<'a> {
+    pipeline: &'a wgpu::ComputePipeline,
+    grid: Dim,
+}
+
+impl Context {
+    fn new() -> Option<Context> {
+        let instance = wgpu::Instance::default();
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))
+        .ok()?;
+        if !adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+        {
+            return None;
+        }
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("kodak"),
+            required_limits: adapter.limits(),
+            ..Default::default()
+        }))
+        .ok()?;
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("kodak"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("gpu.wgsl").into()),
+        });
+        let entry = |binding: u32, ty: wgpu::BufferBindingType| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let storage = |read_only| wgpu::BufferBindingType::Storage { read_only };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("kodak"),
+            entries: &[
+                entry(0, wgpu::BufferBindingType::Uniform),
+                entry(1, storage(true)),
+                entry(2, storage(false)),
+                entry(3, storage(true)),
+                entry(4, storage(false)),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("kodak"),
+            bind_group_layouts: &[Some(&layout)],
+            ..Default::default()
+        });
+        let pipeline = |name: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(name),
+                layout: Some(&pipeline_layout),
+                module: &module,
+                entry_point: Some(name),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        let pipelines = Pipelines {
+            resize_nearest: pipeline("resize_nearest"),
+            resize_bilinear: pipeline("resize_bilinear"),
+            convolve: pipeline("convolve"),
+            blur_horizontal: pipeline("blur_horizontal"),
+            blur_vertical: pipeline("blur_vertical"),
+            overlay: pipeline("overlay"),
+        };
+
+        Some(Context {
+            adapter_name: adapter.get_info().name,
+            device,
+            queue,
+            layout,
+            pipelines,
+        })
+    }
+
+    /// Runs the passes in order and reads back `dst`, which holds `dst_len` pixels and starts
+    /// out as `dst_init` if given. Returns `None` if a buffer or a dispatch would exceed the
+    /// device's limits.
+    fn run(
+        &self,
+        passes: &[Pass],
+        params: &Params,
+        src: &[Colour],
+        dst_len: usize,
+        dst_init: Option<&[Colour]>,
+        weights: &[f32],
+    ) -> Option<Vec<Colour>> {
+        // The blur passes keep four floats per pixel in between.
+        let tmp_size = src.len().max(dst_len) as u64 * 16;
+        let limits = self.device.limits();
+        if tmp_size > limits.max_storage_buffer_binding_size {
+            return None;
+        }
+        let max_groups = limits.max_compute_workgroups_per_dimension;
+        if passes.iter().any(|p| {
+            p.grid.w.div_ceil(WORKGROUP) > max_groups || p.grid.h.div_ceil(WORKGROUP) > max_groups
+        }) {
+            return None;
+        }
+
+        let buffer = |label: &str, contents: &[u8], usage: wgpu::BufferUsages| {
+            self.device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(label),
+                    contents,
+                    usage,
+                })
+        };
+        let params = buffer("params", &params.to_bytes(), wgpu::BufferUsages::UNIFORM);
+        let src = buffer("src", &to_rgba_bytes(src), wgpu::BufferUsages::STORAGE);
+        let dst_size = dst_len as u64 * 4;
+        let dst_usage = wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC;
+        let dst = match dst_init {
+            Some(pixels) => buffer("dst", &to_rgba_bytes(pixels), dst_usage),
+            None => self.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("dst"),
+                size: dst_size,
+                usage: dst_usage,
+                mapped_at_creation: false,
+            }),
+        };
+        // Storage buffers cannot be empty, so resizing gets a single unused weight.
+        let weights: Vec<u8> = weights
+            .iter()
+            .chain(weights.is_empty().then_some(&0.0))
+            .flat_map(|w| w.to_le_bytes())
+            .collect();
+        let weights = buffer("weights", &weights, wgpu::BufferUsages::STORAGE);
+        let needs_tmp = passes
+            .iter()
+            .any(|p| std::ptr::eq(p.pipeline, &self.pipelines.blur_horizontal));
+        let tmp = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("tmp"),
+            size: if needs_tmp { tmp_size } else { 16 },
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size: dst_size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("kodak"),
+            layout: &self.layout,
+            entries: &[&params, &src, &dst, &weights, &tmp]
+                .iter()
+                .enumerate()
+                .map(|(i, b)| wgpu::BindGroupEntry {
+                    binding: i as u32,
+                    resource: b.as_entire_binding(),
+                })
+                .collect::<Vec<_>>(),
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("kodak"),
+            });
+        // Separate compute passes make every pass see the writes of the one before it.
+        for pass in passes {
+            let mut compute = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            compute.set_pipeline(pass.pipeline);
+            compute.set_bind_group(0, &bind_group, &[]);
+            compute.dispatch_workgroups(
+                pass.grid.w.div_ceil(WORKGROUP),
+                pass.grid.h.div_ceil(WORKGROUP),
+                1,
+            );
+        }
+        encoder.copy_buffer_to_buffer(&dst, 0, &readback, 0, dst_size);
+        self.queue.submit([encoder.finish()]);
+
+        let slice = readback.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| {});
+        self.device.poll(wgpu::PollType::wait_indefinitely()).ok()?;
+        let pixels = slice
+            .get_mapped_range()
+            .ok()?
+            .chunks_exact(4)
+            .map(|p| Colour::rgba(p[0], p[1], p[2], p[3]))
+            .collect();
+        Some(pixels)
+    }
+}
+
+/// A handle to a graphics adapter for processing large images with compute shaders.
+///
+/// Creating one looks for an adapter and compiles the shaders, so create it once and reuse it.
+/// Every function falls back to the equivalent `Image` function when the GPU cannot be used.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use kodak::*;
+/// let gpu = Gpu::new();
+/// let img = Image::load_png("assets/olle_ma.png").unwrap();
+/// let blurred = gpu.blur(&img.resize(Dim::square(4096), ResizeFilter::Bilinear), 8.0);
+/// ```
+pub struct Gpu {
+    context: Option<Context>,
+    min_pixels: usize,
+}
+
+impl fmt::Debug for Gpu {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Gpu")
+            .field("adapter", &self.adapter_name())
+            .field("min_pixels", &self.min_pixels)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Gpu {
+    fn default() -> Self {
+        Gpu::new()
+    }
+}
+
+impl Gpu {
+    /// Looks for a graphics adapter that supports compute shaders. If there is none, the
+    /// returned handle does everything on the CPU.
+    pub fn new() -> Gpu {
+        Gpu {
+            context: Context::new(),
+            min_pixels: MIN_PIXELS,
+        }
+    }
+
+    /// Returns whether an adapter was found, so that large images are processed on the GPU.
+    pub fn is_available(&self) -> bool {
+        self.context.is_some()
+    }
+
+    /// Returns the name of the adapter in use, if any.
+    pub fn adapter_name(&self) -> Option<&str> {
+        self.context.as_ref().map(|c| c.adapter_name.as_str())
+    }
+
+    /// Returns the context if an image of `pixels` pixels should be processed on the GPU.
+    fn context_for(&self, pixels: usize) -> Option<&Context> {
+        self.context
+            .as_ref()
+            .filter(|_| pixels > 0 && pixels >= self.min_pixels)
+    }
+
+    /// Resizes an image like `Image::resize()`.
+    pub fn resize(&self, img: &Image, dimension: Dim, filter: ResizeFilter) -> Image {
+        let pixels = img
+            .pixels
+            .len()
+            .max(dimension.w as usize * dimension.h as usize);
+        let result = self
+            .context_for(pixels)
+            .filter(|_| dimension.w > 0 && dimension.h > 0)
+            .and_then(|context| {
+                let pipeline = match filter {
+                    ResizeFilter::Nearest => &context.pipelines.resize_nearest,
+                    ResizeFilter::Bilinear => &context.pipelines.resize_bilinear,
+                    // Area averaging has no shader yet and runs on the CPU.
+                    ResizeFilter::Area => return None,
+                };
+                let params = Params {
+                    src: img.get_dimensions(),
+                    dst: dimension,
+                    scale: (
+                        img.width as f32 / dimension.w as f32,
+                        img.height as f32 / dimension.h as f32,
+                    ),
+                    ..Default::default()
+                };
+                let pass = Pass {
+                    pipeline,
+                    grid: dimension,
+                };
+                context.run(
+                    &[pass],
+                    &params,
+                    &img.pixels,
+                    pixels_in(dimension),
+                    None,
+                    &[],
+                )
+            });
+        match result {
+            Some(pixels) => Image {
+                width: dimension.w,
+                height: dimension.h,
+                pixels: pixels.into(),
+                colour_profile: img.colour_profile.clone(),
+                metadata: img.metadata.clone(),
+            },
+            None => img.resize(dimension, filter),
+        }
+    }
+
+    /// Convolves an image with a kernel like `Image::convolve()`.
+    pub fn convolve(&self, img: &Image, kernel: &Kernel) -> Image {
+        let result = self.context_for(img.pixels.len()).and_then(|context| {
+            let params = Params {
+                src: img.get_dimensions(),
+                radius: kernel.size() / 2,
+                ..Default::default()
+            };
+            let pass = Pass {
+                pipeline: &context.pipelines.convolve,
+                grid: img.get_dimensions(),
+            };
+            let len = img.pixels.len();
+            context.run(&[pass], &params, &img.pixels, len, None, kernel.weights())
+        });
+        match result {
+            Some(pixels) => Image {
+                pixels: pixels.into(),
+                ..img.clone()
+            },
+            None => img.convolve(kernel),
+        }
+    }
+
+    /// Blurs an image like `Image::blur()`.
+    pub fn blur(&self, img: &Image, sigma: f32) -> Image {
+        let result = self.context_for(img.pixels.len()).and_then(|context| {
+            let weights = gaussian_weights(sigma);
+            let params = Params {
+                src: img.get_dimensions(),
+                radius: weights.len() as u32 / 2,
+                ..Default::default()
+            };
+            let grid = img.get_dimensions();
+            let passes = [
+                Pass {
+                    pipeline: &context.pipelines.blur_horizontal,
+                    grid,
+                },
+                Pass {
+                    pipeline: &context.pipelines.blur_vertical,
+                    grid,
+                },
+            ];
+            let len = img.pixels.len();
+            context.run(&passes, &params, &img.pixels, len, None, &weights)
+        });
+        match result {
+            Some(pixels) => Image {
+                pixels: pixels.into(),
+                ..img.clone()
+            },
+            None => img.blur(sigma),
+        }
+    }
+
+    /// Composites `top` over `base` with its top left corner at `offset`, like
+    /// `Image::overlay()`. Parts of `top` that fall outside of `base` are cut off.
+    pub fn overlay(&self, base: &Image, top: &Image, offset: Loc) -> Image {
+        // `Image::overlay()` expects the corner to lie on the base, and otherwise nothing of
+        // `top` would show anyway.
+        if offset.x >= base.width || offset.y >= base.height || top.pixels.is_empty() {
+            return base.clone();
+        }
+        let result = self.context_for(base.pixels.len()).and_then(|context| {
+            let params = Params {
+                src: top.get_dimensions(),
+                dst: base.get_dimensions(),
+                offset,
+                ..Default::default()
+            };
+            let pass = Pass {
+                pipeline: &context.pipelines.overlay,
+                grid: top.get_dimensions(),
+            };
+            let len = base.pixels.len();
+            context.run(&[pass], &params, &top.pixels, len, Some(&base.pixels), &[])
+        });
+        match result {
+            Some(pixels) => Image {
+                pixels: pixels.into(),
+                ..base.clone()
+            },
+            None => base.clone().overlay(top.clone(), offset),
+        }
+    }
+}
+
+fn pixels_in(dimension: Dim) -> usize {
+    dimension.w as usize * dimension.h as usize
+}
+
+#[cfg(test)]
+mod gpu_tests {
+    use super::*;
+    use crate::Region;
+
+    /// Returns a handle that uses the GPU for every image.
+    fn gpu() -> Gpu {
+        let gpu = Gpu {
+            min_pixels: 0,
+            ..Gpu::new()
+        };
+        assert!(gpu.is_available(), "no graphics adapter was found");
+        gpu
+    }
+
+    fn assert_close(gpu: &Image, cpu: &Image) {
+        assert_eq!(gpu.get_dimensions(), cpu.get_dimensions());
+        for (a, b) in gpu.pixels.iter().zip(&cpu.pixels) {
+            let close = a
+                .to_array()
+                .iter()
+                .zip(b.to_array())
+                .all(|(x, y)| x.abs_diff(y) <= 1);
+            assert!(close, "GPU gave {a:?} where the CPU gave {b:?}");
+        }
+    }
+
+    fn test_image() -> Image {
+        Image::blank_with_colour(Dim { w: 37, h: 23 }, Colour::rgba(20, 80, 200, 255))
+            .fill_region(
+                Region {
+                    l: Loc { x: 5, y: 3 },
+                    d: Dim { w: 20, h: 11 },
+                },
+                Colour::rgba(250, 240, 10, 100),
+            )
+            .fill_region(
+                Region {
+                    l: Loc { x: 30, y: 0 },
+                    d: Dim { w: 7, h: 23 },
+                },
+                Colour::TRANSPARENT,
+            )
+    }
+
+    #[test]
+    #[ignore = "needs a graphics adapter"]
+    fn matches_cpu() {
+        let gpu = gpu();
+        let img = test_image();
+
+        for filter in [ResizeFilter::Nearest, ResizeFilter::Bilinear] {
+            for dim in [Dim { w: 80, h: 9 }, Dim { w: 13, h: 50 }] {
+                assert_close(&gpu.resize(&img, dim, filter), &img.resize(dim, filter));
+            }
+        }
+        assert_close(
+            &gpu.convolve(&img, &Kernel::sharpen()),
+            &img.convolve(&Kernel::sharpen()),
+        );
+        assert_close(&gpu.blur(&img, 2.5), &img.blur(2.5));
+
+        let top = Image::blank_with_colour(Dim::square(12), Colour::rgba(255, 0, 0, 128));
+        let offset = Loc { x: 28, y: 15 };
+        assert_close(
+            &gpu.overlay(&img, &top, offset),
+            &img.clone().overlay(top.clone(), offset),
+        );
+    }
+
+    #[test]
+    fn small_images_stay_on_cpu() {
+        let gpu = Gpu::new();
+        let img = test_image();
+
+        assert_eq!(gpu.blur(&img, 1.0).pixels, img.blur(1.0).pixels);
+    }
+
+    #[test]
+    fn overlay_outside_leaves_base() {
+        let gpu = Gpu::new();
+        let img = test_image();
+        let top = Image::blank_with_colour(Dim::square(4), Colour::WHITE);
+
+        assert_eq!(gpu.overlay(&img, &top, Loc { x: 37, y: 0 }), img);
+        assert_eq!(gpu.overlay(&img, &top, Loc { x: 0, y: 99 }), img);
+    }
+}
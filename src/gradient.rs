@@ -0,0 +1,118 @@
+//! Colour gradients that can be sampled independently of any image.
+
+use crate::Colour;
+
+/// The colour space in which a `Gradient` interpolates between its stops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interpolation {
+    /// Plain interpolation of the sRGB channel values.
+    #[default]
+    Srgb,
+    /// Perceptually uniform interpolation in the Oklab colour space.
+    Oklab,
+}
+
+/// A colour gradient, defined by colour stops at positions between 0.0 and 1.0.
+///
+/// # Examples
+///
+/// ```
+/// # use kodak::*;
+/// let gradient = Gradient::new(vec![(0.0, Colour::BLACK), (1.0, Colour::WHITE)])
+///     .with_interpolation(Interpolation::Oklab);
+/// assert_eq!(gradient.sample(0.0), Colour::BLACK);
+/// assert_eq!(gradient.sample(1.0), Colour::WHITE);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    /// The colour stops, sorted by position.
+    stops: Vec<(f32, Colour)>,
+    /// The colour space used to interpolate between stops.
+    interpolation: Interpolation,
+}
+
+impl Gradient {
+    /// Creates a gradient from a list of `(position, colour)` stops.
+    ///
+    /// The stops do not need to be sorted. Positions outside of `0.0..=1.0` are allowed, but the
+    /// gradient is only ever sampled within that range.
+    ///
+    /// # Panics
+    ///
+    /// * if no stops are given,
+    /// * if a position is NaN.
+    pub fn new(mut stops: Vec<(f32, Colour)>) -> Self {
+        assert!(
+            !stops.is_empty(),
+            "A gradient needs at least one colour stop."
+        );
+        assert!(
+            stops.iter().all(|s| !s.0.is_nan()),
+            "Gradient stop positions cannot be NaN."
+        );
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Gradient {
+            stops,
+            interpolation: Interpolation::default(),
+        }
+    }
+
+    /// Creates a two-stop gradient going from one colour to another.
+    pub fn linear(from: Colour, to: Colour) -> Self {
+        Gradient::new(vec![(0.0, from), (1.0, to)])
+    }
+
+    /// Sets the colour space used for interpolation.
+    pub fn with_interpolation(self, interpolation: Interpolation) -> Self {
+        Gradient {
+            interpolation,
+            ..self
+        }
+    }
+
+    /// Returns the colour stops of the gradient, sorted by position.
+    pub fn stops(&self) -> &[(f32, Colour)] {
+        &self.stops
+    }
+
+    /// Returns the colour of the gradient at position `t`, which is clamped to `0.0..=1.0`. A
+    /// `t` of NaN gives the colour of the first stop.
+    pub fn sample(&self, t: f32) -> Colour {
+        let t = t.clamp(0.0, 1.0);
+
+        let first = self.stops[0];
+        let last = self.stops[self.stops.len() - 1];
+        if t.is_nan() || t <= first.0 {
+            return first.1;
+        }
+        if t >= last.0 {
+            return last.1;
+        }
+
+        let upper = self.stops.iter().position(|s| s.0 > t).unwrap();
+        let (p0, c0) = self.stops[upper - 1];
+        let (p1, c1) = self.stops[upper];
+        let local = (t - p0) / (p1 - p0);
+
+        match self.interpolation {
+            Interpolation::Srgb => c0.lerp(c1, local),
+            Interpolation::Oklab => c0.lerp_oklab(c1, local),
+        }
+    }
+}
+
+#[cfg(test)]
+mod gradient_tests {
+    use super::*;
+
+    #[test]
+    fn sample_between_stops() {
+        let red = Colour::rgb(255, 0, 0);
+        let gradient = Gradient::new(vec![(1.0, Colour::WHITE), (0.0, Colour::BLACK), (0.5, red)]);
+
+        assert_eq!(gradient.sample(0.5), red);
+        assert_eq!(gradient.sample(0.25), Colour::rgb(128, 0, 0));
+        assert_eq!(gradient.sample(2.0), Colour::WHITE);
+        assert_eq!(gradient.sample(f32::NAN), Colour::BLACK);
+    }
+}
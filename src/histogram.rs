@@ -0,0 +1,325 @@
+//! Channel histograms, and the automatic colour corrections built on them.
+
+use crate::Image;
+
+/// The number of pixels with each value of each channel, as counted by `Image::histogram()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Histogram {
+    /// The counts for every value of the red channel.
+    pub red: [u64; 256],
+    /// The counts for every value of the green channel.
+    pub green: [u64; 256],
+    /// The counts for every value of the blue channel.
+    pub blue: [u64; 256],
+    /// The counts for every value of the alpha channel.
+    pub alpha: [u64; 256],
+}
+
+impl Histogram {
+    /// Returns the number of pixels that were counted.
+    pub fn total(&self) -> u64 {
+        self.red.iter().sum()
+    }
+
+    /// Returns the lowest value that at least `percent` percent of the counts are at or below,
+    /// so 0.0 gives the smallest value that occurs, 50.0 the median and 100.0 the largest.
+    /// An empty channel gives 0.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let img = Image::blank_with_colour(Dim { w: 4, h: 1 }, Colour::rgb(10, 0, 0))
+    ///     .fill_region(Region::from_top_left(Dim::square(1)), Colour::rgb(200, 0, 0));
+    /// let histogram = img.histogram();
+    /// assert_eq!(Histogram::percentile(&histogram.red, 50.0), 10);
+    /// assert_eq!(Histogram::percentile(&histogram.red, 100.0), 200);
+    /// ```
+    pub fn percentile(counts: &[u64; 256], percent: f32) -> u8 {
+        let total: u64 = counts.iter().sum();
+        let fraction = (percent as f64 / 100.0).clamp(0.0, 1.0);
+        let target = (total as f64 * fraction).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (value, &count) in counts.iter().enumerate() {
+            seen += count;
+            if seen >= target {
+                return value as u8;
+            }
+        }
+        0
+    }
+
+    /// Returns the average value of a channel, or 0.0 if it is empty.
+    pub fn mean(counts: &[u64; 256]) -> f32 {
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return 0.0;
+        }
+        let sum: f64 = counts
+            .iter()
+            .enumerate()
+            .map(|(value, &count)| value as f64 * count as f64)
+            .sum();
+        (sum / total as f64) as f32
+    }
+}
+
+/// How `Image::auto_white_balance()` estimates the colour of the light in a photo.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum WhiteBalance {
+    /// Assumes that the scene averages out to grey, and scales every channel so that its mean
+    /// becomes the mean of all three.
+    #[default]
+    GreyWorld,
+    /// Assumes that the brightest pixels are white, and scales every channel so that the given
+    /// percentile of it becomes 255. Around 99.0 ignores a few blown highlights.
+    Percentile(f32),
+}
+
+/// Returns the table that maps each value of a channel counted in `source` to the value with
+/// the same rank in `reference`: the lowest one that at least as large a share of `reference`
+/// is at or below.
+fn matching_table(source: &[u64; 256], reference: &[u64; 256]) -> [u8; 256] {
+    let (source_total, reference_total) = (
+        source.iter().sum::<u64>() as f64,
+        reference.iter().sum::<u64>() as f64,
+    );
+    let mut table = [0; 256];
+    let (mut seen, mut reference_seen, mut matched) = (0, reference[0], 0);
+    for (value, out) in table.iter_mut().enumerate() {
+        seen += source[value];
+        let share = seen as f64 / source_total;
+        while matched < 255 && (reference_seen as f64 / reference_total) < share {
+            matched += 1;
+            reference_seen += reference[matched];
+        }
+        *out = matched as u8;
+    }
+    table
+}
+
+// The following impl block defines histogram-based adjustment functions for Images.
+impl Image {
+    /// Counts the pixels with each value of each channel.
+    pub fn histogram(&self) -> Histogram {
+        let mut histogram = Histogram {
+            red: [0; 256],
+            green: [0; 256],
+            blue: [0; 256],
+            alpha: [0; 256],
+        };
+        for c in self.pixels.iter() {
+            histogram.red[c.r as usize] += 1;
+            histogram.green[c.g as usize] += 1;
+            histogram.blue[c.b as usize] += 1;
+            histogram.alpha[c.a as usize] += 1;
+        }
+        histogram
+    }
+
+    /// Stretches the colours so that they span the full range from 0 to 255.
+    ///
+    /// The darkest and brightest `clip_percent` percent of the red, green and blue values are
+    /// clipped to black and white, so that a few stray pixels do not keep the rest from being
+    /// stretched; 0.5 to 1.0 are typical values. All three channels are stretched by the same
+    /// amount, which keeps the hues as they are; use `auto_white_balance()` to correct a colour
+    /// cast. Alpha is left alone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let dull = Image::blank_with_colour(Dim { w: 2, h: 1 }, Colour::rgb(100, 100, 100))
+    ///     .fill_region(Region::from_top_left(Dim::square(1)), Colour::rgb(150, 150, 150));
+    /// let crisp = dull.auto_contrast(0.0);
+    /// assert_eq!(crisp.get_pixel(Loc { x: 0, y: 0 }), Ok(Colour::WHITE));
+    /// assert_eq!(crisp.get_pixel(Loc { x: 1, y: 0 }), Ok(Colour::BLACK));
+    /// ```
+    pub fn auto_contrast(mut self, clip_percent: f32) -> Image {
+        let histogram = self.histogram();
+        let mut counts = [0; 256];
+        for (value, count) in counts.iter_mut().enumerate() {
+            *count = histogram.red[value] + histogram.green[value] + histogram.blue[value];
+        }
+        let low = Histogram::percentile(&counts, clip_percent) as f32;
+        let high = Histogram::percentile(&counts, 100.0 - clip_percent) as f32;
+        if high <= low {
+            return self;
+        }
+
+        let mut table = [0; 256];
+        for (value, out) in table.iter_mut().enumerate() {
+            *out = ((value as f32 - low) * 255.0 / (high - low))
+                .round()
+                .clamp(0.0, 255.0) as u8;
+        }
+        for c in self.pixels.iter_mut() {
+            c.r = table[c.r as usize];
+            c.g = table[c.g as usize];
+            c.b = table[c.b as usize];
+        }
+        self
+    }
+
+    /// Removes a colour cast by scaling the red, green and blue channels, estimating the colour
+    /// of the light with `method`. Channels that are entirely black are left alone, as is alpha.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let warm = Image::blank_with_colour(Dim::square(4), Colour::rgb(180, 120, 60));
+    /// let balanced = warm.auto_white_balance(WhiteBalance::GreyWorld);
+    /// assert_eq!(balanced.get_pixel(Loc { x: 0, y: 0 }), Ok(Colour::rgb(120, 120, 120)));
+    /// ```
+    pub fn auto_white_balance(self, method: WhiteBalance) -> Image {
+        let histogram = self.histogram();
+        let channels = [&histogram.red, &histogram.green, &histogram.blue];
+        let factors = match method {
+            WhiteBalance::GreyWorld => {
+                let means = channels.map(Histogram::mean);
+                let grey = means.iter().sum::<f32>() / 3.0;
+                means.map(|mean| if mean > 0.0 { grey / mean } else { 1.0 })
+            }
+            WhiteBalance::Percentile(percent) => {
+                channels.map(|counts| match Histogram::percentile(counts, percent) {
+                    0 => 1.0,
+                    white => 255.0 / white as f32,
+                })
+            }
+        };
+        self.adjust_channels(factors[0], factors[1], factors[2])
+    }
+
+    /// Remaps the red, green and blue channels so that their histograms match those of
+    /// `reference`, giving the image the tonal range and colour balance of the other one.
+    ///
+    /// Every value is replaced with the value of the same rank in the reference: a pixel that is
+    /// brighter than a third of the image in some channel becomes as bright as a third of the
+    /// reference in that channel. This makes photos from different cameras, or the frames of an
+    /// animation, look consistent. Alpha is left alone, and so is everything if either image is
+    /// empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let dark = Image::blank_with_colour(Dim { w: 2, h: 1 }, Colour::rgb(10, 10, 10))
+    ///     .fill_region(Region::from_top_left(Dim::square(1)), Colour::rgb(30, 30, 30));
+    /// let bright = Image::blank_with_colour(Dim { w: 4, h: 1 }, Colour::rgb(200, 200, 200))
+    ///     .fill_region(Region::from_top_left(Dim { w: 2, h: 1 }), Colour::rgb(240, 240, 240));
+    /// let matched = dark.match_histogram(&bright);
+    /// assert_eq!(matched.get_pixel(Loc { x: 0, y: 0 }), Ok(Colour::rgb(240, 240, 240)));
+    /// assert_eq!(matched.get_pixel(Loc { x: 1, y: 0 }), Ok(Colour::rgb(200, 200, 200)));
+    /// ```
+    pub fn match_histogram(mut self, reference: &Image) -> Image {
+        if self.pixels.is_empty() || reference.pixels.is_empty() {
+            return self;
+        }
+        let (source, reference) = (self.histogram(), reference.histogram());
+        let red = matching_table(&source.red, &reference.red);
+        let green = matching_table(&source.green, &reference.green);
+        let blue = matching_table(&source.blue, &reference.blue);
+        for c in self.pixels.iter_mut() {
+            c.r = red[c.r as usize];
+            c.g = green[c.g as usize];
+            c.b = blue[c.b as usize];
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod histogram_tests {
+    use super::*;
+    use crate::{Colour, Dim, Loc, Region};
+
+    #[test]
+    fn counts_and_percentiles() {
+        let img = Image::blank_with_colour(Dim { w: 10, h: 10 }, Colour::rgba(50, 60, 70, 255))
+            .fill_region(
+                Region::from_top_left(Dim { w: 10, h: 1 }),
+                Colour::rgba(250, 0, 0, 255),
+            );
+        let histogram = img.histogram();
+        assert_eq!(histogram.total(), 100);
+        assert_eq!((histogram.red[50], histogram.red[250]), (90, 10));
+        assert_eq!(histogram.alpha[255], 100);
+        assert_eq!(Histogram::percentile(&histogram.red, 0.0), 50);
+        assert_eq!(Histogram::percentile(&histogram.red, 90.0), 50);
+        assert_eq!(Histogram::percentile(&histogram.red, 91.0), 250);
+        assert_eq!(Histogram::mean(&histogram.red), 70.0);
+        assert_eq!(Histogram::percentile(&[0; 256], 50.0), 0);
+        assert_eq!(Histogram::mean(&[0; 256]), 0.0);
+    }
+
+    #[test]
+    fn auto_contrast_clips_outliers() {
+        // A grey gradient from 100 to 149, with one black and one white pixel.
+        let mut img = Image::blank(Dim { w: 52, h: 1 });
+        for (x, c) in img.row_mut(0).iter_mut().enumerate() {
+            let v = match x {
+                0 => 0,
+                51 => 255,
+                x => 99 + x as u8,
+            };
+            *c = Colour::rgba(v, v, v, 128);
+        }
+
+        let unclipped = img.clone().auto_contrast(0.0);
+        assert_eq!(unclipped, img);
+        let stretched = img.auto_contrast(2.0);
+        assert_eq!(
+            stretched.get_pixel(Loc { x: 1, y: 0 }),
+            Ok(Colour::rgba(0, 0, 0, 128))
+        );
+        assert_eq!(
+            stretched.get_pixel(Loc { x: 50, y: 0 }),
+            Ok(Colour::rgba(255, 255, 255, 128))
+        );
+
+        let flat = Image::blank_with_colour(Dim::square(3), Colour::rgb(90, 90, 90));
+        assert_eq!(flat.clone().auto_contrast(1.0), flat);
+    }
+
+    #[test]
+    fn white_balance_by_percentile() {
+        let img = Image::blank_with_colour(Dim { w: 4, h: 1 }, Colour::rgb(20, 20, 0)).fill_region(
+            Region::from_top_left(Dim::square(1)),
+            Colour::rgb(200, 250, 0),
+        );
+        let balanced = img.auto_white_balance(WhiteBalance::Percentile(100.0));
+        assert_eq!(
+            balanced.get_pixel(Loc { x: 0, y: 0 }),
+            Ok(Colour::rgb(255, 255, 0))
+        );
+        assert_eq!(
+            balanced.get_pixel(Loc { x: 1, y: 0 }),
+            Ok(Colour::rgb(26, 20, 0))
+        );
+    }
+
+    #[test]
+    fn matches_histograms() {
+        let mut counts = [0; 256];
+        counts[0..4].copy_from_slice(&[1, 1, 1, 1]);
+        let mut reference = [0; 256];
+        reference[100] = 2;
+        reference[200] = 2;
+        let table = matching_table(&counts, &reference);
+        assert_eq!(table[..4], [100, 100, 200, 200]);
+        assert_eq!(matching_table(&counts, &counts)[..4], [0, 1, 2, 3]);
+
+        // A gradient matched to itself stays the same, and one matched to a brighter copy of
+        // itself takes on the brighter values.
+        let mut img = Image::blank(Dim { w: 64, h: 1 });
+        for (x, c) in img.row_mut(0).iter_mut().enumerate() {
+            *c = Colour::rgba(x as u8 * 2, x as u8, 0, 100);
+        }
+        assert_eq!(img.clone().match_histogram(&img), img);
+        let brighter = img.clone().adjust_channels(1.0, 2.0, 1.0);
+        assert_eq!(img.clone().match_histogram(&brighter), brighter);
+        let empty = Image::blank(Dim { w: 0, h: 0 });
+        assert_eq!(img.clone().match_histogram(&empty), img);
+    }
+}
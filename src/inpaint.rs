@@ -0,0 +1,250 @@
+//! Inpainting: filling selected areas from the pixels around them, to remove blemishes and small
+//! objects.
+
+use crate::filter::{premultiplied_channels, to_colour};
+use crate::{Image, Mask};
+
+/// How `Image::inpaint()` fills the selected area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InpaintMethod {
+    /// Alexandru Telea's fast marching method: the area is filled from its border inwards, each
+    /// pixel with a weighted average of the known pixels within five pixels of it. Those lying
+    /// straight out from the border and at the same depth count most. Fast, and good for
+    /// scratches, dust and other thin areas.
+    #[default]
+    Telea,
+    /// A lightweight relative of the Navier-Stokes method of Bertalmio, Bertozzi and Sapiro.
+    /// The area is filled as for `Telea` and then repeatedly smoothed along isophotes, the lines
+    /// of constant brightness that edges follow, so that edges running into the area carry on
+    /// through it instead of being smeared. Slower, and better for larger areas.
+    NavierStokesLite,
+}
+
+/// The radius of the neighbourhood that `InpaintMethod::Telea` averages over.
+const RADIUS: i64 = 5;
+
+/// Samples premultiplied `values` of an image `w` by `h` pixels at `(x, y)` with bilinear
+/// interpolation, clamping to the edges.
+fn sample(values: &[[f32; 4]], w: i64, h: i64, x: f32, y: f32) -> [f32; 4] {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (tx, ty) = (x - x0, y - y0);
+    let at = |x: i64, y: i64| values[(y.clamp(0, h - 1) * w + x.clamp(0, w - 1)) as usize];
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    let corners = [
+        (at(x0, y0), (1.0 - tx) * (1.0 - ty)),
+        (at(x0 + 1, y0), tx * (1.0 - ty)),
+        (at(x0, y0 + 1), (1.0 - tx) * ty),
+        (at(x0 + 1, y0 + 1), tx * ty),
+    ];
+    let mut sum = [0.0; 4];
+    for (value, weight) in corners {
+        for (s, v) in sum.iter_mut().zip(value) {
+            *s += weight * v;
+        }
+    }
+    sum
+}
+
+// The following impl block defines inpainting functions for Images.
+impl Image {
+    /// Fills the pixels that `mask` selects with colours guessed from the pixels around them,
+    /// which removes whatever was there, such as a scratch, a sensor spot or a stray cable.
+    ///
+    /// Pixels count as selected as decided by `Mask::contains()`. Select a pixel or two beyond
+    /// the object itself, so that none of its outline is mistaken for the background. Nothing
+    /// changes if everything or nothing is selected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mask` differs in size from the image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let blue = Colour::rgb(40, 90, 200);
+    /// let img = Image::blank_with_colour(Dim::square(16), blue)
+    ///     .fill_region(Region { l: Loc { x: 6, y: 6 }, d: Dim::square(3) }, Colour::WHITE);
+    /// let speck = Mask::from_fn(img.get_dimensions(), |l| {
+    ///     if (5..10).contains(&l.x) && (5..10).contains(&l.y) { 255 } else { 0 }
+    /// });
+    /// let clean = img.inpaint(&speck, InpaintMethod::Telea);
+    /// assert_eq!(clean.get_pixel(Loc { x: 7, y: 7 }), Ok(blue));
+    /// ```
+    pub fn inpaint(&self, mask: &Mask, method: InpaintMethod) -> Image {
+        assert_eq!(self.get_dimensions(), mask.get_dimensions());
+        let (w, h) = (self.width as i64, self.height as i64);
+        let hole: Vec<bool> = mask.values().iter().map(|&v| v >= 128).collect();
+        if hole.iter().all(|&h| h) || !hole.contains(&true) {
+            return self.clone();
+        }
+
+        // How deep each pixel lies in the hole, which is also the order it is filled in.
+        let depth = Mask::from_fn(self.get_dimensions(), |loc| {
+            if hole[loc.as_index(self.get_dimensions())] {
+                0
+            } else {
+                255
+            }
+        })
+        .distance_transform();
+        let mut order: Vec<usize> = (0..hole.len()).filter(|&i| hole[i]).collect();
+        order.sort_by(|&a, &b| depth[a].total_cmp(&depth[b]).then(a.cmp(&b)));
+        let depth_at = |x: i64, y: i64| depth[(y.clamp(0, h - 1) * w + x.clamp(0, w - 1)) as usize];
+
+        let mut values: Vec<[f32; 4]> = self
+            .pixels
+            .iter()
+            .map(|&c| premultiplied_channels(c))
+            .collect();
+        let mut known: Vec<bool> = hole.iter().map(|&h| !h).collect();
+        for &i in &order {
+            let (x, y) = (i as i64 % w, i as i64 / w);
+            // The direction straight out of the hole, towards the nearest known pixels.
+            let normal = (
+                depth_at(x + 1, y) - depth_at(x - 1, y),
+                depth_at(x, y + 1) - depth_at(x, y - 1),
+            );
+            let length = normal.0.hypot(normal.1);
+
+            let (mut sum, mut total) = ([0.0f32; 4], 0.0);
+            for ny in (y - RADIUS).max(0)..=(y + RADIUS).min(h - 1) {
+                for nx in (x - RADIUS).max(0)..=(x + RADIUS).min(w - 1) {
+                    let j = (ny * w + nx) as usize;
+                    let (dx, dy) = ((x - nx) as f32, (y - ny) as f32);
+                    let distance_squared = dx * dx + dy * dy;
+                    if !known[j] || distance_squared > (RADIUS * RADIUS) as f32 {
+                        continue;
+                    }
+                    let direction = if length > 0.0 {
+                        ((dx * normal.0 + dy * normal.1) / (length * distance_squared.sqrt())).abs()
+                    } else {
+                        1.0
+                    };
+                    let level = 1.0 / (1.0 + (depth[i] - depth[j]).abs());
+                    let weight = direction.max(0.01) * level / distance_squared;
+                    for (s, v) in sum.iter_mut().zip(values[j]) {
+                        *s += weight * v;
+                    }
+                    total += weight;
+                }
+            }
+            if total > 0.0 {
+                values[i] = sum.map(|s| s / total);
+            }
+            known[i] = true;
+        }
+
+        if method == InpaintMethod::NavierStokesLite {
+            let brightness = |v: [f32; 4]| v[0] + v[1] + v[2];
+            let at = |values: &[[f32; 4]], x: i64, y: i64| {
+                values[(y.clamp(0, h - 1) * w + x.clamp(0, w - 1)) as usize]
+            };
+            for _ in 0..1000 {
+                let mut largest_change = 0.0f32;
+                for &i in &order {
+                    let (x, y) = (i as i64 % w, i as i64 / w);
+                    let gradient = (
+                        brightness(at(&values, x + 1, y)) - brightness(at(&values, x - 1, y)),
+                        brightness(at(&values, x, y + 1)) - brightness(at(&values, x, y - 1)),
+                    );
+                    let length = gradient.0.hypot(gradient.1);
+                    let new = if length > 1.0 {
+                        // Averages the pixels on either side along the isophote through this one.
+                        let (tx, ty) = (-gradient.1 / length, gradient.0 / length);
+                        let (x, y) = (x as f32, y as f32);
+                        let ahead = sample(&values, w, h, x + tx, y + ty);
+                        let behind = sample(&values, w, h, x - tx, y - ty);
+                        [0, 1, 2, 3].map(|c| (ahead[c] + behind[c]) / 2.0)
+                    } else {
+                        let neighbours = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                            .map(|(dx, dy)| at(&values, x + dx, y + dy));
+                        [0, 1, 2, 3].map(|c| neighbours.iter().map(|n| n[c]).sum::<f32>() / 4.0)
+                    };
+                    for (old, new) in values[i].iter_mut().zip(new) {
+                        largest_change = largest_change.max((new - *old).abs());
+                        *old = new;
+                    }
+                }
+                if largest_change < 0.1 {
+                    break;
+                }
+            }
+        }
+
+        let mut result = self.clone();
+        for &i in &order {
+            result.pixels[i] = to_colour(values[i]);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod inpaint_tests {
+    use super::*;
+    use crate::{Colour, Dim, Loc, Region};
+
+    fn square_hole(dim: Dim, from: u32, to: u32) -> Mask {
+        Mask::from_fn(dim, |l| {
+            if (from..to).contains(&l.x) && (from..to).contains(&l.y) {
+                255
+            } else {
+                0
+            }
+        })
+    }
+
+    #[test]
+    fn fills_flat_and_smooth_areas() {
+        let dim = Dim::square(20);
+        let grey = Image::blank_with_colour(dim, Colour::rgb(90, 90, 90));
+        let speckled = grey.clone().fill_region(
+            Region {
+                l: Loc { x: 8, y: 8 },
+                d: Dim::square(4),
+            },
+            Colour::rgb(255, 0, 0),
+        );
+        let hole = square_hole(dim, 6, 14);
+        for method in [InpaintMethod::Telea, InpaintMethod::NavierStokesLite] {
+            assert_eq!(speckled.inpaint(&hole, method), grey);
+        }
+
+        // A horizontal ramp is continued through the hole.
+        let mut ramp = Image::blank(dim);
+        for row in ramp.rows_mut() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = Colour::rgb(10 * x as u8, 0, 0);
+            }
+        }
+        let filled = ramp.inpaint(&hole, InpaintMethod::Telea);
+        for x in 6..14 {
+            let red = filled.get_pixel(Loc { x, y: 10 }).unwrap().r;
+            assert!(red.abs_diff(10 * x as u8) <= 25, "{x}: {red}");
+        }
+
+        assert_eq!(grey.inpaint(&Mask::new(dim), InpaintMethod::Telea), grey);
+        let everything = Mask::new(dim).invert();
+        assert_eq!(
+            speckled.inpaint(&everything, InpaintMethod::Telea),
+            speckled
+        );
+    }
+
+    #[test]
+    fn navier_stokes_keeps_edges() {
+        // Black on the left and white on the right, with a hole across the edge.
+        let dim = Dim::square(20);
+        let img = Image::blank(dim).fill_region(
+            Region {
+                l: Loc { x: 10, y: 0 },
+                d: Dim { w: 10, h: 20 },
+            },
+            Colour::WHITE,
+        );
+        let filled = img.inpaint(&square_hole(dim, 5, 15), InpaintMethod::NavierStokesLite);
+        let red = |x| filled.get_pixel(Loc { x, y: 10 }).unwrap().r;
+        assert!(red(8) < 40 && red(11) > 215, "{} {}", red(8), red(11));
+    }
+}
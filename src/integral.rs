@@ -0,0 +1,54 @@
+//! Summed-area tables, which give the sum of any rectangle of values in constant time.
+
+use crate::{Dim, Loc};
+
+/// A summed-area table over one value per pixel.
+#[derive(Debug, Clone)]
+pub(crate) struct IntegralImage {
+    /// Row length of `sums`, which has an extra row and column of zeros at the top and left.
+    stride: usize,
+    sums: Vec<f64>,
+}
+
+impl IntegralImage {
+    /// Builds the table from one value per pixel, given in row-major order.
+    pub(crate) fn new(dimension: Dim, values: impl IntoIterator<Item = f64>) -> Self {
+        let stride = dimension.w as usize + 1;
+        let mut sums = vec![0.0; stride * (dimension.h as usize + 1)];
+        let mut values = values.into_iter();
+
+        for y in 1..=dimension.h as usize {
+            let mut row_sum = 0.0;
+            for x in 1..stride {
+                row_sum += values
+                    .next()
+                    .expect("There must be a value for every pixel.");
+                sums[y * stride + x] = sums[(y - 1) * stride + x] + row_sum;
+            }
+        }
+        IntegralImage { stride, sums }
+    }
+
+    /// Returns the sum of the values in the rectangle with top left corner `l` and size `d`.
+    pub(crate) fn sum(&self, l: Loc, d: Dim) -> f64 {
+        let (x0, y0) = (l.x as usize, l.y as usize);
+        let (x1, y1) = (x0 + d.w as usize, y0 + d.h as usize);
+        let at = |x: usize, y: usize| self.sums[y * self.stride + x];
+        at(x1, y1) - at(x0, y1) - at(x1, y0) + at(x0, y0)
+    }
+}
+
+#[cfg(test)]
+mod integral_tests {
+    use super::*;
+
+    #[test]
+    fn rectangle_sums() {
+        // 1 2 3
+        // 4 5 6
+        let table = IntegralImage::new(Dim { w: 3, h: 2 }, (1..=6).map(f64::from));
+        assert_eq!(table.sum(Loc { x: 0, y: 0 }, Dim { w: 3, h: 2 }), 21.0);
+        assert_eq!(table.sum(Loc { x: 1, y: 0 }, Dim { w: 2, h: 2 }), 16.0);
+        assert_eq!(table.sum(Loc { x: 2, y: 1 }, Dim::square(1)), 6.0);
+    }
+}
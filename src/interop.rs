@@ -0,0 +1,85 @@
+//! Conversions to and from the buffers of the `image` crate. Only available with the `image`
+//! feature.
+//!
+//! This makes it possible to use kodak's API in projects that already depend on `image`, and to
+//! decode formats kodak does not support itself:
+//!
+//! ```no_run
+//! # use kodak::*;
+//! let decoded = image::open("photo.bmp").unwrap();
+//! let img: Image = decoded.into();
+//! let back: image::DynamicImage = img.flip_horizontal().into();
+//! ```
+
+use crate::{Colour, Dim, Image};
+use image::{DynamicImage, RgbaImage};
+
+impl From<RgbaImage> for Image {
+    fn from(buffer: RgbaImage) -> Self {
+        let dimension = Dim {
+            w: buffer.width(),
+            h: buffer.height(),
+        };
+        let pixels = buffer
+            .pixels()
+            .map(|p| Colour::rgba(p[0], p[1], p[2], p[3]))
+            .collect();
+        Image::from_pixels(dimension, pixels)
+    }
+}
+
+/// Images with more than 8 bits per channel or floating point channels are reduced to 8 bits.
+impl From<DynamicImage> for Image {
+    fn from(img: DynamicImage) -> Self {
+        img.into_rgba8().into()
+    }
+}
+
+/// The colour profile and metadata of the image are not carried over.
+impl From<&Image> for RgbaImage {
+    fn from(img: &Image) -> Self {
+        let data = crate::simd::to_rgba_bytes(&img.pixels);
+        RgbaImage::from_raw(img.width, img.height, data)
+            .expect("An image always holds width * height pixels.")
+    }
+}
+
+impl From<Image> for RgbaImage {
+    fn from(img: Image) -> Self {
+        (&img).into()
+    }
+}
+
+impl From<&Image> for DynamicImage {
+    fn from(img: &Image) -> Self {
+        DynamicImage::ImageRgba8(img.into())
+    }
+}
+
+impl From<Image> for DynamicImage {
+    fn from(img: Image) -> Self {
+        (&img).into()
+    }
+}
+
+#[cfg(test)]
+mod interop_tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let img = Image::blank(Dim { w: 3, h: 2 }).fill_region(
+            crate::Region {
+                l: crate::Loc { x: 2, y: 1 },
+                d: Dim::square(1),
+            },
+            Colour::rgba(10, 20, 30, 40),
+        );
+        let dynamic: DynamicImage = (&img).into();
+        assert_eq!((dynamic.width(), dynamic.height()), (3, 2));
+
+        let back: Image = dynamic.into();
+        assert_eq!(back.pixels, img.pixels);
+        assert_eq!(back.get_dimensions(), img.get_dimensions());
+    }
+}
@@ -1,4 +1,5 @@
 #![crate_name = "kodak"]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![deny(
     missing_docs,
     missing_copy_implementations,
@@ -15,22 +16,232 @@
 //! Kodak makes use of chaining API methods, like seen in functional programming languages.
 //! A typical piece of Kodak code might look like this:
 //!
-//! ```
+//! ```no_run
 //! // Add a white border around an image.
 //! extern crate kodak;
 //! use kodak::*;
 //!
+//! # #[cfg(feature = "png")] {
 //! let border_width = 16;
 //!
 //! let src_img = Image::load_png("assets/olle_voader.png").unwrap();
 //! let new_img = Image::blank(src_img.get_dimensions().expand(2 * border_width))
 //!     .fill(Colour::WHITE)
 //!     .overlay(src_img, Loc { x: border_width, y: border_width });
-//! new_img.save_png("assets/olle_koader.png");
+//! new_img.save_png("assets/olle_koader.png").unwrap();
+//! # }
 //! ```
+//!
+//! # `no_std`
+//!
+//! Without the default `std` feature, Kodak only needs `alloc`, so it can draw on embedded
+//! displays or in bootloaders. What remains is the core: locations, dimensions and colours,
+//! creating, filling, cropping and overlaying images, the built-in bitmap font and
+//! `ImageViewMut` for drawing straight into a framebuffer. File I/O, the image codecs, font
+//! rendering and most filters need `std`.
+//!
+//! # Diagnostics
+//!
+//! Kodak never prints anything itself. With the `tracing` feature, loading, saving, resizing,
+//! filtering, template matching, pipelines and batches are wrapped in `debug` spans of the
+//! `tracing` crate, and files that fail in a batch are reported as `warn` events, so the
+//! application's subscriber decides what is recorded and where it goes.
 
+extern crate alloc;
+#[cfg(feature = "jpeg")]
+extern crate jpeg_decoder;
+#[cfg(feature = "png")]
 extern crate png;
-use std::ops::Add;
+use alloc::vec;
+use alloc::vec::Vec;
+use buffer::Pixels;
+use core::ops::Add;
+#[cfg(not(any(feature = "std", test)))]
+use math::Float;
+
+#[cfg(feature = "std")]
+pub mod analysis;
+#[cfg(feature = "std")]
+mod animation;
+#[cfg(feature = "std")]
+mod annotate;
+#[cfg(feature = "std")]
+mod background;
+#[cfg(all(feature = "std", not(target_arch = "wasm32")))]
+pub mod batch;
+mod bitmap_font;
+#[cfg(feature = "std")]
+mod bits;
+#[cfg(feature = "std")]
+mod brush;
+mod buffer;
+mod cmyk;
+mod colour;
+#[cfg(feature = "std")]
+mod colour_matrix;
+#[cfg(feature = "std")]
+mod concat;
+#[cfg(feature = "std")]
+mod contour;
+#[cfg(feature = "std")]
+mod curve;
+#[cfg(feature = "std")]
+mod despeckle;
+#[cfg(feature = "std")]
+mod distance;
+mod edge;
+#[cfg(feature = "std")]
+mod effects;
+mod exif;
+#[cfg(feature = "std")]
+mod fft;
+#[cfg(feature = "std")]
+mod filter;
+#[cfg(feature = "std")]
+pub mod filters;
+#[cfg(feature = "std")]
+mod formats;
+#[cfg(any(feature = "arbitrary", feature = "proptest"))]
+mod fuzzing;
+#[cfg(feature = "std")]
+pub mod generate;
+#[cfg(feature = "gpu")]
+mod gpu;
+#[cfg(feature = "std")]
+mod gradient;
+#[cfg(feature = "std")]
+mod histogram;
+#[cfg(feature = "std")]
+mod inpaint;
+#[cfg(feature = "std")]
+mod integral;
+#[cfg(feature = "image")]
+mod interop;
+#[cfg(feature = "std")]
+mod mask;
+#[cfg(feature = "std")]
+mod matching;
+#[cfg(not(any(feature = "std", test)))]
+mod math;
+mod metadata;
+#[cfg(feature = "std")]
+mod montage;
+#[cfg(feature = "std")]
+mod palette;
+#[cfg(feature = "std")]
+mod pipeline;
+#[cfg(feature = "std")]
+mod preset;
+mod profile;
+#[cfg(feature = "std")]
+mod progress;
+mod quantize;
+#[cfg(feature = "std")]
+mod random;
+#[cfg(all(feature = "recipe", not(target_arch = "wasm32")))]
+pub mod recipe;
+#[cfg(feature = "std")]
+mod recolour;
+#[cfg(feature = "std")]
+mod resize;
+#[cfg(feature = "std")]
+mod seamless;
+#[cfg(feature = "std")]
+mod shapes;
+mod simd;
+#[cfg(feature = "std")]
+mod skeleton;
+mod split;
+mod stego;
+#[cfg(feature = "std")]
+mod stroke;
+#[cfg(feature = "std")]
+mod terminal;
+#[cfg(all(feature = "png", not(target_arch = "wasm32")))]
+pub mod testing;
+#[cfg(feature = "std")]
+mod text;
+#[cfg(feature = "std")]
+mod threshold;
+mod tilemap;
+#[cfg(all(feature = "png", not(target_arch = "wasm32")))]
+mod tiles;
+#[cfg(feature = "std")]
+mod tone;
+#[cfg(feature = "std")]
+mod transform;
+#[cfg(feature = "std")]
+mod video;
+mod view;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+#[cfg(feature = "std")]
+pub use animation::{Animation, Easing, Frame};
+#[cfg(feature = "std")]
+pub use annotate::AnnotationStyle;
+#[cfg(feature = "std")]
+pub use bits::Channel;
+#[cfg(feature = "std")]
+pub use brush::{Brush, BrushTip};
+#[cfg(feature = "std")]
+pub use colour_matrix::{AnaglyphMode, ColourBlindness};
+#[cfg(feature = "std")]
+pub use concat::{ConcatAlign, ConcatOptions};
+#[cfg(feature = "std")]
+pub use curve::Curve;
+pub use edge::EdgeMode;
+#[cfg(feature = "std")]
+pub use effects::GlitchOptions;
+pub use exif::{Exif, ExifValue};
+#[cfg(feature = "std")]
+pub use fft::Spectrum;
+#[cfg(feature = "std")]
+pub use filter::{Kernel, UnsharpMask};
+#[cfg(all(feature = "png", not(target_arch = "wasm32")))]
+pub use formats::PngRowReader;
+#[cfg(all(feature = "webp", not(target_arch = "wasm32")))]
+pub use formats::WebpQuality;
+#[cfg(feature = "std")]
+pub use formats::{ColourType, ImageInfo};
+#[cfg(feature = "gpu")]
+pub use gpu::Gpu;
+#[cfg(feature = "std")]
+pub use gradient::{Gradient, Interpolation};
+#[cfg(feature = "std")]
+pub use histogram::{Histogram, WhiteBalance};
+#[cfg(feature = "std")]
+pub use inpaint::InpaintMethod;
+#[cfg(feature = "std")]
+pub use mask::Mask;
+pub use metadata::Metadata;
+#[cfg(feature = "std")]
+pub use montage::Montage;
+#[cfg(feature = "std")]
+pub use palette::Palette;
+#[cfg(feature = "std")]
+pub use pipeline::Pipeline;
+#[cfg(feature = "std")]
+pub use preset::Preset;
+pub use profile::ColourProfile;
+#[cfg(feature = "std")]
+pub use progress::CancelToken;
+#[cfg(feature = "std")]
+pub use random::Rng;
+#[cfg(feature = "std")]
+pub use resize::ResizeFilter;
+#[cfg(feature = "std")]
+pub use shapes::{CornerRadii, FillStroke, Marker};
+pub use split::SplitStyle;
+#[cfg(feature = "std")]
+pub use stroke::{Cap, DashPattern, Join, StrokeStyle};
+#[cfg(feature = "std")]
+pub use text::{Alignment, Font, FontStack, Shadow, TextLayout, TextStyle};
+#[cfg(feature = "std")]
+pub use threshold::ThresholdMethod;
+#[cfg(feature = "std")]
+pub use video::VideoWriter;
+pub use view::{ImageView, ImageViewMut, RawFormat};
 
 /// This struct is used to indicate locations on an image.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -43,20 +254,46 @@ pub struct Loc {
 
 impl Loc {
     /// Returns the location from a one-dimensional index given dimensions.
+    ///
+    /// # Panics
+    ///
+    /// * if `dimension.w` is zero, or the index lies so far past the end of the image that its
+    ///   row does not fit into a u32.
     pub fn from_index(idx: usize, dimension: Dim) -> Self {
-        // TODO: rewrite this so that images larger than 2^16 by 2^16 work too
-        let i: u32 = idx
-            .try_into()
-            .expect("An index too large to fit into a u32 was encountered.");
+        let w = dimension.w as usize;
         Loc {
-            x: i % dimension.w,
-            y: i / dimension.w,
+            x: (idx % w) as u32,
+            y: (idx / w)
+                .try_into()
+                .expect("An index too far outside of the image was encountered."),
         }
     }
 
     /// Returns the one-dimensional index from a location given dimensions.
+    ///
+    /// The location is not checked against the dimensions; see `index_checked()`.
     pub fn as_index(&self, dimension: Dim) -> usize {
-        (self.x + self.y * dimension.w).try_into().unwrap()
+        self.x as usize + self.y as usize * dimension.w as usize
+    }
+
+    /// Returns the one-dimensional index from a location given dimensions, or `None` if the
+    /// location falls outside of them or the index does not fit into a usize.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let dim = Dim { w: 100_000, h: 100_000 };
+    /// assert_eq!(Loc { x: 5, y: 70_000 }.index_checked(dim), Some(7_000_000_005));
+    /// assert_eq!(Loc { x: 100_000, y: 0 }.index_checked(dim), None);
+    /// ```
+    pub fn index_checked(&self, dimension: Dim) -> Option<usize> {
+        if self.x >= dimension.w || self.y >= dimension.h {
+            return None;
+        }
+        (self.y as usize)
+            .checked_mul(dimension.w as usize)?
+            .checked_add(self.x as usize)
     }
 
     /// Checks if a location falls inside of a region.
@@ -71,7 +308,7 @@ impl Loc {
         let w = region.d.w;
         let h = region.d.h;
 
-        x >= cx && x < cx + w && y >= cy && y < cy + h
+        x >= cx && x - cx < w && y >= cy && y - cy < h
     }
 }
 
@@ -112,6 +349,22 @@ impl Dim {
     pub fn expand(self, amount: u32) -> Self {
         Dim { w: self.w + amount, h: self.h + amount }
     }
+
+    /// Returns the number of pixels needed to cover a physical width and height in millimetres
+    /// at `dpi` dots per inch, rounded to the nearest pixel.
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// // An A4 page at 300 DPI.
+    /// assert_eq!(Dim::from_physical((210.0, 297.0), 300.0), Dim { w: 2480, h: 3508 });
+    /// ```
+    pub fn from_physical(mm: (f32, f32), dpi: f32) -> Self {
+        let pixels = |mm: f32| (mm / metadata::MM_PER_INCH * dpi).round() as u32;
+        Dim {
+            w: pixels(mm.0),
+            h: pixels(mm.1),
+        }
+    }
 }
 
 /// This struct is used to indicate a region, specified by a top-left Loc and a Dim.
@@ -128,11 +381,14 @@ impl Region {
     pub fn from_top_left( dim: Dim ) -> Self { Region { l: Loc{ x: 0, y: 0 } , d: dim } }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// A struct to represent colours
 ///
-/// Note that it is assumed that all colours are three-channel, 8 bit per pixel and in sRGB colour space.
+/// Note that it is assumed that all colours are 8 bit per channel and in sRGB colour space.
 /// It is outside of the scope of this crate to support colour representations that differ from this.
+///
+/// The alpha channel is stored straight (not premultiplied): 0 is fully transparent and 255 is
+/// fully opaque. Use `premultiply()` and `unpremultiply()` to convert when needed.
 pub struct Colour {
     /// The red channel of the colour.
     pub r: u8,
@@ -140,43 +396,97 @@ pub struct Colour {
     pub g: u8,
     /// The blue channel of the colour.
     pub b: u8,
+    /// The alpha channel of the colour.
+    pub a: u8,
 }
 
 impl Colour {
     /// The colour black.
-    pub const BLACK: Colour = Colour { r: 0, g: 0, b: 0 };
+    pub const BLACK: Colour = Colour::rgb(0, 0, 0);
     /// The colour white.
-    pub const WHITE: Colour = Colour {
-        r: 255,
-        g: 255,
-        b: 255,
-    };
+    pub const WHITE: Colour = Colour::rgb(255, 255, 255);
+    /// A fully transparent colour.
+    pub const TRANSPARENT: Colour = Colour::rgba(0, 0, 0, 0);
+
+    /// Creates an opaque colour from its red, green and blue channels.
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Colour { r, g, b, a: 255 }
+    }
 
-    /// Creates a Colour from a vector of three `u8`'s.
+    /// Creates a colour from its red, green, blue and alpha channels.
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Colour { r, g, b, a }
+    }
+
+    /// Creates a Colour from a vector of three (RGB) or four (RGBA) `u8`'s.
     pub fn from_vec(v: Vec<u8>) -> Self {
-        assert_eq!(v.len(), 3, "Three u8's should be passed to Colour::from_vec().");
-        Colour { r: v[0], g: v[1], b: v[2] }
+        match v[..] {
+            [r, g, b] => Colour::rgb(r, g, b),
+            [r, g, b, a] => Colour::rgba(r, g, b, a),
+            _ => panic!("Three or four u8's should be passed to Colour::from_vec()."),
+        }
     }
 
-    /// Creates a `Vec<u8>` from a Colour.
+    /// Creates a `Vec<u8>` from a Colour, discarding alpha.
     pub fn to_vec(&self) -> Vec<u8> {
         vec![self.r, self.g, self.b]
     }
+
+    /// Creates a `Vec<u8>` of four elements (RGBA) from a Colour.
+    ///
+    /// This allocates; prefer `to_array()` when converting many pixels.
+    pub fn to_rgba_vec(&self) -> Vec<u8> {
+        self.to_array().to_vec()
+    }
+
+    /// Returns the channels of the colour as an RGBA array.
+    pub const fn to_array(&self) -> [u8; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+
+    /// Returns whether the colour is fully opaque.
+    pub fn is_opaque(&self) -> bool {
+        self.a == 255
+    }
+}
+
+impl Default for Colour {
+    /// The default colour is opaque black, matching `Image::blank()`.
+    fn default() -> Self {
+        Colour::BLACK
+    }
 }
 
 /// The Image struct is at the heart of Kodak. You'll be using functions on this 95% of the time.
 ///
 /// Note that the maximum image size is 2^32 - 1 by 2^32 - 1 pixels. This limit was chosen because it is also the maximum of the PNG format.
+///
+/// Cloning an image is cheap: clones share their pixels until one of them is modified, at which
+/// point that one gets its own copy.
 #[derive(Debug, Clone)]
 pub struct Image {
     /// The width of the image in pixels.
     width: u32,
     /// The height of the image in pixels.
     height: u32,
-    /// A vector containing all pixels one-dimensionally.
-    pixels: Vec<Colour>,
+    /// All pixels one-dimensionally, shared between clones until one of them is modified.
+    pixels: Pixels,
+    /// The colour profile the image was loaded with, if any.
+    colour_profile: Option<ColourProfile>,
+    /// Textual and physical metadata attached to the image.
+    metadata: Metadata,
 }
 
+/// Two images are equal if they have the same dimensions and pixels. The colour profile and
+/// metadata are not compared.
+impl PartialEq for Image {
+    fn eq(&self, other: &Self) -> bool {
+        self.get_dimensions() == other.get_dimensions() && self.pixels == other.pixels
+    }
+}
+
+impl Eq for Image {}
+
 // The following impl block defines constructing functions for Images.
 impl Image {
     /// Creates a new blank image.
@@ -197,7 +507,9 @@ impl Image {
         Image {
             width,
             height,
-            pixels: vec![Colour::BLACK; (width * height).try_into().unwrap()],
+            pixels: vec![Colour::BLACK; width as usize * height as usize].into(),
+            colour_profile: None,
+            metadata: Metadata::default(),
         }
     }
 
@@ -211,62 +523,24 @@ impl Image {
         Image {
             width,
             height,
-            pixels: vec![colour; (width * height).try_into().unwrap()],
+            pixels: vec![colour; width as usize * height as usize].into(),
+            colour_profile: None,
+            metadata: Metadata::default(),
         }
     }
 
-    /// Loads a PNG image as an Image struct.
-    ///
-    /// This returns an `Err` if the PNG could not be decoded properly.
-    ///
-    /// # Panics
-    ///
-    /// * if the specified file could not be opened,
-    /// * if the PNG file could not be read into a buffer.
-    ///
-    /// # Examples
+    /// Creates an image from a buffer of pixels in row-major order.
     ///
-    /// ```
-    /// let img = Image::load_png("assets/olle_ma.png").unwrap();
-    /// ```
-    pub fn load_png(file_name: String) -> Result<Self, png::DecodingError> {
-        use std::fs::File;
-
-        let decoder = png::Decoder::new(File::open(file_name).unwrap());
-        let mut reader = decoder.read_info()?;
-        let mut buf = vec![0u8; reader.output_buffer_size()];
-        reader.next_frame(&mut buf).unwrap();
-        let info = reader.info();
-
-        let mut pixels_iterator = buf.iter().peekable();
-        let mut pixels: Vec<Colour> = Vec::new();
-
-        while pixels_iterator.peek().is_some() {
-            pixels.push(Colour::from_vec(
-                pixels_iterator.by_ref().cloned().take(3).collect()
-            ));
+    /// The caller guarantees that the buffer holds exactly `dimension.w * dimension.h` pixels.
+    pub(crate) fn from_pixels(dimension: Dim, pixels: Vec<Colour>) -> Self {
+        debug_assert_eq!(pixels.len(), dimension.w as usize * dimension.h as usize);
+        Image {
+            width: dimension.w,
+            height: dimension.h,
+            pixels: pixels.into(),
+            colour_profile: None,
+            metadata: Metadata::default(),
         }
-
-        Ok(Image { width: info.width, height: info.height, pixels })
-    }
-
-    /// Saves an Image as a PNG file.
-    pub fn save_png(&self, file_name: String) {
-        use std::fs::File;
-        use std::io::BufWriter;
-
-        let mut encoder = png::Encoder::new(
-            BufWriter::new(File::create(file_name).unwrap()),
-            self.width, self.height
-        );
-
-        encoder.set_color(png::ColorType::Rgb);
-        encoder.set_depth(png::BitDepth::Eight);
-
-        let mut writer = encoder.write_header().unwrap();
-
-        let pixel_data: Vec<u8> = self.pixels.iter().flat_map(|c| c.to_vec()).collect();
-        writer.write_image_data(&pixel_data[..]).unwrap();
     }
 }
 
@@ -289,11 +563,139 @@ impl Image {
     /// Tries to look up the colour of a specific pixel; returns an Err<&str>
     /// if the location is out of bounds and an Ok<Colour> if not.
     pub fn get_pixel(&self, loc: Loc) -> Result<Colour, &'static str> {
-        if !loc.inside_region(self.as_region()) {
-            return Err("The specified location falls outside of the image.");
+        loc.index_checked(self.get_dimensions())
+            .map(|i| self.pixels[i])
+            .ok_or("The specified location falls outside of the image.")
+    }
+
+    /// Returns whether the images have the same dimensions and no channel of any pixel, alpha
+    /// included, differs by more than `tolerance`. With a tolerance of 0 this is the same as
+    /// `==`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let grey = Image::blank_with_colour(Dim::square(2), Colour::rgb(100, 100, 100));
+    /// let lighter = Image::blank_with_colour(Dim::square(2), Colour::rgb(102, 100, 100));
+    /// assert!(lighter.approx_eq(&grey, 2));
+    /// assert!(!lighter.approx_eq(&grey, 1));
+    /// assert!(!grey.approx_eq(&Image::blank(Dim::square(3)), 255));
+    /// ```
+    pub fn approx_eq(&self, other: &Image, tolerance: u8) -> bool {
+        if self.get_dimensions() != other.get_dimensions() {
+            return false;
+        }
+        self.pixels.iter().zip(other.pixels.iter()).all(|(a, b)| {
+            a.r.abs_diff(b.r) <= tolerance
+                && a.g.abs_diff(b.g) <= tolerance
+                && a.b.abs_diff(b.b) <= tolerance
+                && a.a.abs_diff(b.a) <= tolerance
+        })
+    }
+
+    /// Returns the smallest region containing every pixel for which `predicate` returns true,
+    /// or `None` if there are no such pixels.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let img = Image::blank(Dim { w: 20, h: 10 }).fill_region(
+    ///     Region { l: Loc { x: 3, y: 4 }, d: Dim { w: 5, h: 2 } },
+    ///     Colour::WHITE,
+    /// );
+    /// let bounds = img.bounds_where(|c| c != Colour::BLACK).unwrap();
+    /// assert_eq!(bounds, Region { l: Loc { x: 3, y: 4 }, d: Dim { w: 5, h: 2 } });
+    /// ```
+    pub fn bounds_where<F: Fn(Colour) -> bool>(&self, predicate: F) -> Option<Region> {
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (u32::MAX, u32::MAX, 0, 0);
+        for (idx, &colour) in self.pixels.iter().enumerate() {
+            if predicate(colour) {
+                let loc = Loc::from_index(idx, self.get_dimensions());
+                min_x = min_x.min(loc.x);
+                min_y = min_y.min(loc.y);
+                max_x = max_x.max(loc.x);
+                max_y = max_y.max(loc.y);
+            }
+        }
+
+        if min_x == u32::MAX {
+            return None;
         }
+        Some(Region {
+            l: Loc { x: min_x, y: min_y },
+            d: Dim {
+                w: max_x - min_x + 1,
+                h: max_y - min_y + 1,
+            },
+        })
+    }
+}
 
-        Ok(self.pixels[loc.as_index(self.get_dimensions())])
+// The following impl block defines scanline access functions for Images.
+impl Image {
+    /// Returns the pixels of row `y`, from left to right.
+    ///
+    /// # Panics
+    ///
+    /// * if `y` is not less than the height of the image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let img = Image::blank(Dim { w: 4, h: 3 })
+    ///     .fill_region(Region { l: Loc { x: 0, y: 1 }, d: Dim { w: 4, h: 1 } }, Colour::WHITE);
+    /// assert!(img.row(1).iter().all(|&c| c == Colour::WHITE));
+    /// assert_eq!(img.rows().filter(|row| row[0] == Colour::BLACK).count(), 2);
+    /// ```
+    pub fn row(&self, y: u32) -> &[Colour] {
+        assert!(y < self.height, "Row {y} falls outside of the image.");
+        let start = y as usize * self.width as usize;
+        &self.pixels[start..start + self.width as usize]
+    }
+
+    /// Returns the pixels of row `y` for modification.
+    ///
+    /// # Panics
+    ///
+    /// * if `y` is not less than the height of the image.
+    pub fn row_mut(&mut self, y: u32) -> &mut [Colour] {
+        assert!(y < self.height, "Row {y} falls outside of the image.");
+        let start = y as usize * self.width as usize;
+        &mut self.pixels[start..start + self.width as usize]
+    }
+
+    /// Iterates over the rows of the image, from top to bottom.
+    pub fn rows(&self) -> impl DoubleEndedIterator<Item = &[Colour]> + ExactSizeIterator {
+        (0..self.height).map(move |y| self.row(y))
+    }
+
+    /// Iterates over the rows of the image for modification, from top to bottom.
+    pub fn rows_mut(&mut self) -> impl ExactSizeIterator<Item = &mut [Colour]> {
+        let width = self.width as usize;
+        let height = self.height as usize;
+        let mut pixels = &mut self.pixels[..];
+        // Split the rows off one by one, so that images without columns still have rows.
+        (0..height).map(move |_| {
+            let (row, rest) = core::mem::take(&mut pixels).split_at_mut(width);
+            pixels = rest;
+            row
+        })
+    }
+
+    /// Iterates over the pixels of column `x`, from top to bottom.
+    ///
+    /// # Panics
+    ///
+    /// * if `x` is not less than the width of the image.
+    pub fn column(
+        &self,
+        x: u32,
+    ) -> impl DoubleEndedIterator<Item = Colour> + ExactSizeIterator + '_ {
+        assert!(x < self.width, "Column {x} falls outside of the image.");
+        (0..self.height).map(move |y| self.row(y)[x as usize])
     }
 }
 
@@ -308,36 +710,67 @@ impl Image {
     /// # Examples
     ///
     /// ```
-    /// let img = Image::blank(30, 20).fill(Colour::WHITE);
-    /// assert_eq!(img.pixels[0], Colour::WHITE);
+    /// # use kodak::*;
+    /// let img = Image::blank(Dim { w: 30, h: 20 }).fill(Colour::WHITE);
+    /// assert_eq!(img.get_pixel(Loc { x: 0, y: 0 }).unwrap(), Colour::WHITE);
     /// ```
     pub fn fill(self, colour: Colour) -> Image {
         let new_pixels = vec![colour; self.pixels.len()];
         Image {
-            pixels: new_pixels,
+            pixels: new_pixels.into(),
             ..self
         }
     }
 
-    /// Fills a region.
-    pub fn fill_region(self, region: Region, colour: Colour) -> Image {
-        let new_pixels = self
-            .pixels
-            .clone()
-            .iter_mut()
-            .enumerate()
-            .map(|c| {
-                if Loc::from_index(c.0, self.get_dimensions()).inside_region(region) {
-                    colour
-                } else {
-                    *c.1 // pass through
-                }
-            })
-            .collect::<Vec<_>>();
-        Image {
-            pixels: new_pixels,
-            ..self
+    /// Fills a region. Parts of the region that fall outside of the image are ignored.
+    pub fn fill_region(mut self, region: Region, colour: Colour) -> Image {
+        let (x0, y0) = (region.l.x.min(self.width), region.l.y.min(self.height));
+        let x1 = region.l.x.saturating_add(region.d.w).min(self.width);
+        let y1 = region.l.y.saturating_add(region.d.h).min(self.height);
+        let width = self.width as usize;
+        for y in y0 as usize..y1 as usize {
+            self.pixels[y * width + x0 as usize..y * width + x1 as usize].fill(colour);
         }
+        self
+    }
+
+    /// Scales the red, green and blue channels of every pixel by their own factor, rounding and
+    /// clamping like `Colour * f32`. Alpha is left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// // Warm up a photo.
+    /// let img = Image::blank_with_colour(Dim::square(4), Colour::rgb(100, 100, 100))
+    ///     .adjust_channels(1.1, 1.0, 0.8);
+    /// assert_eq!(img.get_pixel(Loc { x: 0, y: 0 }).unwrap(), Colour::rgb(110, 100, 80));
+    /// ```
+    pub fn adjust_channels(mut self, red: f32, green: f32, blue: f32) -> Image {
+        simd::scale_channels(&mut self.pixels, red, green, blue);
+        self
+    }
+
+    /// Multiplies every pixel by a colour, channel by channel and alpha included, like
+    /// `Colour * Colour`. Multiplying by white leaves the image as it is.
+    pub fn multiply(mut self, colour: Colour) -> Image {
+        simd::multiply(&mut self.pixels, colour);
+        self
+    }
+
+    /// Converts every pixel to premultiplied alpha.
+    ///
+    /// Kodak's own functions expect straight alpha; this is meant for handing pixel data to APIs
+    /// that want premultiplied input. Use `unpremultiply()` to convert back.
+    pub fn premultiply(mut self) -> Image {
+        simd::premultiply(&mut self.pixels);
+        self
+    }
+
+    /// Converts every pixel from premultiplied alpha back to straight alpha.
+    pub fn unpremultiply(self) -> Image {
+        let pixels = self.pixels.iter().map(|c| c.unpremultiply()).collect();
+        Image { pixels, ..self }
     }
 
     /// Crop a region out of the image and return it. This method is mainly used internally and panics; the safer `crop()` should be used instead.
@@ -356,18 +789,24 @@ impl Image {
             region.l.inside_region(self.as_region()),
             "The corner from which to crop is outside of the image."
         );
-        assert!((region.l + region.d).inside_region(self.as_region()));
+        assert!(
+            region.d.w <= self.width - region.l.x && region.d.h <= self.height - region.l.y,
+            "The region to crop reaches outside of the image."
+        );
 
         Image {
             width: new_width,
             height: new_height,
-            pixels: self
-                .pixels
-                .iter()
-                .enumerate()
-                .filter(|x| Loc::from_index(x.0, self.get_dimensions()).inside_region(region))
-                .map(|x| *x.1)
+            pixels: (region.l.y..region.l.y + new_height)
+                .flat_map(|y| {
+                    let start = Loc { x: region.l.x, y }.as_index(self.get_dimensions());
+                    self.pixels[start..start + new_width as usize]
+                        .iter()
+                        .copied()
+                })
                 .collect(),
+            colour_profile: self.colour_profile.clone(),
+            metadata: self.metadata.clone(),
         }
     }
 
@@ -381,20 +820,21 @@ impl Image {
     /// # Examples
     ///
     /// ```
+    /// # use kodak::*;
     /// let img = Image::blank( Dim { w: 20, h: 30 } )
     ///     .crop( Region { l: Loc { x: 10, y: 10 }, d: Dim { w: 10, h: 10 } } )
     ///     .unwrap();
-    /// assert_eq!(img.width, 10);
+    /// assert_eq!(img.get_dimensions().w, 10);
     /// ```
     pub fn crop(self, region: Region) -> Result<Image, &'static str> {
         if !region.l.inside_region(self.as_region()) {
             return Err("The corner from which to crop falls outside of the image.");
         }
 
-        if !(region.l + region.d).inside_region(self.as_region()) {
+        if region.d.w > self.width - region.l.x || region.d.h > self.height - region.l.y {
             // We clamp the area to be cropped.
-            let new_width = self.width - region.d.w;
-            let new_height = self.height - region.d.h;
+            let new_width = region.d.w.min(self.width - region.l.x);
+            let new_height = region.d.h.min(self.height - region.l.y);
 
             return Ok(self.crop_unclamped(Region {
                 d: Dim {
@@ -410,26 +850,160 @@ impl Image {
 
     /// Overlays a given Image on top of this Image, at the specified location.
     /// This function will not care if the other image is too big to fit on top of the original.
+    ///
+    /// Translucent pixels of the other image are alpha composited onto this image.
     pub fn overlay(self, other: Image, offset: Loc) -> Self {
         let crop_dims = Dim { w: self.width - offset.x, h: self.height - offset.y };
         let cropped = other.crop( Region::from_top_left(crop_dims)).unwrap();
-        println!("The cropped image is {} by {}", cropped.width, cropped.height);
 
-        let mut working_copy = self.pixels.clone();
-        for p in cropped.pixels.iter().enumerate() {
-            let loc_on_other = Loc::from_index(p.0, cropped.get_dimensions());
-            let loc_on_original = loc_on_other + offset;
-            working_copy[loc_on_original.as_index(self.get_dimensions())] = *p.1;
+        let mut working_copy = self.pixels;
+        let row = cropped.width as usize;
+        for (y, top) in cropped.pixels.chunks_exact(row.max(1)).enumerate() {
+            let start = (offset.y as usize + y) * self.width as usize + offset.x as usize;
+            simd::over(top, &mut working_copy[start..start + row]);
         }
 
         Image { pixels: working_copy, ..self }
     }
+
+    /// Copies a region out of the image, leaving the image itself untouched, unlike `crop()`.
+    ///
+    /// Parts of the region that fall outside of the image are cut off, so the copy may be
+    /// smaller than the region, or even empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let img = Image::blank_with_colour(Dim::square(8), Colour::WHITE);
+    /// let corner = img.copy_region(Region { l: Loc { x: 6, y: 6 }, d: Dim::square(4) });
+    /// assert_eq!(corner.get_dimensions(), Dim::square(2));
+    /// ```
+    pub fn copy_region(&self, region: Region) -> Image {
+        let (x0, y0) = (region.l.x.min(self.width), region.l.y.min(self.height));
+        let w = region.d.w.min(self.width - x0);
+        let h = region.d.h.min(self.height - y0);
+        Image {
+            width: w,
+            height: h,
+            pixels: (y0..y0 + h)
+                .flat_map(|y| self.row(y)[x0 as usize..(x0 + w) as usize].iter().copied())
+                .collect(),
+            colour_profile: self.colour_profile.clone(),
+            metadata: self.metadata.clone(),
+        }
+    }
+
+    /// Copies the pixels in `source` to the area with its top left corner at `destination`,
+    /// within the same image, like copy and paste in an image editor.
+    ///
+    /// The pixels are replaced rather than alpha composited, and the two areas may overlap.
+    /// Parts of either area that fall outside of the image are cut off.
+    pub fn paste(mut self, source: Region, destination: Loc) -> Image {
+        let copy = self.copy_region(source);
+        let w = copy.width.min(self.width.saturating_sub(destination.x)) as usize;
+        let h = copy.height.min(self.height.saturating_sub(destination.y));
+        for y in 0..h {
+            self.row_mut(destination.y + y)[destination.x as usize..][..w]
+                .copy_from_slice(&copy.row(y)[..w]);
+        }
+        self
+    }
+
+    /// Moves the content of the image `dx` pixels to the right and `dy` pixels down, or left and
+    /// up for negative values, keeping its dimensions.
+    ///
+    /// With `wrap`, whatever moves out on one side comes back in on the other, for scrolling
+    /// content and for checking that textures tile seamlessly. Without it, the uncovered area
+    /// becomes transparent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let img = Image::blank_with_colour(Dim { w: 4, h: 1 }, Colour::WHITE)
+    ///     .fill_region(Region { l: Loc { x: 3, y: 0 }, d: Dim::square(1) }, Colour::BLACK);
+    /// let wrapped = img.clone().shift(1, 0, true);
+    /// assert_eq!(wrapped.get_pixel(Loc { x: 0, y: 0 }), Ok(Colour::BLACK));
+    /// let scrolled = img.shift(1, 0, false);
+    /// assert_eq!(scrolled.get_pixel(Loc { x: 0, y: 0 }), Ok(Colour::TRANSPARENT));
+    /// ```
+    pub fn shift(self, dx: i32, dy: i32, wrap: bool) -> Image {
+        let (w, h) = (self.width as i64, self.height as i64);
+        let pixels = (0..h)
+            .flat_map(|y| (0..w).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let (mut sx, mut sy) = (x - dx as i64, y - dy as i64);
+                if wrap {
+                    (sx, sy) = (sx.rem_euclid(w), sy.rem_euclid(h));
+                }
+                if (0..w).contains(&sx) && (0..h).contains(&sy) {
+                    self.pixels[(sy * w + sx) as usize]
+                } else {
+                    Colour::TRANSPARENT
+                }
+            })
+            .collect();
+        Image { pixels, ..self }
+    }
 }
 
 #[cfg(test)]
 mod kodak_tests {
     use super::*;
 
+    #[test]
+    fn copies_pastes_and_shifts() {
+        let mut img = Image::blank_with_colour(Dim { w: 4, h: 3 }, Colour::WHITE);
+        for (i, pixel) in img.pixels.iter_mut().enumerate() {
+            *pixel = Colour::rgb(i as u8, 0, 0);
+        }
+        let red = |img: &Image| img.pixels.iter().map(|c| c.r).collect::<Vec<_>>();
+
+        let copy = img.copy_region(Region {
+            l: Loc { x: 1, y: 1 },
+            d: Dim { w: 9, h: 1 },
+        });
+        assert_eq!(
+            (copy.get_dimensions(), red(&copy)),
+            (Dim { w: 3, h: 1 }, vec![5, 6, 7])
+        );
+        let outside = img.copy_region(Region {
+            l: Loc { x: 9, y: 0 },
+            d: Dim::square(2),
+        });
+        assert_eq!(outside.get_dimensions(), Dim { w: 0, h: 2 });
+
+        // An overlapping paste copies the source as it was before pasting.
+        let row = Region {
+            l: Loc { x: 0, y: 0 },
+            d: Dim { w: 3, h: 1 },
+        };
+        let pasted = img.clone().paste(row, Loc { x: 1, y: 0 });
+        assert_eq!(red(&pasted)[..4], [0, 0, 1, 2]);
+        let corner = Region {
+            l: Loc { x: 0, y: 0 },
+            d: Dim::square(2),
+        };
+        let clipped = img.clone().paste(corner, Loc { x: 3, y: 2 });
+        assert_eq!(red(&clipped)[8..], [8, 9, 10, 0]);
+
+        assert_eq!(
+            red(&img.clone().shift(-1, 1, true)),
+            [9, 10, 11, 8, 1, 2, 3, 0, 5, 6, 7, 4]
+        );
+        let scrolled = img.clone().shift(2, 0, false);
+        assert_eq!(
+            scrolled.get_pixel(Loc { x: 1, y: 0 }),
+            Ok(Colour::TRANSPARENT)
+        );
+        assert_eq!(
+            scrolled.get_pixel(Loc { x: 2, y: 0 }),
+            Ok(Colour::rgb(0, 0, 0))
+        );
+        assert_eq!(img.clone().shift(4, -6, true), img);
+    }
+
     #[test]
     fn loc_in_region() {
         let location1 = Loc { x: 10, y: 10 };
@@ -443,6 +1017,63 @@ mod kodak_tests {
             l: Loc { x: 0, y: 0 },
             d: Dim { w: 20, h: 20 }
         }));
+        assert!(!Loc { x: 10, y: 10 }.inside_region(Region {
+            l: Loc { x: 5, y: 5 },
+            d: Dim { w: u32::MAX, h: 2 }
+        }));
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn large_indices() {
+        let dim = Dim {
+            w: 100_000,
+            h: 100_000,
+        };
+        let loc = Loc {
+            x: 99_999,
+            y: 99_999,
+        };
+        let idx = loc.as_index(dim);
+        assert_eq!(idx, 9_999_999_999);
+        assert_eq!(Loc::from_index(idx, dim), loc);
+        assert_eq!(loc.index_checked(dim), Some(idx));
+        assert_eq!(Loc { x: 0, y: 100_000 }.index_checked(dim), None);
+
+        let wide = Dim {
+            w: u32::MAX,
+            h: u32::MAX,
+        };
+        let corner = Loc {
+            x: u32::MAX - 1,
+            y: u32::MAX - 1,
+        };
+        assert_eq!(Loc::from_index(corner.as_index(wide), wide), corner);
+    }
+
+    #[test]
+    fn rows_and_columns() {
+        let mut img = Image::blank(Dim { w: 3, h: 2 });
+        img.row_mut(1)[2] = Colour::WHITE;
+        for (y, row) in img.rows_mut().enumerate() {
+            row[0] = Colour::rgb(y as u8, 0, 0);
+        }
+        assert_eq!(img.get_pixel(Loc { x: 2, y: 1 }).unwrap(), Colour::WHITE);
+        assert_eq!(img.row(1)[0], Colour::rgb(1, 0, 0));
+        assert_eq!(img.rows().len(), 2);
+        assert_eq!(img.rows().next_back().unwrap(), img.row(1));
+        let column: Vec<Colour> = img.column(2).collect();
+        assert_eq!(column, [Colour::BLACK, Colour::WHITE]);
+
+        let mut empty = Image::blank(Dim { w: 0, h: 3 });
+        assert_eq!(empty.rows().len(), 3);
+        assert!(empty.rows_mut().all(|row| row.is_empty()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Row 2 falls outside of the image.")]
+    fn row_out_of_bounds() {
+        Image::blank(Dim { w: 3, h: 2 }).row(2);
     }
 
     #[test]
@@ -460,11 +1091,56 @@ mod kodak_tests {
     }
 
     #[test]
+    #[cfg(feature = "png")]
     fn crop() {
-        let img = Image::load_png(String::from("test.png")).unwrap();
+        let img = Image::load_png(String::from("tests/fixtures/test.png")).unwrap();
         assert_eq!(img.crop(Region::from_top_left(Dim::square(100))).unwrap().width, 100);
     }
 
+    #[test]
+    fn crop_clamps_huge_regions() {
+        let img = Image::blank(Dim { w: 6, h: 4 });
+        let region = Region {
+            l: Loc { x: 2, y: 1 },
+            d: Dim {
+                w: u32::MAX,
+                h: u32::MAX,
+            },
+        };
+        assert_eq!(
+            img.crop(region).unwrap().get_dimensions(),
+            Dim { w: 4, h: 3 }
+        );
+    }
+
+    #[test]
+    fn bounds_where() {
+        let img = Image::blank(Dim { w: 8, h: 8 })
+            .fill_region(
+                Region {
+                    l: Loc { x: 1, y: 6 },
+                    d: Dim::square(1),
+                },
+                Colour::WHITE,
+            )
+            .fill_region(
+                Region {
+                    l: Loc { x: 5, y: 2 },
+                    d: Dim::square(1),
+                },
+                Colour::WHITE,
+            );
+
+        assert_eq!(
+            img.bounds_where(|c| c == Colour::WHITE),
+            Some(Region {
+                l: Loc { x: 1, y: 2 },
+                d: Dim { w: 5, h: 5 }
+            })
+        );
+        assert_eq!(img.bounds_where(|c| !c.is_opaque()), None);
+    }
+
     #[test]
     fn overlay_non_out_of_bounds() {
         let original = Image::blank_with_colour(Dim::square(10), Colour::WHITE);
@@ -479,4 +1155,55 @@ mod kodak_tests {
         assert_eq!(result.get_pixel(Loc {x: 0, y: 5}).unwrap(), Colour::WHITE);
         assert_eq!(result.get_pixel(Loc {x: 5, y: 0}).unwrap(), Colour::WHITE);
     }
+
+    #[test]
+    fn clones_share_pixels_until_written() {
+        let original = Image::blank_with_colour(Dim::square(4), Colour::WHITE);
+        let copy = original.clone();
+        assert!(copy.pixels.ptr_eq(&original.pixels));
+
+        let edited = copy.fill_region(Region::from_top_left(Dim::square(2)), Colour::BLACK);
+        assert!(!edited.pixels.ptr_eq(&original.pixels));
+        assert_eq!(
+            original.get_pixel(Loc { x: 0, y: 0 }).unwrap(),
+            Colour::WHITE
+        );
+        assert_eq!(edited.get_pixel(Loc { x: 0, y: 0 }).unwrap(), Colour::BLACK);
+    }
+
+    #[test]
+    fn equality() {
+        let wide = Image::blank(Dim { w: 3, h: 2 });
+        let tall = Image::blank(Dim { w: 2, h: 3 });
+        assert_ne!(wide, tall);
+        assert!(!wide.approx_eq(&tall, u8::MAX));
+
+        let tagged = wide
+            .clone()
+            .with_metadata(Metadata::default().with_text("Title", "Tagged"));
+        assert_eq!(tagged, wide);
+
+        let tinted = wide.clone().fill_region(
+            Region::from_top_left(Dim::square(1)),
+            Colour::rgba(0, 0, 3, 252),
+        );
+        assert_ne!(tinted, wide);
+        assert!(tinted.approx_eq(&wide, 3));
+        assert!(!tinted.approx_eq(&wide, 2));
+        assert!(wide.approx_eq(&wide.clone(), 0));
+    }
+
+    #[test]
+    fn overlay_translucent() {
+        let original = Image::blank_with_colour(Dim::square(4), Colour::WHITE);
+        let overlay = Image::blank_with_colour(Dim::square(2), Colour::rgba(0, 0, 0, 128));
+
+        let result = original.overlay(overlay, Loc { x: 0, y: 0 });
+
+        assert_eq!(
+            result.get_pixel(Loc { x: 1, y: 1 }).unwrap(),
+            Colour::rgb(127, 127, 127)
+        );
+        assert_eq!(result.get_pixel(Loc { x: 2, y: 2 }).unwrap(), Colour::WHITE);
+    }
 }
@@ -0,0 +1,515 @@
+//! The `kodak` command line tool, which exposes the most common library functions to shell
+//! scripts.
+//!
+//! Every command reads its input with `Image::open()` and writes its result with
+//! `Image::save()`, so the file formats follow from the extensions. Run `kodak help` for usage.
+
+use kodak::*;
+use std::fmt;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: kodak <command> [arguments]
+
+Commands:
+  convert <input> -o <output>                   Convert between formats
+  resize <input> <WxH> [--fit] [--nearest] -o <output>
+                                                Scale to WxH, or to fit within it with --fit
+  crop <input> <X,Y> <WxH> -o <output>          Cut a region out of the image
+  overlay <base> <top> [--at X,Y] -o <output>   Composite one image over another
+  fill <input> <colour> [--region X,Y,WxH] -o <output>
+                                                Fill the image or a region with a colour
+  info <input>...                               Print the size and properties of images
+  run <recipe> [<input>...] [-o <directory>]    Run a TOML or JSON recipe on its inputs, or on
+                                                the given files instead
+  help                                          Show this message
+
+Colours are written as #RGB, #RRGGBB or #RRGGBBAA, or as black, white or transparent.
+The formats of <input> and <output> follow from their extensions.";
+
+/// Everything that can go wrong on the command line.
+#[derive(Debug)]
+enum CliError {
+    /// The arguments do not make sense; the usage is printed along with the message.
+    Usage(String),
+    /// A file could not be read or written.
+    Io(String, std::io::Error),
+    /// The arguments were fine, but the operation failed.
+    Failed(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(message) | CliError::Failed(message) => f.write_str(message),
+            CliError::Io(path, error) => write!(f, "{path}: {error}"),
+        }
+    }
+}
+
+fn usage(message: impl Into<String>) -> CliError {
+    CliError::Usage(message.into())
+}
+
+/// Parses a colour such as `#ff8000`, `#f80`, `#ff800080` or `white`.
+fn parse_colour(s: &str) -> Result<Colour, CliError> {
+    s.parse()
+        .map_err(|e| usage(format!("'{s}' is not a colour; {e}.")))
+}
+
+/// Parses a pair of numbers separated by `sep`, such as `640x480` or `10,20`.
+fn parse_pair(s: &str, sep: char, what: &str) -> Result<(u32, u32), CliError> {
+    let invalid = || usage(format!("'{s}' is not {what}."));
+    let (a, b) = s.split_once(sep).ok_or_else(invalid)?;
+    Ok((
+        a.trim().parse().map_err(|_| invalid())?,
+        b.trim().parse().map_err(|_| invalid())?,
+    ))
+}
+
+/// Parses dimensions written as `WxH`.
+fn parse_dim(s: &str) -> Result<Dim, CliError> {
+    let (w, h) = parse_pair(&s.to_ascii_lowercase(), 'x', "a size like 640x480")?;
+    Ok(Dim { w, h })
+}
+
+/// Parses a location written as `X,Y`.
+fn parse_loc(s: &str) -> Result<Loc, CliError> {
+    let (x, y) = parse_pair(s, ',', "a location like 10,20")?;
+    Ok(Loc { x, y })
+}
+
+/// Parses a region written as `X,Y,WxH`.
+fn parse_region(s: &str) -> Result<Region, CliError> {
+    let invalid = || usage(format!("'{s}' is not a region like 10,20,64x48."));
+    let (loc, dim) = s.rsplit_once(',').ok_or_else(invalid)?;
+    Ok(Region {
+        l: parse_loc(loc).map_err(|_| invalid())?,
+        d: parse_dim(dim).map_err(|_| invalid())?,
+    })
+}
+
+/// The arguments of a command, split into positional arguments, flags and options.
+#[derive(Debug, Default)]
+struct Args {
+    positional: Vec<String>,
+    flags: Vec<String>,
+    options: Vec<(String, String)>,
+}
+
+impl Args {
+    /// Splits `args`, where `valued` lists the options that take a value.
+    fn parse(args: &[String], valued: &[&str]) -> Result<Args, CliError> {
+        let mut parsed = Args::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if !arg.starts_with('-') || arg == "-" {
+                parsed.positional.push(arg.clone());
+                continue;
+            }
+            let name = match arg.as_str() {
+                "-o" => "--output",
+                other => other,
+            };
+            if valued.contains(&name) {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| usage(format!("{name} needs a value.")))?;
+                parsed.options.push((name.to_string(), value.clone()));
+            } else {
+                parsed.flags.push(name.to_string());
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Returns the positional arguments, checking that there are exactly `names.len()`.
+    fn expect_positional(&self, names: &[&str]) -> Result<&[String], CliError> {
+        if self.positional.len() != names.len() {
+            return Err(usage(format!(
+                "Expected {}, but got {} argument(s).",
+                names.join(" "),
+                self.positional.len()
+            )));
+        }
+        Ok(&self.positional)
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn output(&self) -> Result<&str, CliError> {
+        self.option("--output")
+            .ok_or_else(|| usage("No output file given; add -o <output>."))
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|f| f == name)
+    }
+
+    /// Fails if a flag was given that the command does not know about.
+    fn reject_unknown_flags(&self, known: &[&str]) -> Result<(), CliError> {
+        match self.flags.iter().find(|f| !known.contains(&f.as_str())) {
+            Some(flag) => Err(usage(format!("Unknown option {flag}."))),
+            None => Ok(()),
+        }
+    }
+}
+
+fn open(path: &str) -> Result<Image, CliError> {
+    Image::open(path).map_err(|e| CliError::Io(path.to_string(), e))
+}
+
+fn save(img: &Image, path: &str) -> Result<(), CliError> {
+    img.save(path)
+        .map_err(|e| CliError::Io(path.to_string(), e))
+}
+
+/// Describes an image file for `kodak info`.
+fn describe(path: &str, info: &ImageInfo) -> String {
+    let profile = match &info.colour_profile {
+        None => "none (assumed sRGB)".to_string(),
+        Some(ColourProfile::Srgb) => "sRGB".to_string(),
+        Some(ColourProfile::Icc(icc)) => format!("ICC ({} bytes)", icc.len()),
+    };
+    let mut out = format!(
+        "{path}\n  format: {}\n  size: {}x{}\n  colour type: {}, {} bits\n  colour profile: {profile}\n",
+        info.format.unwrap_or("unknown"),
+        info.dimensions.w,
+        info.dimensions.h,
+        info.colour_type,
+        info.bit_depth,
+    );
+    if let Some(size) = info.file_size {
+        out += &format!("  file size: {size} bytes\n");
+    }
+    let metadata = &info.metadata;
+    if let Some((x, y)) = metadata.dpi {
+        out += &format!("  resolution: {x}x{y} dpi\n");
+    }
+    for (key, value) in &metadata.text {
+        out += &format!("  {key}: {value}\n");
+    }
+    if let Some(exif) = &metadata.exif {
+        let camera = [exif.make(), exif.model()].into_iter().flatten();
+        let camera = camera.collect::<Vec<_>>().join(" ");
+        if !camera.is_empty() {
+            out += &format!("  camera: {camera}\n");
+        }
+        if let Some(date) = exif.date_time() {
+            out += &format!("  taken: {date}\n");
+        }
+        if let Some(orientation) = exif.orientation().filter(|&o| o != 1) {
+            out += &format!("  orientation: {orientation}\n");
+        }
+    }
+    out
+}
+
+/// Runs `kodak run`, which processes files as described by a recipe.
+#[cfg(feature = "recipe")]
+fn run_recipe(rest: &[String]) -> Result<String, CliError> {
+    use kodak::recipe::Recipe;
+
+    let args = Args::parse(rest, &["--output"])?;
+    args.reject_unknown_flags(&[])?;
+    let Some((path, inputs)) = args.positional.split_first() else {
+        return Err(usage("Expected <recipe> [<input>...], but got no recipe."));
+    };
+    let mut recipe = Recipe::load(path).map_err(|e| CliError::Io(path.clone(), e))?;
+    if !inputs.is_empty() {
+        recipe = recipe.with_inputs(inputs.iter().map(Into::into).collect::<Vec<_>>());
+    }
+    if let Some(output) = args.option("--output") {
+        recipe = recipe.with_output_dir(output);
+    }
+
+    let report = recipe.run().map_err(|e| CliError::Io(path.clone(), e))?;
+    let mut out = String::new();
+    for (input, output) in &report.succeeded {
+        out += &format!("{} -> {}\n", input.display(), output.display());
+    }
+    if report.is_success() {
+        return Ok(out);
+    }
+    print!("{out}");
+    let failures: Vec<String> = report
+        .failed
+        .iter()
+        .map(|(input, error)| format!("{}: {error}", input.display()))
+        .collect();
+    Err(CliError::Failed(format!(
+        "{} of {} files failed:\n  {}",
+        failures.len(),
+        failures.len() + report.succeeded.len(),
+        failures.join("\n  ")
+    )))
+}
+
+#[cfg(not(feature = "recipe"))]
+fn run_recipe(_: &[String]) -> Result<String, CliError> {
+    Err(CliError::Failed(
+        "this kodak was built without recipes; rebuild it with --features recipe.".to_string(),
+    ))
+}
+
+/// Runs a command and returns what it prints.
+fn run(command: &str, rest: &[String]) -> Result<String, CliError> {
+    match command {
+        "convert" => {
+            let args = Args::parse(rest, &["--output"])?;
+            args.reject_unknown_flags(&[])?;
+            let [input] = args.expect_positional(&["<input>"])? else {
+                unreachable!()
+            };
+            save(&open(input)?, args.output()?)?;
+        }
+        "resize" => {
+            let args = Args::parse(rest, &["--output"])?;
+            args.reject_unknown_flags(&["--fit", "--nearest"])?;
+            let [input, size] = args.expect_positional(&["<input>", "<WxH>"])? else {
+                unreachable!()
+            };
+            let dim = parse_dim(size)?;
+            if dim.w == 0 || dim.h == 0 {
+                return Err(usage("The new size must be at least 1x1."));
+            }
+            let filter = if args.flag("--nearest") {
+                ResizeFilter::Nearest
+            } else {
+                ResizeFilter::Bilinear
+            };
+            let img = open(input)?;
+            let resized = if args.flag("--fit") {
+                img.resize_to_fit(dim, filter)
+            } else {
+                img.resize(dim, filter)
+            };
+            save(&resized, args.output()?)?;
+        }
+        "crop" => {
+            let args = Args::parse(rest, &["--output"])?;
+            args.reject_unknown_flags(&[])?;
+            let [input, corner, size] = args.expect_positional(&["<input>", "<X,Y>", "<WxH>"])?
+            else {
+                unreachable!()
+            };
+            let region = Region {
+                l: parse_loc(corner)?,
+                d: parse_dim(size)?,
+            };
+            let cropped = open(input)?
+                .crop(region)
+                .map_err(|e| CliError::Failed(e.to_string()))?;
+            save(&cropped, args.output()?)?;
+        }
+        "overlay" => {
+            let args = Args::parse(rest, &["--output", "--at"])?;
+            args.reject_unknown_flags(&[])?;
+            let [base, top] = args.expect_positional(&["<base>", "<top>"])? else {
+                unreachable!()
+            };
+            let offset = args.option("--at").map(parse_loc).transpose()?;
+            let offset = offset.unwrap_or_default();
+            let base = open(base)?;
+            let dim = base.get_dimensions();
+            if offset.x >= dim.w || offset.y >= dim.h {
+                return Err(CliError::Failed(format!(
+                    "{},{} lies outside of the {}x{} base image.",
+                    offset.x, offset.y, dim.w, dim.h
+                )));
+            }
+            save(&base.overlay(open(top)?, offset), args.output()?)?;
+        }
+        "fill" => {
+            let args = Args::parse(rest, &["--output", "--region"])?;
+            args.reject_unknown_flags(&[])?;
+            let [input, colour] = args.expect_positional(&["<input>", "<colour>"])? else {
+                unreachable!()
+            };
+            let colour = parse_colour(colour)?;
+            let img = open(input)?;
+            let filled = match args.option("--region").map(parse_region).transpose()? {
+                Some(region) => img.fill_region(region, colour),
+                None => img.fill(colour),
+            };
+            save(&filled, args.output()?)?;
+        }
+        "info" => {
+            let args = Args::parse(rest, &[])?;
+            args.reject_unknown_flags(&[])?;
+            if args.positional.is_empty() {
+                return Err(usage("Expected <input>, but got no files."));
+            }
+            return args
+                .positional
+                .iter()
+                .map(|path| {
+                    let info = ImageInfo::probe(path).map_err(|e| CliError::Io(path.clone(), e))?;
+                    Ok(describe(path, &info))
+                })
+                .collect();
+        }
+        "run" => return run_recipe(rest),
+        "help" | "--help" | "-h" => return Ok(format!("{USAGE}\n")),
+        other => return Err(usage(format!("Unknown command '{other}'."))),
+    }
+    Ok(String::new())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some((command, rest)) = args.split_first() else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    };
+
+    match run(command, rest) {
+        Ok(output) => {
+            print!("{output}");
+            ExitCode::SUCCESS
+        }
+        Err(error @ CliError::Usage(_)) => {
+            eprintln!("kodak: {error}\n\n{USAGE}");
+            ExitCode::from(2)
+        }
+        Err(error) => {
+            eprintln!("kodak: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod cli_tests {
+    use super::*;
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_values() {
+        assert_eq!(parse_colour("#ff8000").unwrap(), Colour::rgb(255, 128, 0));
+        assert_eq!(parse_colour("F80").unwrap(), Colour::rgb(255, 136, 0));
+        assert_eq!(
+            parse_colour("#ff800080").unwrap(),
+            Colour::rgba(255, 128, 0, 128)
+        );
+        assert_eq!(parse_colour("White").unwrap(), Colour::WHITE);
+        assert!(parse_colour("#ff80").is_err());
+        assert!(parse_colour("#+f8000").is_err());
+
+        assert_eq!(parse_dim("640X480").unwrap(), Dim { w: 640, h: 480 });
+        assert!(parse_dim("640").is_err());
+        assert_eq!(
+            parse_region("1,2,3x4").unwrap(),
+            Region {
+                l: Loc { x: 1, y: 2 },
+                d: Dim { w: 3, h: 4 }
+            }
+        );
+    }
+
+    #[test]
+    fn splits_arguments() {
+        let args = Args::parse(
+            &strings(&["in.png", "-o", "out.png", "--fit", "100x50"]),
+            &["--output"],
+        )
+        .unwrap();
+        assert_eq!(args.positional, ["in.png", "100x50"]);
+        assert_eq!(args.output().unwrap(), "out.png");
+        assert!(args.flag("--fit"));
+        assert!(args.reject_unknown_flags(&["--fit"]).is_ok());
+        assert!(args.reject_unknown_flags(&[]).is_err());
+        assert!(Args::parse(&strings(&["-o"]), &["--output"]).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "png")]
+    fn commands_write_files() {
+        let dir = std::env::temp_dir().join("kodak_cli");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+        Image::blank_with_colour(Dim { w: 8, h: 6 }, Colour::WHITE)
+            .save(path("in.png"))
+            .unwrap();
+
+        run(
+            "fill",
+            &strings(&[
+                &path("in.png"),
+                "#f00",
+                "--region",
+                "0,0,2x2",
+                "-o",
+                &path("red.png"),
+            ]),
+        )
+        .unwrap();
+        run(
+            "resize",
+            &strings(&[
+                &path("red.png"),
+                "4x3",
+                "--nearest",
+                "-o",
+                &path("small.png"),
+            ]),
+        )
+        .unwrap();
+        let small = Image::open(path("small.png")).unwrap();
+        assert_eq!(small.get_dimensions(), Dim { w: 4, h: 3 });
+        assert_eq!(
+            small.get_pixel(Loc { x: 0, y: 0 }).unwrap(),
+            Colour::rgb(255, 0, 0)
+        );
+        assert_eq!(small.get_pixel(Loc { x: 3, y: 2 }).unwrap(), Colour::WHITE);
+
+        let info = run("info", &strings(&[&path("small.png")])).unwrap();
+        assert!(info.contains("size: 4x3"));
+        assert!(info.contains("format: PNG"));
+        assert!(matches!(
+            run(
+                "crop",
+                &strings(&[&path("in.png"), "9,9", "1x1", "-o", &path("x.png")])
+            ),
+            Err(CliError::Failed(_))
+        ));
+        assert!(matches!(
+            run(
+                "convert",
+                &strings(&[&path("missing.png"), "-o", &path("x.png")])
+            ),
+            Err(CliError::Io(..))
+        ));
+        assert!(matches!(run("shrink", &[]), Err(CliError::Usage(_))));
+
+        #[cfg(feature = "recipe")]
+        {
+            let recipe = path("flip.json");
+            std::fs::write(
+                &recipe,
+                r#"{"inputs": [], "output_dir": ".", "steps": [{"op": "flip_vertical"}]}"#,
+            )
+            .unwrap();
+            let printed = run(
+                "run",
+                &strings(&[&recipe, &path("red.png"), "-o", &path("out")]),
+            )
+            .unwrap();
+            assert!(printed.contains("red.png ->"));
+            let flipped = Image::open(dir.join("out/red.png")).unwrap();
+            assert_eq!(
+                flipped.get_pixel(Loc { x: 0, y: 5 }).unwrap(),
+                Colour::rgb(255, 0, 0)
+            );
+        }
+    }
+}
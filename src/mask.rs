@@ -0,0 +1,303 @@
+//! Selections: masks of per-pixel coverage, and the magic-wand tools that create them.
+
+use crate::{Colour, Dim, Image, Loc};
+
+/// A selection of pixels, where each pixel is selected with a coverage from 0 (not at all) to
+/// 255 (fully). Coverage in between comes from feathering and gives soft edges.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use kodak::*;
+/// // Turn the sky grey, with a soft transition at the horizon.
+/// let photo = Image::load_png("assets/landscape.png").unwrap();
+/// let sky = photo.select_contiguous(Loc { x: 0, y: 0 }, 40).feather(3);
+/// let grey = Image::blank_with_colour(photo.get_dimensions(), Colour::rgb(128, 128, 128));
+/// photo.blend_masked(&grey, &sky).save_png("grey_sky.png").unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mask {
+    width: u32,
+    height: u32,
+    values: Vec<u8>,
+}
+
+impl Mask {
+    /// Creates a mask in which nothing is selected.
+    pub fn new(dimension: Dim) -> Self {
+        Mask {
+            width: dimension.w,
+            height: dimension.h,
+            values: vec![0; dimension.w as usize * dimension.h as usize],
+        }
+    }
+
+    /// Creates a mask from a function that gives the coverage of every location.
+    pub fn from_fn<F: Fn(Loc) -> u8>(dimension: Dim, f: F) -> Self {
+        Mask {
+            width: dimension.w,
+            height: dimension.h,
+            values: (0..dimension.w as usize * dimension.h as usize)
+                .map(|i| f(Loc::from_index(i, dimension)))
+                .collect(),
+        }
+    }
+
+    /// Returns the dimensions of the mask.
+    pub fn get_dimensions(&self) -> Dim {
+        Dim {
+            w: self.width,
+            h: self.height,
+        }
+    }
+
+    /// Returns the coverage at a location, or 0 if it lies outside of the mask.
+    pub fn get(&self, loc: Loc) -> u8 {
+        if loc.x < self.width && loc.y < self.height {
+            self.values[loc.as_index(self.get_dimensions())]
+        } else {
+            0
+        }
+    }
+
+    /// Sets the coverage at a location. Locations outside of the mask are ignored.
+    pub fn set(&mut self, loc: Loc, coverage: u8) {
+        if loc.x < self.width && loc.y < self.height {
+            let idx = loc.as_index(self.get_dimensions());
+            self.values[idx] = coverage;
+        }
+    }
+
+    /// Returns whether a location is at least half selected.
+    pub fn contains(&self, loc: Loc) -> bool {
+        self.get(loc) >= 128
+    }
+
+    /// Returns the coverage of every pixel, in row-major order.
+    pub fn values(&self) -> &[u8] {
+        &self.values
+    }
+
+    /// Combines two masks of equal dimensions value by value.
+    fn combine(&self, other: &Mask, f: impl Fn(u8, u8) -> u8) -> Mask {
+        assert_eq!(
+            self.get_dimensions(),
+            other.get_dimensions(),
+            "Both masks must have the same dimensions."
+        );
+        Mask {
+            values: self
+                .values
+                .iter()
+                .zip(&other.values)
+                .map(|(&a, &b)| f(a, b))
+                .collect(),
+            ..*self
+        }
+    }
+
+    /// Selects everything that is selected in either mask.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the masks differ in size, as do `intersect()` and `subtract()`.
+    pub fn union(&self, other: &Mask) -> Mask {
+        self.combine(other, u8::max)
+    }
+
+    /// Selects only what is selected in both masks.
+    pub fn intersect(&self, other: &Mask) -> Mask {
+        self.combine(other, u8::min)
+    }
+
+    /// Removes the selection of another mask from this one.
+    pub fn subtract(&self, other: &Mask) -> Mask {
+        self.combine(other, |a, b| a.min(255 - b))
+    }
+
+    /// Selects exactly what is not selected.
+    pub fn invert(&self) -> Mask {
+        Mask {
+            values: self.values.iter().map(|&v| 255 - v).collect(),
+            ..*self
+        }
+    }
+
+    /// Softens the edges of the selection over roughly `radius` pixels in each direction.
+    ///
+    /// The mask is blurred twice with a box of `2 * radius + 1` pixels, which approximates a
+    /// Gaussian blur closely enough for selections.
+    pub fn feather(&self, radius: u32) -> Mask {
+        if radius == 0 {
+            return self.clone();
+        }
+        let (w, h) = (self.width as usize, self.height as usize);
+        let mut values: Vec<f32> = self.values.iter().map(|&v| v as f32).collect();
+
+        // Blurs the runs of `len` values spaced `step` apart that begin at each of `starts`,
+        // clamping at the ends of each run.
+        let box_blur = |values: &mut Vec<f32>, len: usize, step: usize, starts: Vec<usize>| {
+            let r = radius as isize;
+            let mut line = vec![0.0; len];
+            for start in starts {
+                for (i, v) in line.iter_mut().enumerate() {
+                    *v = values[start + i * step];
+                }
+                for i in 0..len as isize {
+                    let sum: f32 = (i - r..=i + r)
+                        .map(|j| line[j.clamp(0, len as isize - 1) as usize])
+                        .sum();
+                    values[start + i as usize * step] = sum / (2 * r + 1) as f32;
+                }
+            }
+        };
+
+        for _ in 0..2 {
+            box_blur(&mut values, w, 1, (0..h).map(|y| y * w).collect());
+            box_blur(&mut values, h, w, (0..w).collect());
+        }
+
+        Mask {
+            values: values.into_iter().map(|v| v.round() as u8).collect(),
+            ..*self
+        }
+    }
+}
+
+/// Returns whether two colours differ by at most `tolerance` in every channel, alpha included.
+pub(crate) fn within_tolerance(a: Colour, b: Colour, tolerance: u8) -> bool {
+    a.r.abs_diff(b.r) <= tolerance
+        && a.g.abs_diff(b.g) <= tolerance
+        && a.b.abs_diff(b.b) <= tolerance
+        && a.a.abs_diff(b.a) <= tolerance
+}
+
+// The following impl block defines selection functions for Images.
+impl Image {
+    /// Selects the area of similar colour connected to `seed`, like the magic wand of an image
+    /// editor.
+    ///
+    /// A pixel is selected if it differs from the seed's colour by at most `tolerance` in every
+    /// channel and can be reached from the seed through selected pixels, moving horizontally or
+    /// vertically. If the seed lies outside of the image, nothing is selected.
+    pub fn select_contiguous(&self, seed: Loc, tolerance: u8) -> Mask {
+        let dim = self.get_dimensions();
+        let mut mask = Mask::new(dim);
+        let Ok(target) = self.get_pixel(seed) else {
+            return mask;
+        };
+
+        let mut stack = vec![seed];
+        mask.set(seed, 255);
+        while let Some(loc) = stack.pop() {
+            let neighbours = [
+                (loc.x.checked_sub(1), Some(loc.y)),
+                (loc.x.checked_add(1), Some(loc.y)),
+                (Some(loc.x), loc.y.checked_sub(1)),
+                (Some(loc.x), loc.y.checked_add(1)),
+            ];
+            for (x, y) in neighbours {
+                let (Some(x), Some(y)) = (x, y) else { continue };
+                let next = Loc { x, y };
+                if x < dim.w
+                    && y < dim.h
+                    && mask.get(next) == 0
+                    && within_tolerance(self.pixels[next.as_index(dim)], target, tolerance)
+                {
+                    mask.set(next, 255);
+                    stack.push(next);
+                }
+            }
+        }
+        mask
+    }
+
+    /// Selects every pixel that differs from `colour` by at most `tolerance` in every channel,
+    /// wherever it is in the image.
+    pub fn select_by_colour(&self, colour: Colour, tolerance: u8) -> Mask {
+        Mask {
+            width: self.width,
+            height: self.height,
+            values: self
+                .pixels
+                .iter()
+                .map(|&c| {
+                    if within_tolerance(c, colour, tolerance) {
+                        255
+                    } else {
+                        0
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Mixes in the pixels of `edited` wherever `mask` selects them, keeping this image's
+    /// pixels elsewhere. Partial coverage blends the two.
+    ///
+    /// This limits any edit to a selection: apply the edit to the whole image, then blend the
+    /// result back in.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `edited` or `mask` differ in size from this image.
+    pub fn blend_masked(&self, edited: &Image, mask: &Mask) -> Image {
+        assert_eq!(self.get_dimensions(), edited.get_dimensions());
+        assert_eq!(self.get_dimensions(), mask.get_dimensions());
+
+        let pixels = self
+            .pixels
+            .iter()
+            .zip(&edited.pixels)
+            .zip(&mask.values)
+            .map(|((&a, &b), &m)| a.lerp(b, m as f32 / 255.0))
+            .collect();
+        Image {
+            pixels,
+            ..self.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod mask_tests {
+    use super::*;
+    use crate::Region;
+
+    #[test]
+    fn magic_wand() {
+        // Two white squares separated by a black column.
+        let img = Image::blank(Dim { w: 5, h: 2 })
+            .fill_region(Region::from_top_left(Dim::square(2)), Colour::WHITE)
+            .fill_region(
+                Region {
+                    l: Loc { x: 3, y: 0 },
+                    d: Dim::square(2),
+                },
+                Colour::rgb(250, 250, 250),
+            );
+
+        let left = img.select_contiguous(Loc { x: 0, y: 0 }, 10);
+        assert_eq!(left.values(), &[255, 255, 0, 0, 0, 255, 255, 0, 0, 0]);
+
+        let white = img.select_by_colour(Colour::WHITE, 10);
+        assert_eq!(
+            white,
+            left.union(&img.select_contiguous(Loc { x: 4, y: 1 }, 10))
+        );
+        assert_eq!(
+            white.invert().intersect(&left),
+            Mask::new(Dim { w: 5, h: 2 })
+        );
+    }
+
+    #[test]
+    fn feather_softens_edges() {
+        let mask = Mask::from_fn(Dim { w: 9, h: 1 }, |l| if l.x < 4 { 255 } else { 0 });
+        let soft = mask.feather(1);
+
+        assert_eq!(soft.get(Loc { x: 0, y: 0 }), 255);
+        assert!(soft.get(Loc { x: 3, y: 0 }) < 255 && soft.get(Loc { x: 4, y: 0 }) > 0);
+        assert_eq!(soft.get(Loc { x: 8, y: 0 }), 0);
+    }
+}
@@ -0,0 +1,212 @@
+//! Template matching: finding where a smaller image occurs inside a larger one.
+
+use crate::integral::IntegralImage;
+use crate::progress::CANCELLED;
+use crate::{CancelToken, Dim, Image, Loc};
+
+// The following impl block defines template matching functions for Images.
+impl Image {
+    /// Scores every position at which `needle` fits inside this image with the normalised
+    /// cross-correlation of their RGB values, from -1.0 (inverted) to 1.0 (identical up to
+    /// brightness and contrast).
+    ///
+    /// Returns the scores in row-major order along with the dimensions of the grid of positions,
+    /// or `None` if the needle is empty or larger than the image. `cancel` is checked after
+    /// every row of positions.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(haystack = ?self.get_dimensions(), needle = ?needle.get_dimensions())
+        )
+    )]
+    fn match_scores(
+        &self,
+        needle: &Image,
+        cancel: &CancelToken,
+    ) -> Result<Option<(Dim, Vec<f32>)>, &'static str> {
+        if needle.pixels.is_empty() || needle.width > self.width || needle.height > self.height {
+            return Ok(None);
+        }
+
+        let values = |img: &Image| -> Vec<[f64; 3]> {
+            img.pixels
+                .iter()
+                .map(|c| [c.r as f64, c.g as f64, c.b as f64])
+                .collect()
+        };
+        let haystack = values(self);
+        let needle_values = values(needle);
+        let dim = self.get_dimensions();
+        let nd = needle.get_dimensions();
+        let n = needle_values.len() as f64 * 3.0;
+
+        // Sums of the haystack's values and squares come from summed-area tables, so only the
+        // cross term needs a full pass over the needle at every position.
+        let sums = IntegralImage::new(dim, haystack.iter().map(|v| v.iter().sum()));
+        let squares =
+            IntegralImage::new(dim, haystack.iter().map(|v| v.iter().map(|x| x * x).sum()));
+
+        let needle_mean = needle_values.iter().flatten().sum::<f64>() / n;
+        let centred: Vec<[f64; 3]> = needle_values
+            .iter()
+            .map(|v| v.map(|x| x - needle_mean))
+            .collect();
+        let needle_variance: f64 = centred.iter().flatten().map(|x| x * x).sum();
+
+        let grid = Dim {
+            w: self.width - needle.width + 1,
+            h: self.height - needle.height + 1,
+        };
+        let mut scores = Vec::with_capacity(grid.w as usize * grid.h as usize);
+        for y in 0..grid.h {
+            if cancel.is_cancelled() {
+                return Err(CANCELLED);
+            }
+            for x in 0..grid.w {
+                let l = Loc { x, y };
+                let sum = sums.sum(l, nd);
+                let variance = squares.sum(l, nd) - sum * sum / n;
+
+                if needle_variance < 1e-6 || variance < 1e-6 {
+                    // Correlation is undefined for flat areas; compare their brightness instead.
+                    let flat = needle_variance < 1e-6 && variance < 1e-6;
+                    let difference = (sum / n - needle_mean).abs() / 255.0;
+                    scores.push(if flat { 1.0 - difference as f32 } else { 0.0 });
+                    continue;
+                }
+
+                let mut cross = 0.0;
+                for ny in 0..nd.h as usize {
+                    let row = (y as usize + ny) * dim.w as usize + x as usize;
+                    let needle_row = ny * nd.w as usize;
+                    for nx in 0..nd.w as usize {
+                        let (a, b) = (haystack[row + nx], centred[needle_row + nx]);
+                        cross += a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+                    }
+                }
+                scores.push((cross / (variance * needle_variance).sqrt()) as f32);
+            }
+        }
+        Ok(Some((grid, scores)))
+    }
+
+    /// Finds the position where `needle` matches this image best, using normalised
+    /// cross-correlation.
+    ///
+    /// `tolerance` ranges from 0.0 (only an exact match up to brightness and contrast) to 1.0;
+    /// a match is accepted when its correlation is at least `1.0 - tolerance`. Small values such
+    /// as 0.05 work well for screenshots. Returns `None` if there is no acceptable match or the
+    /// needle does not fit inside this image.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use kodak::*;
+    /// let screen = Image::load_png("screenshot.png").unwrap();
+    /// let button = Image::load_png("ok_button.png").unwrap();
+    /// if let Some(loc) = screen.find(&button, 0.05) {
+    ///     println!("Click at {}, {}", loc.x, loc.y);
+    /// }
+    /// ```
+    pub fn find(&self, needle: &Image, tolerance: f32) -> Option<Loc> {
+        self.find_cancellable(needle, tolerance, &CancelToken::new())
+            .expect("A fresh token is never cancelled.")
+    }
+
+    /// Like `find()`, but gives up with an `Err` once `cancel` has been cancelled, so that
+    /// searching a large image can be aborted.
+    pub fn find_cancellable(
+        &self,
+        needle: &Image,
+        tolerance: f32,
+        cancel: &CancelToken,
+    ) -> Result<Option<Loc>, &'static str> {
+        let Some((grid, scores)) = self.match_scores(needle, cancel)? else {
+            return Ok(None);
+        };
+        let best = scores
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .filter(|(_, score)| **score >= 1.0 - tolerance);
+        Ok(best.map(|(index, _)| Loc::from_index(index, grid)))
+    }
+
+    /// Finds every position where `needle` matches this image, best matches first.
+    ///
+    /// Matches are accepted as in `find()`. Positions around an accepted match that would
+    /// overlap it are skipped, so every occurrence is reported once.
+    pub fn find_all(&self, needle: &Image, tolerance: f32) -> Vec<Loc> {
+        self.find_all_cancellable(needle, tolerance, &CancelToken::new())
+            .expect("A fresh token is never cancelled.")
+    }
+
+    /// Like `find_all()`, but gives up with an `Err` once `cancel` has been cancelled.
+    pub fn find_all_cancellable(
+        &self,
+        needle: &Image,
+        tolerance: f32,
+        cancel: &CancelToken,
+    ) -> Result<Vec<Loc>, &'static str> {
+        let Some((grid, scores)) = self.match_scores(needle, cancel)? else {
+            return Ok(Vec::new());
+        };
+
+        let mut candidates: Vec<(usize, f32)> = scores
+            .into_iter()
+            .enumerate()
+            .filter(|(_, score)| *score >= 1.0 - tolerance)
+            .collect();
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let mut found: Vec<Loc> = Vec::new();
+        for (index, _) in candidates {
+            let loc = Loc::from_index(index, grid);
+            let overlaps = found
+                .iter()
+                .any(|f| f.x.abs_diff(loc.x) < needle.width && f.y.abs_diff(loc.y) < needle.height);
+            if !overlaps {
+                found.push(loc);
+            }
+        }
+        Ok(found)
+    }
+}
+
+#[cfg(all(test, feature = "png"))]
+mod matching_tests {
+    use super::*;
+    use crate::Region;
+
+    #[test]
+    fn finds_every_occurrence() {
+        let img = Image::load_png("tests/fixtures/test.png").unwrap();
+        let needle = img.clone().crop_unclamped(Region {
+            l: Loc { x: 40, y: 25 },
+            d: Dim { w: 12, h: 9 },
+        });
+        assert_eq!(img.find(&needle, 0.01), Some(Loc { x: 40, y: 25 }));
+
+        let twice = Image::blank(Dim { w: 60, h: 30 })
+            .overlay(needle.clone(), Loc { x: 5, y: 3 })
+            .overlay(needle.clone(), Loc { x: 40, y: 20 });
+        let mut found = twice.find_all(&needle, 0.01);
+        found.sort_by_key(|l| l.x);
+        assert_eq!(found, vec![Loc { x: 5, y: 3 }, Loc { x: 40, y: 20 }]);
+
+        let cancel = CancelToken::new();
+        assert_eq!(
+            img.find_cancellable(&needle, 0.01, &cancel),
+            Ok(Some(Loc { x: 40, y: 25 }))
+        );
+        cancel.cancel();
+        assert!(img.find_cancellable(&needle, 0.01, &cancel).is_err());
+        assert!(twice.find_all_cancellable(&needle, 0.01, &cancel).is_err());
+        assert_eq!(
+            img.find_all_cancellable(&img, 0.01, &cancel),
+            Err(CANCELLED)
+        );
+    }
+}
@@ -0,0 +1,28 @@
+//! Floating point functions for `no_std` builds, where `f32` lacks the methods that need the
+//! platform's maths library. With `std`, the inherent methods are used instead.
+
+/// The `f32` methods Kodak's core needs, implemented with `libm`.
+pub(crate) trait Float {
+    fn powf(self, n: f32) -> f32;
+    fn powi(self, n: i32) -> f32;
+    fn round(self) -> f32;
+    fn cbrt(self) -> f32;
+}
+
+impl Float for f32 {
+    fn powf(self, n: f32) -> f32 {
+        libm::powf(self, n)
+    }
+
+    fn powi(self, n: i32) -> f32 {
+        libm::powf(self, n as f32)
+    }
+
+    fn round(self) -> f32 {
+        libm::roundf(self)
+    }
+
+    fn cbrt(self) -> f32 {
+        libm::cbrtf(self)
+    }
+}
@@ -0,0 +1,214 @@
+//! Textual and physical metadata carried by images.
+
+use crate::{Exif, Image};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+#[cfg(all(feature = "png", not(target_arch = "wasm32")))]
+use std::path::Path;
+
+/// The number of millimetres in an inch, used to convert between pixels and physical sizes.
+pub(crate) const MM_PER_INCH: f32 = 25.4;
+
+/// The number of inches in a metre, used to convert between DPI and PNG's pixels per metre.
+#[cfg(feature = "png")]
+const INCHES_PER_METRE: f32 = 39.370_08;
+
+/// Metadata attached to an Image: key-value text entries and the physical resolution.
+///
+/// When loading a PNG, all tEXt, zTXt and iTXt chunks end up in `text` and a pHYs chunk (if it
+/// specifies metres) ends up in `dpi`. When saving, text that fits in Latin-1 is written as tEXt
+/// and anything else as iTXt. The resolution is also read from the JFIF header of JPEGs and
+/// read from and written to the resolution tags of TIFFs.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use kodak::*;
+/// # #[cfg(feature = "png")] {
+/// let metadata = Metadata::default()
+///     .with_text("Author", "Simeon")
+///     .with_text("Software", "kodak")
+///     .with_dpi(300.0, 300.0);
+/// Image::blank(Dim::square(64))
+///     .save_png_with_metadata("tagged.png", &metadata)
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Metadata {
+    /// Key-value text entries, in the order they appear in the file.
+    pub text: Vec<(String, String)>,
+    /// The horizontal and vertical resolution in dots per inch, if known.
+    pub dpi: Option<(f32, f32)>,
+    /// EXIF data, as found in JPEG files.
+    pub exif: Option<Exif>,
+}
+
+impl Metadata {
+    /// Looks up the first text entry with the given key.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.text
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Adds a text entry, replacing any existing entries with the same key.
+    pub fn with_text(mut self, key: &str, value: &str) -> Self {
+        self.text.retain(|(k, _)| k != key);
+        self.text.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Sets the physical resolution in dots per inch.
+    pub fn with_dpi(self, x: f32, y: f32) -> Self {
+        Metadata {
+            dpi: Some((x, y)),
+            ..self
+        }
+    }
+
+    /// Collects the metadata found in the header and trailing chunks of a decoded PNG.
+    #[cfg(feature = "png")]
+    pub(crate) fn from_png_info(info: &png::Info) -> Self {
+        let mut text: Vec<(String, String)> = info
+            .uncompressed_latin1_text
+            .iter()
+            .map(|t| (t.keyword.clone(), t.text.clone()))
+            .collect();
+        text.extend(
+            info.compressed_latin1_text
+                .iter()
+                .filter_map(|t| Some((t.keyword.clone(), t.get_text().ok()?))),
+        );
+        text.extend(
+            info.utf8_text
+                .iter()
+                .filter_map(|t| Some((t.keyword.clone(), t.get_text().ok()?))),
+        );
+
+        let dpi = info
+            .pixel_dims
+            .filter(|d| d.unit == png::Unit::Meter)
+            .map(|d| {
+                (
+                    d.xppu as f32 / INCHES_PER_METRE,
+                    d.yppu as f32 / INCHES_PER_METRE,
+                )
+            });
+
+        Metadata {
+            text,
+            dpi,
+            exif: None,
+        }
+    }
+
+    /// Registers the metadata's chunks with a PNG encoder.
+    #[cfg(feature = "png")]
+    pub(crate) fn apply_to_encoder<W: std::io::Write>(
+        &self,
+        encoder: &mut png::Encoder<W>,
+    ) -> Result<(), png::EncodingError> {
+        for (key, value) in &self.text {
+            if value.chars().all(|c| (c as u32) < 256) {
+                encoder.add_text_chunk(key.clone(), value.clone())?;
+            } else {
+                encoder.add_itxt_chunk(key.clone(), value.clone())?;
+            }
+        }
+
+        if let Some((x, y)) = self.dpi {
+            encoder.set_pixel_dims(Some(png::PixelDimensions {
+                xppu: (x * INCHES_PER_METRE).round() as u32,
+                yppu: (y * INCHES_PER_METRE).round() as u32,
+                unit: png::Unit::Meter,
+            }));
+        }
+
+        Ok(())
+    }
+}
+
+// The following impl block defines metadata functions for Images.
+impl Image {
+    /// Returns the metadata attached to the image.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Replaces the metadata attached to the image. It is written out by `save_png()`.
+    pub fn with_metadata(self, metadata: Metadata) -> Image {
+        Image { metadata, ..self }
+    }
+
+    /// Returns the physical resolution in dots per inch, horizontally and vertically, if known.
+    pub fn dpi(&self) -> Option<(f32, f32)> {
+        self.metadata.dpi
+    }
+
+    /// Sets the physical resolution in dots per inch, which is written to PNG and TIFF files so
+    /// that they print at the intended size.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// // A 4 by 6 inch print at 300 DPI.
+    /// let mut img = Image::blank(Dim::from_physical((101.6, 152.4), 300.0));
+    /// img.set_dpi(300.0, 300.0);
+    /// assert_eq!(img.get_dimensions(), Dim { w: 1200, h: 1800 });
+    /// assert_eq!(img.physical_size(), Some((101.6, 152.4)));
+    /// ```
+    pub fn set_dpi(&mut self, x: f32, y: f32) {
+        self.metadata.dpi = Some((x, y));
+    }
+
+    /// Returns the width and height the image prints at, in millimetres, or `None` if its
+    /// resolution is unknown.
+    pub fn physical_size(&self) -> Option<(f32, f32)> {
+        let (x, y) = self.metadata.dpi?;
+        Some((
+            self.width as f32 / x * MM_PER_INCH,
+            self.height as f32 / y * MM_PER_INCH,
+        ))
+    }
+
+    /// Saves an Image as a PNG file, writing the given metadata instead of the image's own.
+    ///
+    /// Like `save_png()`, this returns an `Err` if the file could not be written, for instance
+    /// when a text key is not a valid PNG keyword (1 to 79 Latin-1 characters).
+    #[cfg(all(feature = "png", not(target_arch = "wasm32")))]
+    pub fn save_png_with_metadata(
+        &self,
+        file_name: impl AsRef<Path>,
+        metadata: &Metadata,
+    ) -> Result<(), png::EncodingError> {
+        self.write_png(file_name, metadata)
+    }
+}
+
+#[cfg(all(test, feature = "png"))]
+mod metadata_tests {
+    use super::*;
+    use crate::Dim;
+
+    #[test]
+    fn png_round_trip() {
+        let path = std::env::temp_dir().join("kodak_metadata_round_trip.png");
+        let metadata = Metadata::default()
+            .with_text("Author", "Simeon")
+            .with_text("Comment", "Ünïcödé ✓")
+            .with_dpi(300.0, 150.0);
+
+        Image::blank(Dim::square(4))
+            .save_png_with_metadata(&path, &metadata)
+            .unwrap();
+        let loaded = Image::load_png(&path).unwrap();
+
+        assert_eq!(loaded.metadata().get("Author"), Some("Simeon"));
+        assert_eq!(loaded.metadata().get("Comment"), Some("Ünïcödé ✓"));
+        let (x, y) = loaded.metadata().dpi.unwrap();
+        assert!((x - 300.0).abs() < 0.1 && (y - 150.0).abs() < 0.1);
+    }
+}
@@ -0,0 +1,300 @@
+//! Laying out many images in a grid, such as contact sheets of a folder of photos.
+
+use crate::{Colour, Dim, FontStack, Image, Loc, ResizeFilter, TextLayout, TextStyle};
+#[cfg(not(target_arch = "wasm32"))]
+use std::io;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+/// The height of a caption in the built-in bitmap font, in pixels.
+const BITMAP_CAPTION_HEIGHT: u32 = 8;
+
+/// Arranges images in a grid of equally sized cells, each with a thumbnail and a caption below
+/// it.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use kodak::*;
+/// let paths = ["a.jpg", "b.png", "c.tif"];
+/// let sheet = Montage::contact_sheet(&paths, 2, Dim::square(160)).unwrap();
+/// sheet.save("sheet.png").unwrap();
+///
+/// let font = Font::load("assets/DejaVuSans.ttf").unwrap();
+/// let paths = ["holiday/beach.jpg", "holiday/dunes.jpg"];
+/// let sheet = Montage::new(4, Dim { w: 240, h: 160 })
+///     .with_background(Colour::BLACK)
+///     .with_caption_colour(Colour::WHITE)
+///     .with_font(font, 14.0)
+///     .render_files(&paths)
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Montage {
+    columns: u32,
+    thumb_dim: Dim,
+    spacing: u32,
+    background: Colour,
+    caption_colour: Colour,
+    font: Option<(FontStack, f32)>,
+}
+
+impl Montage {
+    /// Creates a montage with `columns` cells per row, whose thumbnails fit in `thumb_dim`. The
+    /// cells are 8 pixels apart on a white background, and the captions are black text in the
+    /// built-in bitmap font.
+    ///
+    /// At least one column is used, and thumbnails are at least one pixel wide and high.
+    pub fn new(columns: u32, thumb_dim: Dim) -> Self {
+        Montage {
+            columns: columns.max(1),
+            thumb_dim: Dim {
+                w: thumb_dim.w.max(1),
+                h: thumb_dim.h.max(1),
+            },
+            spacing: 8,
+            background: Colour::WHITE,
+            caption_colour: Colour::BLACK,
+            font: None,
+        }
+    }
+
+    /// Loads the images at `paths` and lays them out in `columns` columns with thumbnails that
+    /// fit in `thumb_dim`, captioned with their file names. A shorthand for
+    /// `Montage::new(columns, thumb_dim).render_files(paths)`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn contact_sheet(
+        paths: &[impl AsRef<Path>],
+        columns: u32,
+        thumb_dim: Dim,
+    ) -> io::Result<Image> {
+        Montage::new(columns, thumb_dim).render_files(paths)
+    }
+
+    /// Sets the space between the cells, and between the cells and the edge, in pixels.
+    pub fn with_spacing(self, spacing: u32) -> Self {
+        Montage { spacing, ..self }
+    }
+
+    /// Sets the colour behind the cells, which also shows through transparent images.
+    pub fn with_background(self, background: Colour) -> Self {
+        Montage { background, ..self }
+    }
+
+    /// Sets the colour of the captions.
+    pub fn with_caption_colour(self, caption_colour: Colour) -> Self {
+        Montage {
+            caption_colour,
+            ..self
+        }
+    }
+
+    /// Draws the captions with `fonts` at `size` instead of the built-in bitmap font.
+    pub fn with_font(self, fonts: impl Into<FontStack>, size: f32) -> Self {
+        Montage {
+            font: Some((fonts.into(), size)),
+            ..self
+        }
+    }
+
+    /// Returns the height of a caption, including the gap above it.
+    fn caption_height(&self) -> u32 {
+        let text_height = match &self.font {
+            Some((fonts, size)) => fonts.line_height(*size).ceil() as u32,
+            None => BITMAP_CAPTION_HEIGHT,
+        };
+        text_height + 4
+    }
+
+    /// Returns the width of a caption in pixels.
+    fn caption_width(&self, caption: &str) -> u32 {
+        match &self.font {
+            Some((fonts, size)) => fonts.measure(caption, *size).w,
+            None => caption.chars().count() as u32 * BITMAP_CAPTION_HEIGHT,
+        }
+    }
+
+    /// Shortens a caption with an ellipsis until it fits under a thumbnail.
+    fn fit_caption(&self, caption: &str) -> String {
+        if self.caption_width(caption) <= self.thumb_dim.w {
+            return caption.to_string();
+        }
+        let mut chars: Vec<char> = caption.chars().collect();
+        while !chars.is_empty() {
+            chars.pop();
+            let shortened: String = chars.iter().chain(&['.', '.', '.']).collect();
+            if self.caption_width(&shortened) <= self.thumb_dim.w {
+                return shortened;
+            }
+        }
+        String::new()
+    }
+
+    /// Lays out images with their captions, in reading order. Every image is scaled down to
+    /// fit in the thumbnail size, keeping its aspect ratio, and centred in its cell. Captions
+    /// that are wider than a thumbnail are shortened with an ellipsis.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let red = Image::blank_with_colour(Dim { w: 300, h: 200 }, Colour::rgb(255, 0, 0));
+    /// let blue = Image::blank_with_colour(Dim::square(50), Colour::rgb(0, 0, 255));
+    /// let sheet = Montage::new(2, Dim::square(100)).render(&[(red, "red"), (blue, "blue")]);
+    /// // Two cells of 100 by 112 pixels, 8 pixels apart and from the edges.
+    /// assert_eq!(sheet.get_dimensions(), Dim { w: 224, h: 128 });
+    /// ```
+    pub fn render<S: AsRef<str>>(&self, images: &[(Image, S)]) -> Image {
+        let count = images.len() as u32;
+        let columns = self.columns.min(count);
+        let rows = count.div_ceil(self.columns);
+        let cell = Dim {
+            w: self.thumb_dim.w,
+            h: self.thumb_dim.h + self.caption_height(),
+        };
+        let dim = Dim {
+            w: columns * cell.w + (columns + 1) * self.spacing,
+            h: rows * cell.h + (rows + 1) * self.spacing,
+        };
+        let mut sheet = Image::blank_with_colour(dim, self.background);
+
+        for (i, (img, caption)) in images.iter().enumerate() {
+            let (column, row) = (i as u32 % self.columns, i as u32 / self.columns);
+            let left = self.spacing + column * (cell.w + self.spacing);
+            let top = self.spacing + row * (cell.h + self.spacing);
+
+            let fits = img.width <= self.thumb_dim.w && img.height <= self.thumb_dim.h;
+            let thumb = if fits {
+                img.clone()
+            } else {
+                img.resize_to_fit(self.thumb_dim, ResizeFilter::Area)
+            };
+            let offset = Loc {
+                x: left + (self.thumb_dim.w - thumb.width) / 2,
+                y: top + (self.thumb_dim.h - thumb.height) / 2,
+            };
+            // An empty image leaves its cell empty, and `overlay()` needs at least one pixel.
+            if !thumb.pixels.is_empty() {
+                sheet = sheet.overlay(thumb, offset);
+            }
+
+            // The caption is drawn on its own strip, so that the sheet is not copied for it.
+            let caption = self.fit_caption(caption.as_ref());
+            let strip = Image::blank_with_colour(
+                Dim {
+                    w: cell.w,
+                    h: cell.h - self.thumb_dim.h,
+                },
+                Colour::TRANSPARENT,
+            );
+            let loc = Loc {
+                x: (cell.w - self.caption_width(&caption)) / 2,
+                y: 4,
+            };
+            let strip = match &self.font {
+                Some((fonts, size)) => strip.draw_text(
+                    &caption,
+                    fonts,
+                    *size,
+                    loc,
+                    &TextStyle::fill(self.caption_colour),
+                    &TextLayout::default(),
+                ),
+                None => strip.draw_text_bitmap(&caption, loc, 1, self.caption_colour),
+            };
+            sheet = sheet.overlay(
+                strip,
+                Loc {
+                    x: left,
+                    y: top + self.thumb_dim.h,
+                },
+            );
+        }
+        sheet
+    }
+
+    /// Loads the images at `paths` with `Image::open()` and lays them out with `render()`,
+    /// captioned with their file names.
+    ///
+    /// This returns an `Err` if any of the images could not be loaded.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn render_files(&self, paths: &[impl AsRef<Path>]) -> io::Result<Image> {
+        let images = paths
+            .iter()
+            .map(|path| {
+                let path = path.as_ref();
+                let name = path
+                    .file_name()
+                    .map_or_else(String::new, |n| n.to_string_lossy().into_owned());
+                Ok((Image::open(path)?, name))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(self.render(&images))
+    }
+}
+
+#[cfg(test)]
+mod montage_tests {
+    use super::*;
+
+    #[test]
+    fn lays_out_cells() {
+        let wide = Image::blank_with_colour(Dim { w: 40, h: 10 }, Colour::rgb(255, 0, 0));
+        let small = Image::blank_with_colour(Dim::square(4), Colour::rgb(0, 0, 255));
+        let images = [(wide.clone(), "ok"), (small, "far too long"), (wide, "")];
+        let sheet = Montage::new(2, Dim::square(20))
+            .with_spacing(2)
+            .with_background(Colour::rgb(0, 255, 0))
+            .render(&images);
+        // Cells of 20 by 32 pixels in two columns and two rows.
+        assert_eq!(sheet.get_dimensions(), Dim { w: 46, h: 70 });
+
+        let at = |x, y| sheet.get_pixel(Loc { x, y }).unwrap();
+        // The wide image is scaled down to 20 by 5 and centred vertically.
+        assert_eq!(at(2, 8), Colour::rgb(0, 255, 0));
+        assert_eq!(at(2, 9), Colour::rgb(255, 0, 0));
+        assert_eq!(at(21, 13), Colour::rgb(255, 0, 0));
+        assert_eq!(at(21, 14), Colour::rgb(0, 255, 0));
+        // The small image is not scaled up.
+        assert_eq!(at(32, 10), Colour::rgb(0, 0, 255));
+        assert_eq!(at(31, 10), Colour::rgb(0, 255, 0));
+        // Captions are drawn below the thumbnails, unless not even an ellipsis fits.
+        assert!(sheet.row(30)[2..22].contains(&Colour::BLACK));
+        assert!(!sheet.row(30)[24..44].contains(&Colour::BLACK));
+        assert!(!sheet.row(64)[2..22].contains(&Colour::BLACK));
+
+        let empty = Montage::new(3, Dim::square(20)).render::<&str>(&[]);
+        assert_eq!(empty.get_dimensions(), Dim::square(8));
+    }
+
+    #[test]
+    fn shortens_captions() {
+        let montage = Montage::new(1, Dim { w: 40, h: 10 });
+        assert_eq!(montage.fit_caption("fits!"), "fits!");
+        assert_eq!(montage.fit_caption("too long"), "to...");
+        assert_eq!(Montage::new(1, Dim::square(8)).fit_caption("wide"), "");
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn captions_files_with_their_names() {
+        let dir = std::env::temp_dir().join("kodak_montage");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("cell.png");
+        Image::blank_with_colour(Dim::square(12), Colour::rgb(200, 0, 0))
+            .save(&path)
+            .unwrap();
+
+        let sheet = Montage::contact_sheet(&[&path, &path], 1, Dim { w: 64, h: 12 }).unwrap();
+        let caption = Image::blank_with_colour(Dim { w: 64, h: 8 }, Colour::WHITE)
+            .draw_text_bitmap("cell.png", Loc { x: 0, y: 0 }, 1, Colour::BLACK);
+        let drawn = sheet.clone().crop_unclamped(crate::Region {
+            l: Loc { x: 8, y: 24 },
+            d: Dim { w: 64, h: 8 },
+        });
+        assert_eq!(drawn, caption);
+        assert_eq!(sheet.get_dimensions(), Dim { w: 80, h: 72 });
+
+        assert!(Montage::contact_sheet(&[dir.join("missing.png")], 1, Dim::square(8)).is_err());
+    }
+}
@@ -0,0 +1,180 @@
+//! Palettes of colours picked from images, and their export to the swatch formats of design
+//! tools.
+
+use crate::{Colour, Dim, Image, Loc, Region};
+#[cfg(not(target_arch = "wasm32"))]
+use std::io;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+/// An ordered list of colours, such as the dominant colours of a photo.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use kodak::*;
+/// let photo = Image::load_png("assets/olle_voader.png").unwrap();
+/// let palette = Palette::extract(&photo, 6);
+/// palette.to_swatch_image(Dim::square(32)).save_png("swatches.png").unwrap();
+/// palette.save_gpl("olle.gpl").unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Palette {
+    /// The colours, in order.
+    colours: Vec<Colour>,
+}
+
+impl Palette {
+    /// Creates a palette of the given colours, in the given order.
+    pub fn new(colours: Vec<Colour>) -> Self {
+        Palette { colours }
+    }
+
+    /// Picks the at most `n` colours, clamped to between 1 and 256, that best represent
+    /// `img`, most common first.
+    ///
+    /// These are the colours that `Image::quantize()` reduces the image to, chosen by median
+    /// cut. Colours that no pixel ends up closest to are left out, so the palette of an empty
+    /// image is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let img = Image::blank_with_colour(Dim { w: 3, h: 1 }, Colour::WHITE)
+    ///     .fill_region(Region::from_top_left(Dim::square(1)), Colour::BLACK);
+    /// assert_eq!(Palette::extract(&img, 4).colours(), [Colour::WHITE, Colour::BLACK]);
+    /// ```
+    pub fn extract(img: &Image, n: usize) -> Self {
+        let (palette, indices) = img.palette(n);
+        let mut counts = vec![0_usize; palette.len()];
+        for &i in &indices {
+            counts[i as usize] += 1;
+        }
+        let mut order: Vec<usize> = (0..palette.len()).filter(|&i| counts[i] > 0).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(counts[i]));
+        Palette::new(order.into_iter().map(|i| palette[i]).collect())
+    }
+
+    /// Returns the colours of the palette, in order.
+    pub fn colours(&self) -> &[Colour] {
+        &self.colours
+    }
+
+    /// Draws the palette as a row of `cell_dim` sized cells, one for each colour, from left to
+    /// right.
+    pub fn to_swatch_image(&self, cell_dim: Dim) -> Image {
+        let dim = Dim {
+            w: cell_dim.w * self.colours.len() as u32,
+            h: cell_dim.h,
+        };
+        let mut img = Image::blank_with_colour(dim, Colour::TRANSPARENT);
+        for (i, &c) in self.colours.iter().enumerate() {
+            let cell = Region {
+                l: Loc {
+                    x: cell_dim.w * i as u32,
+                    y: 0,
+                },
+                d: cell_dim,
+            };
+            img = img.fill_region(cell, c);
+        }
+        img
+    }
+
+    /// Saves the palette as a GIMP palette, which GIMP, Inkscape and Krita read. Every colour
+    /// is named after its hex code. The format has no alpha, so it is left out.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_gpl(&self, file_name: impl AsRef<Path>) -> io::Result<()> {
+        let mut data = String::from("GIMP Palette\nName: kodak\n#\n");
+        for c in &self.colours {
+            data += &format!(
+                "{:3} {:3} {:3}\t#{:02x}{:02x}{:02x}\n",
+                c.r, c.g, c.b, c.r, c.g, c.b
+            );
+        }
+        std::fs::write(file_name, data)
+    }
+
+    /// Saves the palette as an Adobe Swatch Exchange file, which Photoshop, Illustrator and
+    /// Affinity read. Every colour is stored as an RGB swatch named after its hex code. The
+    /// format has no alpha, so it is left out.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save_ase(&self, file_name: impl AsRef<Path>) -> io::Result<()> {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"ASEF");
+        // Version 1.0.
+        data.extend_from_slice(&[0, 1, 0, 0]);
+        data.extend_from_slice(&(self.colours.len() as u32).to_be_bytes());
+        for c in &self.colours {
+            // Names are null-terminated UTF-16, with their length counted in code units.
+            let name: Vec<u16> = format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b)
+                .encode_utf16()
+                .chain([0])
+                .collect();
+            let length = 2 + 2 * name.len() + 4 + 3 * 4 + 2;
+            // A colour entry.
+            data.extend_from_slice(&[0, 1]);
+            data.extend_from_slice(&(length as u32).to_be_bytes());
+            data.extend_from_slice(&(name.len() as u16).to_be_bytes());
+            for unit in name {
+                data.extend_from_slice(&unit.to_be_bytes());
+            }
+            data.extend_from_slice(b"RGB ");
+            for v in [c.r, c.g, c.b] {
+                data.extend_from_slice(&(v as f32 / 255.0).to_be_bytes());
+            }
+            // A global colour, as opposed to a spot or process colour.
+            data.extend_from_slice(&[0, 0]);
+        }
+        std::fs::write(file_name, data)
+    }
+}
+
+#[cfg(test)]
+mod palette_tests {
+    use super::*;
+
+    #[test]
+    fn extracts_and_exports() {
+        let img = Image::blank_with_colour(Dim { w: 4, h: 2 }, Colour::rgb(10, 200, 30))
+            .fill_region(
+                Region::from_top_left(Dim { w: 3, h: 1 }),
+                Colour::rgb(255, 0, 128),
+            );
+        let palette = Palette::extract(&img, 8);
+        assert_eq!(
+            palette.colours(),
+            [Colour::rgb(10, 200, 30), Colour::rgb(255, 0, 128)]
+        );
+        assert!(Palette::extract(&Image::blank(Dim::square(0)), 8)
+            .colours()
+            .is_empty());
+
+        let swatch = palette.to_swatch_image(Dim { w: 2, h: 3 });
+        assert_eq!(swatch.get_dimensions(), Dim { w: 4, h: 3 });
+        assert_eq!(
+            swatch.get_pixel(Loc { x: 1, y: 2 }),
+            Ok(Colour::rgb(10, 200, 30))
+        );
+        assert_eq!(
+            swatch.get_pixel(Loc { x: 2, y: 0 }),
+            Ok(Colour::rgb(255, 0, 128))
+        );
+
+        let gpl = std::env::temp_dir().join("kodak_palette.gpl");
+        palette.save_gpl(&gpl).unwrap();
+        let text = std::fs::read_to_string(&gpl).unwrap();
+        assert!(text.starts_with("GIMP Palette\n"));
+        assert!(text.ends_with(" 10 200  30\t#0ac81e\n255   0 128\t#ff0080\n"));
+
+        let ase = std::env::temp_dir().join("kodak_palette.ase");
+        palette.save_ase(&ase).unwrap();
+        let data = std::fs::read(&ase).unwrap();
+        assert_eq!(&data[..12], b"ASEF\0\x01\0\0\0\0\0\x02");
+        // Each entry is a 6 byte header and a block of 2 + 16 + 4 + 12 + 2 bytes.
+        assert_eq!(data.len(), 12 + 2 * (6 + 36));
+        let red = f32::from_be_bytes(data[12 + 6 + 2 + 16 + 4..][..4].try_into().unwrap());
+        assert!((red - 10.0 / 255.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,405 @@
+//! Lazy chains of operations that are executed in as few passes over the pixels as possible.
+
+use crate::{colour_matrix, simd, Colour, Curve, Dim, Image, Loc, Region, ResizeFilter};
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
+
+/// A single recorded operation.
+#[derive(Clone)]
+enum Op {
+    Crop(Region),
+    Resize(Dim, ResizeFilter),
+    FlipHorizontal,
+    FlipVertical,
+    /// Lookup tables for the red, green and blue channels.
+    Scale(Box<[[u8; 256]; 3]>),
+    Multiply(Colour),
+    Map(Arc<dyn Fn(Colour) -> Colour + Send + Sync>),
+    Overlay(Image, Loc),
+}
+
+impl Op {
+    /// Returns whether the operation only changes colours, regardless of where they are.
+    fn is_colour(&self) -> bool {
+        matches!(self, Op::Scale(_) | Op::Multiply(_) | Op::Map(_))
+    }
+
+    /// Applies a per-pixel operation to the colour at `loc`.
+    fn apply(&self, colour: Colour, loc: Loc) -> Colour {
+        match self {
+            Op::Scale(tables) => Colour {
+                r: tables[0][colour.r as usize],
+                g: tables[1][colour.g as usize],
+                b: tables[2][colour.b as usize],
+                a: colour.a,
+            },
+            Op::Multiply(tint) => colour * *tint,
+            Op::Map(f) => f(colour),
+            Op::Overlay(top, offset) => {
+                let (Some(x), Some(y)) = (loc.x.checked_sub(offset.x), loc.y.checked_sub(offset.y))
+                else {
+                    return colour;
+                };
+                match top.get_pixel(Loc { x, y }) {
+                    Ok(t) => t.over(colour),
+                    Err(_) => colour,
+                }
+            }
+            _ => unreachable!("Only per-pixel operations are applied to colours."),
+        }
+    }
+}
+
+/// A step that decides which pixel of the previous step ends up at a location.
+enum Geometry {
+    Offset(Loc),
+    FlipHorizontal(u32),
+    FlipVertical(u32),
+    Nearest { src: Dim, scale: (f32, f32) },
+}
+
+impl Geometry {
+    /// Maps a location after this step to the location it came from.
+    fn source(&self, loc: Loc) -> Loc {
+        match *self {
+            Geometry::Offset(l) => Loc {
+                x: loc.x + l.x,
+                y: loc.y + l.y,
+            },
+            Geometry::FlipHorizontal(w) => Loc {
+                x: w - 1 - loc.x,
+                y: loc.y,
+            },
+            Geometry::FlipVertical(h) => Loc {
+                x: loc.x,
+                y: h - 1 - loc.y,
+            },
+            // The same sampling as `Image::resize()` with `ResizeFilter::Nearest`.
+            Geometry::Nearest { src, scale } => Loc {
+                x: (((loc.x as f32 + 0.5) * scale.0) as u32).min(src.w - 1),
+                y: (((loc.y as f32 + 0.5) * scale.1) as u32).min(src.h - 1),
+            },
+        }
+    }
+}
+
+/// A run of operations that is executed in a single pass: first every geometric step, then
+/// every per-pixel step.
+struct Stage<'a> {
+    geometry: Vec<Geometry>,
+    pixel_ops: Vec<&'a Op>,
+    dimension: Dim,
+}
+
+impl<'a> Stage<'a> {
+    fn new(dimension: Dim) -> Self {
+        Stage {
+            geometry: Vec::new(),
+            pixel_ops: Vec::new(),
+            dimension,
+        }
+    }
+
+    /// Returns whether a geometric step can still be added. Colour operations do not care where
+    /// a pixel ends up, so they can be moved past it; overlays cannot.
+    fn accepts_geometry(&self) -> bool {
+        self.pixel_ops.iter().all(|op| op.is_colour())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.geometry.is_empty() && self.pixel_ops.is_empty()
+    }
+
+    /// Computes every pixel of the stage from `img`.
+    fn run(&self, img: &Image) -> Image {
+        let (w, h) = (self.dimension.w, self.dimension.h);
+        let mut pixels = Vec::with_capacity(w as usize * h as usize);
+        for y in 0..h {
+            for x in 0..w {
+                let loc = Loc { x, y };
+                let src = self.geometry.iter().rev().fold(loc, |l, g| g.source(l));
+                let colour = img.pixels[src.as_index(img.get_dimensions())];
+                pixels.push(self.pixel_ops.iter().fold(colour, |c, op| op.apply(c, loc)));
+            }
+        }
+        Image {
+            width: w,
+            height: h,
+            pixels: pixels.into(),
+            colour_profile: img.colour_profile.clone(),
+            metadata: img.metadata.clone(),
+        }
+    }
+}
+
+/// A recorded chain of operations that is applied to an image all at once.
+///
+/// Calling `Image` functions one after another allocates and fills a whole new image for every
+/// call. A pipeline instead fuses crops, flips, nearest-neighbour resizes, colour adjustments
+/// and overlays into a single pass that computes each output pixel straight from the input.
+/// Only bilinear resizes, which need the neighbours of a pixel, and geometric operations that
+/// follow an overlay start a new pass. The result is the same as chaining the calls.
+///
+/// A pipeline can be reused for any number of images.
+///
+/// # Examples
+///
+/// ```
+/// # use kodak::*;
+/// let thumbnail = Pipeline::new()
+///     .crop(Region { l: Loc { x: 10, y: 10 }, d: Dim::square(200) })
+///     .resize(Dim::square(50), ResizeFilter::Nearest)
+///     .brightness(1.2)
+///     .flip_horizontal();
+///
+/// let img = Image::blank_with_colour(Dim::square(400), Colour::rgb(100, 50, 0));
+/// let small = thumbnail.apply(&img).unwrap();
+/// assert_eq!(small.get_dimensions(), Dim::square(50));
+/// assert_eq!(small.get_pixel(Loc { x: 0, y: 0 }).unwrap(), Colour::rgb(120, 60, 0));
+/// ```
+#[derive(Clone, Default)]
+pub struct Pipeline {
+    ops: Vec<Op>,
+}
+
+impl fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("operations", &self.ops.len())
+            .finish()
+    }
+}
+
+impl Pipeline {
+    /// Creates an empty pipeline, which leaves images as they are.
+    pub fn new() -> Self {
+        Pipeline::default()
+    }
+
+    fn then(mut self, op: Op) -> Self {
+        self.ops.push(op);
+        self
+    }
+
+    /// Crops a region out of the image, like `Image::crop()`.
+    pub fn crop(self, region: Region) -> Self {
+        self.then(Op::Crop(region))
+    }
+
+    /// Scales the image to the given dimensions, like `Image::resize()`.
+    pub fn resize(self, dimension: Dim, filter: ResizeFilter) -> Self {
+        self.then(Op::Resize(dimension, filter))
+    }
+
+    /// Mirrors the image left to right.
+    pub fn flip_horizontal(self) -> Self {
+        self.then(Op::FlipHorizontal)
+    }
+
+    /// Mirrors the image top to bottom.
+    pub fn flip_vertical(self) -> Self {
+        self.then(Op::FlipVertical)
+    }
+
+    /// Scales the red, green and blue channels by the same factor.
+    pub fn brightness(self, factor: f32) -> Self {
+        self.adjust_channels(factor, factor, factor)
+    }
+
+    /// Scales the red, green and blue channels by their own factor, like
+    /// `Image::adjust_channels()`.
+    pub fn adjust_channels(self, red: f32, green: f32, blue: f32) -> Self {
+        let tables = [
+            simd::scale_table(red),
+            simd::scale_table(green),
+            simd::scale_table(blue),
+        ];
+        self.then(Op::Scale(Box::new(tables)))
+    }
+
+    /// Maps the red, green and blue channels through a tone curve, like `Image::apply_curve()`.
+    pub fn apply_curve(self, curve: &Curve) -> Self {
+        self.then(Op::Scale(Box::new(curve.tables())))
+    }
+
+    /// Transforms the colours with a 3 by 4 affine colour matrix, like
+    /// `Image::apply_colour_matrix()`.
+    pub fn apply_colour_matrix(self, m: [[f32; 4]; 3]) -> Self {
+        self.map(move |c| colour_matrix::transform(c, &m))
+    }
+
+    /// Multiplies every pixel by a colour, like `Image::multiply()`.
+    pub fn multiply(self, colour: Colour) -> Self {
+        self.then(Op::Multiply(colour))
+    }
+
+    /// Replaces every colour with the result of `f`.
+    pub fn map<F: Fn(Colour) -> Colour + Send + Sync + 'static>(self, f: F) -> Self {
+        self.then(Op::Map(Arc::new(f)))
+    }
+
+    /// Composites `top` over the image with its top left corner at `offset`, like
+    /// `Image::overlay()`. Parts of `top` that fall outside of the image are cut off.
+    pub fn overlay(self, top: Image, offset: Loc) -> Self {
+        self.then(Op::Overlay(top, offset))
+    }
+
+    /// Runs the pipeline on an image.
+    ///
+    /// This returns an `Err` if a crop starts outside of the image as it is at that point.
+    /// Crops that reach past the edge are shrunk to fit, as with `Image::crop()`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(ops = self.ops.len(), width = img.width, height = img.height)
+        )
+    )]
+    pub fn apply(&self, img: &Image) -> Result<Image, &'static str> {
+        let mut current = Cow::Borrowed(img);
+        let mut stage = Stage::new(img.get_dimensions());
+
+        for op in &self.ops {
+            let geometric = matches!(
+                op,
+                Op::Crop(_)
+                    | Op::FlipHorizontal
+                    | Op::FlipVertical
+                    | Op::Resize(_, ResizeFilter::Nearest)
+            );
+            if geometric && !stage.accepts_geometry() {
+                current = Cow::Owned(stage.run(&current));
+                stage = Stage::new(current.get_dimensions());
+            }
+
+            let dim = stage.dimension;
+            match op {
+                Op::Crop(region) => {
+                    if region.l.x >= dim.w || region.l.y >= dim.h {
+                        return Err("The corner from which to crop falls outside of the image.");
+                    }
+                    stage.geometry.push(Geometry::Offset(region.l));
+                    stage.dimension = Dim {
+                        w: region.d.w.min(dim.w - region.l.x),
+                        h: region.d.h.min(dim.h - region.l.y),
+                    };
+                }
+                Op::FlipHorizontal => stage.geometry.push(Geometry::FlipHorizontal(dim.w)),
+                Op::FlipVertical => stage.geometry.push(Geometry::FlipVertical(dim.h)),
+                // An empty image has no pixel to map back to, so it goes through
+                // `Image::resize()` like the other filters.
+                Op::Resize(target, ResizeFilter::Nearest) if dim.w > 0 && dim.h > 0 => {
+                    stage.geometry.push(Geometry::Nearest {
+                        src: dim,
+                        scale: (
+                            dim.w as f32 / target.w as f32,
+                            dim.h as f32 / target.h as f32,
+                        ),
+                    });
+                    stage.dimension = *target;
+                }
+                Op::Resize(target, filter) => {
+                    if !stage.is_empty() {
+                        current = Cow::Owned(stage.run(&current));
+                    }
+                    current = Cow::Owned(current.resize(*target, *filter));
+                    stage = Stage::new(*target);
+                }
+                _ => stage.pixel_ops.push(op),
+            }
+        }
+
+        Ok(if stage.is_empty() {
+            current.into_owned()
+        } else {
+            stage.run(&current)
+        })
+    }
+}
+
+#[cfg(test)]
+mod pipeline_tests {
+    use super::*;
+
+    fn test_image() -> Image {
+        Image::blank_with_colour(Dim { w: 30, h: 20 }, Colour::rgb(40, 90, 160)).fill_region(
+            Region {
+                l: Loc { x: 3, y: 2 },
+                d: Dim { w: 9, h: 13 },
+            },
+            Colour::rgba(250, 200, 10, 180),
+        )
+    }
+
+    #[test]
+    fn matches_chained_calls() {
+        let img = test_image();
+        let top = Image::blank_with_colour(Dim::square(6), Colour::rgba(0, 0, 255, 100));
+        let region = Region {
+            l: Loc { x: 2, y: 1 },
+            d: Dim { w: 40, h: 15 },
+        };
+        let invert = |c: Colour| Colour::rgba(255 - c.r, 255 - c.g, 255 - c.b, c.a);
+        let curve =
+            Curve::new(&[(0, 20), (128, 100), (255, 240)]).with_green(&[(0, 255), (255, 0)]);
+
+        let fused = Pipeline::new()
+            .adjust_channels(1.3, 0.9, 0.5)
+            .apply_curve(&curve)
+            .crop(region)
+            .resize(Dim { w: 17, h: 31 }, ResizeFilter::Nearest)
+            .map(invert)
+            .flip_horizontal()
+            .overlay(top.clone(), Loc { x: 4, y: 20 })
+            .flip_vertical()
+            .resize(Dim { w: 12, h: 12 }, ResizeFilter::Bilinear)
+            .multiply(Colour::rgba(255, 128, 255, 200))
+            .apply(&img)
+            .unwrap();
+
+        let chained = img
+            .adjust_channels(1.3, 0.9, 0.5)
+            .apply_curve(&curve)
+            .crop(region)
+            .unwrap()
+            .resize(Dim { w: 17, h: 31 }, ResizeFilter::Nearest);
+        let chained = Image {
+            pixels: chained.pixels.iter().map(|&c| invert(c)).collect(),
+            ..chained
+        }
+        .flip_horizontal()
+        .overlay(top, Loc { x: 4, y: 20 })
+        .flip_vertical()
+        .resize(Dim { w: 12, h: 12 }, ResizeFilter::Bilinear)
+        .multiply(Colour::rgba(255, 128, 255, 200));
+
+        assert_eq!(fused.get_dimensions(), chained.get_dimensions());
+        assert_eq!(fused.pixels, chained.pixels);
+    }
+
+    #[test]
+    fn crop_outside_is_an_error() {
+        let img = test_image();
+        let pipeline = Pipeline::new()
+            .resize(Dim::square(10), ResizeFilter::Nearest)
+            .crop(Region {
+                l: Loc { x: 12, y: 0 },
+                d: Dim::square(2),
+            });
+
+        assert!(pipeline.apply(&img).is_err());
+        assert_eq!(Pipeline::new().apply(&img).unwrap().pixels, img.pixels);
+    }
+
+    #[test]
+    fn resizing_empty_image_gives_blank() {
+        let img = Image::blank(Dim { w: 0, h: 4 });
+        let pipeline = Pipeline::new()
+            .flip_horizontal()
+            .resize(Dim::square(3), ResizeFilter::Nearest);
+
+        assert_eq!(pipeline.apply(&img).unwrap(), Image::blank(Dim::square(3)));
+    }
+}
@@ -0,0 +1,110 @@
+//! Ready-made looks, built from tone curves, colour matrices, vignettes and grain.
+
+use crate::{Curve, Image};
+
+/// A ready-made look for `Image::apply_preset()` and `filters::preset()`, in the style of photo
+/// sharing apps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// Strong contrast, saturated colours with a green cast in the shadows, and dark corners,
+    /// like a toy camera.
+    Lomo,
+    /// Lifted, slightly warm shadows, soft highlights and muted colours, like an instant photo.
+    Polaroid,
+    /// High-contrast black and white with grain.
+    Noir,
+    /// More saturation and a little more contrast, for colours that pop.
+    Vivid,
+    /// Washed-out blacks and whites and pale colours, like an old print.
+    Faded,
+}
+
+// The following impl block defines preset functions for Images.
+impl Image {
+    /// Gives the image a ready-made look, as a single call.
+    ///
+    /// Every preset is a combination of the methods it is built from, such as `apply_curve()`,
+    /// `saturate()`, `vignette()` and `add_grain()`, which can be used to tune a look of one's
+    /// own. Presets that add grain always add the same grain. Alpha is left alone.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use kodak::*;
+    /// let photo = Image::load_png("assets/olle_voader.png").unwrap();
+    /// photo.apply_preset(Preset::Polaroid).save_png("olle_polaroid.png").unwrap();
+    /// ```
+    pub fn apply_preset(self, preset: Preset) -> Image {
+        match preset {
+            Preset::Lomo => self
+                .apply_curve(
+                    &Curve::new(&[(0, 0), (64, 40), (192, 220), (255, 255)]).with_green(&[
+                        (0, 16),
+                        (128, 134),
+                        (255, 255),
+                    ]),
+                )
+                .saturate(1.3)
+                .vignette(0.7),
+            Preset::Polaroid => self
+                .apply_curve(
+                    &Curve::new(&[(0, 28), (128, 136), (255, 240)])
+                        .with_red(&[(0, 10), (255, 255)])
+                        .with_blue(&[(0, 0), (255, 230)]),
+                )
+                .saturate(0.85)
+                .vignette(0.2)
+                .add_grain(0.03, 0),
+            Preset::Noir => self
+                .saturate(0.0)
+                .apply_curve(&Curve::new(&[(0, 0), (70, 40), (180, 215), (255, 255)]))
+                .vignette(0.4)
+                .add_grain(0.08, 0),
+            Preset::Vivid => self
+                .apply_curve(&Curve::new(&[(0, 0), (64, 58), (192, 200), (255, 255)]))
+                .saturate(1.4),
+            Preset::Faded => self
+                .apply_curve(&Curve::new(&[(0, 48), (128, 134), (255, 225)]))
+                .saturate(0.7)
+                .add_grain(0.02, 0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod preset_tests {
+    use super::*;
+    use crate::{Colour, Dim, Loc};
+
+    #[test]
+    fn presets_have_their_looks() {
+        let photo = Image::from_pixels(
+            Dim { w: 16, h: 16 },
+            (0..256)
+                .map(|i| Colour::rgba(i as u8, 160, 255 - i as u8, 200))
+                .collect(),
+        );
+        let centre = |img: &Image| img.get_pixel(Loc { x: 8, y: 8 }).unwrap();
+        let spread = |c: Colour| c.r.max(c.g).max(c.b) - c.r.min(c.g).min(c.b);
+
+        let noir = photo.clone().apply_preset(Preset::Noir);
+        assert!(noir
+            .pixels
+            .iter()
+            .all(|c| c.r == c.g && c.g == c.b && c.a == 200));
+
+        let faded = photo.clone().apply_preset(Preset::Faded);
+        assert!(faded.pixels.iter().all(|c| c.r >= 30 && c.b <= 240));
+        assert!(spread(centre(&faded)) < spread(centre(&photo)));
+
+        let vivid = photo.clone().apply_preset(Preset::Vivid);
+        assert!(spread(centre(&vivid)) > spread(centre(&photo)));
+
+        let lomo = photo.clone().apply_preset(Preset::Lomo);
+        let corner = lomo.get_pixel(Loc { x: 0, y: 0 }).unwrap();
+        assert!(corner.g < photo.pixels[0].g / 2);
+
+        let polaroid = photo.clone().apply_preset(Preset::Polaroid);
+        assert_eq!(polaroid, photo.apply_preset(Preset::Polaroid));
+    }
+}
@@ -0,0 +1,96 @@
+//! Colour profile handling for images read from and written to files.
+
+#[cfg(feature = "png")]
+use crate::colour::linear_to_srgb;
+#[cfg(feature = "png")]
+use crate::Colour;
+use crate::Image;
+use alloc::vec::Vec;
+
+/// Describes the colour space that the pixels of an Image are in.
+///
+/// Images without a profile are assumed to be sRGB, which is what every function in Kodak
+/// expects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColourProfile {
+    /// The pixels are explicitly tagged as sRGB. When saving, an sRGB chunk is embedded.
+    Srgb,
+    /// The pixels are described by an ICC profile, stored here as raw bytes.
+    ///
+    /// Kodak does not perform ICC colour management; the pixel values are kept as they are and
+    /// the profile is embedded again on save, so that other software shows the right colours.
+    Icc(Vec<u8>),
+}
+
+/// Converts pixels that were encoded with the given PNG gamma (as stored in a gAMA chunk, e.g.
+/// 0.45455 for a 2.2 display gamma) to sRGB.
+#[cfg(feature = "png")]
+pub(crate) fn gamma_to_srgb(pixels: &mut [Colour], file_gamma: f32) {
+    let lut: Vec<u8> = (0..=255u8)
+        .map(|v| linear_to_srgb((v as f32 / 255.0).powf(1.0 / file_gamma)))
+        .collect();
+
+    for c in pixels.iter_mut() {
+        c.r = lut[c.r as usize];
+        c.g = lut[c.g as usize];
+        c.b = lut[c.b as usize];
+    }
+}
+
+// The following impl block defines colour profile functions for Images.
+impl Image {
+    /// Returns the colour profile attached to the image, if any.
+    ///
+    /// PNGs with an iCCP chunk keep their ICC profile, PNGs with an sRGB chunk (or a gAMA chunk,
+    /// which is converted to sRGB while loading) are tagged as `ColourProfile::Srgb`.
+    pub fn colour_profile(&self) -> Option<&ColourProfile> {
+        self.colour_profile.as_ref()
+    }
+
+    /// Attaches a colour profile to the image, replacing any existing one.
+    ///
+    /// This does not change any pixel values; it only changes how the image is tagged when it is
+    /// saved.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use kodak::*;
+    /// // Embed an sRGB chunk in the output.
+    /// # #[cfg(feature = "png")]
+    /// Image::blank(Dim::square(16))
+    ///     .with_colour_profile(Some(ColourProfile::Srgb))
+    ///     .save_png("srgb.png").unwrap();
+    /// ```
+    pub fn with_colour_profile(self, profile: Option<ColourProfile>) -> Image {
+        Image {
+            colour_profile: profile,
+            ..self
+        }
+    }
+}
+
+#[cfg(all(test, feature = "png"))]
+mod profile_tests {
+    use super::*;
+
+    #[test]
+    fn linear_gamma_is_brightened() {
+        let mut pixels = vec![Colour::rgb(0, 128, 255)];
+        gamma_to_srgb(&mut pixels, 1.0);
+        assert_eq!(pixels[0].r, 0);
+        assert!(pixels[0].g > 180);
+        assert_eq!(pixels[0].b, 255);
+    }
+
+    #[test]
+    fn profile_survives_png_round_trip() {
+        let path = std::env::temp_dir().join("kodak_profile_round_trip.png");
+        Image::blank(crate::Dim::square(4))
+            .with_colour_profile(Some(ColourProfile::Srgb))
+            .save_png(&path)
+            .unwrap();
+        let loaded = Image::load_png(&path).unwrap();
+        assert_eq!(loaded.colour_profile(), Some(&ColourProfile::Srgb));
+    }
+}
@@ -0,0 +1,78 @@
+//! Cancelling long-running operations, such as batches, decodes of huge files, resizing and
+//! template matching, from another thread.
+
+use core::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// The error returned by image operations that were stopped through a `CancelToken`.
+pub(crate) const CANCELLED: &str = "The operation was cancelled.";
+
+/// A flag that asks a long-running operation to stop early, for the cancel button of a GUI or
+/// the Ctrl-C handler of a command line tool.
+///
+/// Clones share the same flag, so one clone can be handed to the operation and another kept to
+/// cancel it. Operations check the flag between units of work, such as files or rows, and stop
+/// at the next check after it has been set.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use kodak::*;
+/// let cancel = CancelToken::new();
+/// let handle = cancel.clone();
+/// std::thread::spawn(move || {
+///     std::thread::sleep(std::time::Duration::from_secs(5));
+///     handle.cancel();
+/// });
+/// let result = Image::load_png_with_progress("assets/huge_scan.png", |done| {
+///     println!("{:.0}%", done * 100.0);
+///     cancel.control_flow()
+/// });
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    /// Creates a token that has not been cancelled.
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    /// Asks the operations watching this token to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether `cancel()` has been called on this token or one of its clones.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Returns `ControlFlow::Break` once the token has been cancelled, and
+    /// `ControlFlow::Continue` before, to return from a progress callback.
+    pub fn control_flow(&self) -> ControlFlow<()> {
+        if self.is_cancelled() {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod progress_tests {
+    use super::*;
+
+    #[test]
+    fn clones_share_the_flag() {
+        let token = CancelToken::new();
+        let clone = token.clone();
+        assert!(!token.is_cancelled());
+        assert_eq!(token.control_flow(), ControlFlow::Continue(()));
+        clone.cancel();
+        assert!(token.is_cancelled());
+        assert_eq!(token.control_flow(), ControlFlow::Break(()));
+        assert!(!CancelToken::new().is_cancelled());
+    }
+}
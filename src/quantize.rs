@@ -0,0 +1,203 @@
+//! Colour quantisation: reducing an image to a small palette, for indexed file formats.
+
+use crate::{Colour, Image};
+use alloc::vec::Vec;
+
+/// A run of distinct colours, with how often each occurs, that becomes one palette entry.
+struct ColourBox<'a> {
+    colours: &'a mut [(Colour, u64)],
+}
+
+impl ColourBox<'_> {
+    /// Returns the channel (0 to 3 for red, green, blue and alpha) with the widest range of
+    /// values, along with that range.
+    fn widest_channel(&self) -> (usize, u8) {
+        (0..4)
+            .map(|channel| {
+                let values = self.colours.iter().map(|(c, _)| channel_value(*c, channel));
+                let (min, max) = values.fold((u8::MAX, 0), |(lo, hi), v| (lo.min(v), hi.max(v)));
+                (channel, max.saturating_sub(min))
+            })
+            .max_by_key(|&(_, range)| range)
+            .expect("There are four channels.")
+    }
+
+    /// Returns the average of the colours, weighted by how often they occur.
+    fn average(&self) -> Colour {
+        let total: u64 = self.colours.iter().map(|(_, n)| n).sum();
+        let mean = |channel| {
+            let sum: u64 = self
+                .colours
+                .iter()
+                .map(|&(c, n)| channel_value(c, channel) as u64 * n)
+                .sum();
+            ((sum + total / 2) / total) as u8
+        };
+        Colour::rgba(mean(0), mean(1), mean(2), mean(3))
+    }
+}
+
+fn channel_value(c: Colour, channel: usize) -> u8 {
+    [c.r, c.g, c.b, c.a][channel]
+}
+
+/// Returns the index of the palette entry closest to `c`.
+fn nearest(palette: &[Colour], c: Colour) -> u8 {
+    let distance = |p: &Colour| {
+        (0..4)
+            .map(|channel| {
+                let d = channel_value(*p, channel) as i32 - channel_value(c, channel) as i32;
+                (d * d) as u32
+            })
+            .sum::<u32>()
+    };
+    (0..palette.len())
+        .min_by_key(|&i| distance(&palette[i]))
+        .expect("The palette is never empty.") as u8
+}
+
+// The following impl block defines quantisation functions for Images.
+impl Image {
+    /// Picks a palette of at most `max_colours` colours, clamped to between 1 and 256, and
+    /// returns it along with the palette index of every pixel.
+    ///
+    /// Images with few enough distinct colours keep them exactly. Otherwise the palette is
+    /// built by median cut: the colours are split into boxes along the channel with the widest
+    /// range, at the median pixel, and every box contributes its average colour. Alpha counts
+    /// as a fourth channel.
+    pub(crate) fn palette(&self, max_colours: usize) -> (Vec<Colour>, Vec<u8>) {
+        let max_colours = max_colours.clamp(1, 256);
+
+        let mut packed: Vec<u32> = self.pixels.iter().map(Colour::to_rgba_u32).collect();
+        packed.sort_unstable();
+        let mut counted: Vec<(Colour, u64)> = Vec::new();
+        for value in packed {
+            match counted.last_mut() {
+                Some((c, n)) if c.to_rgba_u32() == value => *n += 1,
+                _ => counted.push((Colour::from_rgba_u32(value), 1)),
+            }
+        }
+        if counted.is_empty() {
+            return (alloc::vec![Colour::TRANSPARENT], Vec::new());
+        }
+        let distinct: Vec<u32> = counted.iter().map(|(c, _)| c.to_rgba_u32()).collect();
+
+        let palette: Vec<Colour> = if counted.len() <= max_colours {
+            counted.iter().map(|&(c, _)| c).collect()
+        } else {
+            let mut boxes = alloc::vec![ColourBox {
+                colours: &mut counted[..],
+            }];
+            while boxes.len() < max_colours {
+                let Some((widest, (channel, _))) = boxes
+                    .iter()
+                    .map(ColourBox::widest_channel)
+                    .enumerate()
+                    .filter(|(_, (_, range))| *range > 0)
+                    .max_by_key(|(_, (_, range))| *range)
+                else {
+                    break;
+                };
+                let splitting = boxes.swap_remove(widest);
+                let colours = splitting.colours;
+                colours.sort_unstable_by_key(|(c, _)| channel_value(*c, channel));
+
+                // Split where half of the box's pixels lie on either side, keeping both halves
+                // non-empty.
+                let half = colours.iter().map(|(_, n)| n).sum::<u64>() / 2;
+                let mut seen = 0;
+                let mut at = colours.len() - 1;
+                for (i, (_, n)) in colours.iter().enumerate() {
+                    seen += n;
+                    if seen > half {
+                        at = i;
+                        break;
+                    }
+                }
+                let (low, high) = colours.split_at_mut(at.max(1));
+                boxes.push(ColourBox { colours: low });
+                boxes.push(ColourBox { colours: high });
+            }
+            boxes.iter().map(ColourBox::average).collect()
+        };
+
+        // Every distinct colour is matched once; pixels look theirs up.
+        let lookup: Vec<u8> = distinct
+            .iter()
+            .map(|&value| nearest(&palette, Colour::from_rgba_u32(value)))
+            .collect();
+        let indices = self
+            .pixels
+            .iter()
+            .map(|c| {
+                let i = distinct
+                    .binary_search(&c.to_rgba_u32())
+                    .expect("Every pixel's colour was counted.");
+                lookup[i]
+            })
+            .collect();
+        (palette, indices)
+    }
+
+    /// Reduces the image to at most `max_colours` colours, clamped to between 1 and 256, chosen
+    /// by median cut. Every pixel is replaced by the closest colour in the palette.
+    ///
+    /// This is what `save_png_indexed()` stores, so it previews how such a file will look.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// // Eight shades of grey, side by side.
+    /// let mut img = Image::blank(Dim { w: 8, h: 1 });
+    /// for x in 0..8 {
+    ///     let grey = x as u8 * 32;
+    ///     let pixel = Region { l: Loc { x, y: 0 }, d: Dim::square(1) };
+    ///     img = img.fill_region(pixel, Colour::rgb(grey, grey, grey));
+    /// }
+    /// let posterised = img.quantize(4);
+    /// let mut colours: Vec<u32> = posterised.row(0).iter().map(Colour::to_u32).collect();
+    /// colours.dedup();
+    /// assert_eq!(colours.len(), 4);
+    /// ```
+    pub fn quantize(self, max_colours: usize) -> Image {
+        let (palette, indices) = self.palette(max_colours);
+        let pixels = indices.iter().map(|&i| palette[i as usize]).collect();
+        Image { pixels, ..self }
+    }
+}
+
+#[cfg(test)]
+mod quantize_tests {
+    use super::*;
+    use crate::{Dim, Loc, Region};
+
+    #[test]
+    fn keeps_few_colours_exactly() {
+        let img = Image::blank_with_colour(Dim { w: 4, h: 2 }, Colour::rgba(9, 8, 7, 6))
+            .fill_region(
+                Region {
+                    l: Loc { x: 1, y: 1 },
+                    d: Dim::square(1),
+                },
+                Colour::WHITE,
+            );
+        let (palette, indices) = img.palette(256);
+        assert_eq!(palette, [Colour::rgba(9, 8, 7, 6), Colour::WHITE]);
+        assert_eq!(indices, [0, 0, 0, 0, 0, 1, 0, 0]);
+        assert_eq!(img.clone().quantize(2), img);
+    }
+
+    #[test]
+    fn median_cut_reduces_colours() {
+        let pixels: Vec<Colour> = (0..=255).map(|v| Colour::rgb(v, 255 - v, v / 2)).collect();
+        let img = Image::from_pixels(Dim { w: 256, h: 1 }, pixels);
+        let (palette, indices) = img.palette(16);
+        assert_eq!(palette.len(), 16);
+        assert!(indices.iter().all(|&i| (i as usize) < palette.len()));
+        // Every pixel ends up close to where it was.
+        let quantized = img.clone().quantize(16);
+        assert!(quantized.approx_eq(&img, 10));
+        assert_eq!(img.palette(0).0.len(), 1);
+    }
+}
@@ -0,0 +1,109 @@
+//! A small deterministic pseudo-random number generator.
+//!
+//! Effects that need randomness take a seed so that the same inputs always produce the same
+//! output. This generator is SplitMix64, which is fast and good enough for visual purposes; it is
+//! not suitable for cryptography.
+
+use crate::{Colour, Dim, Image};
+
+/// A seeded SplitMix64 pseudo-random number generator.
+///
+/// The same seed always produces the same numbers, on every platform, which makes it suitable
+/// for reproducible test images and effects. It is not suitable for cryptography.
+///
+/// # Examples
+///
+/// ```
+/// # use kodak::*;
+/// let mut rng = Rng::new(7);
+/// let noise = Image::random(Dim::square(16), &mut rng);
+/// let tint = Colour::random(&mut rng);
+///
+/// let mut again = Rng::new(7);
+/// let same_noise = Image::random(Dim::square(16), &mut again);
+/// assert_eq!(noise, same_noise);
+/// assert_eq!(Colour::random(&mut again), tint);
+/// ```
+#[derive(Debug, Clone)]
+#[allow(missing_copy_implementations)] // copying a generator would silently repeat its numbers
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Creates a generator from a seed.
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    /// Returns the next 64 random bits.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a random number in `0..bound`. `bound` must not be zero.
+    pub fn below(&mut self, bound: u32) -> u32 {
+        (self.next_u64() % bound as u64) as u32
+    }
+
+    /// Returns a random number in `low..=high`.
+    pub fn range_i32(&mut self, low: i32, high: i32) -> i32 {
+        let span = (high as i64 - low as i64 + 1) as u64;
+        (low as i64 + (self.next_u64() % span) as i64) as i32
+    }
+
+    /// Returns a random float in `0.0..1.0`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+// The following impl block defines random generation functions for Colours.
+impl Colour {
+    /// Returns a colour with random red, green, blue and alpha channels.
+    pub fn random(rng: &mut Rng) -> Colour {
+        let [r, g, b, a, ..] = rng.next_u64().to_le_bytes();
+        Colour::rgba(r, g, b, a)
+    }
+}
+
+// The following impl block defines random generation functions for Images.
+impl Image {
+    /// Creates an image of the given size filled with random colours, alpha included.
+    pub fn random(dimension: Dim, rng: &mut Rng) -> Image {
+        let pixels = (0..dimension.w as usize * dimension.h as usize)
+            .map(|_| Colour::random(rng))
+            .collect();
+        Image::from_pixels(dimension, pixels)
+    }
+}
+
+#[cfg(test)]
+mod random_tests {
+    use super::*;
+    use crate::Loc;
+
+    #[test]
+    fn is_deterministic() {
+        let mut a = Rng::new(42);
+        let mut b = a.clone();
+        assert_eq!(Colour::random(&mut a), Colour::random(&mut b));
+        assert_ne!(Colour::random(&mut a), Colour::random(&mut Rng::new(43)));
+
+        let noise = Image::random(Dim { w: 5, h: 3 }, &mut Rng::new(1));
+        assert_eq!(noise.get_dimensions(), Dim { w: 5, h: 3 });
+        assert_ne!(
+            noise.get_pixel(Loc { x: 0, y: 0 }),
+            noise.get_pixel(Loc { x: 4, y: 2 })
+        );
+        for _ in 0..100 {
+            assert!(a.below(3) < 3);
+            assert!((-2..=2).contains(&a.range_i32(-2, 2)));
+            assert!((0.0..1.0).contains(&a.next_f32()));
+        }
+    }
+}
@@ -0,0 +1,535 @@
+//! Batch jobs described in TOML or JSON files instead of Rust. Only available with the `recipe`
+//! feature.
+//!
+//! A recipe names its inputs, where the results go and the steps that every image goes
+//! through:
+//!
+//! ```toml
+//! inputs = "sprites/*.png"     # or a list: ["a.png", "b.png"]
+//! output_dir = "build/sprites"
+//! extension = "tga"            # optional, converts the results
+//! threads = 4                  # optional, defaults to one per CPU core
+//!
+//! [[steps]]
+//! op = "resize"
+//! width = 64
+//! height = 64
+//! filter = "area"              # optional, "nearest", "bilinear" (default) or "area"
+//! fit = true                   # optional, keeps the aspect ratio
+//!
+//! [[steps]]
+//! op = "overlay"
+//! image = "frame.png"
+//! x = 0
+//! y = 0
+//! ```
+//!
+//! The other steps are `crop` (`x`, `y`, `width`, `height`), `flip_horizontal`,
+//! `flip_vertical`, `rotate` (`degrees`, optional `background`), `brightness` (`factor`),
+//! `adjust_channels` (`red`, `green`, `blue`), `multiply` (`colour`), `fill` (`colour`,
+//! optional `region = { x, y, width, height }`) and `blur` (`sigma`). Colours are written like
+//! `"#ff8000"`.
+//!
+//! The same recipe in JSON is an object with the same keys, with `steps` as an array of
+//! objects.
+
+use crate::batch::{self, BatchOptions, BatchReport, Inputs};
+use crate::{Colour, Dim, Image, Loc, Pipeline, Region, ResizeFilter};
+use serde::Deserialize;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A colour in a recipe, written as a string like `"#ff8000"`.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(try_from = "String")]
+struct RecipeColour(Colour);
+
+impl TryFrom<String> for RecipeColour {
+    type Error = &'static str;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse().map(RecipeColour)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum RecipeFilter {
+    Nearest,
+    #[default]
+    Bilinear,
+    Area,
+}
+
+impl From<RecipeFilter> for ResizeFilter {
+    fn from(filter: RecipeFilter) -> Self {
+        match filter {
+            RecipeFilter::Nearest => ResizeFilter::Nearest,
+            RecipeFilter::Bilinear => ResizeFilter::Bilinear,
+            RecipeFilter::Area => ResizeFilter::Area,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RecipeRegion {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl From<RecipeRegion> for Region {
+    fn from(r: RecipeRegion) -> Self {
+        Region {
+            l: Loc { x: r.x, y: r.y },
+            d: Dim {
+                w: r.width,
+                h: r.height,
+            },
+        }
+    }
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+fn transparent() -> RecipeColour {
+    RecipeColour(Colour::TRANSPARENT)
+}
+
+/// A step as written in a recipe file.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case", deny_unknown_fields)]
+enum Step {
+    Resize {
+        width: u32,
+        height: u32,
+        #[serde(default)]
+        filter: RecipeFilter,
+        #[serde(default)]
+        fit: bool,
+    },
+    Crop {
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    },
+    FlipHorizontal,
+    FlipVertical,
+    Rotate {
+        degrees: f32,
+        #[serde(default = "transparent")]
+        background: RecipeColour,
+    },
+    Brightness {
+        factor: f32,
+    },
+    AdjustChannels {
+        #[serde(default = "one")]
+        red: f32,
+        #[serde(default = "one")]
+        green: f32,
+        #[serde(default = "one")]
+        blue: f32,
+    },
+    Multiply {
+        colour: RecipeColour,
+    },
+    Fill {
+        colour: RecipeColour,
+        region: Option<RecipeRegion>,
+    },
+    Blur {
+        sigma: f32,
+    },
+    Overlay {
+        image: PathBuf,
+        #[serde(default)]
+        x: u32,
+        #[serde(default)]
+        y: u32,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RecipeInputs {
+    Glob(PathBuf),
+    Paths(Vec<PathBuf>),
+}
+
+/// A recipe file as written.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RecipeFile {
+    inputs: RecipeInputs,
+    output_dir: PathBuf,
+    extension: Option<String>,
+    #[serde(default)]
+    threads: usize,
+    #[serde(default)]
+    steps: Vec<Step>,
+}
+
+/// A part of a recipe that is run at once. Steps that `Pipeline` supports are fused together.
+#[derive(Debug)]
+enum Pass {
+    Pipeline(Pipeline),
+    ResizeToFit(Dim, ResizeFilter),
+    Rotate(f32, Colour),
+    Fill(Colour, Option<Region>),
+    Blur(f32),
+}
+
+/// A batch job loaded from a TOML or JSON file: which images to load, what to do with them and
+/// where to save the results. See the module documentation for the file format.
+///
+/// # Examples
+///
+/// ```no_run
+/// use kodak::recipe::Recipe;
+///
+/// let recipe = Recipe::load("assets/thumbnails.toml").unwrap();
+/// let report = recipe.run().unwrap();
+/// println!("{} images saved", report.succeeded.len());
+/// ```
+#[derive(Debug)]
+pub struct Recipe {
+    inputs: Inputs,
+    output_dir: PathBuf,
+    extension: Option<String>,
+    threads: usize,
+    passes: Vec<Pass>,
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+impl Recipe {
+    /// Parses a recipe written in TOML. Relative paths are relative to the working directory.
+    ///
+    /// This returns an `Err` if the recipe is malformed or an image it overlays could not be
+    /// loaded.
+    pub fn from_toml(text: &str) -> io::Result<Recipe> {
+        let file = toml::from_str(text).map_err(invalid_data)?;
+        Recipe::compile(file, Path::new(""))
+    }
+
+    /// Parses a recipe written in JSON. Relative paths are relative to the working directory.
+    ///
+    /// This returns an `Err` if the recipe is malformed or an image it overlays could not be
+    /// loaded.
+    pub fn from_json(text: &str) -> io::Result<Recipe> {
+        let file = serde_json::from_str(text).map_err(invalid_data)?;
+        Recipe::compile(file, Path::new(""))
+    }
+
+    /// Loads a recipe from a `.toml` or `.json` file. Relative paths in it are relative to the
+    /// directory the recipe is in.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Recipe> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase());
+        let file = match extension.as_deref() {
+            Some("toml") => toml::from_str(&text).map_err(invalid_data)?,
+            Some("json") => serde_json::from_str(&text).map_err(invalid_data)?,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "recipes must be .toml or .json files",
+                ))
+            }
+        };
+        Recipe::compile(file, path.parent().unwrap_or(Path::new("")))
+    }
+
+    fn compile(file: RecipeFile, base: &Path) -> io::Result<Recipe> {
+        let mut passes = Vec::new();
+        let mut pipeline: Option<Pipeline> = None;
+        for step in file.steps {
+            let pass = match step {
+                Step::Resize {
+                    width,
+                    height,
+                    filter,
+                    fit,
+                } => {
+                    let dim = Dim {
+                        w: width,
+                        h: height,
+                    };
+                    if width == 0 || height == 0 {
+                        return Err(invalid_data("resize steps need a size of at least 1x1"));
+                    }
+                    if !fit {
+                        pipeline = Some(
+                            pipeline
+                                .take()
+                                .unwrap_or_default()
+                                .resize(dim, filter.into()),
+                        );
+                        continue;
+                    }
+                    Pass::ResizeToFit(dim, filter.into())
+                }
+                Step::Crop {
+                    x,
+                    y,
+                    width,
+                    height,
+                } => {
+                    let region = RecipeRegion {
+                        x,
+                        y,
+                        width,
+                        height,
+                    };
+                    pipeline = Some(pipeline.take().unwrap_or_default().crop(region.into()));
+                    continue;
+                }
+                Step::FlipHorizontal => {
+                    pipeline = Some(pipeline.take().unwrap_or_default().flip_horizontal());
+                    continue;
+                }
+                Step::FlipVertical => {
+                    pipeline = Some(pipeline.take().unwrap_or_default().flip_vertical());
+                    continue;
+                }
+                Step::Brightness { factor } => {
+                    pipeline = Some(pipeline.take().unwrap_or_default().brightness(factor));
+                    continue;
+                }
+                Step::AdjustChannels { red, green, blue } => {
+                    pipeline = Some(
+                        pipeline
+                            .take()
+                            .unwrap_or_default()
+                            .adjust_channels(red, green, blue),
+                    );
+                    continue;
+                }
+                Step::Multiply { colour } => {
+                    pipeline = Some(pipeline.take().unwrap_or_default().multiply(colour.0));
+                    continue;
+                }
+                Step::Overlay { image, x, y } => {
+                    let path = base.join(image);
+                    let top = Image::open(&path).map_err(|e| {
+                        io::Error::new(e.kind(), format!("{}: {e}", path.display()))
+                    })?;
+                    pipeline = Some(
+                        pipeline
+                            .take()
+                            .unwrap_or_default()
+                            .overlay(top, Loc { x, y }),
+                    );
+                    continue;
+                }
+                Step::Rotate {
+                    degrees,
+                    background,
+                } => Pass::Rotate(degrees, background.0),
+                Step::Fill { colour, region } => Pass::Fill(colour.0, region.map(Region::from)),
+                Step::Blur { sigma } => Pass::Blur(sigma),
+            };
+            passes.extend(pipeline.take().map(Pass::Pipeline));
+            passes.push(pass);
+        }
+        passes.extend(pipeline.map(Pass::Pipeline));
+
+        let inputs = match file.inputs {
+            RecipeInputs::Glob(pattern) => Inputs::Glob(base.join(pattern)),
+            RecipeInputs::Paths(paths) => {
+                Inputs::Paths(paths.into_iter().map(|p| base.join(p)).collect())
+            }
+        };
+        Ok(Recipe {
+            inputs,
+            output_dir: base.join(file.output_dir),
+            extension: file.extension,
+            threads: file.threads,
+            passes,
+        })
+    }
+
+    /// Replaces the inputs named in the recipe, for running the same steps on other files.
+    pub fn with_inputs(self, inputs: impl Into<Inputs>) -> Self {
+        Recipe {
+            inputs: inputs.into(),
+            ..self
+        }
+    }
+
+    /// Replaces the output directory named in the recipe.
+    pub fn with_output_dir(self, output_dir: impl Into<PathBuf>) -> Self {
+        Recipe {
+            output_dir: output_dir.into(),
+            ..self
+        }
+    }
+
+    /// Returns the options the recipe passes to `batch::try_process()`, to which a progress
+    /// callback can be added.
+    pub fn batch_options(&self) -> BatchOptions {
+        let options = BatchOptions::new(&self.output_dir).with_threads(self.threads);
+        match &self.extension {
+            Some(extension) => options.with_extension(extension),
+            None => options,
+        }
+    }
+
+    /// Runs the steps of the recipe on a single image.
+    ///
+    /// This returns an `Err` if a crop starts outside of the image as it is at that point.
+    pub fn apply(&self, img: Image) -> io::Result<Image> {
+        self.passes.iter().try_fold(img, |img, pass| {
+            Ok(match pass {
+                Pass::Pipeline(pipeline) => pipeline
+                    .apply(&img)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?,
+                Pass::ResizeToFit(dim, filter) => img.resize_to_fit(*dim, *filter),
+                Pass::Rotate(degrees, background) => img.rotate(*degrees, *background),
+                Pass::Fill(colour, None) => img.fill(*colour),
+                Pass::Fill(colour, Some(region)) => img.fill_region(*region, *colour),
+                Pass::Blur(sigma) => img.blur(*sigma),
+            })
+        })
+    }
+
+    /// Runs the recipe on all of its inputs, like `batch::try_process()`.
+    pub fn run(&self) -> io::Result<BatchReport> {
+        self.run_with(self.batch_options())
+    }
+
+    /// Runs the recipe on all of its inputs with the given options, which usually come from
+    /// `batch_options()`.
+    pub fn run_with(&self, options: BatchOptions) -> io::Result<BatchReport> {
+        batch::try_process(self.inputs.clone(), &options, |img| self.apply(img))
+    }
+}
+
+#[cfg(test)]
+mod recipe_tests {
+    use super::*;
+
+    #[test]
+    fn fuses_pipeline_steps() {
+        let recipe = Recipe::from_toml(
+            r##"
+            inputs = ["a.png", "b.png"]
+            output_dir = "out"
+
+            [[steps]]
+            op = "crop"
+            x = 1
+            y = 1
+            width = 4
+            height = 2
+
+            [[steps]]
+            op = "brightness"
+            factor = 2.0
+
+            [[steps]]
+            op = "fill"
+            colour = "#00f"
+            region = { x = 0, y = 0, width = 1, height = 1 }
+
+            [[steps]]
+            op = "flip_horizontal"
+            "##,
+        )
+        .unwrap();
+        assert_eq!(recipe.passes.len(), 3);
+        assert_eq!(
+            recipe.inputs,
+            Inputs::Paths(vec!["a.png".into(), "b.png".into()])
+        );
+
+        let img = Image::blank_with_colour(Dim { w: 6, h: 4 }, Colour::rgb(50, 60, 70));
+        let result = recipe.apply(img).unwrap();
+        assert_eq!(result.get_dimensions(), Dim { w: 4, h: 2 });
+        assert_eq!(
+            result.get_pixel(Loc { x: 3, y: 0 }).unwrap(),
+            Colour::rgb(0, 0, 255)
+        );
+        assert_eq!(
+            result.get_pixel(Loc { x: 0, y: 0 }).unwrap(),
+            Colour::rgb(100, 120, 140)
+        );
+
+        let small = Image::blank(Dim::square(1));
+        assert!(recipe.apply(small).is_err());
+    }
+
+    #[test]
+    fn reports_mistakes() {
+        let base = r#""inputs": "*.png", "output_dir": "out""#;
+        assert!(Recipe::from_json(&format!("{{{base}}}")).is_ok());
+        for step in [
+            r#"{"op": "sharpen"}"#,
+            r#"{"op": "blur"}"#,
+            r#"{"op": "blur", "sigma": 1.0, "radius": 2}"#,
+            r#"{"op": "multiply", "colour": "red"}"#,
+            r#"{"op": "resize", "width": 0, "height": 10}"#,
+        ] {
+            let text = format!(r#"{{{base}, "steps": [{step}]}}"#);
+            let error = Recipe::from_json(&text).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{step}");
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "png", feature = "tga"))]
+    fn runs_relative_to_the_recipe() {
+        let dir = std::env::temp_dir().join("kodak_recipe");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("in")).unwrap();
+        for name in ["a", "b"] {
+            Image::blank_with_colour(Dim { w: 8, h: 4 }, Colour::WHITE)
+                .save(dir.join(format!("in/{name}.png")))
+                .unwrap();
+        }
+        Image::blank_with_colour(Dim::square(1), Colour::BLACK)
+            .save(dir.join("dot.png"))
+            .unwrap();
+        std::fs::write(
+            dir.join("recipe.toml"),
+            r#"
+            inputs = "in/*.png"
+            output_dir = "out"
+            extension = "tga"
+
+            [[steps]]
+            op = "resize"
+            width = 4
+            height = 4
+            fit = true
+
+            [[steps]]
+            op = "overlay"
+            image = "dot.png"
+            x = 1
+            "#,
+        )
+        .unwrap();
+
+        let report = Recipe::load(dir.join("recipe.toml"))
+            .unwrap()
+            .run()
+            .unwrap();
+        assert!(report.is_success());
+        assert_eq!(report.succeeded.len(), 2);
+        let result = Image::open(dir.join("out/b.tga")).unwrap();
+        assert_eq!(result.get_dimensions(), Dim { w: 4, h: 2 });
+        assert_eq!(result.get_pixel(Loc { x: 1, y: 0 }).unwrap(), Colour::BLACK);
+        assert_eq!(result.get_pixel(Loc { x: 0, y: 0 }).unwrap(), Colour::WHITE);
+    }
+}
@@ -0,0 +1,135 @@
+//! Recolouring: swapping one colour for another, and changing the hue of a whole image.
+
+use crate::colour::{from_oklab, to_oklab};
+use crate::mask::within_tolerance;
+use crate::{Colour, Image};
+
+/// Returns the fully saturated colour of `hue`, in degrees around the HSL colour wheel from red
+/// through green at 120 and blue at 240.
+fn pure_hue(hue: f32) -> Colour {
+    let h = hue.rem_euclid(360.0) / 60.0;
+    let x = 1.0 - (h % 2.0 - 1.0).abs();
+    let (r, g, b) = match h as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    };
+    let channel = |v: f32| (v * 255.0).round() as u8;
+    Colour::rgb(channel(r), channel(g), channel(b))
+}
+
+// The following impl block defines recolouring functions for Images.
+impl Image {
+    /// Replaces `from` with `to`, along with the colours that differ from `from` by at most
+    /// `tolerance` in every channel, alpha included.
+    ///
+    /// Colours within the tolerance keep their difference from `from`, so the slight shading
+    /// of a logo or the dithering of a sprite carries over to the new colour. With a tolerance
+    /// of 0 this swaps exactly one entry of a palette.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let red = Colour::rgb(200, 0, 0);
+    /// let img = Image::blank_with_colour(Dim { w: 2, h: 1 }, red)
+    ///     .fill_region(Region::from_top_left(Dim::square(1)), Colour::rgb(200, 10, 0))
+    ///     .replace_colour(red, Colour::rgb(0, 0, 200), 10);
+    /// assert_eq!(img.get_pixel(Loc { x: 0, y: 0 }), Ok(Colour::rgb(0, 10, 200)));
+    /// assert_eq!(img.get_pixel(Loc { x: 1, y: 0 }), Ok(Colour::rgb(0, 0, 200)));
+    /// ```
+    pub fn replace_colour(mut self, from: Colour, to: Colour, tolerance: u8) -> Image {
+        let shift = |value: u8, from: u8, to: u8| {
+            (value as i16 - from as i16 + to as i16).clamp(0, 255) as u8
+        };
+        for c in self.pixels.iter_mut() {
+            if within_tolerance(*c, from, tolerance) {
+                *c = Colour {
+                    r: shift(c.r, from.r, to.r),
+                    g: shift(c.g, from.g, to.g),
+                    b: shift(c.b, from.b, to.b),
+                    a: shift(c.a, from.a, to.a),
+                };
+            }
+        }
+        self
+    }
+
+    /// Gives every colour the hue `target_hue`, in degrees around the colour wheel from red
+    /// through green at 120 and blue at 240, keeping how light and how colourful it is.
+    ///
+    /// The change is made in the Oklab colour space, so lightness is kept as it is perceived:
+    /// a logo recoloured from yellow to blue keeps its contrast with the background instead of
+    /// turning dark. Greys stay grey, and colours that cannot be as colourful in the new hue
+    /// are clipped. Alpha is left alone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let logo = Image::blank_with_colour(Dim::square(2), Colour::rgb(220, 40, 40));
+    /// let blue = logo.recolour_preserving_luminance(240.0);
+    /// let c = blue.get_pixel(Loc { x: 0, y: 0 }).unwrap();
+    /// assert!(c.b > c.r && c.b > c.g);
+    /// ```
+    pub fn recolour_preserving_luminance(mut self, target_hue: f32) -> Image {
+        let target = to_oklab(pure_hue(target_hue));
+        let length = target[1].hypot(target[2]);
+        let (cos, sin) = (target[1] / length, target[2] / length);
+        for c in self.pixels.iter_mut() {
+            let lab = to_oklab(*c);
+            let chroma = lab[1].hypot(lab[2]);
+            *c = Colour {
+                a: c.a,
+                ..from_oklab([lab[0], chroma * cos, chroma * sin])
+            };
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod recolour_tests {
+    use super::*;
+    use crate::{Dim, Loc, Region};
+
+    #[test]
+    fn hues() {
+        assert_eq!(pure_hue(0.0), Colour::rgb(255, 0, 0));
+        assert_eq!(pure_hue(180.0), Colour::rgb(0, 255, 255));
+        assert_eq!(pure_hue(-60.0), Colour::rgb(255, 0, 255));
+        assert_eq!(pure_hue(30.0), Colour::rgb(255, 128, 0));
+    }
+
+    #[test]
+    fn replaces_within_tolerance() {
+        let img = Image::blank_with_colour(Dim { w: 3, h: 1 }, Colour::rgb(10, 10, 10));
+        let img = img
+            .fill_region(
+                Region::from_top_left(Dim::square(1)),
+                Colour::rgb(14, 10, 10),
+            )
+            .replace_colour(Colour::rgb(10, 10, 10), Colour::rgba(250, 0, 0, 128), 3);
+        let reds: Vec<(u8, u8)> = img.pixels.iter().map(|c| (c.r, c.a)).collect();
+        assert_eq!(reds, [(14, 255), (250, 128), (250, 128)]);
+    }
+
+    #[test]
+    fn recolours_keeping_lightness() {
+        let img = Image::blank_with_colour(Dim { w: 3, h: 1 }, Colour::rgba(180, 150, 90, 90))
+            .fill_region(
+                Region::from_top_left(Dim::square(1)),
+                Colour::rgb(70, 70, 70),
+            );
+        let recoloured = img.clone().recolour_preserving_luminance(200.0);
+        let at = |img: &Image, x| img.get_pixel(Loc { x, y: 0 }).unwrap();
+        assert_eq!(at(&recoloured, 0), Colour::rgb(70, 70, 70));
+        let (before, after) = (at(&img, 1), at(&recoloured, 1));
+        assert_eq!(after.a, 90);
+        assert!(after.b > after.r && after.g > after.r);
+        assert!((to_oklab(before)[0] - to_oklab(after)[0]).abs() < 0.02);
+    }
+}
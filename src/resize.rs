@@ -0,0 +1,429 @@
+//! Scaling images to new dimensions, and the mipmaps and pyramids built on top of that.
+
+use crate::progress::CANCELLED;
+use crate::{CancelToken, Colour, Dim, Image, Loc, UnsharpMask};
+
+/// The filter used by `Image::resize()` to compute the new pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResizeFilter {
+    /// Takes the pixel whose centre is closest. Fast and keeps hard edges, which suits pixel
+    /// art scaled by whole factors.
+    Nearest,
+    /// Interpolates between the four nearest pixels.
+    #[default]
+    Bilinear,
+    /// Averages every pixel that the new pixel covers, weighted by how much of it is covered.
+    /// Bilinear filtering only looks at four pixels however much the image shrinks, so it skips
+    /// detail and aliases; this is the filter for thumbnails and other large reductions.
+    Area,
+}
+
+/// Returns the pixels of an axis `from` pixels long that pixel `i` of the same axis scaled to
+/// `to` pixels covers, each with how much of it is covered, the weights adding up to one.
+fn area_weights(i: u32, from: u32, to: u32) -> Vec<(usize, f32)> {
+    let scale = from as f64 / to as f64;
+    let (start, end) = (i as f64 * scale, (i as f64 + 1.0) * scale);
+    let mut weights: Vec<(usize, f32)> = (start.floor() as u32..(end.ceil() as u32).min(from))
+        .map(|j| {
+            let covered = (end.min(j as f64 + 1.0) - start.max(j as f64)) / scale;
+            (j as usize, covered as f32)
+        })
+        .filter(|&(_, w)| w > 0.0)
+        .collect();
+    if weights.is_empty() {
+        weights.push(((start as u32).min(from - 1) as usize, 1.0));
+    }
+    weights
+}
+
+// The following impl block defines resizing functions for Images.
+impl Image {
+    /// Scales the image to the given dimensions, stretching it if the aspect ratio changes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let img = Image::blank_with_colour(Dim { w: 40, h: 30 }, Colour::WHITE);
+    /// let small = img.resize(Dim { w: 4, h: 3 }, ResizeFilter::Bilinear);
+    /// assert_eq!(small.get_dimensions(), Dim { w: 4, h: 3 });
+    /// assert_eq!(small.get_pixel(Loc { x: 2, y: 1 }).unwrap(), Colour::WHITE);
+    /// ```
+    pub fn resize(&self, dimension: Dim, filter: ResizeFilter) -> Image {
+        self.resize_cancellable(dimension, filter, &CancelToken::new())
+            .expect("A fresh token is never cancelled.")
+    }
+
+    /// Like `resize()`, but checks `cancel` after every row of the result and gives up with an
+    /// `Err` once it has been cancelled, so that scaling a gigantic image can be aborted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let img = Image::blank(Dim::square(64));
+    /// let cancel = CancelToken::new();
+    /// cancel.cancel();
+    /// assert!(img.resize_cancellable(Dim::square(32), ResizeFilter::Nearest, &cancel).is_err());
+    /// ```
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(from = ?self.get_dimensions(), to = ?dimension, filter = ?filter)
+        )
+    )]
+    pub fn resize_cancellable(
+        &self,
+        dimension: Dim,
+        filter: ResizeFilter,
+        cancel: &CancelToken,
+    ) -> Result<Image, &'static str> {
+        // An empty image has nothing to sample from, so the result is blank.
+        if self.width == 0 || self.height == 0 {
+            return Ok(Image {
+                colour_profile: self.colour_profile.clone(),
+                metadata: self.metadata.clone(),
+                ..Image::blank(dimension)
+            });
+        }
+
+        let scale_x = self.width as f32 / dimension.w as f32;
+        let scale_y = self.height as f32 / dimension.h as f32;
+        let area_columns: Vec<Vec<(usize, f32)>> = match filter {
+            ResizeFilter::Area => (0..dimension.w)
+                .map(|x| area_weights(x, self.width, dimension.w))
+                .collect(),
+            _ => Vec::new(),
+        };
+        let premultiplied: Vec<Colour> = match filter {
+            ResizeFilter::Area => self.pixels.iter().map(|c| c.premultiply()).collect(),
+            _ => Vec::new(),
+        };
+
+        let sample = |x: u32, y: u32| -> Colour {
+            // Pixel centres of the result, in the coordinates of the source.
+            let sx = (x as f32 + 0.5) * scale_x;
+            let sy = (y as f32 + 0.5) * scale_y;
+            match filter {
+                ResizeFilter::Nearest => {
+                    self.pixels[Loc {
+                        x: (sx as u32).min(self.width - 1),
+                        y: (sy as u32).min(self.height - 1),
+                    }
+                    .as_index(self.get_dimensions())]
+                }
+                ResizeFilter::Bilinear => self.sample_bilinear(sx, sy).unwrap_or(Colour::BLACK),
+                ResizeFilter::Area => {
+                    // Averaged on premultiplied values, so that transparent pixels do not
+                    // bleed their colour into the result.
+                    let mut sum = [0.0f32; 4];
+                    for (row, wy) in area_weights(y, self.height, dimension.h) {
+                        let start = row * self.width as usize;
+                        for &(column, wx) in &area_columns[x as usize] {
+                            let c = premultiplied[start + column];
+                            for (s, v) in sum.iter_mut().zip([c.r, c.g, c.b, c.a]) {
+                                *s += wx * wy * v as f32;
+                            }
+                        }
+                    }
+                    let [r, g, b, a] = sum.map(|v| v.round().clamp(0.0, 255.0) as u8);
+                    Colour::rgba(r, g, b, a).unpremultiply()
+                }
+            }
+        };
+
+        let mut pixels = Vec::with_capacity(dimension.w as usize * dimension.h as usize);
+        for y in 0..dimension.h {
+            if cancel.is_cancelled() {
+                return Err(CANCELLED);
+            }
+            pixels.extend((0..dimension.w).map(|x| sample(x, y)));
+        }
+
+        Ok(Image {
+            width: dimension.w,
+            height: dimension.h,
+            pixels: pixels.into(),
+            colour_profile: self.colour_profile.clone(),
+            metadata: self.metadata.clone(),
+        })
+    }
+
+    /// Scales the image so that it fits within the given dimensions, keeping its aspect ratio.
+    ///
+    /// The result is as large as possible, so one of its sides equals the corresponding side of
+    /// `bounds`, and neither side is smaller than one pixel.
+    pub fn resize_to_fit(&self, bounds: Dim, filter: ResizeFilter) -> Image {
+        let scale = f32::min(
+            bounds.w as f32 / self.width as f32,
+            bounds.h as f32 / self.height as f32,
+        );
+        let dimension = Dim {
+            w: ((self.width as f32 * scale).round() as u32).clamp(1, bounds.w.max(1)),
+            h: ((self.height as f32 * scale).round() as u32).clamp(1, bounds.h.max(1)),
+        };
+        self.resize(dimension, filter)
+    }
+
+    /// Scales the image to the given dimensions for display, the way web thumbnails are made:
+    /// shrinking averages every pixel with `ResizeFilter::Area`, and the result is then lightly
+    /// sharpened with the default `UnsharpMask` so that it does not look soft.
+    ///
+    /// Sides that grow are interpolated bilinearly instead, each side with its own filter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let img = Image::blank_with_colour(Dim { w: 400, h: 300 }, Colour::WHITE);
+    /// let thumbnail = img.smart_resize(Dim { w: 160, h: 120 });
+    /// assert_eq!(thumbnail.get_dimensions(), Dim { w: 160, h: 120 });
+    /// assert_eq!(thumbnail.get_pixel(Loc { x: 80, y: 60 }), Ok(Colour::WHITE));
+    /// ```
+    pub fn smart_resize(&self, dimension: Dim) -> Image {
+        self.smart_resize_with(dimension, UnsharpMask::default())
+    }
+
+    /// Scales the image like `smart_resize()`, sharpening the result with `sharpening`. An
+    /// `amount` of zero skips sharpening altogether.
+    pub fn smart_resize_with(&self, dimension: Dim, sharpening: UnsharpMask) -> Image {
+        let filter = |grows: bool| {
+            if grows {
+                ResizeFilter::Bilinear
+            } else {
+                ResizeFilter::Area
+            }
+        };
+        let (wider, taller) = (dimension.w > self.width, dimension.h > self.height);
+        let resized = if wider == taller {
+            self.resize(dimension, filter(wider))
+        } else {
+            // One side grows and the other shrinks, so they are scaled one after the other.
+            let across = Dim {
+                w: dimension.w,
+                h: self.height,
+            };
+            self.resize(across, filter(wider))
+                .resize(dimension, filter(taller))
+        };
+        if sharpening.amount == 0.0 {
+            return resized;
+        }
+        resized.unsharp_mask(sharpening)
+    }
+}
+
+// The following impl block defines multi-scale functions for Images.
+impl Image {
+    /// Returns the full mipmap chain: the image itself, followed by successively halved copies
+    /// down to a single pixel.
+    ///
+    /// Each level is half as large as the one before it, rounded down but never below one
+    /// pixel, as in OpenGL and Vulkan. Every level is computed from the previous one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let levels = Image::blank(Dim { w: 8, h: 2 }).mipmaps(ResizeFilter::Bilinear);
+    /// let widths: Vec<u32> = levels.iter().map(|l| l.get_dimensions().w).collect();
+    /// assert_eq!(widths, [8, 4, 2, 1]);
+    /// assert_eq!(levels[3].get_dimensions(), Dim::square(1));
+    /// ```
+    pub fn mipmaps(&self, filter: ResizeFilter) -> Vec<Image> {
+        self.halvings(filter, usize::MAX)
+    }
+
+    /// Returns an image pyramid of at most `levels` levels, starting with the image itself and
+    /// halving at every step, as used by multi-scale algorithms.
+    ///
+    /// The pyramid stops early once a level of one by one pixel has been reached. Levels are
+    /// computed with bilinear filtering, which averages each block of 2 by 2 pixels.
+    pub fn pyramid(&self, levels: usize) -> Vec<Image> {
+        self.halvings(ResizeFilter::Bilinear, levels)
+    }
+
+    /// Halves the image repeatedly, returning at most `count` levels including the original.
+    fn halvings(&self, filter: ResizeFilter, count: usize) -> Vec<Image> {
+        let mut levels: Vec<Image> = Vec::new();
+        if count == 0 {
+            return levels;
+        }
+        levels.push(self.clone());
+
+        while levels.len() < count {
+            let last = levels.last().expect("There is at least one level.");
+            let dim = last.get_dimensions();
+            if dim.w <= 1 && dim.h <= 1 {
+                break;
+            }
+            let half = Dim {
+                w: (dim.w / 2).max(1),
+                h: (dim.h / 2).max(1),
+            };
+            levels.push(last.resize(half, filter));
+        }
+        levels
+    }
+}
+
+#[cfg(test)]
+mod resize_tests {
+    use super::*;
+    use crate::Region;
+
+    #[test]
+    fn nearest_keeps_blocks() {
+        let img = Image::blank(Dim::square(2)).fill_region(
+            Region {
+                l: Loc { x: 1, y: 0 },
+                d: Dim { w: 1, h: 2 },
+            },
+            Colour::WHITE,
+        );
+        let big = img.resize(Dim::square(4), ResizeFilter::Nearest);
+
+        for y in 0..4 {
+            for x in 0..4 {
+                let expected = if x < 2 { Colour::BLACK } else { Colour::WHITE };
+                assert_eq!(big.get_pixel(Loc { x, y }).unwrap(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn pyramid_averages_blocks() {
+        let img = Image::blank(Dim::square(4)).fill_region(
+            Region {
+                l: Loc { x: 0, y: 0 },
+                d: Dim { w: 1, h: 4 },
+            },
+            Colour::WHITE,
+        );
+        let levels = img.pyramid(2);
+
+        assert_eq!(levels.len(), 2);
+        assert_eq!(
+            levels[1].get_pixel(Loc { x: 0, y: 1 }).unwrap(),
+            Colour::rgb(128, 128, 128)
+        );
+        assert_eq!(img.pyramid(10).len(), 3);
+    }
+
+    #[test]
+    fn fit_keeps_aspect_ratio() {
+        let img = Image::blank(Dim { w: 200, h: 100 });
+        let fitted = img.resize_to_fit(Dim::square(64), ResizeFilter::Bilinear);
+        assert_eq!(fitted.get_dimensions(), Dim { w: 64, h: 32 });
+    }
+
+    #[test]
+    fn empty_source_gives_blank() {
+        for source in [Dim { w: 0, h: 5 }, Dim { w: 5, h: 0 }] {
+            let img = Image::blank(source);
+            for filter in [
+                ResizeFilter::Nearest,
+                ResizeFilter::Bilinear,
+                ResizeFilter::Area,
+            ] {
+                assert_eq!(
+                    img.resize(Dim::square(3), filter),
+                    Image::blank(Dim::square(3))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn cancellable_matches_resize() {
+        let img = Image::blank(Dim { w: 9, h: 5 }).fill_region(
+            Region {
+                l: Loc { x: 2, y: 1 },
+                d: Dim::square(3),
+            },
+            Colour::rgb(200, 40, 90),
+        );
+        let cancel = CancelToken::new();
+        for filter in [
+            ResizeFilter::Nearest,
+            ResizeFilter::Bilinear,
+            ResizeFilter::Area,
+        ] {
+            let resized = img.resize_cancellable(Dim { w: 4, h: 7 }, filter, &cancel);
+            assert_eq!(resized, Ok(img.resize(Dim { w: 4, h: 7 }, filter)));
+        }
+        cancel.cancel();
+        let cancelled = img.resize_cancellable(Dim::square(3), ResizeFilter::Bilinear, &cancel);
+        assert_eq!(cancelled, Err(CANCELLED));
+    }
+
+    #[test]
+    fn area_averages_everything_covered() {
+        assert_eq!(area_weights(1, 10, 4), [(2, 0.2), (3, 0.4), (4, 0.4)]);
+        assert_eq!(area_weights(3, 2, 4), [(1, 1.0)]);
+
+        // Single-pixel stripes every fourth column, which cover a quarter of the image but which
+        // bilinear filtering over- or underweights when shrinking by eight.
+        let mut img = Image::blank(Dim { w: 64, h: 8 });
+        for row in img.rows_mut() {
+            for pixel in row.iter_mut().step_by(4) {
+                *pixel = Colour::WHITE;
+            }
+        }
+        let small = img.resize(Dim { w: 8, h: 1 }, ResizeFilter::Area);
+        assert!(small.pixels.iter().all(|&c| c == Colour::rgb(64, 64, 64)));
+        let aliased = img.resize(Dim { w: 8, h: 1 }, ResizeFilter::Bilinear);
+        assert!(aliased.pixels.iter().all(|&c| c != Colour::rgb(64, 64, 64)));
+
+        // Enlarging blends only where a new pixel straddles two old ones.
+        let two = Image::blank(Dim { w: 2, h: 1 }).fill_region(
+            Region {
+                l: Loc { x: 1, y: 0 },
+                d: Dim::square(1),
+            },
+            Colour::WHITE,
+        );
+        let wide = two.resize(Dim { w: 3, h: 1 }, ResizeFilter::Area);
+        let reds: Vec<u8> = wide.pixels.iter().map(|c| c.r).collect();
+        assert_eq!(reds, [0, 128, 255]);
+    }
+
+    #[test]
+    fn smart_resize_sharpens_averages() {
+        // A black half and a white half, whose border softens as it shrinks.
+        let img = Image::blank(Dim { w: 64, h: 4 }).fill_region(
+            Region {
+                l: Loc { x: 29, y: 0 },
+                d: Dim { w: 35, h: 4 },
+            },
+            Colour::WHITE,
+        );
+        let dim = Dim { w: 16, h: 1 };
+        let soft = img.resize(dim, ResizeFilter::Area);
+        let sharp = img.smart_resize(dim);
+        let red = |img: &Image, x| img.get_pixel(Loc { x, y: 0 }).unwrap().r;
+        // The straddling pixel is three quarters white, and brightens away from its neighbours.
+        assert_eq!(red(&soft, 7), 191);
+        assert!(red(&sharp, 7) > 191);
+        assert_eq!((red(&sharp, 0), red(&sharp, 15)), (0, 255));
+
+        let unsharpened = UnsharpMask {
+            amount: 0.0,
+            ..UnsharpMask::default()
+        };
+        assert_eq!(img.smart_resize_with(dim, unsharpened), soft);
+        let big = img.smart_resize_with(Dim { w: 128, h: 8 }, unsharpened);
+        assert_eq!(
+            big,
+            img.resize(Dim { w: 128, h: 8 }, ResizeFilter::Bilinear)
+        );
+
+        // Growing one side while shrinking the other still blends the border on the side that
+        // grows, instead of doubling its pixels.
+        let mixed = img.smart_resize_with(Dim { w: 128, h: 2 }, unsharpened);
+        assert!((0..128).any(|x| (1..255).contains(&red(&mixed, x))));
+        assert_eq!((red(&mixed, 0), red(&mixed, 127)), (0, 255));
+    }
+}
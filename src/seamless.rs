@@ -0,0 +1,201 @@
+//! Seamless cloning: pasting patches that take on the colours of their new surroundings.
+
+use crate::{Colour, EdgeMode, Image, Loc, Region};
+
+/// Returns the red, green and blue channels of a colour.
+fn channels(c: Colour) -> [f32; 3] {
+    [c.r as f32, c.g as f32, c.b as f32]
+}
+
+// The following impl block defines gradient-domain compositing functions for Images.
+impl Image {
+    /// Pastes `src_region` of `src` into the image with its top left corner at `dest_loc`,
+    /// blending it in so that no seam shows, as the clone stamp of a photo editor does.
+    ///
+    /// Rather than its colours, the patch keeps its gradients: the differences between
+    /// neighbouring pixels, which are what the eye picks out as detail. The colours are solved
+    /// for so that they meet the image's own colours along the border of the patch, which is
+    /// known as Poisson blending. An object copied from a sunny photo into a shady one thus
+    /// takes on the shade. Where the patch touches the border of the image, nothing constrains
+    /// it; a patch covering the whole image is pasted as it is.
+    ///
+    /// Only red, green and blue are blended, and the image keeps its own alpha, so this is meant
+    /// for opaque images. Parts of either area that fall outside of their images are cut off.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// // A bright dot on grey, cloned onto a darker grey.
+    /// let src = Image::blank_with_colour(Dim::square(9), Colour::rgb(200, 200, 200))
+    ///     .fill_region(Region { l: Loc { x: 4, y: 4 }, d: Dim::square(1) }, Colour::WHITE);
+    /// let img = Image::blank_with_colour(Dim::square(32), Colour::rgb(100, 100, 100))
+    ///     .seamless_clone(&src, Region::from_top_left(Dim::square(9)), Loc { x: 10, y: 10 });
+    /// // The dot is as much brighter than its surroundings as it was, and the grey around it
+    /// // has become that of the image.
+    /// let dot = img.get_pixel(Loc { x: 14, y: 14 }).unwrap();
+    /// assert!(dot.r > 145 && dot.r < 160);
+    /// assert_eq!(img.get_pixel(Loc { x: 11, y: 11 }), Ok(Colour::rgb(100, 100, 100)));
+    /// ```
+    pub fn seamless_clone(mut self, src: &Image, src_region: Region, dest_loc: Loc) -> Image {
+        let patch = src.copy_region(src_region);
+        let w = patch.width.min(self.width.saturating_sub(dest_loc.x)) as i64;
+        let h = patch.height.min(self.height.saturating_sub(dest_loc.y)) as i64;
+        if w == 0 || h == 0 {
+            return self;
+        }
+        let (width, height) = (self.width as i64, self.height as i64);
+        let (x0, y0) = (dest_loc.x as i64, dest_loc.y as i64);
+        let (sx0, sy0) = (
+            src_region.l.x.min(src.width) as i64,
+            src_region.l.y.min(src.height) as i64,
+        );
+
+        // The correction added to the patch is smooth inside it and makes up the difference
+        // between the image and the source just outside of it. Beyond the source image, its
+        // edge pixels stand in.
+        let outside = |x: i64, y: i64| -> Option<[f32; 3]> {
+            if !(0..width).contains(&(x0 + x)) || !(0..height).contains(&(y0 + y)) {
+                return None;
+            }
+            let here = channels(self.pixels[((y0 + y) * width + x0 + x) as usize]);
+            let there = channels(EdgeMode::Clamp.pixel(src, sx0 + x, sy0 + y));
+            Some([0, 1, 2].map(|i| here[i] - there[i]))
+        };
+        let neighbours = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+        // Starting from the average difference around the border lets the solver converge on
+        // the details instead of spending its time moving everything.
+        let mut border = Vec::new();
+        for y in -1..=h {
+            for x in -1..=w {
+                let inside = (0..w).contains(&x) && (0..h).contains(&y);
+                let adjacent = neighbours
+                    .iter()
+                    .any(|(dx, dy)| (0..w).contains(&(x + dx)) && (0..h).contains(&(y + dy)));
+                if !inside && adjacent {
+                    border.extend(outside(x, y));
+                }
+            }
+        }
+        let start = if border.is_empty() {
+            [0.0; 3]
+        } else {
+            [0, 1, 2].map(|i| border.iter().map(|d| d[i]).sum::<f32>() / border.len() as f32)
+        };
+
+        // Successive over-relaxation, with the factor that is optimal for a patch of this size.
+        let mut correction = vec![start; (w * h) as usize];
+        let omega = 2.0 / (1.0 + (core::f32::consts::PI / (w.max(h) + 1) as f32).sin());
+        for _ in 0..20 * (w + h) + 50 {
+            let mut largest_change = 0.0f32;
+            for y in 0..h {
+                for x in 0..w {
+                    let (mut sum, mut count) = ([0.0f32; 3], 0);
+                    for (dx, dy) in neighbours {
+                        let (nx, ny) = (x + dx, y + dy);
+                        let value = if (0..w).contains(&nx) && (0..h).contains(&ny) {
+                            Some(correction[(ny * w + nx) as usize])
+                        } else {
+                            outside(nx, ny)
+                        };
+                        if let Some(value) = value {
+                            for (s, v) in sum.iter_mut().zip(value) {
+                                *s += v;
+                            }
+                            count += 1;
+                        }
+                    }
+                    if count == 0 {
+                        continue;
+                    }
+                    let current = &mut correction[(y * w + x) as usize];
+                    for (c, s) in current.iter_mut().zip(sum) {
+                        let change = omega * (s / count as f32 - *c);
+                        *c += change;
+                        largest_change = largest_change.max(change.abs());
+                    }
+                }
+            }
+            if largest_change < 0.01 {
+                break;
+            }
+        }
+
+        let channel = |v: f32| v.round().clamp(0.0, 255.0) as u8;
+        for y in 0..h {
+            let row = &mut self.row_mut((y0 + y) as u32)[x0 as usize..][..w as usize];
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let source = channels(patch.pixels[y as usize * patch.width as usize + x]);
+                let d = correction[(y * w) as usize + x];
+                *pixel = Colour {
+                    r: channel(source[0] + d[0]),
+                    g: channel(source[1] + d[1]),
+                    b: channel(source[2] + d[2]),
+                    a: pixel.a,
+                };
+            }
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod seamless_tests {
+    use super::*;
+    use crate::Dim;
+
+    #[test]
+    fn flat_patches_take_on_the_surroundings() {
+        let src = Image::blank_with_colour(Dim::square(6), Colour::rgb(20, 200, 90));
+        let img = Image::blank_with_colour(Dim { w: 12, h: 10 }, Colour::rgb(120, 60, 30));
+        let region = Region::from_top_left(Dim::square(6));
+        assert_eq!(
+            img.clone().seamless_clone(&src, region, Loc { x: 3, y: 2 }),
+            img
+        );
+        assert_eq!(
+            img.clone()
+                .seamless_clone(&src, region, Loc { x: 12, y: 0 }),
+            img
+        );
+        // With nothing around it to match, the patch is pasted as it is.
+        let covered = Image::blank_with_colour(Dim::square(6), Colour::WHITE);
+        assert_eq!(
+            covered.seamless_clone(&src, region, Loc { x: 0, y: 0 }),
+            src
+        );
+    }
+
+    #[test]
+    fn gradients_are_kept() {
+        // A horizontal ramp, cloned into a flat image whose left and right sides differ.
+        let mut src = Image::blank(Dim { w: 10, h: 5 });
+        for row in src.rows_mut() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = Colour::rgb(50 + 10 * x as u8, 0, 0);
+            }
+        }
+        let img = Image::blank_with_colour(Dim { w: 20, h: 9 }, Colour::rgb(100, 0, 0))
+            .fill_region(
+                Region {
+                    l: Loc { x: 10, y: 0 },
+                    d: Dim { w: 10, h: 9 },
+                },
+                Colour::rgb(200, 0, 0),
+            );
+        let cloned = img.seamless_clone(
+            &src,
+            Region {
+                l: Loc { x: 0, y: 0 },
+                d: Dim { w: 10, h: 5 },
+            },
+            Loc { x: 5, y: 2 },
+        );
+        let red = |x| cloned.get_pixel(Loc { x, y: 4 }).unwrap().r;
+        // The ramp still rises from left to right, but starts and ends near the image's colours.
+        assert!((5..14).all(|x| red(x) <= red(x + 1)));
+        assert!(red(5) < 120 && red(14) > 180);
+        assert_eq!(red(4), 100);
+    }
+}
@@ -0,0 +1,611 @@
+//! Filled and outlined shapes for mockups, badges, buttons and charts.
+
+use crate::stroke::{Point, Shape};
+use crate::{Colour, Image, Loc, Region, StrokeStyle};
+use std::f32::consts::FRAC_PI_2;
+
+/// How a shape is painted: filled, outlined, or both, with the outline on top.
+///
+/// # Examples
+///
+/// ```
+/// # use kodak::*;
+/// // A blue button with a darker border.
+/// let style = FillStroke::fill(Colour::rgb(40, 110, 230))
+///     .with_stroke(Colour::rgb(20, 60, 140), StrokeStyle::new(2.0));
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FillStroke {
+    /// The colour inside the shape, or `None` to leave it unfilled.
+    pub fill: Option<Colour>,
+    /// The colour and style of the outline, or `None` for no outline. The outline is centred on
+    /// the edge of the shape, so half of its width lies outside.
+    pub stroke: Option<(Colour, StrokeStyle)>,
+}
+
+impl FillStroke {
+    /// Creates a style that only fills the shape with a colour.
+    pub fn fill(colour: Colour) -> Self {
+        FillStroke {
+            fill: Some(colour),
+            stroke: None,
+        }
+    }
+
+    /// Creates a style that only outlines the shape.
+    pub fn stroke(colour: Colour, style: StrokeStyle) -> Self {
+        FillStroke {
+            fill: None,
+            stroke: Some((colour, style)),
+        }
+    }
+
+    /// Adds an outline.
+    pub fn with_stroke(self, colour: Colour, style: StrokeStyle) -> Self {
+        FillStroke {
+            stroke: Some((colour, style)),
+            ..self
+        }
+    }
+}
+
+/// The symbol drawn at every point by `Image::draw_markers()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Marker {
+    /// A filled circle.
+    #[default]
+    Circle,
+    /// A filled square with its sides upright.
+    Square,
+    /// An upright cross like a plus sign, with arms a fifth of its size thick.
+    Cross,
+    /// A filled square standing on one of its corners.
+    Diamond,
+}
+
+impl Marker {
+    /// Returns the shapes that make up the marker, `size` pixels across, around `centre`.
+    fn shapes(self, centre: Point, size: f32) -> Vec<Shape> {
+        let half = size / 2.0;
+        let (x, y) = centre;
+        let rect = |w: f32, h: f32| {
+            Shape::Polygon(vec![
+                (x - w, y - h),
+                (x + w, y - h),
+                (x + w, y + h),
+                (x - w, y + h),
+            ])
+        };
+        match self {
+            Marker::Circle => vec![Shape::Disk(centre, half)],
+            Marker::Square => vec![rect(half, half)],
+            Marker::Cross => {
+                let arm = (size / 10.0).max(0.5);
+                vec![rect(half, arm), rect(arm, half)]
+            }
+            Marker::Diamond => vec![Shape::Polygon(vec![
+                (x, y - half),
+                (x + half, y),
+                (x, y + half),
+                (x - half, y),
+            ])],
+        }
+    }
+}
+
+/// The radii of the four corners of a rounded rectangle, in pixels.
+///
+/// A single radius converts into equal corners, and an array into the corners in the order of
+/// CSS's `border-radius`: top left, top right, bottom right, bottom left.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CornerRadii {
+    /// The radius of the top left corner.
+    pub top_left: f32,
+    /// The radius of the top right corner.
+    pub top_right: f32,
+    /// The radius of the bottom right corner.
+    pub bottom_right: f32,
+    /// The radius of the bottom left corner.
+    pub bottom_left: f32,
+}
+
+impl From<f32> for CornerRadii {
+    fn from(radius: f32) -> Self {
+        CornerRadii::from([radius; 4])
+    }
+}
+
+impl From<[f32; 4]> for CornerRadii {
+    fn from(radii: [f32; 4]) -> Self {
+        let [top_left, top_right, bottom_right, bottom_left] = radii;
+        CornerRadii {
+            top_left,
+            top_right,
+            bottom_right,
+            bottom_left,
+        }
+    }
+}
+
+impl CornerRadii {
+    /// Shrinks the radii to fit a `w` by `h` rectangle: as in CSS, all are scaled down together
+    /// until no two neighbouring corners overlap. Radii longer than the rectangle count as its
+    /// longer side, so that infinite ones give a pill.
+    fn fit(self, w: f32, h: f32) -> [f32; 4] {
+        let limit = w.max(h);
+        let radii = [
+            self.top_left,
+            self.top_right,
+            self.bottom_right,
+            self.bottom_left,
+        ]
+        .map(|r| if r.is_nan() { 0.0 } else { r.clamp(0.0, limit) });
+        let [tl, tr, br, bl] = radii;
+        let scale = [w / (tl + tr), h / (tr + br), w / (br + bl), h / (bl + tl)]
+            .into_iter()
+            .filter(|s| s.is_finite())
+            .fold(1.0f32, f32::min);
+        radii.map(|r| r * scale)
+    }
+}
+
+/// Returns how many straight pieces an arc of `radius` spanning `angle` radians is drawn with,
+/// so that they stray no more than a tenth of a pixel from the true arc.
+fn arc_segments(radius: f32, angle: f32) -> usize {
+    if radius <= 0.1 {
+        return 1;
+    }
+    let step = 2.0 * (1.0 - 0.1 / radius).acos();
+    ((angle.abs() / step).ceil() as usize).clamp(1, 256)
+}
+
+/// Returns the corners of a rounded rectangle, clockwise from the start of the top edge, with
+/// every rounded corner flattened into short straight pieces.
+fn rounded_rect_outline(region: Region, radii: CornerRadii) -> Vec<Point> {
+    let (x0, y0) = (region.l.x as f32, region.l.y as f32);
+    let (x1, y1) = (x0 + region.d.w as f32, y0 + region.d.h as f32);
+    let [tl, tr, br, bl] = radii.fit(region.d.w as f32, region.d.h as f32);
+
+    // The centre, radius and starting angle of every corner, clockwise on screen.
+    let corners = [
+        ((x0 + tl, y0 + tl), tl, 2.0 * FRAC_PI_2),
+        ((x1 - tr, y0 + tr), tr, 3.0 * FRAC_PI_2),
+        ((x1 - br, y1 - br), br, 0.0),
+        ((x0 + bl, y1 - bl), bl, FRAC_PI_2),
+    ];
+    let mut points = Vec::new();
+    for (centre, radius, start) in corners {
+        let n = arc_segments(radius, FRAC_PI_2);
+        for i in 0..=n {
+            let angle = start + FRAC_PI_2 * i as f32 / n as f32;
+            points.push((
+                centre.0 + radius * angle.cos(),
+                centre.1 + radius * angle.sin(),
+            ));
+        }
+    }
+    let close = |a: Point, b: Point| (a.0 - b.0).hypot(a.1 - b.1) < 1e-4;
+    points.dedup_by(|a, b| close(*a, *b));
+    if points.len() > 1 && close(points[0], points[points.len() - 1]) {
+        points.pop();
+    }
+    points
+}
+
+/// Returns points along the arc of a circle from `start` to `start + sweep`, in degrees
+/// clockwise from the positive x axis.
+fn arc_points(centre: Point, radius: f32, start: f32, sweep: f32) -> Vec<Point> {
+    let (start, sweep) = (start.to_radians(), sweep.to_radians());
+    let n = arc_segments(radius, sweep);
+    (0..=n)
+        .map(|i| {
+            let (sin, cos) = (start + sweep * i as f32 / n as f32).sin_cos();
+            (centre.0 + radius * cos, centre.1 + radius * sin)
+        })
+        .collect()
+}
+
+/// Returns the angle swept from `start` to `end`, in degrees, and whether that is a full turn.
+fn sweep(start: f32, end: f32) -> (f32, bool) {
+    let sweep = (end - start).clamp(-360.0, 360.0);
+    (sweep, sweep.abs() == 360.0)
+}
+
+// The following impl block defines shape drawing functions for Images.
+impl Image {
+    /// Fills and outlines the convex polygon with corners `points`.
+    fn draw_convex(self, points: Vec<Point>, style: &FillStroke) -> Image {
+        let mut img = self;
+        if let Some(fill) = style.fill {
+            img = img.fill_shapes(&[Shape::Polygon(points.clone())], fill);
+        }
+        if let Some((colour, stroke)) = &style.stroke {
+            img = img.stroke_polygon(&points, *colour, stroke);
+        }
+        img
+    }
+
+    /// Fills the area enclosed by `outlines` by the even-odd rule, and outlines each of them.
+    fn draw_area(self, outlines: Vec<Vec<Point>>, style: &FillStroke) -> Image {
+        let mut img = self;
+        if let Some((colour, stroke)) = &style.stroke {
+            if let Some(fill) = style.fill {
+                img = img.fill_shapes(&[Shape::Area(outlines.clone())], fill);
+            }
+            for outline in &outlines {
+                img = img.stroke_polygon(outline, *colour, stroke);
+            }
+        } else if let Some(fill) = style.fill {
+            img = img.fill_shapes(&[Shape::Area(outlines)], fill);
+        }
+        img
+    }
+
+    /// Draws a rectangle covering `region` with rounded corners, anti-aliased and composited
+    /// over the image.
+    ///
+    /// `radius` is either one radius for every corner or a `CornerRadii`/`[f32; 4]` with one per
+    /// corner. Radii that do not fit are shrunk as in CSS, so `f32::INFINITY` gives a pill
+    /// whose short sides are half circles.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let badge = Region { l: Loc { x: 4, y: 4 }, d: Dim { w: 56, h: 20 } };
+    /// let img = Image::blank_with_colour(Dim { w: 64, h: 28 }, Colour::WHITE)
+    ///     .draw_rounded_rect(badge, f32::INFINITY, &FillStroke::fill(Colour::rgb(0, 150, 80)));
+    /// assert_eq!(img.get_pixel(Loc { x: 32, y: 14 }), Ok(Colour::rgb(0, 150, 80)));
+    /// // The corners of the region are cut away by the rounded ends.
+    /// assert_eq!(img.get_pixel(Loc { x: 4, y: 4 }), Ok(Colour::WHITE));
+    ///
+    /// // A tab with only its top corners rounded.
+    /// let tab = img.draw_rounded_rect(badge, [8.0, 8.0, 0.0, 0.0], &FillStroke::default());
+    /// ```
+    pub fn draw_rounded_rect(
+        self,
+        region: Region,
+        radius: impl Into<CornerRadii>,
+        style: &FillStroke,
+    ) -> Image {
+        if region.d.w == 0 || region.d.h == 0 {
+            return self;
+        }
+        self.draw_convex(rounded_rect_outline(region, radius.into()), style)
+    }
+
+    /// Strokes the arc of the circle around `centre` from `start_angle` to `end_angle`.
+    ///
+    /// Angles are in degrees clockwise from three o'clock, so -90 is twelve o'clock, and the
+    /// arc runs counter-clockwise if `end_angle` is the smaller. Sweeps beyond a full turn are
+    /// cut to one. With a wide stroke and round caps, this draws circular progress indicators.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// // A spinner that is three quarters done.
+    /// let style = StrokeStyle::new(6.0).with_cap(Cap::Round);
+    /// let img = Image::blank_with_colour(Dim::square(48), Colour::WHITE)
+    ///     .draw_arc((24.0, 24.0), 18.0, -90.0, 180.0, Colour::rgb(0, 120, 255), &style);
+    /// // The gap lies between nine and twelve o'clock.
+    /// assert_eq!(img.get_pixel(Loc { x: 11, y: 11 }), Ok(Colour::WHITE));
+    /// assert_eq!(img.get_pixel(Loc { x: 36, y: 36 }), Ok(Colour::rgb(0, 120, 255)));
+    /// ```
+    pub fn draw_arc(
+        self,
+        centre: Point,
+        radius: f32,
+        start_angle: f32,
+        end_angle: f32,
+        colour: Colour,
+        style: &StrokeStyle,
+    ) -> Image {
+        let (sweep, full) = sweep(start_angle, end_angle);
+        let points = arc_points(centre, radius, start_angle, sweep);
+        if full {
+            self.stroke_polygon(&points, colour, style)
+        } else {
+            self.stroke_polyline(&points, colour, style)
+        }
+    }
+
+    /// Draws a slice of the disk around `centre`, between `start_angle` and `end_angle` as in
+    /// `draw_arc()`. A full turn draws the whole disk.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// // A pie chart of 30% and 70%.
+    /// let shares = [(0.3, Colour::rgb(230, 80, 60)), (0.7, Colour::rgb(60, 130, 230))];
+    /// let mut chart = Image::blank_with_colour(Dim::square(100), Colour::WHITE);
+    /// let mut angle = -90.0;
+    /// for (share, colour) in shares {
+    ///     let end = angle + share * 360.0;
+    ///     chart = chart.draw_pie((50.0, 50.0), 40.0, angle, end, &FillStroke::fill(colour));
+    ///     angle = end;
+    /// }
+    /// assert_eq!(chart.get_pixel(Loc { x: 70, y: 30 }), Ok(Colour::rgb(230, 80, 60)));
+    /// ```
+    pub fn draw_pie(
+        self,
+        centre: Point,
+        radius: f32,
+        start_angle: f32,
+        end_angle: f32,
+        style: &FillStroke,
+    ) -> Image {
+        let (sweep, full) = sweep(start_angle, end_angle);
+        if sweep == 0.0 || radius.is_nan() || radius <= 0.0 {
+            return self;
+        }
+        let mut outline = arc_points(centre, radius, start_angle, sweep);
+        if full {
+            outline.pop();
+        } else {
+            outline.push(centre);
+        }
+        self.draw_area(vec![outline], style)
+    }
+
+    /// Draws a slice of the ring between `inner_radius` and `outer_radius` around `centre`,
+    /// between `start_angle` and `end_angle` as in `draw_arc()`. A full turn draws the whole
+    /// ring, and an inner radius of 0 a pie slice. This is the shape of a donut chart's slices.
+    pub fn draw_ring_segment(
+        self,
+        centre: Point,
+        inner_radius: f32,
+        outer_radius: f32,
+        start_angle: f32,
+        end_angle: f32,
+        style: &FillStroke,
+    ) -> Image {
+        let (sweep, full) = sweep(start_angle, end_angle);
+        let inner = inner_radius.max(0.0);
+        if sweep == 0.0 || outer_radius.is_nan() || outer_radius <= inner {
+            return self;
+        }
+        let mut outer = arc_points(centre, outer_radius, start_angle, sweep);
+        let mut inner = if inner > 0.0 {
+            arc_points(centre, inner, start_angle, sweep)
+        } else {
+            vec![centre]
+        };
+        let outlines = if full {
+            outer.pop();
+            inner.pop();
+            vec![outer, inner]
+        } else {
+            inner.reverse();
+            outer.extend(inner);
+            vec![outer]
+        };
+        self.draw_area(outlines, style)
+    }
+
+    /// Draws a marker centred on the pixel at every location in `locs`, `size` pixels across,
+    /// for plotting point clouds or showing detected features.
+    ///
+    /// The marker is rendered anti-aliased once and then stamped at every location, so this is
+    /// much faster than drawing the points one at a time. Markers are cut off at the edges of
+    /// the image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let corners = [Loc { x: 10, y: 10 }, Loc { x: 50, y: 12 }, Loc { x: 30, y: 40 }];
+    /// let img = Image::blank_with_colour(Dim::square(64), Colour::BLACK)
+    ///     .draw_markers(&corners, Marker::Cross, 9.0, Colour::rgb(0, 255, 0));
+    /// assert_eq!(img.get_pixel(Loc { x: 50, y: 8 }), Ok(Colour::rgb(0, 255, 0)));
+    /// ```
+    pub fn draw_markers(
+        mut self,
+        locs: &[Loc],
+        marker: Marker,
+        size: f32,
+        colour: Colour,
+    ) -> Image {
+        if !size.is_finite() || size <= 0.0 {
+            return self;
+        }
+        let reach = (size / 2.0).ceil() as i64 + 1;
+        let centre = (reach as f32 + 0.5, reach as f32 + 0.5);
+        let shapes = marker.shapes(centre, size);
+        let mut stamp = Vec::new();
+        for y in 0..=2 * reach {
+            for x in 0..=2 * reach {
+                let p = (x as f32 + 0.5, y as f32 + 0.5);
+                let coverage = shapes.iter().map(|s| s.coverage(p)).fold(0.0, f32::max);
+                if coverage > 0.0 {
+                    let alpha = (colour.a as f32 * coverage).round() as u8;
+                    stamp.push((x - reach, y - reach, Colour { a: alpha, ..colour }));
+                }
+            }
+        }
+
+        let (w, h) = (self.width as i64, self.height as i64);
+        for loc in locs {
+            for &(dx, dy, c) in &stamp {
+                let (x, y) = (loc.x as i64 + dx, loc.y as i64 + dy);
+                if x >= 0 && y >= 0 && x < w && y < h {
+                    let pixel = &mut self.pixels[(y * w + x) as usize];
+                    *pixel = c.over(*pixel);
+                }
+            }
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod shapes_tests {
+    use super::*;
+    use crate::{Dim, Loc};
+
+    #[test]
+    fn fits_radii() {
+        let radii = CornerRadii::from([30.0, 30.0, 0.0, -2.0]);
+        assert_eq!(radii.fit(40.0, 100.0), [20.0, 20.0, 0.0, 0.0]);
+        // Neighbours that together are longer than their side shrink in proportion.
+        let radii = CornerRadii::from([30.0, 10.0, 0.0, 0.0]);
+        assert_eq!(radii.fit(20.0, 100.0), [15.0, 5.0, 0.0, 0.0]);
+        assert_eq!(CornerRadii::from(f32::INFINITY).fit(10.0, 4.0), [2.0; 4]);
+    }
+
+    #[test]
+    fn draws_rounded_rects() {
+        let canvas = Image::blank_with_colour(Dim::square(40), Colour::WHITE);
+        let region = Region {
+            l: Loc { x: 10, y: 10 },
+            d: Dim::square(20),
+        };
+        let black = Colour::BLACK;
+        let at = |img: &Image, x, y| img.get_pixel(Loc { x, y }).unwrap();
+
+        let square = canvas
+            .clone()
+            .draw_rounded_rect(region, 0.0, &FillStroke::fill(black));
+        assert_eq!(square, canvas.clone().fill_region(region, black));
+
+        let rounded = canvas.clone().draw_rounded_rect(
+            region,
+            [8.0, 0.0, 0.0, 0.0],
+            &FillStroke::fill(black),
+        );
+        assert_eq!(
+            (at(&rounded, 10, 10), at(&rounded, 29, 10)),
+            (Colour::WHITE, black)
+        );
+        assert_eq!(at(&rounded, 13, 13), black);
+        assert_eq!(at(&rounded, 29, 29), black);
+
+        let style = FillStroke::stroke(black, StrokeStyle::new(2.0));
+        let outlined = canvas.clone().draw_rounded_rect(region, 4.0, &style);
+        assert_eq!(
+            (at(&outlined, 20, 9), at(&outlined, 20, 10)),
+            (black, black)
+        );
+        assert_eq!(at(&outlined, 20, 20), Colour::WHITE);
+        let empty = Region {
+            l: Loc { x: 5, y: 5 },
+            d: Dim { w: 0, h: 9 },
+        };
+        assert_eq!(canvas.clone().draw_rounded_rect(empty, 1.0, &style), canvas);
+    }
+
+    #[test]
+    fn draws_arcs_and_slices() {
+        let canvas = Image::blank_with_colour(Dim::square(40), Colour::WHITE);
+        let black = Colour::BLACK;
+        let at = |img: &Image, x, y| img.get_pixel(Loc { x, y }).unwrap();
+        let centre = (20.0, 20.0);
+
+        // A quarter arc from three to six o'clock.
+        let arc = canvas
+            .clone()
+            .draw_arc(centre, 15.0, 0.0, 90.0, black, &StrokeStyle::new(2.0));
+        assert_eq!((at(&arc, 30, 30), at(&arc, 9, 9)), (black, Colour::WHITE));
+        let backwards =
+            canvas
+                .clone()
+                .draw_arc(centre, 15.0, 90.0, 0.0, black, &StrokeStyle::new(2.0));
+        assert_eq!(arc, backwards);
+
+        // Three quarters of a pie is concave where the missing quarter is.
+        let pie = canvas
+            .clone()
+            .draw_pie(centre, 15.0, 0.0, 270.0, &FillStroke::fill(black));
+        assert_eq!((at(&pie, 10, 10), at(&pie, 25, 25)), (black, black));
+        assert_eq!(at(&pie, 28, 12), Colour::WHITE);
+        let disk = canvas
+            .clone()
+            .draw_pie(centre, 15.0, 0.0, 720.0, &FillStroke::fill(black));
+        assert_eq!(
+            (at(&disk, 28, 12), at(&disk, 4, 20)),
+            (black, Colour::WHITE)
+        );
+
+        let ring = canvas.clone().draw_ring_segment(
+            centre,
+            8.0,
+            15.0,
+            0.0,
+            360.0,
+            &FillStroke::fill(black).with_stroke(Colour::rgb(255, 0, 0), StrokeStyle::new(2.0)),
+        );
+        assert_eq!(
+            (at(&ring, 20, 20), at(&ring, 20, 31)),
+            (Colour::WHITE, black)
+        );
+        assert_eq!(at(&ring, 20, 5), Colour::rgb(255, 0, 0));
+        let segment = canvas.clone().draw_ring_segment(
+            centre,
+            8.0,
+            15.0,
+            180.0,
+            270.0,
+            &FillStroke::fill(black),
+        );
+        assert_eq!(
+            (at(&segment, 10, 10), at(&segment, 30, 30)),
+            (black, Colour::WHITE)
+        );
+        assert_eq!(at(&segment, 17, 17), Colour::WHITE);
+        assert_eq!(
+            canvas
+                .clone()
+                .draw_pie(centre, 15.0, 10.0, 10.0, &FillStroke::fill(black)),
+            canvas
+        );
+    }
+
+    #[test]
+    fn draws_markers() {
+        let canvas = Image::blank_with_colour(Dim::square(20), Colour::WHITE);
+        let black = Colour::BLACK;
+        let locs = [
+            Loc { x: 5, y: 5 },
+            Loc { x: 14, y: 14 },
+            Loc { x: 19, y: 0 },
+        ];
+        let draw = |marker| canvas.clone().draw_markers(&locs, marker, 7.0, black);
+        let at = |img: &Image, x, y| img.get_pixel(Loc { x, y }).unwrap();
+
+        let squares = draw(Marker::Square);
+        let region = Region {
+            l: Loc { x: 2, y: 2 },
+            d: Dim::square(7),
+        };
+        let expected = canvas.clone().fill_region(region, black);
+        // A square of odd size covers whole pixels exactly.
+        assert!((0..10).all(|y| squares.row(y)[..10] == expected.row(y)[..10]));
+        assert_eq!(
+            (at(&squares, 17, 17), at(&squares, 18, 18)),
+            (black, Colour::WHITE)
+        );
+        assert_eq!(at(&squares, 16, 3), black);
+
+        let circles = draw(Marker::Circle);
+        assert_eq!(
+            (at(&circles, 5, 5), at(&circles, 2, 2)),
+            (black, Colour::WHITE)
+        );
+        let diamonds = draw(Marker::Diamond);
+        assert_eq!(
+            (at(&diamonds, 5, 3), at(&diamonds, 2, 2)),
+            (black, Colour::WHITE)
+        );
+        let crosses = draw(Marker::Cross);
+        assert_eq!((at(&crosses, 2, 5), at(&crosses, 5, 2)), (black, black));
+        assert_eq!(at(&crosses, 3, 3), Colour::WHITE);
+
+        assert_eq!(
+            canvas
+                .clone()
+                .draw_markers(&locs, Marker::Circle, 0.0, black),
+            canvas
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! Bulk pixel kernels for fills, blends, channel adjustments and byte conversions.
+//!
+//! These work on whole slices at a time with straight-line loops and fixed-size chunks, which
+//! lets the compiler vectorise them on stable Rust. The results are identical to applying the
+//! per-colour functions one pixel at a time.
+
+#[cfg(not(any(feature = "std", test)))]
+use crate::math::Float;
+use crate::Colour;
+#[cfg(feature = "std")]
+use alloc::{vec, vec::Vec};
+
+/// The number of pixels handled per chunk: 32 bytes, or one AVX2 register.
+const LANES: usize = 8;
+
+/// Flattens colours into RGBA bytes.
+#[cfg(feature = "std")]
+pub(crate) fn to_rgba_bytes(pixels: &[Colour]) -> Vec<u8> {
+    let mut out = vec![0; pixels.len() * 4];
+    for (bytes, c) in out.chunks_exact_mut(4).zip(pixels) {
+        bytes.copy_from_slice(&c.to_array());
+    }
+    out
+}
+
+/// Flattens colours into RGB bytes, dropping alpha.
+#[cfg(feature = "std")]
+#[allow(dead_code)] // only the codecs use it, and they can all be disabled
+pub(crate) fn to_rgb_bytes(pixels: &[Colour]) -> Vec<u8> {
+    let mut out = vec![0; pixels.len() * 3];
+    for (bytes, c) in out.chunks_exact_mut(3).zip(pixels) {
+        bytes.copy_from_slice(&[c.r, c.g, c.b]);
+    }
+    out
+}
+
+/// Reads RGBA bytes as colours. Trailing bytes that do not make up a pixel are ignored.
+#[cfg(feature = "std")]
+#[allow(dead_code)] // only the codecs use it, and they can all be disabled
+pub(crate) fn from_rgba_bytes(bytes: &[u8]) -> Vec<Colour> {
+    bytes
+        .chunks_exact(4)
+        .map(|c| Colour::rgba(c[0], c[1], c[2], c[3]))
+        .collect()
+}
+
+/// Reads RGB bytes as opaque colours. Trailing bytes that do not make up a pixel are ignored.
+#[cfg(feature = "std")]
+#[allow(dead_code)] // only the codecs use it, and they can all be disabled
+pub(crate) fn from_rgb_bytes(bytes: &[u8]) -> Vec<Colour> {
+    bytes
+        .chunks_exact(3)
+        .map(|c| Colour::rgb(c[0], c[1], c[2]))
+        .collect()
+}
+
+/// Premultiplies every colour in place, like `Colour::premultiply()`.
+pub(crate) fn premultiply(pixels: &mut [Colour]) {
+    for c in pixels {
+        *c = c.premultiply();
+    }
+}
+
+/// Composites `top` over the pixels of `base` it lines up with, like `Colour::over()`.
+///
+/// Chunks that are entirely opaque are copied and chunks that are entirely transparent are
+/// skipped, so only the translucent edges of a sprite pay for the full blend.
+pub(crate) fn over(top: &[Colour], base: &mut [Colour]) {
+    let len = top.len().min(base.len());
+    let (top, base) = (&top[..len], &mut base[..len]);
+
+    let mut top_chunks = top.chunks_exact(LANES);
+    let mut base_chunks = base.chunks_exact_mut(LANES);
+    for (src, dst) in (&mut top_chunks).zip(&mut base_chunks) {
+        let alpha: [u8; LANES] = core::array::from_fn(|i| src[i].a);
+        if alpha == [255; LANES] {
+            dst.copy_from_slice(src);
+        } else if alpha != [0; LANES] {
+            for (d, s) in dst.iter_mut().zip(src) {
+                *d = s.over(*d);
+            }
+        }
+    }
+    for (d, s) in base_chunks
+        .into_remainder()
+        .iter_mut()
+        .zip(top_chunks.remainder())
+    {
+        *d = s.over(*d);
+    }
+}
+
+/// Builds a table that scales an 8-bit value by `factor`, rounding and clamping like
+/// `Colour * f32`.
+pub(crate) fn scale_table(factor: f32) -> [u8; 256] {
+    core::array::from_fn(|v| (v as f32 * factor).round().clamp(0.0, 255.0) as u8)
+}
+
+/// Scales the colour channels of every pixel by their own factor, leaving alpha untouched.
+pub(crate) fn scale_channels(pixels: &mut [Colour], red: f32, green: f32, blue: f32) {
+    let (r, g, b) = (scale_table(red), scale_table(green), scale_table(blue));
+    for c in pixels {
+        c.r = r[c.r as usize];
+        c.g = g[c.g as usize];
+        c.b = b[c.b as usize];
+    }
+}
+
+/// Multiplies every channel, alpha included, by the matching channel of `tint`, like
+/// `Colour * Colour`.
+pub(crate) fn multiply(pixels: &mut [Colour], tint: Colour) {
+    for c in pixels {
+        *c = *c * tint;
+    }
+}
+
+#[cfg(test)]
+mod simd_tests {
+    use super::*;
+
+    /// Every combination of a few interesting channel values, with a length that leaves a
+    /// remainder after the chunks.
+    fn samples() -> Vec<Colour> {
+        let values = [0, 1, 64, 127, 128, 200, 254, 255];
+        let mut out = Vec::new();
+        for &a in &values {
+            for &v in &values {
+                out.push(Colour::rgba(v, 255 - v, v / 2, a));
+            }
+        }
+        out.push(Colour::rgba(10, 20, 30, 40));
+        out
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn byte_round_trip() {
+        let pixels = samples();
+        assert_eq!(from_rgba_bytes(&to_rgba_bytes(&pixels)), pixels);
+
+        let opaque: Vec<Colour> = pixels.iter().map(|c| Colour { a: 255, ..*c }).collect();
+        assert_eq!(from_rgb_bytes(&to_rgb_bytes(&opaque)), opaque);
+    }
+
+    #[test]
+    fn kernels_match_per_pixel_functions() {
+        let pixels = samples();
+
+        let mut premultiplied = pixels.clone();
+        premultiply(&mut premultiplied);
+        for (p, c) in premultiplied.iter().zip(&pixels) {
+            assert_eq!(*p, c.premultiply());
+        }
+
+        let mut base = vec![Colour::rgba(30, 160, 90, 200); pixels.len()];
+        over(&pixels, &mut base);
+        for (b, c) in base.iter().zip(&pixels) {
+            assert_eq!(*b, c.over(Colour::rgba(30, 160, 90, 200)));
+        }
+
+        let mut scaled = pixels.clone();
+        scale_channels(&mut scaled, 0.5, 1.0, 1.7);
+        for (s, c) in scaled.iter().zip(&pixels) {
+            assert_eq!(s.r, (*c * 0.5).r);
+            assert_eq!(s.g, c.g);
+            assert_eq!(s.b, (*c * 1.7).b);
+            assert_eq!(s.a, c.a);
+        }
+
+        let tint = Colour::rgba(255, 128, 0, 200);
+        let mut tinted = pixels.clone();
+        multiply(&mut tinted, tint);
+        for (t, c) in tinted.iter().zip(&pixels) {
+            assert_eq!(*t, *c * tint);
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Thinning selections down to their skeletons.
+
+use crate::{Dim, Mask};
+
+impl Mask {
+    /// Thins the selection down to lines one pixel wide that run along the middle of its
+    /// shapes, keeping them connected, with the algorithm of Zhang and Suen.
+    ///
+    /// Pixels count as selected as decided by `contains()`, and the skeleton is fully selected.
+    /// Skeletons of handwriting or line art, say after `Image::adaptive_threshold()`, show how
+    /// the strokes run regardless of the width of the pen.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// // A bar three pixels thick thins to its middle row.
+    /// let bar = Mask::from_fn(Dim { w: 12, h: 5 }, |l| {
+    ///     if (1..4).contains(&l.y) && (1..11).contains(&l.x) { 255 } else { 0 }
+    /// });
+    /// let skeleton = bar.skeletonize();
+    /// assert!(skeleton.contains(Loc { x: 5, y: 2 }));
+    /// assert!(!skeleton.contains(Loc { x: 5, y: 1 }) && !skeleton.contains(Loc { x: 5, y: 3 }));
+    /// ```
+    pub fn skeletonize(&self) -> Mask {
+        let Dim { w, h } = self.get_dimensions();
+        let (w, h) = (w as i64, h as i64);
+        let mut on: Vec<bool> = self.values().iter().map(|&v| v >= 128).collect();
+        let at = |on: &[bool], x: i64, y: i64| {
+            (0..w).contains(&x) && (0..h).contains(&y) && on[(y * w + x) as usize]
+        };
+
+        let mut removed = Vec::new();
+        loop {
+            let mut changed = false;
+            for first in [true, false] {
+                for y in 0..h {
+                    for x in 0..w {
+                        if !on[(y * w + x) as usize] {
+                            continue;
+                        }
+                        // The neighbours clockwise from the one above: P2 to P9 in the paper.
+                        let p = [
+                            (0, -1),
+                            (1, -1),
+                            (1, 0),
+                            (1, 1),
+                            (0, 1),
+                            (-1, 1),
+                            (-1, 0),
+                            (-1, -1),
+                        ]
+                        .map(|(dx, dy)| at(&on, x + dx, y + dy));
+                        let neighbours = p.iter().filter(|&&n| n).count();
+                        let transitions = (0..8).filter(|&i| !p[i] && p[(i + 1) % 8]).count();
+                        // The pairs that must not both be set: south-east boundaries and
+                        // north-west corners in the first pass, and the reverse in the second.
+                        let (a, b) = if first {
+                            (p[0] && p[2] && p[4], p[2] && p[4] && p[6])
+                        } else {
+                            (p[0] && p[2] && p[6], p[0] && p[4] && p[6])
+                        };
+                        if (2..=6).contains(&neighbours) && transitions == 1 && !a && !b {
+                            removed.push((y * w + x) as usize);
+                        }
+                    }
+                }
+                changed |= !removed.is_empty();
+                for i in removed.drain(..) {
+                    on[i] = false;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        Mask::from_fn(self.get_dimensions(), |l| {
+            if on[l.as_index(self.get_dimensions())] {
+                255
+            } else {
+                0
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod skeleton_tests {
+    use super::*;
+    use crate::Loc;
+
+    #[test]
+    fn thins_to_connected_lines() {
+        // An L of strokes five pixels thick.
+        let l_shape = Mask::from_fn(Dim::square(30), |l| {
+            let vertical = (5..10).contains(&l.x) && (3..27).contains(&l.y);
+            let horizontal = (5..27).contains(&l.x) && (22..27).contains(&l.y);
+            if vertical || horizontal {
+                255
+            } else {
+                0
+            }
+        });
+        let skeleton = l_shape.skeletonize();
+        let selected = |y: u32| (0..30).filter(|&x| skeleton.contains(Loc { x, y })).count();
+        // Every row of the vertical stroke is crossed once, and nothing is added.
+        assert!((6..18).all(|y| selected(y) == 1));
+        assert!(skeleton.subtract(&l_shape).values().iter().all(|&v| v == 0));
+
+        // The skeleton hangs together: everything is reachable from one of its pixels.
+        let start = (0..900)
+            .map(|i| Loc::from_index(i, Dim::square(30)))
+            .find(|&l| skeleton.contains(l))
+            .unwrap();
+        let mut seen = vec![start];
+        let mut stack = vec![start];
+        while let Some(l) = stack.pop() {
+            for (dx, dy) in [
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (-1, 0),
+                (1, 0),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+            ] {
+                let next = Loc {
+                    x: (l.x as i32 + dx) as u32,
+                    y: (l.y as i32 + dy) as u32,
+                };
+                if skeleton.contains(next) && !seen.contains(&next) {
+                    seen.push(next);
+                    stack.push(next);
+                }
+            }
+        }
+        let total = skeleton.values().iter().filter(|&&v| v == 255).count();
+        assert_eq!(seen.len(), total);
+
+        let dot = Mask::from_fn(Dim::square(3), |l| {
+            if l == (Loc { x: 1, y: 1 }) {
+                255
+            } else {
+                0
+            }
+        });
+        assert_eq!(dot.skeletonize(), dot);
+    }
+}
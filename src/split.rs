@@ -0,0 +1,216 @@
+//! Before and after comparisons of two versions of an image.
+
+use crate::{Colour, Dim, Image, Loc, Region};
+
+/// The padding around a label of `Image::compare_split_labelled()`, in pixels.
+const LABEL_PADDING: u32 = 4;
+
+/// How `Image::compare_split()` divides the composite between the two images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplitStyle {
+    /// The first image on the left half and the second on the right half.
+    Vertical,
+    /// The first image on the top half and the second on the bottom half.
+    Horizontal,
+    /// A checkerboard of squares with the given side in pixels, starting with the first image
+    /// in the top left corner.
+    Checker(u32),
+}
+
+impl SplitStyle {
+    /// Returns whether the second image is shown at `loc`.
+    fn shows_second(self, loc: Loc, dim: Dim) -> bool {
+        match self {
+            SplitStyle::Vertical => loc.x >= dim.w / 2,
+            SplitStyle::Horizontal => loc.y >= dim.h / 2,
+            SplitStyle::Checker(side) => {
+                let side = side.max(1);
+                (loc.x / side + loc.y / side) % 2 == 1
+            }
+        }
+    }
+}
+
+// The following impl block defines comparison functions for Images.
+impl Image {
+    /// Combines the image and `other`, such as a photo before and after a filter, into one
+    /// composite that shows part of each, divided according to `style`.
+    ///
+    /// This returns an `Err` if the images do not have the same dimensions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let before = Image::blank_with_colour(Dim::square(8), Colour::rgb(200, 120, 40));
+    /// let after = before.clone().adjust_channels(0.5, 1.0, 1.5);
+    /// let both = before.compare_split(&after, SplitStyle::Vertical).unwrap();
+    /// assert_eq!(both.get_pixel(Loc { x: 3, y: 0 }), before.get_pixel(Loc { x: 3, y: 0 }));
+    /// assert_eq!(both.get_pixel(Loc { x: 4, y: 0 }), after.get_pixel(Loc { x: 4, y: 0 }));
+    /// ```
+    pub fn compare_split(&self, other: &Image, style: SplitStyle) -> Result<Image, &'static str> {
+        let dim = self.get_dimensions();
+        if other.get_dimensions() != dim {
+            return Err("Only images of the same size can be compared.");
+        }
+        let pixels = self
+            .pixels
+            .iter()
+            .zip(other.pixels.iter())
+            .enumerate()
+            .map(|(i, (&first, &second))| {
+                if style.shows_second(Loc::from_index(i, dim), dim) {
+                    second
+                } else {
+                    first
+                }
+            })
+            .collect();
+        Ok(Image {
+            pixels,
+            ..self.clone()
+        })
+    }
+
+    /// Like `compare_split()`, but marks the halves with a white divider and labels each image
+    /// with white text on a black box in the built-in bitmap font, such as
+    /// `["before", "after"]`. With `SplitStyle::Checker` there is no divider, and the labels go
+    /// in the first two squares.
+    ///
+    /// This returns an `Err` if the images do not have the same dimensions.
+    pub fn compare_split_labelled(
+        &self,
+        other: &Image,
+        style: SplitStyle,
+        labels: [&str; 2],
+    ) -> Result<Image, &'static str> {
+        let mut img = self.compare_split(other, style)?;
+        let dim = img.get_dimensions();
+
+        let divider = match style {
+            SplitStyle::Vertical => Some(Region {
+                l: Loc {
+                    x: (dim.w / 2).saturating_sub(1),
+                    y: 0,
+                },
+                d: Dim { w: 2, h: dim.h },
+            }),
+            SplitStyle::Horizontal => Some(Region {
+                l: Loc {
+                    x: 0,
+                    y: (dim.h / 2).saturating_sub(1),
+                },
+                d: Dim { w: dim.w, h: 2 },
+            }),
+            SplitStyle::Checker(_) => None,
+        };
+        if let Some(divider) = divider {
+            img = img.fill_region(divider, Colour::WHITE);
+        }
+
+        let label_width = |label: &str| label.chars().count() as u32 * 8;
+        let second = match style {
+            SplitStyle::Vertical => Loc {
+                x: dim
+                    .w
+                    .saturating_sub(label_width(labels[1]) + 3 * LABEL_PADDING),
+                y: 2 * LABEL_PADDING,
+            },
+            SplitStyle::Horizontal => Loc {
+                x: 2 * LABEL_PADDING,
+                y: dim.h / 2 + 2 * LABEL_PADDING,
+            },
+            SplitStyle::Checker(side) => Loc {
+                x: side.max(1) + LABEL_PADDING,
+                y: LABEL_PADDING,
+            },
+        };
+        let first = match style {
+            SplitStyle::Checker(_) => Loc {
+                x: LABEL_PADDING,
+                y: LABEL_PADDING,
+            },
+            _ => Loc {
+                x: 2 * LABEL_PADDING,
+                y: 2 * LABEL_PADDING,
+            },
+        };
+
+        for (label, loc) in labels.into_iter().zip([first, second]) {
+            if label.is_empty() {
+                continue;
+            }
+            let backdrop = Region {
+                l: Loc {
+                    x: loc.x.saturating_sub(LABEL_PADDING),
+                    y: loc.y.saturating_sub(LABEL_PADDING),
+                },
+                d: Dim {
+                    w: label_width(label) + 2 * LABEL_PADDING,
+                    h: 8 + 2 * LABEL_PADDING,
+                },
+            };
+            img = img.fill_region(backdrop, Colour::BLACK).draw_text_bitmap(
+                label,
+                loc,
+                1,
+                Colour::WHITE,
+            );
+        }
+        Ok(img)
+    }
+}
+
+#[cfg(test)]
+mod split_tests {
+    use super::*;
+
+    fn pair() -> (Image, Image) {
+        (
+            Image::blank_with_colour(Dim { w: 6, h: 4 }, Colour::rgb(10, 10, 10)),
+            Image::blank_with_colour(Dim { w: 6, h: 4 }, Colour::rgb(90, 90, 90)),
+        )
+    }
+
+    #[test]
+    fn splits() {
+        let (a, b) = pair();
+        let shows = |style, x, y| {
+            let img = a.compare_split(&b, style).unwrap();
+            img.get_pixel(Loc { x, y }).unwrap() == Colour::rgb(90, 90, 90)
+        };
+        assert!(!shows(SplitStyle::Vertical, 2, 3) && shows(SplitStyle::Vertical, 3, 0));
+        assert!(!shows(SplitStyle::Horizontal, 5, 1) && shows(SplitStyle::Horizontal, 0, 2));
+        assert!(!shows(SplitStyle::Checker(2), 1, 1) && shows(SplitStyle::Checker(2), 2, 1));
+        assert!(!shows(SplitStyle::Checker(2), 2, 2) && shows(SplitStyle::Checker(0), 1, 0));
+
+        let small = Image::blank(Dim::square(2));
+        assert!(a.compare_split(&small, SplitStyle::Vertical).is_err());
+    }
+
+    #[test]
+    fn labels_and_divider() {
+        let a = Image::blank_with_colour(Dim { w: 200, h: 40 }, Colour::rgb(10, 10, 10));
+        let b = Image::blank_with_colour(Dim { w: 200, h: 40 }, Colour::rgb(90, 90, 90));
+        let img = a
+            .compare_split_labelled(&b, SplitStyle::Vertical, ["before", "after"])
+            .unwrap();
+        let at = |x, y| img.get_pixel(Loc { x, y }).unwrap();
+
+        assert_eq!((at(99, 39), at(100, 0)), (Colour::WHITE, Colour::WHITE));
+        // The label boxes sit in the top corners, inside their own halves.
+        assert_eq!(at(4, 4), Colour::BLACK);
+        assert_eq!(at(3, 4), Colour::rgb(10, 10, 10));
+        assert_eq!(at(191, 4), Colour::BLACK);
+        assert_eq!(at(192, 4), Colour::rgb(90, 90, 90));
+        assert!(img.row(10)[8..56].contains(&Colour::WHITE));
+
+        let unlabelled = a
+            .compare_split_labelled(&b, SplitStyle::Checker(20), ["", ""])
+            .unwrap();
+        assert_eq!(
+            unlabelled,
+            a.compare_split(&b, SplitStyle::Checker(20)).unwrap()
+        );
+    }
+}
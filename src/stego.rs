@@ -0,0 +1,155 @@
+//! Hiding data in the least significant bits of an image's pixels.
+//!
+//! Every pixel carries three bits, one in the lowest bit of its red, green and blue channel,
+//! which changes each channel by at most one. Alpha is left alone. The data is preceded by a
+//! header holding a magic number, the length and a CRC-32 of the data, so that `extract_bytes()`
+//! can tell whether an image holds any data and whether it survived intact.
+
+use crate::{Colour, Image};
+use alloc::vec::Vec;
+
+/// Marks an image that holds embedded data.
+const MAGIC: [u8; 2] = *b"KS";
+/// The size of the magic number, the 32-bit length and the 32-bit checksum.
+const HEADER_LEN: usize = 10;
+
+/// Computes the CRC-32 (as used by PNG and zip) of some bytes.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// Iterates over the channels that carry data, in the order the bits are stored.
+fn carriers(pixels: &mut [Colour]) -> impl Iterator<Item = &mut u8> {
+    pixels
+        .iter_mut()
+        .flat_map(|c| [&mut c.r, &mut c.g, &mut c.b])
+}
+
+// The following impl block defines steganography functions for Images.
+impl Image {
+    /// Returns the number of bytes `embed_bytes()` can hide in the image.
+    pub fn embed_capacity(&self) -> usize {
+        (self.pixels.len() * 3 / 8).saturating_sub(HEADER_LEN)
+    }
+
+    /// Hides `data` in the least significant bits of the red, green and blue channels. Pixels
+    /// after the end of the data keep their colour.
+    ///
+    /// The data survives lossless formats such as PNG, but not JPEG compression, resizing or
+    /// any other operation that changes colours.
+    ///
+    /// This returns an `Err` if the data does not fit; see `embed_capacity()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let cover = Image::blank_with_colour(Dim::square(32), Colour::rgb(200, 120, 40));
+    /// let secret = cover.embed_bytes(b"meet me at noon").unwrap();
+    /// assert_eq!(secret.extract_bytes().unwrap(), b"meet me at noon");
+    /// assert!(cover.extract_bytes().is_err());
+    /// ```
+    pub fn embed_bytes(&self, data: &[u8]) -> Result<Image, &'static str> {
+        if data.len() > self.embed_capacity() {
+            return Err("The data does not fit in the image.");
+        }
+        let length =
+            u32::try_from(data.len()).map_err(|_| "The data does not fit in the image.")?;
+
+        let mut message = Vec::with_capacity(HEADER_LEN + data.len());
+        message.extend_from_slice(&MAGIC);
+        message.extend_from_slice(&length.to_be_bytes());
+        message.extend_from_slice(&crc32(data).to_be_bytes());
+        message.extend_from_slice(data);
+
+        let mut result = self.clone();
+        let bits = message
+            .iter()
+            .flat_map(|&byte| (0..8).rev().map(move |i| (byte >> i) & 1));
+        for (channel, bit) in carriers(&mut result.pixels).zip(bits) {
+            *channel = (*channel & !1) | bit;
+        }
+        Ok(result)
+    }
+
+    /// Recovers data hidden by `embed_bytes()`.
+    ///
+    /// This returns an `Err` if the image holds no data or the data was damaged.
+    pub fn extract_bytes(&self) -> Result<Vec<u8>, &'static str> {
+        let mut bytes = self
+            .pixels
+            .iter()
+            .flat_map(|c| [c.r, c.g, c.b])
+            .map(|v| v & 1);
+        let mut next_byte =
+            || -> Option<u8> { (0..8).try_fold(0u8, |byte, _| Some(byte << 1 | bytes.next()?)) };
+
+        let mut header = [0u8; HEADER_LEN];
+        for byte in &mut header {
+            *byte = next_byte().ok_or("The image is too small to hold any data.")?;
+        }
+        if header[..2] != MAGIC {
+            return Err("The image does not hold any embedded data.");
+        }
+        let length = u32::from_be_bytes(header[2..6].try_into().unwrap()) as usize;
+        let checksum = u32::from_be_bytes(header[6..10].try_into().unwrap());
+        if length > self.embed_capacity() {
+            return Err("The embedded data is damaged.");
+        }
+
+        let data: Vec<u8> = (0..length).map_while(|_| next_byte()).collect();
+        if crc32(&data) != checksum {
+            return Err("The embedded data is damaged.");
+        }
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod stego_tests {
+    use super::*;
+    use crate::{Dim, Loc};
+
+    #[test]
+    fn crc_matches_the_standard() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn round_trip() {
+        let cover = Image::blank_with_colour(Dim { w: 10, h: 8 }, Colour::rgba(7, 8, 9, 100));
+        assert_eq!(cover.embed_capacity(), 20);
+
+        let data: Vec<u8> = (0..20).collect();
+        let secret = cover.embed_bytes(&data).unwrap();
+        assert_eq!(secret.extract_bytes().unwrap(), data);
+        for c in secret.pixels.iter() {
+            assert!(c.r.abs_diff(7) <= 1 && c.g.abs_diff(8) <= 1 && c.b.abs_diff(9) <= 1);
+            assert_eq!(c.a, 100);
+        }
+        assert!(cover.embed_bytes(&[0; 21]).is_err());
+        assert_eq!(
+            cover.embed_bytes(&[]).unwrap().extract_bytes().unwrap(),
+            [0u8; 0]
+        );
+
+        let mut damaged = secret.clone();
+        let last = Loc { x: 9, y: 7 };
+        let c = damaged.get_pixel(last).unwrap();
+        let index = last.as_index(damaged.get_dimensions());
+        damaged.pixels[index] = Colour { b: c.b ^ 1, ..c };
+        assert_eq!(secret.get_pixel(last), Ok(c));
+        assert_eq!(
+            damaged.extract_bytes(),
+            Err("The embedded data is damaged.")
+        );
+        assert!(Image::blank(Dim::square(1)).extract_bytes().is_err());
+    }
+}
@@ -0,0 +1,608 @@
+//! Stroking lines and outlines with a width, caps, joins and dashes, anti-aliased.
+//!
+//! Coordinates are in pixels and may be fractional: pixel `(x, y)` covers the square from
+//! `(x, y)` to `(x + 1, y + 1)`, so its centre is at `(x + 0.5, y + 0.5)`. A one pixel wide
+//! horizontal line through `y = 4.5` covers exactly row 4.
+
+use crate::{Colour, Image};
+
+/// The longest a miter join may be, as a multiple of the stroke width, before it is drawn as a
+/// bevel instead. This is the default of SVG and most vector editors.
+const MITER_LIMIT: f32 = 4.0;
+
+pub(crate) type Point = (f32, f32);
+
+/// The shape drawn at the open ends of a stroke and of every dash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Cap {
+    /// The stroke ends exactly at its end point.
+    #[default]
+    Butt,
+    /// A half circle around the end point.
+    Round,
+    /// The stroke continues for half its width past the end point.
+    Square,
+}
+
+/// The shape drawn where two segments of a stroke meet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Join {
+    /// The outer edges are extended until they meet in a point, unless that point would be
+    /// more than four times the stroke width away, in which case the corner is bevelled.
+    #[default]
+    Miter,
+    /// A circular arc around the corner.
+    Round,
+    /// The corner is cut off straight.
+    Bevel,
+}
+
+/// Alternating lengths of dashes and gaps, in pixels, that a stroke is broken into.
+///
+/// A pattern with an odd number of lengths is repeated once to make it even, as in SVG, so
+/// `[4.0]` draws dashes and gaps of four pixels.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DashPattern {
+    lengths: Vec<f32>,
+    offset: f32,
+}
+
+impl DashPattern {
+    /// Creates a pattern from the lengths of the first dash, the first gap, the second dash and
+    /// so on. Negative lengths are treated as zero, and a pattern that adds up to nothing
+    /// draws a solid stroke.
+    pub fn new(lengths: &[f32]) -> Self {
+        let mut lengths: Vec<f32> = lengths.iter().map(|l| l.max(0.0)).collect();
+        if lengths.len() % 2 == 1 {
+            lengths.extend_from_within(..);
+        }
+        DashPattern {
+            lengths,
+            offset: 0.0,
+        }
+    }
+
+    /// Starts the pattern `offset` pixels in, which shifts the dashes back along the stroke.
+    pub fn with_offset(self, offset: f32) -> Self {
+        DashPattern { offset, ..self }
+    }
+
+    /// Splits a polyline into the pieces that fall on dashes.
+    fn split(&self, points: &[Point]) -> Vec<Vec<Point>> {
+        let period: f32 = self.lengths.iter().sum();
+        if period <= 0.0 || !period.is_finite() {
+            return vec![points.to_vec()];
+        }
+
+        // Find where in the pattern the stroke starts.
+        let mut index = 0;
+        let mut remaining = self.lengths[0];
+        let mut skip = self.offset.rem_euclid(period);
+        while skip >= remaining {
+            skip -= remaining;
+            index = (index + 1) % self.lengths.len();
+            remaining = self.lengths[index];
+        }
+        remaining -= skip;
+
+        let mut pieces = Vec::new();
+        let mut current = Vec::new();
+        if index % 2 == 0 {
+            current.push(points[0]);
+        }
+        for segment in points.windows(2) {
+            let (a, b) = (segment[0], segment[1]);
+            let length = distance(a, b);
+            let mut position = 0.0;
+            while length - position > remaining {
+                position += remaining;
+                let t = position / length;
+                let point = (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t);
+                if index % 2 == 0 {
+                    current.push(point);
+                    pieces.push(std::mem::take(&mut current));
+                } else {
+                    current = vec![point];
+                }
+                index = (index + 1) % self.lengths.len();
+                remaining = self.lengths[index];
+            }
+            remaining -= length - position;
+            if index % 2 == 0 {
+                current.push(b);
+            }
+        }
+        if index % 2 == 0 && !current.is_empty() {
+            pieces.push(current);
+        }
+        pieces
+    }
+}
+
+/// How lines and outlines are stroked by `Image::stroke_line()` and friends.
+///
+/// # Examples
+///
+/// ```
+/// # use kodak::*;
+/// let style = StrokeStyle::new(3.0)
+///     .with_cap(Cap::Round)
+///     .with_dash(DashPattern::new(&[12.0, 6.0]));
+/// let img = Image::blank_with_colour(Dim { w: 64, h: 16 }, Colour::WHITE)
+///     .stroke_line((4.0, 8.0), (60.0, 8.0), Colour::rgb(200, 0, 0), &style);
+/// assert_eq!(img.get_pixel(Loc { x: 8, y: 7 }), Ok(Colour::rgb(200, 0, 0)));
+/// // The first gap runs from x = 16 to 22.
+/// assert_eq!(img.get_pixel(Loc { x: 19, y: 7 }), Ok(Colour::WHITE));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrokeStyle {
+    /// The width of the stroke in pixels.
+    pub width: f32,
+    /// The shape of the ends of the stroke and of its dashes.
+    pub cap: Cap,
+    /// The shape of the corners.
+    pub join: Join,
+    /// The dashes to break the stroke into, or `None` for a solid stroke.
+    pub dash: Option<DashPattern>,
+}
+
+impl Default for StrokeStyle {
+    fn default() -> Self {
+        StrokeStyle::new(1.0)
+    }
+}
+
+impl StrokeStyle {
+    /// Creates a solid stroke of the given width with butt caps and miter joins.
+    pub fn new(width: f32) -> Self {
+        StrokeStyle {
+            width,
+            cap: Cap::default(),
+            join: Join::default(),
+            dash: None,
+        }
+    }
+
+    /// Sets the shape of the ends.
+    pub fn with_cap(self, cap: Cap) -> Self {
+        StrokeStyle { cap, ..self }
+    }
+
+    /// Sets the shape of the corners.
+    pub fn with_join(self, join: Join) -> Self {
+        StrokeStyle { join, ..self }
+    }
+
+    /// Breaks the stroke into dashes.
+    pub fn with_dash(self, dash: DashPattern) -> Self {
+        StrokeStyle {
+            dash: Some(dash),
+            ..self
+        }
+    }
+}
+
+fn distance(a: Point, b: Point) -> f32 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+fn cross(a: Point, b: Point) -> f32 {
+    a.0 * b.1 - a.1 * b.0
+}
+
+/// Returns the unit vector from `a` to `b`, which must be distinct.
+fn direction(a: Point, b: Point) -> Point {
+    let length = distance(a, b);
+    ((b.0 - a.0) / length, (b.1 - a.1) / length)
+}
+
+/// Returns the distance from `p` to the closest point on the line segment from `a` to `b`.
+fn distance_to_segment(p: Point, a: Point, b: Point) -> f32 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared > 0.0 {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    distance(p, (a.0 + dx * t, a.1 + dy * t))
+}
+
+/// A piece of a stroke or filled shape, whose union with the other pieces is the whole.
+pub(crate) enum Shape {
+    /// A convex polygon, with its corners in order.
+    Polygon(Vec<Point>),
+    /// A disk with a centre and a radius.
+    Disk(Point, f32),
+    /// Any polygon, given as one or more closed outlines. Where outlines overlap, the even-odd
+    /// rule decides what is inside, so an outline within another cuts a hole into it.
+    Area(Vec<Vec<Point>>),
+}
+
+impl Shape {
+    /// Returns the bounding box as the minimum and maximum corner.
+    fn bounds(&self) -> (Point, Point) {
+        match self {
+            Shape::Polygon(corners) => corners.iter().fold(
+                ((f32::MAX, f32::MAX), (f32::MIN, f32::MIN)),
+                |(lo, hi), &(x, y)| ((lo.0.min(x), lo.1.min(y)), (hi.0.max(x), hi.1.max(y))),
+            ),
+            &Shape::Disk((x, y), r) => ((x - r, y - r), (x + r, y + r)),
+            Shape::Area(outlines) => Shape::Polygon(outlines.concat()).bounds(),
+        }
+    }
+
+    /// Returns how much of the pixel centred at `p` the shape covers, from 0.0 to 1.0, judged by
+    /// the signed distance from `p` to its edge.
+    pub(crate) fn coverage(&self, p: Point) -> f32 {
+        let signed_distance = match self {
+            Shape::Polygon(corners) => {
+                // Inside a convex polygon, the distance to its edge is the smallest distance
+                // to the lines through its sides; outside, this slightly rounds the corners.
+                let n = corners.len();
+                let area: f32 = (0..n)
+                    .map(|i| cross(corners[i], corners[(i + 1) % n]))
+                    .sum();
+                let winding = area.signum();
+                (0..n)
+                    .map(|i| {
+                        let (a, b) = (corners[i], corners[(i + 1) % n]);
+                        let (dx, dy) = direction(a, b);
+                        // The outward normal of the side.
+                        let normal = (dy * winding, -dx * winding);
+                        normal.0 * (p.0 - a.0) + normal.1 * (p.1 - a.1)
+                    })
+                    .fold(f32::MIN, f32::max)
+            }
+            &Shape::Disk(centre, r) => distance(p, centre) - r,
+            Shape::Area(outlines) => {
+                let mut inside = false;
+                let mut nearest = f32::MAX;
+                for outline in outlines {
+                    let n = outline.len();
+                    for i in 0..n {
+                        let (a, b) = (outline[i], outline[(i + 1) % n]);
+                        if (a.1 > p.1) != (b.1 > p.1)
+                            && p.0 < a.0 + (p.1 - a.1) / (b.1 - a.1) * (b.0 - a.0)
+                        {
+                            inside = !inside;
+                        }
+                        nearest = nearest.min(distance_to_segment(p, a, b));
+                    }
+                }
+                if inside {
+                    -nearest
+                } else {
+                    nearest
+                }
+            }
+        };
+        (0.5 - signed_distance).clamp(0.0, 1.0)
+    }
+}
+
+/// Breaks the stroke of a polyline into convex shapes. If `closed`, the last point connects
+/// back to the first with a join instead of caps.
+fn outline(points: &[Point], closed: bool, style: &StrokeStyle) -> Vec<Shape> {
+    let mut points: Vec<Point> = points.to_vec();
+    points.dedup_by(|a, b| distance(*a, *b) < 1e-4);
+    if closed && points.len() > 2 && distance(points[0], points[points.len() - 1]) < 1e-4 {
+        points.pop();
+    }
+    if points.is_empty() || !style.width.is_finite() || style.width <= 0.0 {
+        return Vec::new();
+    }
+
+    let pieces = match &style.dash {
+        Some(dash) => {
+            let mut path = points.clone();
+            if closed {
+                path.push(points[0]);
+            }
+            dash.split(&path)
+        }
+        None => vec![points],
+    };
+    let closed = closed && style.dash.is_none();
+
+    let mut shapes = Vec::new();
+    for piece in pieces {
+        stroke_piece(&piece, closed, style, &mut shapes);
+    }
+    shapes
+}
+
+/// Adds the shapes of one solid stretch of a stroke.
+fn stroke_piece(points: &[Point], closed: bool, style: &StrokeStyle, shapes: &mut Vec<Shape>) {
+    let half = style.width / 2.0;
+    let mut points = points.to_vec();
+    points.dedup_by(|a, b| distance(*a, *b) < 1e-4);
+    if points.len() == 1 {
+        // A dash of zero length is only visible as a dot with round caps.
+        if style.cap == Cap::Round {
+            shapes.push(Shape::Disk(points[0], half));
+        }
+        return;
+    }
+
+    let n = points.len();
+    let segments = if closed { n } else { n - 1 };
+    for i in 0..segments {
+        let (a, b) = (points[i], points[(i + 1) % n]);
+        let (dx, dy) = direction(a, b);
+        let extend = |at_end: bool| {
+            let open_end = !closed && if at_end { i + 2 == n } else { i == 0 };
+            if open_end && style.cap == Cap::Square {
+                half
+            } else {
+                0.0
+            }
+        };
+        let (start, end) = (extend(false), extend(true));
+        let a = (a.0 - dx * start, a.1 - dy * start);
+        let b = (b.0 + dx * end, b.1 + dy * end);
+        let (nx, ny) = (-dy * half, dx * half);
+        shapes.push(Shape::Polygon(vec![
+            (a.0 + nx, a.1 + ny),
+            (b.0 + nx, b.1 + ny),
+            (b.0 - nx, b.1 - ny),
+            (a.0 - nx, a.1 - ny),
+        ]));
+    }
+
+    if !closed && style.cap == Cap::Round {
+        shapes.push(Shape::Disk(points[0], half));
+        shapes.push(Shape::Disk(points[n - 1], half));
+    }
+
+    let corners = if closed { 0..n } else { 1..n - 1 };
+    for i in corners {
+        let (before, corner, after) = (points[(i + n - 1) % n], points[i], points[(i + 1) % n]);
+        let (d0, d1) = (direction(before, corner), direction(corner, after));
+        let turn = cross(d0, d1);
+        if turn.abs() < 1e-6 && d0.0 * d1.0 + d0.1 * d1.1 > 0.0 {
+            continue;
+        }
+        if style.join == Join::Round {
+            shapes.push(Shape::Disk(corner, half));
+            continue;
+        }
+
+        // The corners of the two segments on the outside of the turn.
+        let side = if turn > 0.0 { -half } else { half };
+        let p0 = (corner.0 - d0.1 * side, corner.1 + d0.0 * side);
+        let p1 = (corner.0 - d1.1 * side, corner.1 + d1.0 * side);
+        if turn.abs() < 1e-6 {
+            // A full reversal has no outside to fill.
+            continue;
+        }
+        let mid = (
+            (p0.0 + p1.0) / 2.0 - corner.0,
+            (p0.1 + p1.1) / 2.0 - corner.1,
+        );
+        let mid_length = mid.0.hypot(mid.1);
+        // The miter reaches half / cos(a) from the corner, where a is half the angle between
+        // the segments' normals; mid_length is half * cos(a).
+        let ratio = half / mid_length;
+        if style.join == Join::Miter && ratio <= MITER_LIMIT {
+            let reach = ratio * half;
+            let tip = (
+                corner.0 + mid.0 / mid_length * reach,
+                corner.1 + mid.1 / mid_length * reach,
+            );
+            shapes.push(Shape::Polygon(vec![corner, p0, tip, p1]));
+        } else {
+            shapes.push(Shape::Polygon(vec![corner, p0, p1]));
+        }
+    }
+}
+
+// The following impl block defines stroking functions for Images.
+impl Image {
+    /// Draws a straight line from `from` to `to`, anti-aliased and composited over the image.
+    ///
+    /// See the `stroke` module documentation for how coordinates map to pixels.
+    pub fn stroke_line(self, from: Point, to: Point, colour: Colour, style: &StrokeStyle) -> Image {
+        self.stroke_polyline(&[from, to], colour, style)
+    }
+
+    /// Draws a line through `points` in turn, with the style's joins at the corners and its
+    /// caps at both ends.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// let chart = [(8.0, 56.0), (24.0, 20.0), (40.0, 36.0), (56.0, 8.0)];
+    /// let style = StrokeStyle::new(2.0).with_join(Join::Round);
+    /// let img = Image::blank_with_colour(Dim::square(64), Colour::WHITE)
+    ///     .stroke_polyline(&chart, Colour::rgb(0, 90, 200), &style);
+    /// ```
+    pub fn stroke_polyline(self, points: &[Point], colour: Colour, style: &StrokeStyle) -> Image {
+        self.fill_shapes(&outline(points, false, style), colour)
+    }
+
+    /// Draws the outline of the polygon with corners `points`, joining the last corner back to
+    /// the first. Caps are only drawn at the ends of dashes.
+    pub fn stroke_polygon(self, points: &[Point], colour: Colour, style: &StrokeStyle) -> Image {
+        self.fill_shapes(&outline(points, true, style), colour)
+    }
+
+    /// Composites `colour` over every pixel covered by the union of `shapes`.
+    pub(crate) fn fill_shapes(mut self, shapes: &[Shape], colour: Colour) -> Image {
+        if shapes.is_empty() || self.pixels.is_empty() {
+            return self;
+        }
+        let (lo, hi) = shapes
+            .iter()
+            .map(Shape::bounds)
+            .reduce(|(lo, hi), (l, h)| {
+                (
+                    (lo.0.min(l.0), lo.1.min(l.1)),
+                    (hi.0.max(h.0), hi.1.max(h.1)),
+                )
+            })
+            .expect("There is at least one shape.");
+        let clamp_x = |v: f32| (v.floor().max(0.0) as u32).min(self.width);
+        let clamp_y = |v: f32| (v.floor().max(0.0) as u32).min(self.height);
+        let (x0, x1) = (clamp_x(lo.0 - 1.0), clamp_x(hi.0 + 2.0));
+        let (y0, y1) = (clamp_y(lo.1 - 1.0), clamp_y(hi.1 + 2.0));
+        if x0 >= x1 || y0 >= y1 {
+            return self;
+        }
+
+        // The coverage of the union is the largest coverage of any shape, so that overlapping
+        // segments and joins do not darken where they meet.
+        let w = (x1 - x0) as usize;
+        let mut coverage = vec![0.0f32; w * (y1 - y0) as usize];
+        for shape in shapes {
+            let (l, h) = shape.bounds();
+            let (sx0, sx1) = (clamp_x(l.0 - 1.0).max(x0), clamp_x(h.0 + 2.0).min(x1));
+            let (sy0, sy1) = (clamp_y(l.1 - 1.0).max(y0), clamp_y(h.1 + 2.0).min(y1));
+            for y in sy0..sy1 {
+                for x in sx0..sx1 {
+                    let c = shape.coverage((x as f32 + 0.5, y as f32 + 0.5));
+                    let cell = &mut coverage[(y - y0) as usize * w + (x - x0) as usize];
+                    *cell = cell.max(c);
+                }
+            }
+        }
+
+        for y in y0..y1 {
+            let row = &mut self.row_mut(y)[x0 as usize..x1 as usize];
+            let covered = &coverage[(y - y0) as usize * w..][..w];
+            for (pixel, &c) in row.iter_mut().zip(covered) {
+                if c > 0.0 {
+                    let alpha = (colour.a as f32 * c).round() as u8;
+                    *pixel = Colour { a: alpha, ..colour }.over(*pixel);
+                }
+            }
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod stroke_tests {
+    use super::*;
+    use crate::{Dim, Loc};
+
+    fn canvas() -> Image {
+        Image::blank_with_colour(Dim::square(20), Colour::WHITE)
+    }
+
+    fn at(img: &Image, x: u32, y: u32) -> Colour {
+        img.get_pixel(Loc { x, y }).unwrap()
+    }
+
+    #[test]
+    fn widths_and_caps() {
+        let black = Colour::BLACK;
+        let line =
+            |style: &StrokeStyle| canvas().stroke_line((4.0, 10.0), (16.0, 10.0), black, style);
+
+        let butt = line(&StrokeStyle::new(4.0));
+        assert_eq!((at(&butt, 4, 8), at(&butt, 15, 11)), (black, black));
+        assert_eq!(
+            (at(&butt, 4, 7), at(&butt, 4, 12)),
+            (Colour::WHITE, Colour::WHITE)
+        );
+        assert_eq!(
+            (at(&butt, 3, 10), at(&butt, 16, 10)),
+            (Colour::WHITE, Colour::WHITE)
+        );
+
+        let square = line(&StrokeStyle::new(4.0).with_cap(Cap::Square));
+        assert_eq!((at(&square, 2, 8), at(&square, 17, 11)), (black, black));
+        assert_eq!(at(&square, 1, 10), Colour::WHITE);
+
+        let round = line(&StrokeStyle::new(4.0).with_cap(Cap::Round));
+        assert_eq!(at(&round, 3, 10), black);
+        // The corners of a round cap are cut away, leaving only partial coverage.
+        assert_ne!(at(&round, 2, 8), black);
+        assert_eq!(at(&round, 1, 7), Colour::WHITE);
+
+        // A thin diagonal line is anti-aliased.
+        let diagonal =
+            canvas().stroke_line((0.0, 0.0), (20.0, 13.0), black, &StrokeStyle::new(1.0));
+        assert!(diagonal.row(5).iter().any(|c| c.r > 0 && c.r < 255));
+    }
+
+    #[test]
+    fn joins() {
+        let corner = [(4.0, 16.0), (10.0, 4.0), (16.0, 16.0)];
+        let draw = |join| {
+            let style = StrokeStyle::new(4.0).with_join(join);
+            canvas().stroke_polyline(&corner, Colour::BLACK, &style)
+        };
+        let (miter, round, bevel) = (draw(Join::Miter), draw(Join::Round), draw(Join::Bevel));
+        // The miter reaches highest above the corner, and the bevel lowest.
+        let top = |img: &Image| (0..20).find(|&y| img.row(y)[10] != Colour::WHITE).unwrap();
+        assert!(top(&miter) < top(&bevel));
+        assert!(top(&miter) <= top(&round) && top(&round) <= top(&bevel));
+        // Sharp corners beyond the miter limit are bevelled.
+        let spike = [(8.0, 18.0), (10.0, 2.0), (12.0, 18.0)];
+        let style = StrokeStyle::new(2.0);
+        let img = canvas().stroke_polyline(&spike, Colour::BLACK, &style);
+        let bevelled =
+            canvas().stroke_polyline(&spike, Colour::BLACK, &style.with_join(Join::Bevel));
+        assert_eq!(img, bevelled);
+    }
+
+    #[test]
+    fn dashes() {
+        let pattern = DashPattern::new(&[2.0, 3.0]);
+        let pieces = pattern.split(&[(0.0, 0.0), (6.0, 0.0), (6.0, 6.0)]);
+        assert_eq!(
+            pieces,
+            [
+                vec![(0.0, 0.0), (2.0, 0.0)],
+                vec![(5.0, 0.0), (6.0, 0.0), (6.0, 1.0)],
+                vec![(6.0, 4.0), (6.0, 6.0)],
+            ]
+        );
+        let shifted = pattern.with_offset(3.0).split(&[(0.0, 0.0), (10.0, 0.0)]);
+        assert_eq!(
+            shifted,
+            [vec![(2.0, 0.0), (4.0, 0.0)], vec![(7.0, 0.0), (9.0, 0.0)]]
+        );
+        let solid = DashPattern::new(&[0.0]).split(&[(0.0, 0.0), (1.0, 0.0)]);
+        assert_eq!(solid, [vec![(0.0, 0.0), (1.0, 0.0)]]);
+
+        let style = StrokeStyle::new(2.0).with_dash(DashPattern::new(&[4.0]));
+        let img = canvas().stroke_polygon(
+            &[(2.0, 2.0), (18.0, 2.0), (18.0, 18.0)],
+            Colour::BLACK,
+            &style,
+        );
+        assert_eq!(at(&img, 3, 2), Colour::BLACK);
+        assert_eq!(at(&img, 7, 2), Colour::WHITE);
+    }
+
+    #[test]
+    fn closed_outlines_and_clipping() {
+        let square = [(4.0, 4.0), (16.0, 4.0), (16.0, 16.0), (4.0, 16.0)];
+        let img = canvas().stroke_polygon(&square, Colour::BLACK, &StrokeStyle::new(2.0));
+        // The closing corner is mitred like the others.
+        assert_eq!(
+            (at(&img, 3, 3), at(&img, 16, 16)),
+            (Colour::BLACK, Colour::BLACK)
+        );
+        assert_eq!(at(&img, 10, 10), Colour::WHITE);
+
+        let outside = canvas().stroke_line(
+            (-50.0, -5.0),
+            (90.0, 30.0),
+            Colour::BLACK,
+            &StrokeStyle::new(3.0),
+        );
+        assert!(outside.row(10).contains(&Colour::BLACK));
+        let nothing = canvas().stroke_line(
+            (1.0, 1.0),
+            (5.0, 5.0),
+            Colour::BLACK,
+            &StrokeStyle::new(0.0),
+        );
+        assert_eq!(nothing, canvas());
+    }
+}
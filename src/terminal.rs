@@ -0,0 +1,216 @@
+//! Inline image output for terminals: sixel graphics and the kitty and iTerm2 protocols.
+
+use crate::{Colour, Image};
+
+/// The number of levels per channel in the fixed sixel palette. Green gets the most, since the
+/// eye is most sensitive to it.
+const SIXEL_LEVELS: [u32; 3] = [6, 7, 6];
+
+/// The largest amount of base64 data the kitty protocol accepts in a single escape sequence.
+const KITTY_CHUNK: usize = 4096;
+
+/// Encodes bytes as standard base64 with padding.
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Returns the index of the colour closest to `c` in the fixed sixel palette.
+fn sixel_index(c: Colour) -> usize {
+    let level = |v: u8, levels: u32| ((v as u32 * (levels - 1) + 127) / 255) as usize;
+    let [lr, lg, lb] = SIXEL_LEVELS;
+    (level(c.r, lr) * lg as usize + level(c.g, lg)) * lb as usize + level(c.b, lb)
+}
+
+// The following impl block defines terminal output functions for Images.
+impl Image {
+    /// Encodes the image as a sixel escape sequence, which terminals such as xterm, foot,
+    /// mlterm and WezTerm display inline when it is printed.
+    ///
+    /// Colours are reduced to a fixed palette of 252 colours. Pixels that are less than half
+    /// opaque are left transparent, showing the terminal's background.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use kodak::*;
+    /// let img = Image::load_png("assets/olle_voader.png").unwrap();
+    /// print!("{}", img.to_sixel());
+    /// ```
+    pub fn to_sixel(&self) -> String {
+        let (w, h) = (self.width as usize, self.height as usize);
+        // P2 = 1 keeps pixels without a colour transparent.
+        let mut out = format!("\x1bP0;1;0q\"1;1;{w};{h}");
+
+        let [lr, lg, lb] = SIXEL_LEVELS;
+        let percent = |level: u32, levels: u32| level * 100 / (levels - 1);
+        for r in 0..lr {
+            for g in 0..lg {
+                for b in 0..lb {
+                    let index = (r * lg + g) * lb + b;
+                    out += &format!(
+                        "#{index};2;{};{};{}",
+                        percent(r, lr),
+                        percent(g, lg),
+                        percent(b, lb)
+                    );
+                }
+            }
+        }
+
+        let indices: Vec<Option<usize>> = self
+            .pixels
+            .iter()
+            .map(|&c| (c.a >= 128).then(|| sixel_index(c)))
+            .collect();
+
+        let mut used = vec![false; (lr * lg * lb) as usize];
+        for band in (0..h).step_by(6) {
+            let rows = band..(band + 6).min(h);
+            used.iter_mut().for_each(|u| *u = false);
+            for y in rows.clone() {
+                for index in indices[y * w..(y + 1) * w].iter().flatten() {
+                    used[*index] = true;
+                }
+            }
+
+            let mut first = true;
+            for colour in (0..used.len()).filter(|&i| used[i]) {
+                if !first {
+                    // Return to the start of the band to overlay the next colour.
+                    out.push('$');
+                }
+                first = false;
+                out += &format!("#{colour}");
+
+                // Every column becomes one character, with one bit per row of the band.
+                let sixels = (0..w).map(|x| {
+                    let bits = rows
+                        .clone()
+                        .filter(|y| indices[y * w + x] == Some(colour))
+                        .fold(0u8, |bits, y| bits | 1 << (y - band));
+                    (63 + bits) as char
+                });
+                let mut run: Option<(char, usize)> = None;
+                let flush = |out: &mut String, (c, n): (char, usize)| match n {
+                    1..=3 => out.extend(std::iter::repeat_n(c, n)),
+                    _ => *out += &format!("!{n}{c}"),
+                };
+                for c in sixels {
+                    run = match run {
+                        Some((previous, n)) if previous == c => Some((c, n + 1)),
+                        Some(previous) => {
+                            flush(&mut out, previous);
+                            Some((c, 1))
+                        }
+                        None => Some((c, 1)),
+                    };
+                }
+                if let Some(last) = run {
+                    flush(&mut out, last);
+                }
+            }
+            out.push('-');
+        }
+        out += "\x1b\\";
+        out
+    }
+
+    /// Encodes the image for the kitty terminal graphics protocol, which kitty, WezTerm,
+    /// Konsole and Ghostty display inline at full resolution when it is printed.
+    ///
+    /// The RGBA pixels are sent as-is, split over as many escape sequences as needed.
+    pub fn to_kitty_escape(&self) -> String {
+        let data = crate::simd::to_rgba_bytes(&self.pixels);
+        let encoded = base64(&data);
+        let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(KITTY_CHUNK).collect();
+
+        let mut out = String::with_capacity(encoded.len() + chunks.len() * 32);
+        for (i, chunk) in chunks.iter().enumerate() {
+            let more = u8::from(i + 1 < chunks.len());
+            let payload = std::str::from_utf8(chunk).expect("base64 is ASCII");
+            if i == 0 {
+                out += &format!(
+                    "\x1b_Ga=T,f=32,s={},v={},m={more};{payload}\x1b\\",
+                    self.width, self.height
+                );
+            } else {
+                out += &format!("\x1b_Gm={more};{payload}\x1b\\");
+            }
+        }
+        if chunks.is_empty() {
+            out += &format!("\x1b_Ga=T,f=32,s={},v={};\x1b\\", self.width, self.height);
+        }
+        out
+    }
+
+    /// Encodes the image for the iTerm2 inline image protocol, which iTerm2 and WezTerm
+    /// display when it is printed. The image is sent as a PNG file.
+    #[cfg(feature = "png")]
+    pub fn to_iterm2_escape(&self) -> String {
+        let mut png = Vec::new();
+        self.encode_png(&mut png, &crate::Metadata::default())
+            .expect("Encoding a PNG in memory should not fail.");
+        format!(
+            "\x1b]1337;File=inline=1;size={};width={}px;height={}px:{}\x07",
+            png.len(),
+            self.width,
+            self.height,
+            base64(&png)
+        )
+    }
+}
+
+#[cfg(test)]
+mod terminal_tests {
+    use super::*;
+    use crate::Dim;
+
+    #[test]
+    fn base64_padding() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn sixel_bands() {
+        // Seven rows: one full band and one band with a single row.
+        let img = Image::blank_with_colour(Dim { w: 8, h: 7 }, Colour::WHITE);
+        let sixel = img.to_sixel();
+        let white = sixel_index(Colour::WHITE);
+
+        assert!(sixel.starts_with("\x1bP0;1;0q\"1;1;8;7"));
+        assert!(sixel.ends_with(&format!("-#{white}!8@-\x1b\\")));
+        assert!(sixel.contains(&format!("#{white}!8~-")));
+    }
+
+    #[test]
+    fn kitty_chunks() {
+        // 2048 pixels of 4 bytes become 10924 characters of base64, which needs three chunks.
+        let img = Image::blank(Dim { w: 64, h: 32 });
+        let escape = img.to_kitty_escape();
+
+        assert!(escape.starts_with("\x1b_Ga=T,f=32,s=64,v=32,m=1;"));
+        assert_eq!(escape.matches("\x1b_G").count(), 3);
+        assert_eq!(escape.matches("m=1;").count(), 2);
+        assert!(escape.contains("\x1b_Gm=0;"));
+    }
+}
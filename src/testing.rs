@@ -0,0 +1,282 @@
+//! Assertions for visual regression tests. Only available with the `png` feature.
+//!
+//! `assert_images_eq!` and `assert_images_similar!` compare two images pixel by pixel. When
+//! they differ, the actual image, the expected image and a diff image, in which the differing
+//! pixels are red, are saved as PNGs before the test fails, so the failure can be inspected
+//! instead of guessed at. The files go to `$KODAK_TEST_OUTPUT` if it is set, and otherwise to
+//! `kodak-test-failures` in the cargo target directory.
+//!
+//! ```
+//! use kodak::*;
+//!
+//! let expected = Image::blank_with_colour(Dim::square(4), Colour::rgb(200, 100, 0));
+//! let actual = expected.clone().adjust_channels(1.01, 1.0, 1.0);
+//! assert_images_similar!(actual, expected, 2);
+//! ```
+
+use crate::{Colour, Dim, Image, Loc};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// How two images differ, as found by `compare()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mismatch {
+    /// The images do not have the same dimensions.
+    Dimensions {
+        /// The dimensions of the actual image.
+        actual: Dim,
+        /// The dimensions of the expected image.
+        expected: Dim,
+    },
+    /// Some pixels differ by more than the tolerance.
+    Pixels {
+        /// The number of pixels that differ by more than the tolerance.
+        count: usize,
+        /// The first of those pixels, in reading order.
+        first: Loc,
+        /// The largest difference in any channel, over all pixels.
+        max_difference: u8,
+    },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mismatch::Dimensions { actual, expected } => write!(
+                f,
+                "the images have different sizes: {}x{} != {}x{}",
+                actual.w, actual.h, expected.w, expected.h
+            ),
+            Mismatch::Pixels {
+                count,
+                first,
+                max_difference,
+            } => write!(
+                f,
+                "{count} pixel(s) differ, the first at ({}, {}), by up to {max_difference}",
+                first.x, first.y
+            ),
+        }
+    }
+}
+
+/// Returns the largest difference between two colours in any channel, alpha included.
+fn difference(a: Colour, b: Colour) -> u8 {
+    let (a, b) = (a.to_array(), b.to_array());
+    (0..4).map(|i| a[i].abs_diff(b[i])).max().unwrap_or(0)
+}
+
+/// Compares two images, allowing every channel of every pixel to differ by up to `tolerance`.
+///
+/// # Examples
+///
+/// ```
+/// # use kodak::*;
+/// # use kodak::testing::{compare, Mismatch};
+/// let grey = Image::blank_with_colour(Dim::square(2), Colour::rgb(100, 100, 100));
+/// let lighter = Image::blank_with_colour(Dim::square(2), Colour::rgb(103, 100, 100));
+/// assert_eq!(compare(&lighter, &grey, 3), Ok(()));
+/// assert!(matches!(compare(&lighter, &grey, 2), Err(Mismatch::Pixels { count: 4, .. })));
+/// ```
+pub fn compare(actual: &Image, expected: &Image, tolerance: u8) -> Result<(), Mismatch> {
+    let (dim, expected_dim) = (actual.get_dimensions(), expected.get_dimensions());
+    if dim != expected_dim {
+        return Err(Mismatch::Dimensions {
+            actual: dim,
+            expected: expected_dim,
+        });
+    }
+
+    let mut count = 0;
+    let mut first = None;
+    let mut max_difference = 0;
+    for (i, (&a, &e)) in actual.pixels.iter().zip(expected.pixels.iter()).enumerate() {
+        let d = difference(a, e);
+        max_difference = max_difference.max(d);
+        if d > tolerance {
+            count += 1;
+            first.get_or_insert(Loc::from_index(i, dim));
+        }
+    }
+    match first {
+        None => Ok(()),
+        Some(first) => Err(Mismatch::Pixels {
+            count,
+            first,
+            max_difference,
+        }),
+    }
+}
+
+/// Draws the differences between two images of the same size: pixels that differ by more than
+/// `tolerance` are red, and the others are a faded grey version of `expected`.
+///
+/// # Panics
+///
+/// * if the images do not have the same dimensions.
+pub fn diff_image(actual: &Image, expected: &Image, tolerance: u8) -> Image {
+    let dim = expected.get_dimensions();
+    assert_eq!(
+        actual.get_dimensions(),
+        dim,
+        "Only images of the same size can be diffed."
+    );
+
+    let pixels = actual
+        .pixels
+        .iter()
+        .zip(expected.pixels.iter())
+        .map(|(&a, &e)| {
+            if difference(a, e) > tolerance {
+                Colour::rgb(255, 0, 0)
+            } else {
+                let grey = (e.relative_luminance() * 255.0) as u8;
+                Colour::rgb(grey, grey, grey).mix(Colour::WHITE, 0.7)
+            }
+        })
+        .collect();
+    Image::from_pixels(dim, pixels)
+}
+
+/// Returns the directory that the images of failed assertions are saved to.
+pub fn output_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("KODAK_TEST_OUTPUT") {
+        return dir.into();
+    }
+    let target = std::env::var_os("CARGO_TARGET_DIR").map_or("target".into(), PathBuf::from);
+    target.join("kodak-test-failures")
+}
+
+/// Compares the images and, if they differ, saves them and the diff to `dir` under `name`.
+/// Returns the failure message.
+fn check(
+    actual: &Image,
+    expected: &Image,
+    tolerance: u8,
+    name: &str,
+    dir: &Path,
+) -> Result<(), String> {
+    let mismatch = match compare(actual, expected, tolerance) {
+        Ok(()) => return Ok(()),
+        Err(mismatch) => mismatch,
+    };
+
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect();
+    let mut saved = vec![
+        (actual.clone(), dir.join(format!("{name}.actual.png"))),
+        (expected.clone(), dir.join(format!("{name}.expected.png"))),
+    ];
+    if let Mismatch::Pixels { .. } = mismatch {
+        let diff = diff_image(actual, expected, tolerance);
+        saved.push((diff, dir.join(format!("{name}.diff.png"))));
+    }
+
+    let written = std::fs::create_dir_all(dir)
+        .and_then(|()| saved.iter().try_for_each(|(img, path)| img.save(path)));
+    let location = match written {
+        Ok(()) => format!(
+            "were saved as {}",
+            dir.join(format!("{name}.*.png")).display()
+        ),
+        Err(e) => format!("could not be saved in {}: {e}", dir.display()),
+    };
+    Err(format!(
+        "images are not {}: {mismatch}\n  the images {location}",
+        if tolerance == 0 { "equal" } else { "similar" }
+    ))
+}
+
+/// The function behind `assert_images_eq!` and `assert_images_similar!`. `name` is used for the
+/// file names of the saved images.
+///
+/// # Panics
+///
+/// * if the images differ by more than `tolerance`.
+#[track_caller]
+pub fn assert_similar(actual: &Image, expected: &Image, tolerance: u8, name: &str) {
+    if let Err(message) = check(actual, expected, tolerance, name, &output_dir()) {
+        panic!("{message}");
+    }
+}
+
+/// Asserts that two images are identical. If they are not, the test fails and the images and
+/// their diff are saved, as described in the `testing` module.
+///
+/// # Examples
+///
+/// ```
+/// # use kodak::*;
+/// let img = Image::blank_with_colour(Dim::square(4), Colour::WHITE);
+/// assert_images_eq!(img.flip_horizontal(), img);
+/// ```
+#[macro_export]
+macro_rules! assert_images_eq {
+    ($actual:expr, $expected:expr $(,)?) => {
+        $crate::assert_images_similar!($actual, $expected, 0)
+    };
+}
+
+/// Asserts that no channel of any pixel differs by more than `tolerance` between two images.
+/// If it does, the test fails and the images and their diff are saved, as described in the
+/// `testing` module.
+#[macro_export]
+macro_rules! assert_images_similar {
+    ($actual:expr, $expected:expr, $tolerance:expr $(,)?) => {
+        $crate::testing::assert_similar(
+            &$actual,
+            &$expected,
+            $tolerance,
+            concat!(module_path!(), "-", line!()),
+        )
+    };
+}
+
+#[cfg(test)]
+mod testing_tests {
+    use super::*;
+
+    #[test]
+    fn saves_failures() {
+        let dir = std::env::temp_dir().join("kodak_testing");
+        let _ = std::fs::remove_dir_all(&dir);
+        let expected = Image::blank_with_colour(Dim { w: 3, h: 2 }, Colour::rgb(0, 0, 200));
+        let actual = expected
+            .clone()
+            .fill_region(Image::blank(Dim::square(1)).as_region(), Colour::WHITE);
+
+        assert!(check(&actual, &expected, 0, "same::one", &dir.join("a")).is_err());
+        assert!(check(&expected, &expected, 0, "same", &dir.join("b")).is_ok());
+        assert!(!dir.join("b").exists());
+
+        let diff = Image::open(dir.join("a/same--one.diff.png")).unwrap();
+        assert_eq!(
+            diff.get_pixel(Loc { x: 0, y: 0 }),
+            Ok(Colour::rgb(255, 0, 0))
+        );
+        assert_ne!(
+            diff.get_pixel(Loc { x: 2, y: 1 }),
+            Ok(Colour::rgb(255, 0, 0))
+        );
+        assert!(dir.join("a/same--one.actual.png").exists());
+
+        let small = Image::blank(Dim::square(1));
+        let message = check(&small, &expected, 0, "size", &dir).unwrap_err();
+        assert!(message.contains("1x1 != 3x2"));
+        assert!(!dir.join("size.diff.png").exists());
+    }
+
+    #[test]
+    #[should_panic(expected = "images are not similar: 6 pixel(s) differ")]
+    fn macro_panics() {
+        let a = Image::blank_with_colour(Dim { w: 3, h: 2 }, Colour::rgb(10, 10, 10));
+        let b = Image::blank_with_colour(Dim { w: 3, h: 2 }, Colour::rgb(20, 10, 10));
+        std::env::set_var(
+            "KODAK_TEST_OUTPUT",
+            std::env::temp_dir().join("kodak_testing_macro"),
+        );
+        assert_images_similar!(a, b, 9);
+    }
+}
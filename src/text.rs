@@ -0,0 +1,633 @@
+//! Text rendering: TrueType and OpenType fonts with fallbacks, measurement, and multi-line
+//! layout.
+
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+use ab_glyph::{Font as _, FontArc, PxScale, ScaleFont};
+
+use crate::{Colour, Dim, Image, Loc, Mask};
+
+/// A TrueType or OpenType font.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use kodak::*;
+/// let font = Font::load("assets/DejaVuSans.ttf").unwrap();
+/// let size = font.measure("Hello, world!", 32.0);
+/// let img = Image::blank_with_colour(size.expand(16), Colour::WHITE).draw_text(
+///     "Hello, world!",
+///     &font,
+///     32.0,
+///     Loc { x: 8, y: 8 },
+///     &TextStyle::fill(Colour::BLACK),
+///     &TextLayout::default(),
+/// );
+/// ```
+#[derive(Clone)]
+pub struct Font {
+    inner: FontArc,
+}
+
+impl fmt::Debug for Font {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Font")
+            .field("glyph_count", &self.inner.glyph_count())
+            .finish_non_exhaustive()
+    }
+}
+
+/// How the lines of a block of text are aligned to each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Alignment {
+    /// Every line starts at the left edge.
+    #[default]
+    Left,
+    /// Every line is centred.
+    Center,
+    /// Every line ends at the right edge.
+    Right,
+    /// Wrapped lines are stretched to fill the whole width by widening the spaces between
+    /// words. The last line of a paragraph is aligned to the left.
+    Justify,
+}
+
+/// Options for laying out a block of text over several lines.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextLayout {
+    /// The width in pixels at which lines are wrapped between words, or `None` to only break
+    /// lines at newlines. Words that are wider than this on their own are not split.
+    pub max_width: Option<u32>,
+    /// How lines are aligned within the block.
+    pub alignment: Alignment,
+    /// The distance between baselines, as a multiple of the font's own line height.
+    pub line_spacing: f32,
+}
+
+impl Default for TextLayout {
+    fn default() -> Self {
+        TextLayout {
+            max_width: None,
+            alignment: Alignment::Left,
+            line_spacing: 1.0,
+        }
+    }
+}
+
+/// A character placed on its baseline by `layout()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct PlacedChar {
+    pub(crate) c: char,
+    pub(crate) x: f32,
+    /// The index of the line the character is on.
+    pub(crate) line: usize,
+}
+
+/// Lays out text into lines, wrapping between words and aligning the lines.
+///
+/// `advance` returns how far the pen moves for a character, given the character before it on
+/// the same word (for kerning). Returns the placed characters, without whitespace, along with
+/// the width of the block and the number of lines.
+pub(crate) fn layout(
+    text: &str,
+    options: &TextLayout,
+    advance: impl Fn(Option<char>, char) -> f32,
+) -> (Vec<PlacedChar>, f32, usize) {
+    let word_width = |word: &str| {
+        let mut previous = None;
+        word.chars()
+            .map(|c| {
+                let width = advance(previous, c);
+                previous = Some(c);
+                width
+            })
+            .sum::<f32>()
+    };
+    let space = advance(None, ' ');
+    let max_width = options.max_width.map(|w| w as f32);
+
+    // Every line is a list of words with their widths, and whether it ends its paragraph.
+    let mut lines: Vec<(Vec<(&str, f32)>, bool)> = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line: Vec<(&str, f32)> = Vec::new();
+        let mut line_width = 0.0;
+        for word in paragraph.split_whitespace() {
+            let width = word_width(word);
+            let fits = match max_width {
+                Some(max) => line.is_empty() || line_width + space + width <= max,
+                None => true,
+            };
+            if !fits {
+                lines.push((std::mem::take(&mut line), false));
+                line_width = 0.0;
+            }
+            if !line.is_empty() {
+                line_width += space;
+            }
+            line_width += width;
+            line.push((word, width));
+        }
+        lines.push((line, true));
+    }
+
+    let natural_width = |words: &[(&str, f32)]| {
+        words.iter().map(|(_, w)| w).sum::<f32>() + space * words.len().saturating_sub(1) as f32
+    };
+    let block_width = max_width.unwrap_or_else(|| {
+        lines
+            .iter()
+            .map(|(words, _)| natural_width(words))
+            .fold(0.0, f32::max)
+    });
+
+    let mut placed = Vec::new();
+    for (index, (words, ends_paragraph)) in lines.iter().enumerate() {
+        let slack = (block_width - natural_width(words)).max(0.0);
+        let (mut x, gap) = match options.alignment {
+            Alignment::Left => (0.0, space),
+            Alignment::Center => (slack / 2.0, space),
+            Alignment::Right => (slack, space),
+            Alignment::Justify if *ends_paragraph || words.len() < 2 => (0.0, space),
+            Alignment::Justify => (0.0, space + slack / (words.len() - 1) as f32),
+        };
+        for (word, width) in words {
+            let mut previous = None;
+            let mut pen = x;
+            for c in word.chars() {
+                pen += advance(previous, c) - advance(None, c);
+                placed.push(PlacedChar {
+                    c,
+                    x: pen,
+                    line: index,
+                });
+                pen += advance(None, c);
+                previous = Some(c);
+            }
+            x += width + gap;
+        }
+    }
+    (placed, block_width, lines.len())
+}
+
+/// Returns whether a character is a combining mark, which is drawn on top of the character
+/// before it instead of taking up space of its own.
+fn is_combining_mark(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0300}'..='\u{036F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE20}'..='\u{FE2F}'
+    )
+}
+
+/// Returns whether a character is invisible and only changes how its neighbours are shown,
+/// like zero width joiners and variation selectors. These are skipped entirely.
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{200B}'..='\u{200F}' | '\u{2060}' | '\u{FE00}'..='\u{FE0F}' | '\u{FEFF}'
+    )
+}
+
+impl Font {
+    /// Creates a font from the contents of a TrueType (`.ttf`) or OpenType (`.otf`) file.
+    pub fn from_bytes(data: Vec<u8>) -> Result<Font, &'static str> {
+        FontArc::try_from_vec(data)
+            .map(|inner| Font { inner })
+            .map_err(|_| "The data is not a valid TrueType or OpenType font.")
+    }
+
+    /// Loads a TrueType (`.ttf`) or OpenType (`.otf`) font from a file.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Font> {
+        Font::from_bytes(std::fs::read(path)?)
+            .map_err(|msg| io::Error::new(io::ErrorKind::InvalidData, msg))
+    }
+
+    /// Returns whether the font has a glyph for a character.
+    pub fn has_glyph(&self, c: char) -> bool {
+        self.inner.glyph_id(c).0 != 0
+    }
+
+    /// Returns the distance between the baselines of two lines at a size in pixels.
+    pub fn line_height(&self, size: f32) -> f32 {
+        let scaled = self.inner.as_scaled(PxScale::from(size));
+        scaled.height() + scaled.line_gap()
+    }
+
+    /// Returns the size of the box that `text` takes up on a single line per paragraph, when
+    /// drawn at `size` pixels.
+    ///
+    /// Lines only break at newlines; use `measure_layout()` to wrap text to a maximum width.
+    pub fn measure(&self, text: &str, size: f32) -> Dim {
+        FontStack::from(self).measure(text, size)
+    }
+
+    /// Returns the size of the box that `text` takes up when laid out with `options` and drawn
+    /// at `size` pixels by `Image::draw_text()`.
+    pub fn measure_layout(&self, text: &str, size: f32, options: &TextLayout) -> Dim {
+        FontStack::from(self).measure_layout(text, size, options)
+    }
+}
+
+/// A list of fonts in order of preference. Every character is drawn with the first font that
+/// has a glyph for it, so a font for another script or for symbols can fill the gaps of the
+/// main font.
+///
+/// Line heights come from the first font. Characters that no font has are drawn with the first
+/// font's placeholder glyph, except for combining marks, which are left out.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use kodak::*;
+/// let fonts = FontStack::new(Font::load("assets/DejaVuSans.ttf").unwrap())
+///     .with_fallback(Font::load("assets/NotoSansJP-Regular.ttf").unwrap());
+/// let img = Image::blank(Dim { w: 320, h: 64 }).draw_text(
+///     "Kodak コダック",
+///     &fonts,
+///     32.0,
+///     Loc { x: 8, y: 8 },
+///     &TextStyle::fill(Colour::WHITE),
+///     &TextLayout::default(),
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct FontStack {
+    fonts: Vec<Font>,
+}
+
+impl From<Font> for FontStack {
+    fn from(font: Font) -> Self {
+        FontStack::new(font)
+    }
+}
+
+impl From<&Font> for FontStack {
+    fn from(font: &Font) -> Self {
+        FontStack::new(font.clone())
+    }
+}
+
+impl From<&FontStack> for FontStack {
+    fn from(stack: &FontStack) -> Self {
+        stack.clone()
+    }
+}
+
+impl FontStack {
+    /// Creates a stack with a single font, which also provides the line height.
+    pub fn new(primary: Font) -> Self {
+        FontStack {
+            fonts: vec![primary],
+        }
+    }
+
+    /// Adds a font to the end of the stack, to be used for characters that none of the fonts
+    /// before it have.
+    pub fn with_fallback(mut self, font: Font) -> Self {
+        self.push_fallback(font);
+        self
+    }
+
+    /// Adds a font to the end of the stack, in place.
+    pub fn push_fallback(&mut self, font: Font) {
+        self.fonts.push(font);
+    }
+
+    /// Returns the fonts in the stack, in order of preference.
+    pub fn fonts(&self) -> &[Font] {
+        &self.fonts
+    }
+
+    /// Returns the first font that has a glyph for `c`, if any.
+    pub fn font_for(&self, c: char) -> Option<&Font> {
+        self.fonts.iter().find(|font| font.has_glyph(c))
+    }
+
+    /// Returns the distance between the baselines of two lines at a size in pixels, according
+    /// to the first font.
+    pub fn line_height(&self, size: f32) -> f32 {
+        self.fonts[0].line_height(size)
+    }
+
+    /// Returns how far the pen moves for `c` at a size in pixels, including the kerning between
+    /// it and the character before it when both come from the same font.
+    fn advance(&self, size: f32, previous: Option<char>, c: char) -> f32 {
+        if is_combining_mark(c) || is_invisible(c) {
+            return 0.0;
+        }
+        let font = self.font_for(c).unwrap_or(&self.fonts[0]);
+        let scaled = font.inner.as_scaled(PxScale::from(size));
+        let id = font.inner.glyph_id(c);
+        let kern = previous
+            .filter(|&p| font.has_glyph(p))
+            .map_or(0.0, |p| scaled.kern(font.inner.glyph_id(p), id));
+        scaled.h_advance(id) + kern
+    }
+
+    /// Returns the size of the box that `text` takes up on a single line per paragraph, when
+    /// drawn at `size` pixels.
+    pub fn measure(&self, text: &str, size: f32) -> Dim {
+        self.measure_layout(text, size, &TextLayout::default())
+    }
+
+    /// Returns the size of the box that `text` takes up when laid out with `options` and drawn
+    /// at `size` pixels by `Image::draw_text()`.
+    pub fn measure_layout(&self, text: &str, size: f32, options: &TextLayout) -> Dim {
+        let (_, width, lines) = layout(text, options, |p, c| self.advance(size, p, c));
+        let scaled = self.fonts[0].inner.as_scaled(PxScale::from(size));
+        let height =
+            self.line_height(size) * options.line_spacing * (lines - 1) as f32 + scaled.height();
+        Dim {
+            w: width.ceil() as u32,
+            h: height.ceil() as u32,
+        }
+    }
+}
+
+/// A drop shadow cast by text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shadow {
+    /// The colour of the shadow; a translucent colour gives a lighter shadow.
+    pub colour: Colour,
+    /// How far the shadow is moved from the text, in pixels to the right and down.
+    pub offset: (i32, i32),
+    /// How far the edges of the shadow are softened, in pixels. 0 gives a hard shadow.
+    pub blur: u32,
+}
+
+/// How text is painted by `Image::draw_text()`.
+///
+/// The parts are painted from the back to the front: background, shadow, stroke, and fill.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use kodak::*;
+/// // A meme-style caption: white text with a thick black outline.
+/// let font = Font::load("assets/Impact.ttf").unwrap();
+/// let photo = Image::load_png("assets/cat.png").unwrap();
+/// let style = TextStyle {
+///     stroke: Some((Colour::BLACK, 3)),
+///     ..TextStyle::fill(Colour::WHITE)
+/// };
+/// photo
+///     .draw_text("I CAN HAS", &font, 48.0, Loc { x: 16, y: 8 }, &style, &TextLayout::default())
+///     .save_png("meme.png").unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextStyle {
+    /// The colour of the glyphs themselves.
+    pub fill: Colour,
+    /// An outline around the glyphs, given as its colour and width in pixels.
+    pub stroke: Option<(Colour, u32)>,
+    /// A drop shadow behind the glyphs and their outline.
+    pub shadow: Option<Shadow>,
+    /// A box behind the whole block of text, with a little padding around it.
+    pub background: Option<Colour>,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        TextStyle::fill(Colour::BLACK)
+    }
+}
+
+impl TextStyle {
+    /// Creates a style that only fills the glyphs with a colour.
+    pub fn fill(colour: Colour) -> Self {
+        TextStyle {
+            fill: colour,
+            stroke: None,
+            shadow: None,
+            background: None,
+        }
+    }
+}
+
+/// Paints `colour` onto an image wherever `mask` covers it, alpha compositing partial coverage.
+fn paint(img: &mut Image, mask: &Mask, colour: Colour) {
+    for (pixel, &coverage) in img.pixels.iter_mut().zip(mask.values()) {
+        if coverage > 0 {
+            let alpha = (colour.a as u32 * coverage as u32 + 127) / 255;
+            *pixel = Colour {
+                a: alpha as u8,
+                ..colour
+            }
+            .over(*pixel);
+        }
+    }
+}
+
+// The following impl block defines text drawing functions for Images.
+impl Image {
+    /// Renders the anti-aliased coverage of laid out text onto a mask the size of this image.
+    fn text_coverage(
+        &self,
+        text: &str,
+        fonts: &FontStack,
+        size: f32,
+        loc: Loc,
+        options: &TextLayout,
+    ) -> Mask {
+        let scale = PxScale::from(size);
+        let ascent = fonts.fonts[0].inner.as_scaled(scale).ascent();
+        let line_height = fonts.line_height(size) * options.line_spacing;
+        let (placed, _, _) = layout(text, options, |p, c| fonts.advance(size, p, c));
+
+        let dim = self.get_dimensions();
+        let mut mask = Mask::new(dim);
+        for PlacedChar { c, x, line } in placed {
+            if is_invisible(c) {
+                continue;
+            }
+            let font = match fonts.font_for(c) {
+                Some(font) => font,
+                None if is_combining_mark(c) => continue,
+                None => &fonts.fonts[0],
+            };
+            let position = ab_glyph::point(
+                loc.x as f32 + x,
+                loc.y as f32 + ascent + line as f32 * line_height,
+            );
+            let glyph = font
+                .inner
+                .glyph_id(c)
+                .with_scale_and_position(scale, position);
+            let Some(outline) = font.inner.outline_glyph(glyph) else {
+                continue;
+            };
+            let bounds = outline.px_bounds();
+            outline.draw(|gx, gy, coverage| {
+                let px = bounds.min.x as i64 + gx as i64;
+                let py = bounds.min.y as i64 + gy as i64;
+                if px < 0 || py < 0 || px >= dim.w as i64 || py >= dim.h as i64 {
+                    return;
+                }
+                let l = Loc {
+                    x: px as u32,
+                    y: py as u32,
+                };
+                let value = (coverage.min(1.0) * 255.0).round() as u8;
+                mask.set(l, mask.get(l).max(value));
+            });
+        }
+        mask
+    }
+
+    /// Draws text with its top left corner at `loc`, laid out according to `options` and
+    /// painted according to `style`.
+    ///
+    /// `fonts` is either a single `Font` or a `FontStack` with fallbacks. Combining marks are
+    /// drawn on top of the character before them. The text is anti-aliased and alpha
+    /// composited onto the image; parts that fall outside of the image are cut off. The box the
+    /// text occupies can be computed beforehand with `Font::measure_layout()`.
+    pub fn draw_text(
+        &self,
+        text: &str,
+        fonts: impl Into<FontStack>,
+        size: f32,
+        loc: Loc,
+        style: &TextStyle,
+        options: &TextLayout,
+    ) -> Image {
+        let fonts = fonts.into();
+        let dim = self.get_dimensions();
+        let mut result = self.clone();
+
+        if let Some(colour) = style.background {
+            let block = fonts.measure_layout(text, size, options);
+            let stroke = style.stroke.map_or(0, |(_, width)| width);
+            let padding = (stroke as f32 + fonts.line_height(size) * 0.2).round() as i64;
+            let (left, top) = (loc.x as i64 - padding, loc.y as i64 - padding);
+            let right = loc.x as i64 + block.w as i64 + padding;
+            let bottom = loc.y as i64 + block.h as i64 + padding;
+            let area = Mask::from_fn(dim, |l| {
+                let (x, y) = (l.x as i64, l.y as i64);
+                if x >= left && x < right && y >= top && y < bottom {
+                    255
+                } else {
+                    0
+                }
+            });
+            paint(&mut result, &area, colour);
+        }
+
+        let fill = self.text_coverage(text, &fonts, size, loc, options);
+        // The stroke covers everything within its width of the glyphs, with a soft edge.
+        let outline = style.stroke.map(|(colour, width)| {
+            let distances = fill.distance_transform();
+            let covered = Mask::from_fn(dim, |l| {
+                let d = distances[l.as_index(dim)];
+                let edge = ((width as f32 + 0.5 - d).clamp(0.0, 1.0) * 255.0).round() as u8;
+                edge.max(fill.get(l))
+            });
+            (colour, covered)
+        });
+
+        if let Some(shadow) = style.shadow {
+            let caster = outline.as_ref().map_or(&fill, |(_, covered)| covered);
+            let (dx, dy) = (shadow.offset.0 as i64, shadow.offset.1 as i64);
+            let shifted = Mask::from_fn(dim, |l| {
+                let (x, y) = (l.x as i64 - dx, l.y as i64 - dy);
+                if x < 0 || y < 0 {
+                    return 0;
+                }
+                caster.get(Loc {
+                    x: x as u32,
+                    y: y as u32,
+                })
+            });
+            paint(&mut result, &shifted.feather(shadow.blur), shadow.colour);
+        }
+        if let Some((colour, covered)) = &outline {
+            paint(&mut result, covered, *colour);
+        }
+        paint(&mut result, &fill, style.fill);
+        result
+    }
+}
+
+#[cfg(test)]
+mod text_tests {
+    use super::*;
+
+    /// Lays out text in a monospaced font where every character is one unit wide.
+    fn mono(text: &str, max_width: Option<u32>, alignment: Alignment) -> Vec<(char, f32, usize)> {
+        let options = TextLayout {
+            max_width,
+            alignment,
+            ..TextLayout::default()
+        };
+        layout(text, &options, |_, _| 1.0)
+            .0
+            .into_iter()
+            .map(|p| (p.c, p.x, p.line))
+            .collect()
+    }
+
+    #[test]
+    fn wraps_between_words() {
+        let placed = mono("aa bb cc\ndd", Some(5), Alignment::Left);
+        assert_eq!(
+            placed,
+            vec![
+                ('a', 0.0, 0),
+                ('a', 1.0, 0),
+                ('b', 3.0, 0),
+                ('b', 4.0, 0),
+                ('c', 0.0, 1),
+                ('c', 1.0, 1),
+                ('d', 0.0, 2),
+                ('d', 1.0, 2),
+            ]
+        );
+        // A word that is too wide on its own overflows instead of being split.
+        assert_eq!(mono("abcdefg", Some(3), Alignment::Left).len(), 7);
+    }
+
+    #[test]
+    fn aligns_lines() {
+        let first_x = |alignment| mono("a bb ccc", Some(6), alignment)[0].1;
+        assert_eq!(first_x(Alignment::Left), 0.0);
+        assert_eq!(first_x(Alignment::Center), 1.0);
+        assert_eq!(first_x(Alignment::Right), 2.0);
+
+        // "a bb" is stretched to six units; "ccc" ends the paragraph and stays left aligned.
+        let justified = mono("a bb ccc", Some(6), Alignment::Justify);
+        assert_eq!(justified[2], ('b', 5.0, 0));
+        assert_eq!(justified[3], ('c', 0.0, 1));
+        assert!(Font::from_bytes(vec![0; 16]).is_err());
+    }
+
+    #[test]
+    fn paint_composites_coverage() {
+        let mut img = Image::blank_with_colour(Dim { w: 3, h: 1 }, Colour::WHITE);
+        let mask = Mask::from_fn(img.get_dimensions(), |l| [0, 128, 255][l.x as usize]);
+        paint(&mut img, &mask, Colour::BLACK);
+
+        assert_eq!(img.pixels[0], Colour::WHITE);
+        assert_eq!(img.pixels[1], Colour::rgb(127, 127, 127));
+        assert_eq!(img.pixels[2], Colour::BLACK);
+    }
+
+    #[test]
+    fn combining_marks_take_no_space() {
+        let options = TextLayout::default();
+        let advance = |_, c| if is_combining_mark(c) { 0.0 } else { 1.0 };
+        let (placed, width, _) = layout("e\u{301}x", &options, advance);
+
+        // The acute accent sits where the e ends, on top of it.
+        let xs: Vec<f32> = placed.iter().map(|p| p.x).collect();
+        assert_eq!(xs, vec![0.0, 1.0, 1.0]);
+        assert_eq!(width, 2.0);
+        assert!(is_invisible('\u{200D}') && !is_combining_mark('e'));
+    }
+}
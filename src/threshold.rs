@@ -0,0 +1,146 @@
+//! Adaptive thresholding, which turns unevenly lit images such as photographed documents into
+//! black and white.
+
+use crate::analysis::brightness;
+use crate::filter::gaussian_weights;
+use crate::integral::IntegralImage;
+use crate::{Colour, Dim, Image, Loc};
+
+/// How `Image::adaptive_threshold()` computes the local brightness that each pixel is compared
+/// with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThresholdMethod {
+    /// The plain average of the window around the pixel. Fast, whatever the size of the window.
+    #[default]
+    Mean,
+    /// An average weighted by a Gaussian, so that nearby pixels count most. Follows changes in
+    /// lighting more closely, and is slower for large windows.
+    Gaussian,
+}
+
+// The following impl block defines thresholding functions for Images.
+impl Image {
+    /// Turns the image into opaque black and white, comparing the brightness of every pixel
+    /// with the brightness around it rather than with a single threshold.
+    ///
+    /// A pixel becomes white if its brightness, from 0 to 255, is above the average of the
+    /// `window` by `window` pixels around it minus `c`, and black otherwise. This copes with
+    /// shadows and gradients across a page that a global threshold cannot. The window should be
+    /// a few times as large as the strokes of the text; a `c` of around 10 keeps noise in flat
+    /// areas white. Windows are cut off at the borders of the image.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use kodak::*;
+    /// let photo = Image::load_png("assets/receipt.png").unwrap();
+    /// photo
+    ///     .adaptive_threshold(25, 10.0, ThresholdMethod::Mean)
+    ///     .save_png("receipt_binary.png").unwrap();
+    /// ```
+    pub fn adaptive_threshold(&self, window: u32, c: f32, method: ThresholdMethod) -> Image {
+        let dim = self.get_dimensions();
+        let values: Vec<f32> = brightness(self).into_iter().map(|v| v * 255.0).collect();
+        let (w, h) = (self.width as i64, self.height as i64);
+        let r = (window / 2) as i64;
+
+        let local: Vec<f32> = match method {
+            ThresholdMethod::Mean => {
+                let table = IntegralImage::new(dim, values.iter().map(|&v| v as f64));
+                (0..values.len())
+                    .map(|i| {
+                        let (x, y) = (i as i64 % w, i as i64 / w);
+                        let (x0, y0) = ((x - r).max(0), (y - r).max(0));
+                        let (x1, y1) = ((x + r + 1).min(w), (y + r + 1).min(h));
+                        let d = Dim {
+                            w: (x1 - x0) as u32,
+                            h: (y1 - y0) as u32,
+                        };
+                        let l = Loc {
+                            x: x0 as u32,
+                            y: y0 as u32,
+                        };
+                        (table.sum(l, d) / (d.w * d.h) as f64) as f32
+                    })
+                    .collect()
+            }
+            ThresholdMethod::Gaussian => {
+                // The standard deviation that OpenCV picks for a window of this size.
+                let sigma = 0.3 * ((window.max(1) as f32 - 1.0) * 0.5 - 1.0) + 0.8;
+                let weights = gaussian_weights(sigma);
+                let k = weights.len() as i64 / 2;
+                // Weights that fall outside of the image are left out, and the rest rescaled.
+                let pass = |values: &[f32], horizontal: bool| -> Vec<f32> {
+                    (0..values.len())
+                        .map(|i| {
+                            let (x, y) = (i as i64 % w, i as i64 / w);
+                            let (mut sum, mut total) = (0.0, 0.0);
+                            for (j, weight) in (-k..=k).zip(&weights) {
+                                let (sx, sy) = if horizontal { (x + j, y) } else { (x, y + j) };
+                                if (0..w).contains(&sx) && (0..h).contains(&sy) {
+                                    sum += weight * values[(sy * w + sx) as usize];
+                                    total += weight;
+                                }
+                            }
+                            sum / total
+                        })
+                        .collect()
+                };
+                pass(&pass(&values, true), false)
+            }
+        };
+
+        let pixels = values
+            .iter()
+            .zip(&local)
+            .map(|(&v, &mean)| {
+                if v > mean - c {
+                    Colour::WHITE
+                } else {
+                    Colour::BLACK
+                }
+            })
+            .collect();
+        Image {
+            pixels,
+            ..self.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod threshold_tests {
+    use super::*;
+
+    #[test]
+    fn copes_with_uneven_lighting() {
+        // A page that darkens from left to right, with a dark line across it that is brighter
+        // on the left than the paper on the right.
+        let mut page = Image::blank(Dim { w: 60, h: 20 });
+        for (y, row) in page.rows_mut().enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                let paper = 240 - 2 * x as u8;
+                let v = if (9..11).contains(&y) {
+                    paper - 60
+                } else {
+                    paper
+                };
+                *pixel = Colour::rgb(v, v, v);
+            }
+        }
+        for method in [ThresholdMethod::Mean, ThresholdMethod::Gaussian] {
+            let binary = page.adaptive_threshold(9, 10.0, method);
+            let at = |x, y| binary.get_pixel(Loc { x, y }).unwrap();
+            for x in [0, 30, 59] {
+                assert_eq!((at(x, 2), at(x, 17)), (Colour::WHITE, Colour::WHITE));
+                assert_eq!((at(x, 9), at(x, 10)), (Colour::BLACK, Colour::BLACK));
+            }
+        }
+
+        let empty = Image::blank(Dim { w: 0, h: 3 });
+        assert_eq!(
+            empty.adaptive_threshold(5, 0.0, ThresholdMethod::Gaussian),
+            empty
+        );
+    }
+}
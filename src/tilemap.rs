@@ -0,0 +1,127 @@
+//! Rendering tilemaps, as used by 2D games, from a tileset image.
+
+use crate::{Colour, Dim, Image};
+use alloc::vec::Vec;
+
+// The following impl block defines tilemap functions for Images.
+impl Image {
+    /// Indicates an empty cell in the map passed to `render_tilemap()`.
+    pub const EMPTY_TILE: u32 = u32::MAX;
+
+    /// Draws a map of tiles taken from a tileset.
+    ///
+    /// The tileset is cut into tiles of `tile_dim`, numbered from 0 in reading order; a partial
+    /// tile at its right or bottom edge is ignored. `map` holds the tile number of every cell,
+    /// one row of cells at a time. Rows may be shorter than the longest one, and the cells they
+    /// lack are left transparent, as are cells holding `Image::EMPTY_TILE`. The tiles are copied
+    /// as they are, transparency included.
+    ///
+    /// This returns an `Err` if a tile is empty or larger than the tileset, if the map refers to
+    /// a tile that the tileset does not have, or if the result would be too large.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// // A tileset of two 8 by 8 tiles: grass and water.
+    /// let water = Region { l: Loc { x: 8, y: 0 }, d: Dim::square(8) };
+    /// let tileset = Image::blank_with_colour(Dim { w: 16, h: 8 }, Colour::rgb(40, 160, 40))
+    ///     .fill_region(water, Colour::rgb(30, 60, 200));
+    /// let map = vec![vec![0, 0, 1], vec![0, 1, 1]];
+    /// let level = Image::render_tilemap(&tileset, Dim::square(8), &map).unwrap();
+    /// assert_eq!(level.get_dimensions(), Dim { w: 24, h: 16 });
+    /// assert_eq!(level.get_pixel(Loc { x: 20, y: 3 }), Ok(Colour::rgb(30, 60, 200)));
+    /// ```
+    pub fn render_tilemap(
+        tileset: &Image,
+        tile_dim: Dim,
+        map: &[Vec<u32>],
+    ) -> Result<Image, &'static str> {
+        if tile_dim.w == 0 || tile_dim.h == 0 {
+            return Err("Tiles must be at least one pixel wide and high.");
+        }
+        let columns = tileset.width / tile_dim.w;
+        let tile_count = columns.saturating_mul(tileset.height / tile_dim.h);
+        if tile_count == 0 {
+            return Err("The tileset is smaller than a single tile.");
+        }
+
+        let too_large = "The tilemap is too large to render.";
+        let widest = map.iter().map(Vec::len).max().unwrap_or(0);
+        let cells = |count: usize| u32::try_from(count).map_err(|_| too_large);
+        let dim = Dim {
+            w: cells(widest)?.checked_mul(tile_dim.w).ok_or(too_large)?,
+            h: cells(map.len())?.checked_mul(tile_dim.h).ok_or(too_large)?,
+        };
+
+        let mut img = Image::blank_with_colour(dim, Colour::TRANSPARENT);
+        let tw = tile_dim.w as usize;
+        for (cy, row) in map.iter().enumerate() {
+            for (cx, &tile) in row.iter().enumerate() {
+                if tile == Image::EMPTY_TILE {
+                    continue;
+                }
+                if tile >= tile_count {
+                    return Err("The map refers to a tile that is not in the tileset.");
+                }
+                let sx = (tile % columns) as usize * tw;
+                let sy = (tile / columns) * tile_dim.h;
+                for ty in 0..tile_dim.h {
+                    let dy = cy as u32 * tile_dim.h + ty;
+                    img.row_mut(dy)[cx * tw..(cx + 1) * tw]
+                        .copy_from_slice(&tileset.row(sy + ty)[sx..sx + tw]);
+                }
+            }
+        }
+        Ok(img)
+    }
+}
+
+#[cfg(test)]
+mod tilemap_tests {
+    use super::*;
+    use crate::{Loc, Region};
+
+    /// A 3 by 2 tileset of 2 by 2 tiles, each with its number in the red channel, plus a spare
+    /// column and row that do not make up a whole tile.
+    fn tileset() -> Image {
+        let mut img = Image::blank(Dim { w: 7, h: 5 });
+        for tile in 0..6 {
+            img = img.fill_region(
+                Region {
+                    l: Loc {
+                        x: tile % 3 * 2,
+                        y: tile / 3 * 2,
+                    },
+                    d: Dim::square(2),
+                },
+                Colour::rgb(tile as u8, 0, 0),
+            );
+        }
+        img
+    }
+
+    #[test]
+    fn places_tiles() {
+        let map = vec![vec![5, Image::EMPTY_TILE, 0], vec![3]];
+        let img = Image::render_tilemap(&tileset(), Dim::square(2), &map).unwrap();
+        assert_eq!(img.get_dimensions(), Dim { w: 6, h: 4 });
+
+        let at = |x, y| img.get_pixel(Loc { x, y }).unwrap();
+        assert_eq!(at(1, 1), Colour::rgb(5, 0, 0));
+        assert_eq!(at(2, 0), Colour::TRANSPARENT);
+        assert_eq!(at(4, 1), Colour::rgb(0, 0, 0));
+        assert_eq!(at(0, 3), Colour::rgb(3, 0, 0));
+        assert_eq!(at(5, 3), Colour::TRANSPARENT);
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        let tileset = tileset();
+        assert!(Image::render_tilemap(&tileset, Dim::square(2), &[vec![6]]).is_err());
+        assert!(Image::render_tilemap(&tileset, Dim::square(0), &[vec![0]]).is_err());
+        assert!(Image::render_tilemap(&tileset, Dim::square(8), &[vec![0]]).is_err());
+        let empty = Image::render_tilemap(&tileset, Dim::square(2), &[]).unwrap();
+        assert_eq!(empty.get_dimensions(), Dim { w: 0, h: 0 });
+    }
+}
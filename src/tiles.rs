@@ -0,0 +1,143 @@
+//! Deep Zoom (DZI) tile pyramids, for viewing huge images in the browser with viewers such as
+//! OpenSeadragon.
+
+use crate::{Dim, Image, Loc, Metadata, ResizeFilter};
+use std::path::Path;
+
+/// Copies the pixels of a region that lies completely inside the image.
+fn tile(img: &Image, l: Loc, d: Dim) -> Image {
+    let mut pixels = Vec::with_capacity(d.w as usize * d.h as usize);
+    for y in l.y..l.y + d.h {
+        let start = Loc { x: l.x, y }.as_index(img.get_dimensions());
+        pixels.extend_from_slice(&img.pixels[start..start + d.w as usize]);
+    }
+    Image::from_pixels(d, pixels)
+}
+
+// The following impl block defines tiling functions for Images.
+impl Image {
+    /// Saves the image as a Deep Zoom pyramid of PNG tiles.
+    ///
+    /// Following the DZI layout, the descriptor is written to `<base>.dzi` and the tiles to
+    /// `<base>_files/<level>/<column>_<row>.png`. The highest level holds the image at full
+    /// size and every level below it is half as large, down to a single pixel at level 0.
+    /// Tiles are `tile_size` pixels square, except at the right and bottom edges, and do not
+    /// overlap.
+    ///
+    /// `levels` limits how many levels are written, counting down from full size; `None` writes
+    /// all of them, which most viewers expect.
+    ///
+    /// This returns an `Err` if `tile_size` is zero or a file could not be written.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use kodak::*;
+    /// // Writes artwork.dzi and the artwork_files directory.
+    /// let img = Image::load_png("assets/huge_painting.png").unwrap();
+    /// img.save_tiles("artwork", 256, None).unwrap();
+    /// ```
+    pub fn save_tiles(
+        &self,
+        base: impl AsRef<Path>,
+        tile_size: u32,
+        levels: Option<u32>,
+    ) -> Result<(), png::EncodingError> {
+        if tile_size == 0 {
+            return Err(png::EncodingError::IoError(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Tiles must be at least one pixel wide.",
+            )));
+        }
+
+        let base = base.as_ref();
+        let mut files_dir = base.as_os_str().to_owned();
+        files_dir.push("_files");
+        let files_dir = Path::new(&files_dir);
+
+        // The level at which the image is shown at full size.
+        let max_level = self
+            .width
+            .max(self.height)
+            .max(1)
+            .next_power_of_two()
+            .ilog2();
+        let lowest = levels.map_or(0, |n| (max_level + 1).saturating_sub(n));
+
+        let mut level_image = self.clone();
+        for level in (lowest..=max_level).rev() {
+            let dir = files_dir.join(level.to_string());
+            std::fs::create_dir_all(&dir)?;
+
+            let dim = level_image.get_dimensions();
+            for row in 0..dim.h.div_ceil(tile_size) {
+                for column in 0..dim.w.div_ceil(tile_size) {
+                    let l = Loc {
+                        x: column * tile_size,
+                        y: row * tile_size,
+                    };
+                    let d = Dim {
+                        w: tile_size.min(dim.w - l.x),
+                        h: tile_size.min(dim.h - l.y),
+                    };
+                    tile(&level_image, l, d).write_png(
+                        dir.join(format!("{}_{}.png", column, row)),
+                        &Metadata::default(),
+                    )?;
+                }
+            }
+
+            let half = Dim {
+                w: dim.w.div_ceil(2),
+                h: dim.h.div_ceil(2),
+            };
+            level_image = level_image.resize(half, ResizeFilter::Bilinear);
+        }
+
+        let descriptor = format!(
+            concat!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+                "<Image xmlns=\"http://schemas.microsoft.com/deepzoom/2008\" ",
+                "Format=\"png\" Overlap=\"0\" TileSize=\"{}\">\n",
+                "  <Size Width=\"{}\" Height=\"{}\"/>\n",
+                "</Image>\n"
+            ),
+            tile_size, self.width, self.height
+        );
+        let mut descriptor_path = base.as_os_str().to_owned();
+        descriptor_path.push(".dzi");
+        std::fs::write(descriptor_path, descriptor)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tiles_tests {
+    use super::*;
+
+    #[test]
+    fn pyramid_layout() {
+        let base = std::env::temp_dir().join("kodak_pyramid");
+        let files = std::env::temp_dir().join("kodak_pyramid_files");
+        let _ = std::fs::remove_dir_all(&files);
+
+        // 128x128 gives levels 0 (1x1) up to 7 (full size).
+        let img = Image::load_png("tests/fixtures/test.png").unwrap();
+        img.save_tiles(&base, 100, Some(3)).unwrap();
+
+        let full = Image::load_png(files.join("7/1_1.png")).unwrap();
+        assert_eq!(full.get_dimensions(), Dim::square(28));
+        assert_eq!(
+            full.pixels[0],
+            img.get_pixel(Loc { x: 100, y: 100 }).unwrap()
+        );
+        assert!(files.join("6/0_0.png").exists());
+        assert!(!files.join("6/1_0.png").exists());
+        assert!(files.join("5/0_0.png").exists());
+        assert!(!files.join("4").exists());
+
+        let descriptor = std::fs::read_to_string(base.with_extension("dzi")).unwrap();
+        assert!(descriptor.contains("TileSize=\"100\""));
+        assert!(descriptor.contains("<Size Width=\"128\" Height=\"128\"/>"));
+    }
+}
@@ -0,0 +1,95 @@
+//! Local tone adjustments, which treat dark and bright areas of an image differently.
+
+use crate::analysis::brightness;
+use crate::{Colour, Image};
+
+// The following impl block defines local tone functions for Images.
+impl Image {
+    /// Brightens the shadows by `shadow_amount` and darkens the highlights by
+    /// `highlight_amount`, bringing back detail in backlit faces and washed-out skies.
+    ///
+    /// Whether a pixel lies in the shadows or the highlights is decided by a mask of the
+    /// brightness of the image, blurred with a Gaussian of standard deviation `radius` pixels.
+    /// Basing the mask on areas rather than single pixels keeps the contrast of the details
+    /// inside them, where a tone curve would flatten it. A radius of a few percent of the width
+    /// of the image works well; much smaller radii give halos around edges.
+    ///
+    /// Amounts go from 0, which changes nothing, to 1 for the strongest effect, which moves the
+    /// darkest and brightest areas by up to half of the range. Every channel of a pixel is
+    /// moved by the same amount, so that its hue is kept. Alpha is left alone.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use kodak::*;
+    /// let backlit = Image::load_png("assets/backlit.png").unwrap();
+    /// backlit
+    ///     .shadows_highlights(0.6, 0.2, 30.0)
+    ///     .save_png("recovered.png").unwrap();
+    /// ```
+    pub fn shadows_highlights(
+        mut self,
+        shadow_amount: f32,
+        highlight_amount: f32,
+        radius: f32,
+    ) -> Image {
+        let grey: Vec<Colour> = brightness(&self)
+            .into_iter()
+            .map(|l| {
+                let v = (l * 255.0).round() as u8;
+                Colour::rgb(v, v, v)
+            })
+            .collect();
+        let mask = Image::from_pixels(self.get_dimensions(), grey).blur(radius);
+
+        for (c, m) in self.pixels.iter_mut().zip(&mask.pixels) {
+            let area = m.r as f32 / 255.0;
+            // The darker the area, the more it is lifted, by up to half of the range; bright
+            // areas are pulled down in the same way. All pixels of an area move alike, so the
+            // details in it keep their contrast.
+            let lift = shadow_amount * (1.0 - area).powi(2) * 0.5;
+            let pull = highlight_amount * area.powi(2) * 0.5;
+            let shift = (lift - pull) * 255.0;
+            let adjust = |v: u8| (v as f32 + shift).round().clamp(0.0, 255.0) as u8;
+            (c.r, c.g, c.b) = (adjust(c.r), adjust(c.g), adjust(c.b));
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tone_tests {
+    use super::*;
+    use crate::{Dim, Loc, Region};
+
+    #[test]
+    fn lifts_shadows_and_pulls_highlights() {
+        // A dark left half with a little detail in it, and a bright right half.
+        let img = Image::blank_with_colour(Dim { w: 40, h: 10 }, Colour::rgb(230, 230, 230))
+            .fill_region(
+                Region::from_top_left(Dim { w: 20, h: 10 }),
+                Colour::rgb(20, 30, 40),
+            )
+            .fill_region(
+                Region {
+                    l: Loc { x: 5, y: 5 },
+                    d: Dim::square(1),
+                },
+                Colour::rgb(40, 50, 60),
+            );
+        assert_eq!(img.clone().shadows_highlights(0.0, 0.0, 3.0), img);
+
+        let at = |img: &Image, x| img.get_pixel(Loc { x, y: 5 }).unwrap();
+        let shadows = img.clone().shadows_highlights(0.8, 0.0, 3.0);
+        let (before, after) = (at(&img, 2), at(&shadows, 2));
+        assert!(after.r > before.r + 60);
+        assert_eq!((after.g - after.r, after.b - after.r), (10, 20));
+        // The detail keeps most of its contrast with its surroundings.
+        assert!(at(&shadows, 5).r - at(&shadows, 4).r >= 15);
+        assert!(at(&shadows, 37).r.abs_diff(230) <= 1);
+
+        let highlights = img.shadows_highlights(0.0, 0.8, 3.0);
+        assert!(at(&highlights, 37).r < 160);
+        assert!(at(&highlights, 2).r.abs_diff(20) <= 1);
+    }
+}
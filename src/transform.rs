@@ -0,0 +1,469 @@
+//! Geometric transforms: inverse-mapping resampling and lens distortion.
+
+use crate::{Colour, Dim, EdgeMode, Image, Loc};
+
+/// Interpolates between the top left, top right, bottom left and bottom right of four pixels,
+/// `tx` of the way to the right and `ty` of the way down.
+fn bilinear(corners: [Colour; 4], tx: f32, ty: f32) -> Colour {
+    // Interpolating premultiplied values keeps transparent pixels from bleeding their
+    // (meaningless) colour into the result.
+    let [c00, c10, c01, c11] = corners.map(|c| c.premultiply());
+    let mix = |a: u8, b: u8, c: u8, d: u8| {
+        let top = a as f32 + (b as f32 - a as f32) * tx;
+        let bottom = c as f32 + (d as f32 - c as f32) * tx;
+        (top + (bottom - top) * ty).round() as u8
+    };
+    Colour::rgba(
+        mix(c00.r, c10.r, c01.r, c11.r),
+        mix(c00.g, c10.g, c01.g, c11.g),
+        mix(c00.b, c10.b, c01.b, c11.b),
+        mix(c00.a, c10.a, c01.a, c11.a),
+    )
+    .unpremultiply()
+}
+
+// The following impl block defines resampling functions for Images.
+impl Image {
+    /// Samples the image at a continuous position using bilinear interpolation.
+    ///
+    /// Pixel `(i, j)` covers the square from `(i, j)` to `(i + 1, j + 1)`, so its centre lies at
+    /// `(i + 0.5, j + 0.5)`. Positions near the border are clamped to the nearest edge pixel.
+    /// Returns `None` if the position falls outside of the image, and always for an empty image.
+    pub fn sample_bilinear(&self, x: f32, y: f32) -> Option<Colour> {
+        if self.width == 0 || self.height == 0 {
+            return None;
+        }
+        if !(x >= 0.0 && y >= 0.0 && x <= self.width as f32 && y <= self.height as f32) {
+            return None;
+        }
+
+        let max_x = self.width as f32 - 1.0;
+        let max_y = self.height as f32 - 1.0;
+        let fx = (x - 0.5).clamp(0.0, max_x);
+        let fy = (y - 0.5).clamp(0.0, max_y);
+
+        let x0 = fx.floor() as u32;
+        let y0 = fy.floor() as u32;
+        let x1 = (x0 + 1).min(self.width - 1);
+        let y1 = (y0 + 1).min(self.height - 1);
+        let tx = fx - x0 as f32;
+        let ty = fy - y0 as f32;
+
+        let dim = self.get_dimensions();
+        let at = |x: u32, y: u32| self.pixels[Loc { x, y }.as_index(dim)];
+        Some(bilinear(
+            [at(x0, y0), at(x1, y0), at(x0, y1), at(x1, y1)],
+            tx,
+            ty,
+        ))
+    }
+
+    /// Samples the image at a continuous position using bilinear interpolation, like
+    /// `sample_bilinear()`, with `edge` deciding what lies beyond the border.
+    ///
+    /// With `EdgeMode::Constant`, positions outside of the image get the constant colour, while
+    /// the half pixel along the inside of the border still repeats the edge pixels, exactly as
+    /// `sample_bilinear(x, y).unwrap_or(colour)` would.
+    pub fn sample_with_edge(&self, x: f32, y: f32, edge: EdgeMode) -> Colour {
+        if let EdgeMode::Constant(colour) = edge {
+            return self.sample_bilinear(x, y).unwrap_or(colour);
+        }
+        if !(x.is_finite() && y.is_finite()) {
+            return Colour::TRANSPARENT;
+        }
+        let (fx, fy) = (x - 0.5, y - 0.5);
+        let (x0, y0) = (fx.floor(), fy.floor());
+        let (tx, ty) = (fx - x0, fy - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let at = |x: i64, y: i64| edge.pixel(self, x, y);
+        bilinear(
+            [
+                at(x0, y0),
+                at(x0 + 1, y0),
+                at(x0, y0 + 1),
+                at(x0 + 1, y0 + 1),
+            ],
+            tx,
+            ty,
+        )
+    }
+
+    /// Builds a new image of the given dimensions by inverse mapping.
+    ///
+    /// For every pixel centre `(x, y)` of the output image, `map` returns the position in this
+    /// image that should be sampled, or `None` to leave the pixel black. Sampling is bilinear.
+    /// Positions that fall outside of this image are left black as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// // Mirror an image horizontally.
+    /// let img = Image::blank(Dim { w: 4, h: 4 })
+    ///     .fill_region(Region::from_top_left(Dim { w: 2, h: 4 }), Colour::WHITE);
+    /// let w = img.get_dimensions().w as f32;
+    /// let mirrored = img.remap(img.get_dimensions(), |x, y| Some((w - x, y)));
+    /// assert_eq!(mirrored.get_pixel(Loc { x: 3, y: 0 }).unwrap(), Colour::WHITE);
+    /// ```
+    pub fn remap<F>(&self, dimension: Dim, map: F) -> Image
+    where
+        F: Fn(f32, f32) -> Option<(f32, f32)>,
+    {
+        self.remap_with_edge(dimension, EdgeMode::Constant(Colour::BLACK), map)
+    }
+
+    /// Builds a new image by inverse mapping like `remap()`, with `edge` deciding what is
+    /// sampled at positions outside of this image. Pixels for which `map` returns `None` are
+    /// still left black.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kodak::*;
+    /// // Tile an image twice in both directions.
+    /// let tile = Image::blank_with_colour(Dim::square(8), Colour::WHITE);
+    /// let tiled = tile.remap_with_edge(Dim::square(16), EdgeMode::Wrap, |x, y| Some((x, y)));
+    /// assert_eq!(tiled.get_pixel(Loc { x: 12, y: 12 }).unwrap(), Colour::WHITE);
+    /// ```
+    pub fn remap_with_edge<F>(&self, dimension: Dim, edge: EdgeMode, map: F) -> Image
+    where
+        F: Fn(f32, f32) -> Option<(f32, f32)>,
+    {
+        let pixels = (0..dimension.w as usize * dimension.h as usize)
+            .map(|i| {
+                let loc = Loc::from_index(i, dimension);
+                map(loc.x as f32 + 0.5, loc.y as f32 + 0.5).map_or(Colour::BLACK, |(sx, sy)| {
+                    self.sample_with_edge(sx, sy, edge)
+                })
+            })
+            .collect();
+
+        Image {
+            width: dimension.w,
+            height: dimension.h,
+            pixels,
+            colour_profile: self.colour_profile.clone(),
+            metadata: self.metadata.clone(),
+        }
+    }
+
+    /// Rotates the image clockwise by an arbitrary angle in degrees around its centre, keeping
+    /// its dimensions.
+    ///
+    /// Corners that rotate out of view are cut off, and the areas rotated into view are filled
+    /// with `background`. For multiples of 90 degrees, `rotate_90()` and friends are lossless.
+    pub fn rotate(&self, degrees: f32, background: Colour) -> Image {
+        self.rotate_with_edge(degrees, EdgeMode::Constant(background))
+    }
+
+    /// Rotates the image clockwise around its centre like `rotate()`, with `edge` deciding what
+    /// is rotated into view. `EdgeMode::Mirror` avoids empty corners when straightening photos.
+    pub fn rotate_with_edge(&self, degrees: f32, edge: EdgeMode) -> Image {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let cx = self.width as f32 / 2.0;
+        let cy = self.height as f32 / 2.0;
+        let dim = self.get_dimensions();
+
+        let pixels = (0..self.pixels.len())
+            .map(|i| {
+                let loc = Loc::from_index(i, dim);
+                let dx = loc.x as f32 + 0.5 - cx;
+                let dy = loc.y as f32 + 0.5 - cy;
+                self.sample_with_edge(cx + dx * cos + dy * sin, cy - dx * sin + dy * cos, edge)
+            })
+            .collect();
+
+        Image {
+            pixels,
+            ..self.clone()
+        }
+    }
+}
+
+// The following impl block defines lens distortion functions for Images.
+impl Image {
+    /// Maps a pixel position to coordinates relative to the image centre, normalised so that the
+    /// corners lie at a radius of 1.
+    fn to_normalised(&self, x: f32, y: f32) -> (f32, f32, f32) {
+        let cx = self.width as f32 / 2.0;
+        let cy = self.height as f32 / 2.0;
+        let half_diagonal = (cx * cx + cy * cy).sqrt();
+        (
+            (x - cx) / half_diagonal,
+            (y - cy) / half_diagonal,
+            half_diagonal,
+        )
+    }
+
+    /// Applies radial (Brown-Conrady) lens distortion to the image.
+    ///
+    /// A point at normalised radius `r` from the centre is moved to `r * (1 + k1 r² + k2 r⁴)`,
+    /// where the image corners lie at `r = 1`. Positive coefficients produce pincushion
+    /// distortion, negative ones barrel distortion. Use `lens_undistort()` with the same
+    /// coefficients to undo it.
+    pub fn lens_distort(&self, k1: f32, k2: f32) -> Image {
+        let cx = self.width as f32 / 2.0;
+        let cy = self.height as f32 / 2.0;
+
+        self.remap(self.get_dimensions(), |x, y| {
+            let (nx, ny, scale) = self.to_normalised(x, y);
+            let rd = (nx * nx + ny * ny).sqrt();
+            if rd == 0.0 {
+                return Some((x, y));
+            }
+
+            // Invert rd = ru * (1 + k1 ru² + k2 ru⁴) with a few Newton iterations.
+            let mut ru = rd;
+            for _ in 0..20 {
+                let r2 = ru * ru;
+                let f = ru * (1.0 + k1 * r2 + k2 * r2 * r2) - rd;
+                let df = 1.0 + 3.0 * k1 * r2 + 5.0 * k2 * r2 * r2;
+                if df.abs() < f32::EPSILON {
+                    return None;
+                }
+                ru -= f / df;
+            }
+            if !ru.is_finite() || ru < 0.0 {
+                return None;
+            }
+
+            let factor = ru / rd;
+            Some((cx + nx * factor * scale, cy + ny * factor * scale))
+        })
+    }
+
+    /// Corrects radial (Brown-Conrady) lens distortion with the given coefficients.
+    ///
+    /// This is the inverse of `lens_distort()`: pass the coefficients describing the lens that
+    /// took the photo to straighten lines that appear bent.
+    pub fn lens_undistort(&self, k1: f32, k2: f32) -> Image {
+        let cx = self.width as f32 / 2.0;
+        let cy = self.height as f32 / 2.0;
+
+        self.remap(self.get_dimensions(), |x, y| {
+            let (nx, ny, scale) = self.to_normalised(x, y);
+            let r2 = nx * nx + ny * ny;
+            let factor = 1.0 + k1 * r2 + k2 * r2 * r2;
+            Some((cx + nx * factor * scale, cy + ny * factor * scale))
+        })
+    }
+
+    /// Renders the image as if it were seen through an equidistant fisheye lens.
+    ///
+    /// # Arguments
+    ///
+    /// * `fov` - the field of view of the lens in degrees, between 0 and 180 (exclusive).
+    ///   Larger values bulge the centre of the image more strongly.
+    ///
+    /// # Panics
+    ///
+    /// * if `fov` is not between 0 and 180 degrees.
+    pub fn fisheye(&self, fov: f32) -> Image {
+        assert!(
+            fov > 0.0 && fov < 180.0,
+            "The field of view of a fisheye lens should be between 0 and 180 degrees."
+        );
+
+        let cx = self.width as f32 / 2.0;
+        let cy = self.height as f32 / 2.0;
+        let half_fov = fov.to_radians() / 2.0;
+
+        self.remap(self.get_dimensions(), |x, y| {
+            let (nx, ny, scale) = self.to_normalised(x, y);
+            let rf = (nx * nx + ny * ny).sqrt();
+            if rf == 0.0 {
+                return Some((x, y));
+            }
+
+            let theta = rf * half_fov;
+            if theta >= std::f32::consts::FRAC_PI_2 {
+                return None;
+            }
+
+            let factor = theta.tan() / half_fov.tan() / rf;
+            Some((cx + nx * factor * scale, cy + ny * factor * scale))
+        })
+    }
+}
+
+// The following impl block defines lossless orientation functions for Images.
+impl Image {
+    /// Builds a new image of the given dimensions where every pixel is copied from the location
+    /// returned by `source`.
+    fn rearrange<F: Fn(u32, u32) -> Loc>(&self, dimension: Dim, source: F) -> Image {
+        let own = self.get_dimensions();
+        let pixels = (0..dimension.w as usize * dimension.h as usize)
+            .map(|i| {
+                let loc = Loc::from_index(i, dimension);
+                self.pixels[source(loc.x, loc.y).as_index(own)]
+            })
+            .collect();
+
+        Image {
+            width: dimension.w,
+            height: dimension.h,
+            pixels,
+            colour_profile: self.colour_profile.clone(),
+            metadata: self.metadata.clone(),
+        }
+    }
+
+    /// Mirrors the image left to right.
+    pub fn flip_horizontal(&self) -> Image {
+        let w = self.width;
+        self.rearrange(self.get_dimensions(), |x, y| Loc { x: w - 1 - x, y })
+    }
+
+    /// Mirrors the image top to bottom.
+    pub fn flip_vertical(&self) -> Image {
+        let h = self.height;
+        self.rearrange(self.get_dimensions(), |x, y| Loc { x, y: h - 1 - y })
+    }
+
+    /// Rotates the image by 90 degrees clockwise.
+    pub fn rotate_90(&self) -> Image {
+        let h = self.height;
+        let dim = Dim {
+            w: self.height,
+            h: self.width,
+        };
+        self.rearrange(dim, |x, y| Loc { x: y, y: h - 1 - x })
+    }
+
+    /// Rotates the image by 180 degrees.
+    pub fn rotate_180(&self) -> Image {
+        let (w, h) = (self.width, self.height);
+        self.rearrange(self.get_dimensions(), |x, y| Loc {
+            x: w - 1 - x,
+            y: h - 1 - y,
+        })
+    }
+
+    /// Rotates the image by 270 degrees clockwise (90 degrees counter-clockwise).
+    pub fn rotate_270(&self) -> Image {
+        let w = self.width;
+        let dim = Dim {
+            w: self.height,
+            h: self.width,
+        };
+        self.rearrange(dim, |x, y| Loc { x: w - 1 - y, y: x })
+    }
+
+    /// Mirrors the image across its main diagonal, swapping rows and columns.
+    pub fn transpose(&self) -> Image {
+        let dim = Dim {
+            w: self.height,
+            h: self.width,
+        };
+        self.rearrange(dim, |x, y| Loc { x: y, y: x })
+    }
+}
+
+#[cfg(test)]
+mod transform_tests {
+    use super::*;
+
+    #[test]
+    fn samples_with_edges() {
+        let img = Image::blank_with_colour(Dim { w: 2, h: 1 }, Colour::WHITE)
+            .fill_region(crate::Region::from_top_left(Dim::square(1)), Colour::BLACK);
+        // Halfway between the right edge pixel and whatever lies beyond it.
+        let beyond = |edge| img.sample_with_edge(2.5, 0.5, edge);
+        assert_eq!(beyond(EdgeMode::Clamp), Colour::WHITE);
+        assert_eq!(beyond(EdgeMode::Wrap), Colour::BLACK);
+        assert_eq!(beyond(EdgeMode::Mirror), Colour::WHITE);
+        assert_eq!(
+            img.sample_with_edge(3.0, 0.5, EdgeMode::Wrap),
+            Colour::rgb(128, 128, 128)
+        );
+        let red = Colour::rgb(255, 0, 0);
+        assert_eq!(beyond(EdgeMode::Constant(red)), red);
+        assert_eq!(
+            img.sample_with_edge(1.9, 0.5, EdgeMode::Constant(red)),
+            Colour::WHITE
+        );
+
+        let rotated = img.rotate_with_edge(180.0, EdgeMode::Clamp);
+        assert_eq!(rotated, img.rotate(180.0, Colour::BLACK));
+        let tiled = img.remap_with_edge(Dim { w: 4, h: 1 }, EdgeMode::Wrap, |x, y| Some((x, y)));
+        assert_eq!(tiled.get_pixel(Loc { x: 2, y: 0 }), Ok(Colour::BLACK));
+    }
+
+    #[test]
+    fn distortion_round_trip() {
+        let img = Image::blank(Dim::square(40)).fill_region(
+            crate::Region {
+                l: Loc { x: 10, y: 10 },
+                d: Dim::square(20),
+            },
+            Colour::WHITE,
+        );
+
+        let restored = img.lens_distort(0.2, 0.05).lens_undistort(0.2, 0.05);
+        assert_eq!(
+            restored.get_pixel(Loc { x: 20, y: 20 }).unwrap(),
+            Colour::WHITE
+        );
+        assert_eq!(
+            restored.get_pixel(Loc { x: 5, y: 5 }).unwrap(),
+            Colour::BLACK
+        );
+    }
+
+    #[test]
+    fn empty_images_are_not_sampled() {
+        let empty = Image::blank(Dim { w: 0, h: 0 });
+        assert_eq!(empty.sample_bilinear(0.0, 0.0), None);
+        assert_eq!(
+            Image::blank(Dim { w: 3, h: 0 }).sample_bilinear(1.0, 0.0),
+            None
+        );
+
+        let remapped = empty.remap(Dim::square(2), |x, y| Some((x, y)));
+        assert_eq!(remapped, Image::blank(Dim::square(2)));
+        assert_eq!(empty.lens_distort(0.2, 0.05), empty);
+        assert_eq!(empty.lens_undistort(0.2, 0.05), empty);
+        assert_eq!(empty.fisheye(120.0), empty);
+    }
+
+    #[test]
+    fn rotations() {
+        let img = Image::blank(Dim { w: 3, h: 2 }).fill_region(
+            crate::Region {
+                l: Loc { x: 0, y: 0 },
+                d: Dim::square(1),
+            },
+            Colour::WHITE,
+        );
+
+        let rotated = img.rotate_90();
+        assert_eq!(rotated.get_dimensions(), Dim { w: 2, h: 3 });
+        assert_eq!(
+            rotated.get_pixel(Loc { x: 1, y: 0 }).unwrap(),
+            Colour::WHITE
+        );
+        assert_eq!(
+            img.rotate_270().get_pixel(Loc { x: 0, y: 2 }).unwrap(),
+            Colour::WHITE
+        );
+        assert_eq!(
+            img.rotate_180().get_pixel(Loc { x: 2, y: 1 }).unwrap(),
+            Colour::WHITE
+        );
+        assert_eq!(rotated.rotate_270().pixels, img.pixels);
+    }
+
+    #[test]
+    fn fisheye_keeps_centre() {
+        let img = Image::blank(Dim::square(21))
+            .fill_region(
+                crate::Region {
+                    l: Loc { x: 10, y: 10 },
+                    d: Dim::square(1),
+                },
+                Colour::WHITE,
+            )
+            .fisheye(120.0);
+        assert_eq!(img.get_pixel(Loc { x: 10, y: 10 }).unwrap(), Colour::WHITE);
+    }
+}
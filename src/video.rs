@@ -0,0 +1,175 @@
+//! Streaming frames as uncompressed yuv4mpeg2 video, which ffmpeg and most other video tools
+//! read directly.
+
+use crate::{Animation, Colour, Dim, Image};
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs::File;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::BufWriter;
+use std::io::{self, Write};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+/// Converts a colour to limited-range BT.601 Y'CbCr, treating translucent pixels as if they were
+/// composited over black.
+fn to_ycbcr(colour: Colour) -> [f32; 3] {
+    let c = colour.premultiply();
+    let (r, g, b) = (c.r as f32, c.g as f32, c.b as f32);
+    let y = 0.299 * r + 0.587 * g + 0.114 * b;
+    [
+        16.0 + y * 219.0 / 255.0,
+        128.0 + (b - y) / 1.772 * 224.0 / 255.0,
+        128.0 + (r - y) / 1.402 * 224.0 / 255.0,
+    ]
+}
+
+/// Writes frames as a yuv4mpeg2 (`.y4m`) stream, to a file or any other writer such as the
+/// standard input of an ffmpeg process.
+///
+/// Frames are converted to limited-range BT.601 with 4:2:0 chroma subsampling, which is what
+/// video encoders expect by default. Alpha is dropped by compositing over black.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use kodak::*;
+/// # use std::process::{Command, Stdio};
+/// let mut ffmpeg = Command::new("ffmpeg")
+///     .args(["-i", "-", "out.mp4"])
+///     .stdin(Stdio::piped())
+///     .spawn()
+///     .unwrap();
+/// let mut video = VideoWriter::new(ffmpeg.stdin.take().unwrap(), Dim::square(256), 30).unwrap();
+/// for i in 0..=255 {
+///     video
+///         .write_frame(&Image::blank_with_colour(Dim::square(256), Colour::rgb(i, 0, 0)))
+///         .unwrap();
+/// }
+/// video.finish().unwrap();
+/// ffmpeg.wait().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct VideoWriter<W: Write> {
+    writer: W,
+    dimension: Dim,
+    fps: u32,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl VideoWriter<BufWriter<File>> {
+    /// Creates a `.y4m` file and writes the stream header to it.
+    pub fn create(file_name: impl AsRef<Path>, dimension: Dim, fps: u32) -> io::Result<Self> {
+        VideoWriter::new(BufWriter::new(File::create(file_name)?), dimension, fps)
+    }
+}
+
+impl<W: Write> VideoWriter<W> {
+    /// Starts a stream of frames with the given dimensions and frame rate, writing the stream
+    /// header right away.
+    ///
+    /// This returns an `Err` with `io::ErrorKind::InvalidInput` if the dimensions or the frame
+    /// rate are zero, or any error from the writer.
+    pub fn new(mut writer: W, dimension: Dim, fps: u32) -> io::Result<Self> {
+        if dimension.w == 0 || dimension.h == 0 || fps == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "A video needs a non-zero size and frame rate.",
+            ));
+        }
+        writeln!(
+            writer,
+            "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C420jpeg",
+            dimension.w, dimension.h, fps
+        )?;
+        Ok(VideoWriter {
+            writer,
+            dimension,
+            fps,
+        })
+    }
+
+    /// Appends a frame to the stream.
+    ///
+    /// This returns an `Err` with `io::ErrorKind::InvalidInput` if the image does not have the
+    /// dimensions of the stream, or any error from the writer.
+    pub fn write_frame(&mut self, frame: &Image) -> io::Result<()> {
+        if frame.get_dimensions() != self.dimension {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Every frame must have the dimensions of the video.",
+            ));
+        }
+
+        let (w, h) = (self.dimension.w as usize, self.dimension.h as usize);
+        let ycbcr: Vec<[f32; 3]> = frame.pixels.iter().map(|&c| to_ycbcr(c)).collect();
+        let plane = |channel: usize| -> Vec<f32> { ycbcr.iter().map(|p| p[channel]).collect() };
+        let to_u8 = |v: f32| v.round().clamp(0.0, 255.0) as u8;
+
+        let mut data = Vec::with_capacity(w * h * 3 / 2 + 6);
+        data.extend_from_slice(b"FRAME\n");
+        data.extend(plane(0).into_iter().map(to_u8));
+
+        // Each chroma sample averages a block of up to 2 by 2 pixels.
+        let (cw, ch) = (w.div_ceil(2), h.div_ceil(2));
+        for chroma in [plane(1), plane(2)] {
+            for cy in 0..ch {
+                for cx in 0..cw {
+                    let (mut sum, mut count) = (0.0, 0.0);
+                    for y in (cy * 2)..(cy * 2 + 2).min(h) {
+                        for x in (cx * 2)..(cx * 2 + 2).min(w) {
+                            sum += chroma[y * w + x];
+                            count += 1.0;
+                        }
+                    }
+                    data.push(to_u8(sum / count));
+                }
+            }
+        }
+
+        self.writer.write_all(&data)
+    }
+
+    /// Appends every frame of an animation, repeating each one so that it stays on screen for
+    /// its delay (rounded to whole video frames, at least one) at the stream's frame rate.
+    pub fn write_animation(&mut self, animation: &Animation) -> io::Result<()> {
+        for frame in &animation.frames {
+            let repeats = (frame.delay.as_secs_f64() * self.fps as f64)
+                .round()
+                .max(1.0) as usize;
+            for _ in 0..repeats {
+                self.write_frame(&frame.image)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Flushes the stream and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod video_tests {
+    use super::*;
+
+    #[test]
+    fn y4m_layout() {
+        let dim = Dim { w: 3, h: 2 };
+        let mut video = VideoWriter::new(Vec::new(), dim, 25).unwrap();
+        video
+            .write_frame(&Image::blank_with_colour(dim, Colour::WHITE))
+            .unwrap();
+        video.write_frame(&Image::blank(dim)).unwrap();
+        assert!(video.write_frame(&Image::blank(Dim::square(2))).is_err());
+        let data = video.finish().unwrap();
+
+        let header = b"YUV4MPEG2 W3 H2 F25:1 Ip A1:1 C420jpeg\n";
+        assert!(data.starts_with(header));
+        // Six luma samples and two 2x1 chroma planes per frame.
+        let frame = &data[header.len()..header.len() + 16];
+        assert_eq!(frame, b"FRAME\n\xeb\xeb\xeb\xeb\xeb\xeb\x80\x80\x80\x80");
+        assert_eq!(data.len(), header.len() + 2 * 16);
+    }
+}
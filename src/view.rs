@@ -0,0 +1,354 @@
+//! Borrowed views of pixel buffers owned by something else, such as a window's framebuffer or a
+//! camera frame, so that kodak can read from and draw into them without copying.
+
+use crate::{Colour, Dim, Image, Loc, Region};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// The byte layout of the pixels in a raw buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawFormat {
+    /// Three bytes per pixel: red, green, blue. Every pixel is opaque.
+    Rgb,
+    /// Four bytes per pixel: red, green, blue, alpha.
+    Rgba,
+    /// Four bytes per pixel: blue, green, red, alpha. This is how `0x00RRGGBB` pixels are laid
+    /// out in memory on little-endian machines, as used by minifb and softbuffer; set the alpha
+    /// byte to 255 or ignore it there.
+    Bgra,
+}
+
+impl RawFormat {
+    /// Returns the number of bytes a single pixel takes up.
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            RawFormat::Rgb => 3,
+            RawFormat::Rgba | RawFormat::Bgra => 4,
+        }
+    }
+
+    fn decode(self, bytes: &[u8]) -> Colour {
+        match self {
+            RawFormat::Rgb => Colour::rgb(bytes[0], bytes[1], bytes[2]),
+            RawFormat::Rgba => Colour::rgba(bytes[0], bytes[1], bytes[2], bytes[3]),
+            RawFormat::Bgra => Colour::rgba(bytes[2], bytes[1], bytes[0], bytes[3]),
+        }
+    }
+
+    fn encode(self, colour: Colour, bytes: &mut [u8]) {
+        match self {
+            RawFormat::Rgb => bytes.copy_from_slice(&[colour.r, colour.g, colour.b]),
+            RawFormat::Rgba => bytes.copy_from_slice(&[colour.r, colour.g, colour.b, colour.a]),
+            RawFormat::Bgra => bytes.copy_from_slice(&[colour.b, colour.g, colour.r, colour.a]),
+        }
+    }
+}
+
+/// Checks that a buffer of `len` bytes can hold an image with the given layout.
+fn check_layout(
+    len: usize,
+    dimension: Dim,
+    format: RawFormat,
+    stride: usize,
+) -> Result<(), &'static str> {
+    let row = dimension.w as usize * format.bytes_per_pixel();
+    if stride < row {
+        return Err("The stride is shorter than a row of pixels.");
+    }
+    let needed = match dimension.h as usize {
+        0 => 0,
+        h => stride * (h - 1) + row,
+    };
+    if len < needed {
+        return Err("The buffer is too small for the given dimensions.");
+    }
+    Ok(())
+}
+
+/// A read-only view of a pixel buffer owned by someone else.
+///
+/// # Examples
+///
+/// ```
+/// # use kodak::*;
+/// let frame = vec![255u8; 4 * 3 * 2];
+/// let view = ImageView::from_raw_parts(&frame, Dim { w: 4, h: 2 }, RawFormat::Rgb).unwrap();
+/// assert_eq!(view.get_pixel(Loc { x: 3, y: 1 }), Ok(Colour::WHITE));
+/// let owned: Image = view.to_image();
+/// ```
+#[derive(Clone, Copy)]
+pub struct ImageView<'a> {
+    data: &'a [u8],
+    dimension: Dim,
+    format: RawFormat,
+    stride: usize,
+}
+
+impl fmt::Debug for ImageView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImageView")
+            .field("dimension", &self.dimension)
+            .field("format", &self.format)
+            .field("stride", &self.stride)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> ImageView<'a> {
+    /// Wraps a buffer whose rows follow each other without padding.
+    ///
+    /// This returns an `Err` if the buffer is too small for the given dimensions.
+    pub fn from_raw_parts(
+        data: &'a [u8],
+        dimension: Dim,
+        format: RawFormat,
+    ) -> Result<Self, &'static str> {
+        let stride = dimension.w as usize * format.bytes_per_pixel();
+        ImageView::from_raw_parts_strided(data, dimension, format, stride)
+    }
+
+    /// Wraps a buffer whose rows start every `stride` bytes, as camera APIs and GPU readbacks
+    /// often pad rows to a multiple of some alignment.
+    ///
+    /// This returns an `Err` if the stride is shorter than a row or the buffer is too small.
+    pub fn from_raw_parts_strided(
+        data: &'a [u8],
+        dimension: Dim,
+        format: RawFormat,
+        stride: usize,
+    ) -> Result<Self, &'static str> {
+        check_layout(data.len(), dimension, format, stride)?;
+        Ok(ImageView {
+            data,
+            dimension,
+            format,
+            stride,
+        })
+    }
+
+    /// Returns the dimensions of the view.
+    pub fn get_dimensions(&self) -> Dim {
+        self.dimension
+    }
+
+    /// Returns the byte layout of the pixels.
+    pub fn format(&self) -> RawFormat {
+        self.format
+    }
+
+    /// Returns the colour at the given location, or an `Err` if it lies outside the view.
+    pub fn get_pixel(&self, loc: Loc) -> Result<Colour, &'static str> {
+        if loc.x >= self.dimension.w || loc.y >= self.dimension.h {
+            return Err("The location is outside of the image.");
+        }
+        let start = loc.y as usize * self.stride + loc.x as usize * self.format.bytes_per_pixel();
+        Ok(self.format.decode(&self.data[start..]))
+    }
+
+    /// Copies the pixels into an owned Image, so every other kodak function can be used on them.
+    pub fn to_image(&self) -> Image {
+        // Matching on the format once per image rather than once per pixel lets each loop be
+        // vectorised.
+        let pixels = match self.format {
+            RawFormat::Rgb => self.decode_rows(|p| Colour::rgb(p[0], p[1], p[2])),
+            RawFormat::Rgba => self.decode_rows(|p| Colour::rgba(p[0], p[1], p[2], p[3])),
+            RawFormat::Bgra => self.decode_rows(|p| Colour::rgba(p[2], p[1], p[0], p[3])),
+        };
+        Image::from_pixels(self.dimension, pixels)
+    }
+
+    /// Decodes every row of the view with `decode`, which reads the bytes of a single pixel.
+    fn decode_rows<F: Fn(&[u8]) -> Colour>(&self, decode: F) -> Vec<Colour> {
+        let bpp = self.format.bytes_per_pixel();
+        let row = self.dimension.w as usize * bpp;
+        let len = self.dimension.w as usize * self.dimension.h as usize;
+        let mut pixels = Vec::with_capacity(len);
+        for y in 0..self.dimension.h as usize {
+            let start = y * self.stride;
+            pixels.extend(self.data[start..start + row].chunks_exact(bpp).map(&decode));
+        }
+        pixels
+    }
+}
+
+/// A writable view of a pixel buffer owned by someone else, such as a window's framebuffer.
+///
+/// Drawing functions write straight into the buffer, so the result shows up the next time it is
+/// presented.
+///
+/// # Examples
+///
+/// ```
+/// # use kodak::*;
+/// // A window framebuffer with blue, green, red and padding bytes per pixel.
+/// let mut framebuffer = vec![0u8; 64 * 48 * 4];
+/// let mut view =
+///     ImageViewMut::from_raw_parts(&mut framebuffer, Dim { w: 64, h: 48 }, RawFormat::Bgra).unwrap();
+/// let sprite = Image::blank_with_colour(Dim::square(8), Colour::rgb(255, 0, 0));
+/// view.draw_image(&sprite, Loc { x: 10, y: 10 });
+/// assert_eq!(view.get_pixel(Loc { x: 12, y: 12 }), Ok(Colour::rgb(255, 0, 0)));
+/// ```
+pub struct ImageViewMut<'a> {
+    data: &'a mut [u8],
+    dimension: Dim,
+    format: RawFormat,
+    stride: usize,
+}
+
+impl fmt::Debug for ImageViewMut<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImageViewMut")
+            .field("dimension", &self.dimension)
+            .field("format", &self.format)
+            .field("stride", &self.stride)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> ImageViewMut<'a> {
+    /// Wraps a buffer whose rows follow each other without padding.
+    ///
+    /// This returns an `Err` if the buffer is too small for the given dimensions.
+    pub fn from_raw_parts(
+        data: &'a mut [u8],
+        dimension: Dim,
+        format: RawFormat,
+    ) -> Result<Self, &'static str> {
+        let stride = dimension.w as usize * format.bytes_per_pixel();
+        ImageViewMut::from_raw_parts_strided(data, dimension, format, stride)
+    }
+
+    /// Wraps a buffer whose rows start every `stride` bytes.
+    ///
+    /// This returns an `Err` if the stride is shorter than a row or the buffer is too small.
+    pub fn from_raw_parts_strided(
+        data: &'a mut [u8],
+        dimension: Dim,
+        format: RawFormat,
+        stride: usize,
+    ) -> Result<Self, &'static str> {
+        check_layout(data.len(), dimension, format, stride)?;
+        Ok(ImageViewMut {
+            data,
+            dimension,
+            format,
+            stride,
+        })
+    }
+
+    /// Returns a read-only view of the same buffer.
+    pub fn as_view(&self) -> ImageView<'_> {
+        ImageView {
+            data: self.data,
+            dimension: self.dimension,
+            format: self.format,
+            stride: self.stride,
+        }
+    }
+
+    /// Returns the dimensions of the view.
+    pub fn get_dimensions(&self) -> Dim {
+        self.dimension
+    }
+
+    /// Returns the colour at the given location, or an `Err` if it lies outside the view.
+    pub fn get_pixel(&self, loc: Loc) -> Result<Colour, &'static str> {
+        self.as_view().get_pixel(loc)
+    }
+
+    /// Overwrites the colour at the given location, or returns an `Err` if it lies outside the
+    /// view. Buffers without an alpha channel drop the alpha of `colour`.
+    pub fn set_pixel(&mut self, loc: Loc, colour: Colour) -> Result<(), &'static str> {
+        if loc.x >= self.dimension.w || loc.y >= self.dimension.h {
+            return Err("The location is outside of the image.");
+        }
+        let bpp = self.format.bytes_per_pixel();
+        let start = loc.y as usize * self.stride + loc.x as usize * bpp;
+        self.format
+            .encode(colour, &mut self.data[start..start + bpp]);
+        Ok(())
+    }
+
+    /// Fills a region with a colour, clipped to the view.
+    pub fn fill_region(&mut self, region: Region, colour: Colour) {
+        let right = region.l.x.saturating_add(region.d.w).min(self.dimension.w);
+        let bottom = region.l.y.saturating_add(region.d.h).min(self.dimension.h);
+        for y in region.l.y..bottom {
+            for x in region.l.x..right {
+                self.set_pixel(Loc { x, y }, colour).unwrap();
+            }
+        }
+    }
+
+    /// Composites an image over the buffer with its top left corner at `offset`, clipped to the
+    /// view. This is how anything rendered with kodak ends up in an external framebuffer.
+    pub fn draw_image(&mut self, img: &Image, offset: Loc) {
+        let dim = img.get_dimensions();
+        let w = dim.w.min(self.dimension.w.saturating_sub(offset.x));
+        let h = dim.h.min(self.dimension.h.saturating_sub(offset.y));
+        for y in 0..h {
+            for x in 0..w {
+                let source = img.pixels[Loc { x, y }.as_index(dim)];
+                let loc = Loc {
+                    x: offset.x + x,
+                    y: offset.y + y,
+                };
+                let colour = match source.a {
+                    255 => source,
+                    0 => continue,
+                    _ => source.over(self.get_pixel(loc).unwrap()),
+                };
+                self.set_pixel(loc, colour).unwrap();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod view_tests {
+    use super::*;
+
+    #[test]
+    fn strided_rows_are_skipped() {
+        // Two rows of two RGB pixels, padded to eight bytes per row.
+        let data = [1, 2, 3, 4, 5, 6, 0, 0, 7, 8, 9, 10, 11, 12, 0, 0];
+        let view =
+            ImageView::from_raw_parts_strided(&data, Dim::square(2), RawFormat::Rgb, 8).unwrap();
+        let img = view.to_image();
+
+        assert_eq!(img.pixels[2], Colour::rgb(7, 8, 9));
+        assert_eq!(img.pixels[3], Colour::rgb(10, 11, 12));
+        assert!(ImageView::from_raw_parts(&data[..5], Dim::square(2), RawFormat::Rgb).is_err());
+    }
+
+    #[test]
+    fn draws_into_the_buffer() {
+        let mut data = vec![0u8; 3 * 2 * 4];
+        let mut view =
+            ImageViewMut::from_raw_parts(&mut data, Dim { w: 3, h: 2 }, RawFormat::Bgra).unwrap();
+        let sprite = Image::blank_with_colour(Dim::square(2), Colour::rgb(10, 20, 30));
+        view.draw_image(&sprite, Loc { x: 2, y: 1 });
+
+        assert_eq!(
+            view.get_pixel(Loc { x: 2, y: 1 }),
+            Ok(Colour::rgb(10, 20, 30))
+        );
+        assert_eq!(&data[20..24], &[30, 20, 10, 255]);
+        assert_eq!(&data[..20], &[0; 20]);
+    }
+
+    #[test]
+    fn fill_region_is_clipped() {
+        let mut data = vec![0u8; 3 * 2 * 3];
+        let mut view =
+            ImageViewMut::from_raw_parts(&mut data, Dim { w: 3, h: 2 }, RawFormat::Rgb).unwrap();
+        let region = Region {
+            l: Loc { x: 1, y: 1 },
+            d: Dim::square(u32::MAX),
+        };
+        view.fill_region(region, Colour::WHITE);
+
+        assert_eq!(&data[..12], &[0; 12]);
+        assert_eq!(&data[12..], &[255; 6]);
+    }
+}
@@ -0,0 +1,43 @@
+//! Conversions to and from HTML canvas `ImageData`. Only available with the `wasm` feature.
+//!
+//! The rest of the library compiles to `wasm32-unknown-unknown` as it is. There is no file system
+//! there, so everything that takes a path is left out on that target: the functions that load,
+//! open or save files, the async file functions, and the `batch`, `recipe` and `testing` modules.
+//! Get pixels in and out of the browser through a canvas, or with `Image::from_png_bytes()` and
+//! `Image::to_png_bytes()`.
+//!
+//! ```ignore
+//! # use kodak::*;
+//! let data = context.get_image_data(0.0, 0.0, 640.0, 480.0)?;
+//! let img = Image::from_image_data(&data).flip_horizontal();
+//! context.put_image_data(&img.to_image_data()?, 0.0, 0.0)?;
+//! ```
+
+use crate::{Dim, Image};
+use wasm_bindgen::{Clamped, JsValue};
+use web_sys::ImageData;
+
+// The following impl block defines canvas functions for Images.
+impl Image {
+    /// Copies the pixels of a canvas `ImageData` into an Image.
+    ///
+    /// Canvases store straight (not premultiplied) alpha, as Images do.
+    pub fn from_image_data(data: &ImageData) -> Image {
+        let dimension = Dim {
+            w: data.width(),
+            h: data.height(),
+        };
+        let pixels = crate::simd::from_rgba_bytes(&data.data());
+        Image::from_pixels(dimension, pixels)
+    }
+
+    /// Copies the image into a new `ImageData`, ready for
+    /// `CanvasRenderingContext2d::put_image_data()`.
+    ///
+    /// This returns an `Err` with the JavaScript exception if the browser refuses to create the
+    /// `ImageData`, for example when the image has no pixels.
+    pub fn to_image_data(&self) -> Result<ImageData, JsValue> {
+        let data = crate::simd::to_rgba_bytes(&self.pixels);
+        ImageData::new_with_u8_clamped_array_and_sh(Clamped(&data), self.width, self.height)
+    }
+}